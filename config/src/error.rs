@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use figment::Source;
+
+// Why the configuration couldn't be loaded
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Cli(#[from] clap::Error),
+    #[error("Config file {} not found", .0.display())]
+    NotFound(PathBuf),
+    // Wrong types, unknown keys or unparsable values, with the file or variable they came from
+    #[error("{}", describe(.0))]
+    Layer(Box<figment::Error>),
+    #[error("Invalid [{section}] configuration: {message}")]
+    Invalid {
+        section: &'static str,
+        message: String,
+    },
+    // A secret reference (see `secret`) that can't be read
+    #[error("Cannot read the secret for `{key}`: {message}")]
+    Secret { key: String, message: String },
+    // --check found something missing
+    #[error("Check failed: {0}")]
+    Check(String),
+    #[error("Arguments don't serialize to a table")]
+    NotATable,
+    #[error("Cannot print the configuration: {0}")]
+    Print(#[from] toml::ser::Error),
+}
+
+impl From<figment::Error> for ConfigError {
+    fn from(err: figment::Error) -> Self {
+        ConfigError::Layer(Box::new(err))
+    }
+}
+
+// ✅ One line per problem, naming the key and the file or variables it came from
+fn describe(err: &figment::Error) -> String {
+    err.clone()
+        .into_iter()
+        .map(|err| {
+            let mut line = format!("Invalid configuration: {}", err.kind);
+            if !err.path.is_empty() {
+                line.push_str(&format!(" for `{}`", err.path.join(".")));
+            }
+            if let Some(metadata) = err.metadata {
+                match &metadata.source {
+                    Some(Source::File(path)) => line.push_str(&format!(" in {}", path.display())),
+                    _ => line.push_str(&format!(" from {}", metadata.name)),
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
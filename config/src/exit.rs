@@ -0,0 +1,12 @@
+// Process exit codes shared by every binary, so a supervisor (Docker, systemd,
+// Kubernetes) can tell a failure worth restarting from one that isn't:
+//
+//   0  done (or --check / --print-config passed)
+//   1  runtime failure: signaling lost, pipeline error, device gone; restart
+//   2  bad flags or configuration; restarting won't help, fix the config
+//   3  --check failed: something the configuration needs isn't installed or
+//      available (GStreamer plugins, the listen address); fix the image/host
+
+pub const FAILURE: i32 = 1;
+pub const CONFIG: i32 = 2;
+pub const UNAVAILABLE: i32 = 3;
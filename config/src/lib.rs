@@ -0,0 +1,410 @@
+// Layered configuration shared by the transmitter, streamer and watcher.
+//
+// A binary's clap `Args` doubles as its configuration schema; values are layered
+// with later layers winning:
+//
+//   1. flag defaults
+//   2. the binary's table in tuesdays.toml (or --config PATH / TUESDAYS_CONFIG)
+//   3. TUESDAYS_<BINARY>_<FLAG> environment variables, e.g. TUESDAYS_WATCHER_MIN_FPS=5
+//   4. flags given on the command line
+//
+// Keys are the flag names with underscores:
+//
+//   [watcher]
+//   server = "wss://tuesdays.example"
+//   stats = true
+//   min_fps = 15.0
+//
+// Any value in the file can instead name where a secret is kept (an environment
+// variable, a private file, an age-encrypted file); see `secret`.
+//
+// Every binary also accepts --print-config, which prints the effective
+// configuration (and where each value came from) and exits, and --check, which
+// probes what the configuration needs at runtime (see `exit` for the codes).
+
+mod error;
+pub mod exit;
+mod secret;
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, FromArgMatches, Parser};
+use figment::providers::{Env, Format, Toml};
+use figment::value::{Dict, Map, Value};
+use figment::{Figment, Metadata, Profile, Provider, Source};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub use error::ConfigError;
+pub use secret::AGE_IDENTITY;
+
+use secret::Secrets;
+
+// Looked up in the working directory when neither --config nor TUESDAYS_CONFIG is set
+pub const DEFAULT_FILE: &str = "tuesdays.toml";
+
+const DEFAULTS: &str = "defaults";
+const COMMAND_LINE: &str = "command line";
+
+// A binary's command-line arguments, loadable from the layers above
+pub trait Config: Parser + Serialize + DeserializeOwned {
+    // Table in the config file and infix of the environment variables
+    const SECTION: &'static str;
+
+    // Checks the types can't express; the message is shown as is
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Runtime probes for --check (GStreamer plugins, a free listen address...);
+    // returns what to report when everything is there
+    fn check(&self) -> Result<String, String> {
+        Ok("Configuration is valid".to_string())
+    }
+
+    // Take over what only the command line can say, e.g. a `#[serde(skip)]`
+    // subcommand, which never reaches the layers
+    fn command_line_only(&mut self, _cli: Self) {}
+}
+
+// Added to every binary's flags
+#[derive(Args, Debug, Default)]
+struct ConfigArgs {
+    /// Configuration file [default: tuesdays.toml, if present]
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print the effective configuration and where each value came from, then exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Check that everything the configuration needs is available, then exit
+    #[arg(long)]
+    check: bool,
+}
+
+// What loading came to: the configuration to run with, or what --print-config or
+// --check has to show instead
+#[derive(Debug)]
+pub enum Loaded<C> {
+    Config(C),
+    Printed(String),
+    Checked(String),
+}
+
+// ✅ Like `Parser::parse`: exits with a message on bad flags or configuration, and
+// once --print-config or --check has shown what it has to
+pub fn load<C: Config>() -> C {
+    match try_load_from(std::env::args_os()) {
+        Ok(Loaded::Config(config)) => config,
+        Ok(Loaded::Printed(config)) => {
+            print!("{}", config);
+            std::process::exit(0);
+        }
+        Ok(Loaded::Checked(report)) => {
+            println!("✅ {}", report);
+            std::process::exit(0);
+        }
+        Err(ConfigError::Cli(err)) => err.exit(),
+        Err(err) => {
+            eprintln!("❌ {}", err);
+            std::process::exit(match err {
+                ConfigError::Check(_) => exit::UNAVAILABLE,
+                _ => exit::CONFIG,
+            });
+        }
+    }
+}
+
+pub fn try_load_from<C, I, T>(args: I) -> Result<Loaded<C>, ConfigError>
+where
+    C: Config,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = ConfigArgs::augment_args(C::command()).try_get_matches_from(args)?;
+    let options = ConfigArgs::from_arg_matches(&matches)?;
+    let cli = C::from_arg_matches(&matches)?;
+
+    let mut secrets = Secrets::default();
+    let figment = layers(&cli, &matches, options.config.as_deref(), &mut secrets)?;
+    let mut config: C = figment.extract()?;
+    config.command_line_only(cli);
+    config.validate().map_err(|message| ConfigError::Invalid {
+        section: C::SECTION,
+        message,
+    })?;
+
+    if options.print_config {
+        return Ok(Loaded::Printed(describe(&config, &figment, &secrets)?));
+    }
+    if options.check {
+        return Ok(Loaded::Checked(config.check().map_err(ConfigError::Check)?));
+    }
+    Ok(Loaded::Config(config))
+}
+
+// ✅ defaults ← file ← environment ← command line
+fn layers<C: Config>(
+    cli: &C,
+    matches: &ArgMatches,
+    file: Option<&Path>,
+    secrets: &mut Secrets,
+) -> Result<Figment, ConfigError> {
+    let defaults = Value::serialize(cli)?
+        .into_dict()
+        .ok_or(ConfigError::NotATable)?;
+
+    // Only flags that were actually typed override the layers below
+    let mut given = defaults.clone();
+    given.retain(|key, _| matches.value_source(key) == Some(ValueSource::CommandLine));
+
+    let file = match file {
+        Some(path) if !path.is_file() => return Err(ConfigError::NotFound(path.to_path_buf())),
+        Some(path) => Some(path.to_path_buf()),
+        None => std::env::var_os("TUESDAYS_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(DEFAULT_FILE)).filter(|path| path.is_file())),
+    };
+    let file = match file {
+        Some(path) => {
+            // Straight from the file, so the values are tagged as this layer's
+            let mut values = match Toml::file_exact(&path)
+                .data()?
+                .remove(&Profile::Default)
+                .and_then(|mut file| file.remove(C::SECTION))
+            {
+                Some(Value::Dict(_, values)) => values,
+                Some(_) => {
+                    let message = format!("`{}` in {} is not a table", C::SECTION, path.display());
+                    return Err(figment::Error::from(message).into());
+                }
+                None => Dict::new(),
+            };
+            *secrets = Secrets::new(&path);
+            secrets.resolve(&mut values)?;
+            Figment::from(Layer::file(path, values))
+        }
+        None => Figment::new(),
+    };
+
+    // Unrelated TUESDAYS_* variables (e.g. the test harness's) are not errors
+    let keys: Vec<String> = defaults.keys().cloned().collect();
+    let env = Env::prefixed(&env_prefix::<C>())
+        .filter(move |key| keys.iter().any(|known| key == known.as_str()));
+
+    Ok(Figment::from(Layer::new(DEFAULTS, defaults))
+        .merge(file)
+        .merge(env)
+        .merge(Layer::new(COMMAND_LINE, given)))
+}
+
+// ✅ The effective configuration as a TOML table, annotated with each value's origin;
+// secrets only with theirs
+fn describe<C: Config>(
+    config: &C,
+    figment: &Figment,
+    secrets: &Secrets,
+) -> Result<String, ConfigError> {
+    let table = toml::to_string(&BTreeMap::from([(C::SECTION, config)]))?;
+    let mut out = String::new();
+    for line in table.lines() {
+        if let Some((key, _)) = line.split_once(" = ")
+            && let Some(origin) = secrets.origin(key)
+            && figment
+                .find_metadata(key)
+                .is_some_and(|m| m.source.is_some())
+        {
+            out.push_str(&format!("{} = \"<redacted>\"  # {}\n", key, origin));
+            continue;
+        }
+        out.push_str(line);
+        if let Some((key, _)) = line.split_once(" = ")
+            && let Some(origin) = origin::<C>(figment, key)
+        {
+            out.push_str("  # ");
+            out.push_str(&origin);
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn origin<C: Config>(figment: &Figment, key: &str) -> Option<String> {
+    let metadata = figment.find_metadata(key)?;
+    match &metadata.source {
+        Some(Source::File(path)) => Some(path.display().to_string()),
+        _ if metadata.name == DEFAULTS => None,
+        _ if metadata.name == COMMAND_LINE => Some(COMMAND_LINE.to_string()),
+        _ => Some(env_var::<C>(key)),
+    }
+}
+
+fn env_prefix<C: Config>() -> String {
+    format!("TUESDAYS_{}_", C::SECTION.to_uppercase())
+}
+
+fn env_var<C: Config>(key: &str) -> String {
+    format!("{}{}", env_prefix::<C>(), key.to_uppercase())
+}
+
+// A fixed set of values, named for --print-config and error messages
+struct Layer {
+    metadata: Metadata,
+    values: Dict,
+}
+
+impl Layer {
+    fn new(name: &'static str, values: Dict) -> Self {
+        Layer {
+            metadata: Metadata::named(name),
+            values,
+        }
+    }
+
+    // The configuration file's values, once its secrets are read
+    fn file(path: PathBuf, values: Dict) -> Self {
+        Layer {
+            metadata: Metadata::from("TOML file", Source::File(path)),
+            values,
+        }
+    }
+}
+
+impl Provider for Layer {
+    fn metadata(&self) -> Metadata {
+        self.metadata.clone()
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Ok(Profile::Default.collect(self.values.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Parser, Serialize, Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    pub(crate) struct Layered {
+        #[arg(long, default_value = "default")]
+        from_defaults: String,
+        #[arg(long, default_value = "default")]
+        from_file: String,
+        #[arg(long, default_value = "default")]
+        from_env: String,
+        #[arg(long, default_value = "default")]
+        from_cli: String,
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+    }
+
+    impl Config for Layered {
+        const SECTION: &'static str = "layered";
+
+        fn validate(&self) -> Result<(), String> {
+            match self.count {
+                0 => Err("count must be at least 1".to_string()),
+                _ => Ok(()),
+            }
+        }
+
+        fn check(&self) -> Result<String, String> {
+            Ok(format!("{} things", self.count))
+        }
+    }
+
+    // A configuration file of its own, for one test
+    pub(crate) fn config_file(name: &str, contents: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tuesdays-config-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DEFAULT_FILE);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub(crate) fn load<C: Config>(file: &Path, flags: &[&str]) -> Result<Loaded<C>, ConfigError> {
+        let config = file.display().to_string();
+        let args = ["test", "--config", &config]
+            .into_iter()
+            .chain(flags.iter().copied());
+        try_load_from(args)
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let file = config_file(
+            "layers",
+            "[layered]\nfrom_file = \"file\"\nfrom_env = \"file\"\nfrom_cli = \"file\"\n",
+        );
+        // SAFETY: no other test reads or writes these variables
+        unsafe {
+            std::env::set_var("TUESDAYS_LAYERED_FROM_ENV", "env");
+            std::env::set_var("TUESDAYS_LAYERED_FROM_CLI", "env");
+        }
+
+        let loaded = load::<Layered>(&file, &["--from-cli", "cli"]).unwrap();
+
+        let Loaded::Config(config) = loaded else {
+            panic!("expected a configuration, got {:?}", loaded);
+        };
+        assert_eq!(config.from_defaults, "default");
+        assert_eq!(config.from_file, "file");
+        assert_eq!(config.from_env, "env");
+        assert_eq!(config.from_cli, "cli");
+    }
+
+    #[test]
+    fn print_config_says_where_each_value_came_from() {
+        let file = config_file("print", "[layered]\nfrom_file = \"file\"\n");
+
+        let loaded = load::<Layered>(&file, &["--from-cli", "cli", "--print-config"]).unwrap();
+
+        let Loaded::Printed(printed) = loaded else {
+            panic!("expected the printed configuration, got {:?}", loaded);
+        };
+        assert!(printed.starts_with("[layered]\n"));
+        assert!(printed.contains("from_defaults = \"default\"\n"));
+        assert!(printed.contains(&format!("from_file = \"file\"  # {}\n", file.display())));
+        assert!(printed.contains("from_cli = \"cli\"  # command line\n"));
+    }
+
+    #[test]
+    fn check_reports_what_it_found() {
+        let file = config_file("check", "[layered]\ncount = 3\n");
+
+        let loaded = load::<Layered>(&file, &["--check"]).unwrap();
+
+        assert!(matches!(loaded, Loaded::Checked(report) if report == "3 things"));
+    }
+
+    #[test]
+    fn bad_configuration_is_refused() {
+        let file = config_file("invalid", "[layered]\ncount = 0\n");
+        assert!(matches!(
+            load::<Layered>(&file, &[]),
+            Err(ConfigError::Invalid {
+                section: "layered",
+                ..
+            })
+        ));
+
+        let file = config_file("unknown", "[layered]\ncolour = \"red\"\n");
+        assert!(matches!(
+            load::<Layered>(&file, &[]),
+            Err(ConfigError::Layer(_))
+        ));
+
+        let missing = std::env::temp_dir().join("tuesdays-config-missing.toml");
+        assert!(matches!(
+            load::<Layered>(&missing, &[]),
+            Err(ConfigError::NotFound(_))
+        ));
+    }
+}
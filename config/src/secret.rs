@@ -0,0 +1,346 @@
+// Secrets in the configuration file: instead of a plaintext value, a key can say
+// where its value is kept, and it's read when the configuration is loaded:
+//
+//   [streamer]
+//   server = { env = "CAMERA_SERVER_URL" }            # an environment variable
+//   server = { file = "/run/secrets/server_url" }     # a file only its owner may read
+//   server = { age = "secrets.age", key = "server" }  # a key of an age-encrypted TOML file
+//   server = { age = "server_url.age" }               # a whole age-encrypted file
+//
+// Relative paths are relative to the configuration file. age files are decrypted
+// with the `age` command and the identity file in TUESDAYS_AGE_IDENTITY, once per
+// load however many keys they hold. --print-config shows where each secret came
+// from, never its value.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use figment::value::{Dict, Value};
+
+use crate::ConfigError;
+
+pub const AGE_IDENTITY: &str = "TUESDAYS_AGE_IDENTITY";
+
+// Where one key's value is kept
+enum Reference {
+    Env(String),
+    File(PathBuf),
+    Age { path: PathBuf, key: Option<String> },
+}
+
+// The secrets of one load: where each came from, and the age files decrypted so far
+#[derive(Default)]
+pub(crate) struct Secrets {
+    base: PathBuf,
+    origins: HashMap<String, String>,
+    decrypted: HashMap<PathBuf, String>,
+}
+
+impl Secrets {
+    // Secrets referenced from `config_file`
+    pub fn new(config_file: &Path) -> Self {
+        Secrets {
+            base: config_file
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            ..Secrets::default()
+        }
+    }
+
+    // ✅ Replace every reference among `values` with the secret it names
+    pub fn resolve(&mut self, values: &mut Dict) -> Result<(), ConfigError> {
+        for (key, value) in values.iter_mut() {
+            let Some(reference) = self.reference(value) else {
+                continue;
+            };
+            let (secret, origin) = self
+                .read(reference)
+                .map_err(|message| ConfigError::Secret {
+                    key: key.clone(),
+                    message,
+                })?;
+            *value = Value::from(secret);
+            self.origins.insert(key.clone(), origin);
+        }
+        Ok(())
+    }
+
+    // Where a key's secret came from, if it is one
+    pub fn origin(&self, key: &str) -> Option<&str> {
+        self.origins.get(key).map(String::as_str)
+    }
+
+    fn reference(&self, value: &Value) -> Option<Reference> {
+        let Value::Dict(_, dict) = value else {
+            return None;
+        };
+        let text = |name: &str| dict.get(name).and_then(Value::as_str);
+        match (
+            text("env"),
+            text("file"),
+            text("age"),
+            text("key"),
+            dict.len(),
+        ) {
+            (Some(name), None, None, None, 1) => Some(Reference::Env(name.to_string())),
+            (None, Some(path), None, None, 1) => Some(Reference::File(self.base.join(path))),
+            (None, None, Some(path), key, len) if len == 1 + key.is_some() as usize => {
+                Some(Reference::Age {
+                    path: self.base.join(path),
+                    key: key.map(String::from),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // ✅ The secret, and where it came from
+    fn read(&mut self, reference: Reference) -> Result<(String, String), String> {
+        match reference {
+            Reference::Env(name) => {
+                let secret = std::env::var(&name)
+                    .map_err(|_| format!("environment variable {} is not set", name))?;
+                Ok((secret, format!("secret from ${}", name)))
+            }
+            Reference::File(path) => {
+                ensure_private(&path)?;
+                let secret = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+                let secret = secret.trim_end_matches(['\r', '\n']).to_string();
+                Ok((secret, format!("secret from {}", path.display())))
+            }
+            Reference::Age { path, key } => {
+                if !self.decrypted.contains_key(&path) {
+                    let plaintext = decrypt(&path)?;
+                    self.decrypted.insert(path.clone(), plaintext);
+                }
+                let plaintext = &self.decrypted[&path];
+                let Some(key) = key else {
+                    let secret = plaintext.trim_end_matches(['\r', '\n']).to_string();
+                    return Ok((secret, format!("secret from {}", path.display())));
+                };
+                let table: toml::Table = plaintext.parse().map_err(|err| {
+                    format!("{} is not TOML once decrypted: {}", path.display(), err)
+                })?;
+                let secret = table
+                    .get(&key)
+                    .and_then(toml::Value::as_str)
+                    .ok_or_else(|| format!("{} has no string '{}'", path.display(), key))?;
+                Ok((
+                    secret.to_string(),
+                    format!("secret '{}' from {}", key, path.display()),
+                ))
+            }
+        }
+    }
+}
+
+// Secrets kept in plain files must be the owner's alone
+#[cfg(unix)]
+fn ensure_private(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)
+        .map_err(|err| format!("cannot read {}: {}", path.display(), err))?
+        .permissions()
+        .mode();
+    if mode & 0o077 != 0 {
+        return Err(format!(
+            "{} may be read by others (mode {:o}); chmod 600 it",
+            path.display(),
+            mode & 0o777
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn ensure_private(_: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+// ✅ Decrypt an age file with the `age` command
+fn decrypt(path: &Path) -> Result<String, String> {
+    let identity = std::env::var_os(AGE_IDENTITY).ok_or_else(|| {
+        format!(
+            "set {} to the age identity file that decrypts {}",
+            AGE_IDENTITY,
+            path.display()
+        )
+    })?;
+    let output = Command::new("age")
+        .arg("--decrypt")
+        .arg("--identity")
+        .arg(identity)
+        .arg(path)
+        .output()
+        .map_err(|err| format!("cannot run age (is it installed?): {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "age cannot decrypt {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} is not text", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Loaded;
+    use crate::tests::{Layered, config_file, load};
+    use figment::providers::{Format, Toml};
+    use figment::{Profile, Provider};
+
+    fn values(toml: &str) -> Dict {
+        Toml::string(toml)
+            .data()
+            .unwrap()
+            .remove(&Profile::Default)
+            .unwrap()
+    }
+
+    // ✅ A secrets file next to a configuration file, readable as `mode` says
+    #[cfg(unix)]
+    fn secret_file(name: &str, mode: u32) -> (Secrets, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let config = config_file(name, "");
+        let path = config.with_file_name("secret");
+        std::fs::write(&path, "hunter2\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        (Secrets::new(&config), path)
+    }
+
+    #[test]
+    fn env_references_are_read_from_the_environment() {
+        // SAFETY: no other test reads or writes these variables
+        unsafe {
+            std::env::set_var("TUESDAYS_TEST_SECRET", "hunter2");
+            std::env::remove_var("TUESDAYS_TEST_UNSET");
+        }
+        let mut secrets = Secrets::default();
+        let mut found = values(r#"password = { env = "TUESDAYS_TEST_SECRET" }"#);
+
+        secrets.resolve(&mut found).unwrap();
+
+        assert_eq!(found["password"].as_str(), Some("hunter2"));
+        assert_eq!(
+            secrets.origin("password"),
+            Some("secret from $TUESDAYS_TEST_SECRET")
+        );
+
+        let mut missing = values(r#"password = { env = "TUESDAYS_TEST_UNSET" }"#);
+        let err = secrets.resolve(&mut missing).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot read the secret for `password`: \
+             environment variable TUESDAYS_TEST_UNSET is not set"
+        );
+    }
+
+    #[test]
+    fn plain_values_and_other_tables_are_left_alone() {
+        let mut secrets = Secrets::default();
+        let mut found = values(
+            r#"
+            server = "ws://localhost:8080"
+            limits = { env = "A", key = "b" }
+            "#,
+        );
+
+        secrets.resolve(&mut found).unwrap();
+
+        assert_eq!(found["server"].as_str(), Some("ws://localhost:8080"));
+        assert!(found["limits"].as_dict().is_some());
+        assert_eq!(secrets.origin("server"), None);
+        assert_eq!(secrets.origin("limits"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_references_are_read_relative_to_the_config_file() {
+        let (mut secrets, path) = secret_file("secret-private", 0o600);
+        let mut found = values(r#"password = { file = "secret" }"#);
+
+        secrets.resolve(&mut found).unwrap();
+
+        assert_eq!(found["password"].as_str(), Some("hunter2"));
+        let origin = format!("secret from {}", path.display());
+        assert_eq!(secrets.origin("password"), Some(origin.as_str()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_references_others_may_read_are_refused() {
+        let (mut secrets, path) = secret_file("secret-shared", 0o644);
+        let mut found = values(r#"password = { file = "secret" }"#);
+
+        let err = secrets.resolve(&mut found).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            format!(
+                "Cannot read the secret for `password`: \
+                 {} may be read by others (mode 644); chmod 600 it",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn age_references_need_the_age_command() {
+        let mut secrets = Secrets::default();
+        let mut found = values(r#"password = { age = "secrets.age", key = "password" }"#);
+        let empty = std::env::temp_dir().join(format!("tuesdays-no-age-{}", std::process::id()));
+        std::fs::create_dir_all(&empty).unwrap();
+        let path = std::env::var_os("PATH");
+        // SAFETY: no other test reads or writes these variables, nor runs commands
+        unsafe {
+            std::env::set_var(AGE_IDENTITY, "identity.txt");
+            std::env::set_var("PATH", &empty);
+        }
+
+        let err = secrets.resolve(&mut found).unwrap_err().to_string();
+
+        // SAFETY: as above
+        unsafe {
+            match path {
+                Some(path) => std::env::set_var("PATH", path),
+                None => std::env::remove_var("PATH"),
+            }
+        }
+        assert!(
+            err.starts_with(
+                "Cannot read the secret for `password`: cannot run age (is it installed?): "
+            ),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn printed_configuration_redacts_secrets() {
+        let (_, secret) = secret_file("secret-print", 0o600);
+        let config = config_file(
+            "secret-print",
+            "[layered]\nfrom_file = { file = \"secret\" }\nfrom_env = \"plain\"\n",
+        );
+
+        let loaded = load::<Layered>(&config, &["--print-config"]).unwrap();
+
+        let Loaded::Printed(printed) = loaded else {
+            panic!("expected the printed configuration, got {:?}", loaded);
+        };
+        assert!(!printed.contains("hunter2"), "{}", printed);
+        assert!(printed.contains(&format!(
+            "from_file = \"<redacted>\"  # secret from {}\n",
+            secret.display()
+        )));
+        assert!(printed.contains(&format!("from_env = \"plain\"  # {}\n", config.display())));
+    }
+}
@@ -0,0 +1,252 @@
+// Replays a signaling capture (transmitter --capture-dir) against a server, to
+// reproduce a failed handshake offline:
+//
+//   replay captures/cam1-<session>.jsonl              # in-process test server
+//   replay captures/cam1-<session>.jsonl --server ws://127.0.0.1:9001
+//
+// Every captured member connects with its original request and sends what it
+// sent, at the captured pace. Captures don't keep tokens or stream keys, so
+// --server wants a transmitter that doesn't check them. What the server answers is printed as it comes
+// and compared with what it answered back then.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use serde_json::Value;
+use tuesdays_integration::TestServer;
+use tuesdays_protocol::{CaptureEvent, CaptureRecord};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+// How long to keep listening after the last captured frame
+const SETTLE: Duration = Duration::from_secs(1);
+const POLL: Duration = Duration::from_millis(10);
+
+#[derive(Parser)]
+#[command(about = "Replay a captured signaling session against a server")]
+struct Args {
+    /// Capture file (JSON Lines) written by transmitter --capture-dir
+    capture: PathBuf,
+
+    /// Transmitter to replay against, instead of an in-process test server
+    #[arg(long)]
+    server: Option<String>,
+
+    /// Replay speed; 2 is twice as fast as captured, 0 sends without pausing
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+}
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+// One captured member, replayed
+#[derive(Default)]
+struct Replayed {
+    socket: Option<Socket>,
+    expected: Vec<String>,
+    received: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match replay(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(2),
+        Err(err) => {
+            eprintln!("❌ {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ✅ Replay the capture; true when every member got what it got back then
+fn replay(args: &Args) -> io::Result<bool> {
+    let records = read_capture(&args.capture)?;
+    let _server;
+    let url = match &args.server {
+        Some(url) => url.trim_end_matches('/').to_string(),
+        None => {
+            let server = TestServer::start().map_err(io::Error::other)?;
+            let url = server.ws_url();
+            _server = server;
+            url
+        }
+    };
+    println!(
+        "🎞️ Replaying {} frames from {} against {}",
+        records.len(),
+        args.capture.display(),
+        url
+    );
+
+    let mut members: HashMap<String, Replayed> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let started = Instant::now();
+    for record in records {
+        let due = if args.speed > 0.0 {
+            Duration::from_secs_f64(record.at_ms as f64 / 1000.0 / args.speed)
+        } else {
+            Duration::ZERO
+        };
+        drain_until(&mut members, started + due);
+
+        if !members.contains_key(&record.member_id) {
+            order.push(record.member_id.clone());
+        }
+        let member = members.entry(record.member_id.clone()).or_default();
+        match record.event {
+            CaptureEvent::Joined { request } => {
+                println!("➕ {} joins: {}", record.member_id, request);
+                match connect(&format!("{}{}", url, request)) {
+                    Ok(socket) => member.socket = Some(socket),
+                    Err(err) => println!("❌ {} cannot join: {}", record.member_id, err),
+                }
+            }
+            CaptureEvent::Received { text } => {
+                println!("⬆️ {}: {}", record.member_id, text);
+                if let Some(socket) = &mut member.socket
+                    && let Err(err) = socket.send(Message::text(text))
+                {
+                    println!("❌ {} cannot send: {}", record.member_id, err);
+                    member.socket = None;
+                }
+            }
+            CaptureEvent::Sent { text } => member.expected.push(text),
+            CaptureEvent::Left { reason } => {
+                println!("➖ {} leaves ({})", record.member_id, reason);
+                drain(&record.member_id, member);
+                if let Some(mut socket) = member.socket.take() {
+                    let _ = socket.close(None);
+                    let _ = socket.flush();
+                }
+            }
+        }
+    }
+    drain_until(&mut members, Instant::now() + SETTLE);
+
+    let mut same = true;
+    for member_id in &order {
+        same &= compare(member_id, &members[member_id]);
+    }
+    Ok(same)
+}
+
+fn read_capture(path: &PathBuf) -> io::Result<Vec<CaptureRecord>> {
+    let file = File::open(path)?;
+    let mut records = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|err| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), number + 1, err),
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn connect(url: &str) -> io::Result<Socket> {
+    let (socket, _) = tungstenite::connect(url).map_err(io::Error::other)?;
+    // Poll every member from one thread
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream.set_read_timeout(Some(POLL))?;
+    }
+    Ok(socket)
+}
+
+// ✅ Print what the server sends every member until `deadline`
+fn drain_until(members: &mut HashMap<String, Replayed>, deadline: Instant) {
+    loop {
+        for (member_id, member) in members.iter_mut() {
+            drain(member_id, member);
+        }
+        if Instant::now() >= deadline {
+            return;
+        }
+        thread::sleep(POLL.min(deadline.saturating_duration_since(Instant::now())));
+    }
+}
+
+fn drain(member_id: &str, member: &mut Replayed) {
+    let Some(socket) = &mut member.socket else {
+        return;
+    };
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                println!("⬇️ {}: {}", member_id, text);
+                member.received.push(text.to_string());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                return;
+            }
+            Err(err) => {
+                println!("🔌 {} disconnected: {}", member_id, err);
+                member.socket = None;
+                return;
+            }
+        }
+    }
+}
+
+// ✅ Report the replies a member got that weren't captured, and the other way round
+fn compare(member_id: &str, member: &Replayed) -> bool {
+    let mut missing: Vec<String> = member.expected.iter().map(|t| comparable(t)).collect();
+    let mut unexpected = Vec::new();
+    for text in &member.received {
+        let text = comparable(text);
+        match missing.iter().position(|expected| *expected == text) {
+            Some(index) => {
+                missing.remove(index);
+            }
+            None => unexpected.push(text),
+        }
+    }
+    if missing.is_empty() && unexpected.is_empty() {
+        println!(
+            "✅ {}: {} frames as captured",
+            member_id,
+            member.received.len()
+        );
+        return true;
+    }
+    println!(
+        "⚠️ {}: captured {} frames, got {}",
+        member_id,
+        member.expected.len(),
+        member.received.len()
+    );
+    for text in missing {
+        println!("   - {}", text);
+    }
+    for text in unexpected {
+        println!("   + {}", text);
+    }
+    false
+}
+
+// Session ids are minted anew on every run; compare frames without them
+fn comparable(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(mut object)) if object.contains_key("session_id") => {
+            object.insert("session_id".to_string(), Value::from("…"));
+            Value::Object(object).to_string()
+        }
+        _ => text.to_string(),
+    }
+}
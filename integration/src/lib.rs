@@ -0,0 +1,408 @@
+// End-to-end test harness: an in-process signaling server on an ephemeral port,
+// WebSocket clients to talk to it as streamers and watchers would, plus helpers
+// to build and run the streamer/watcher binaries against it.
+//
+// The binaries are built from the sibling crates on first use; point
+// TUESDAYS_STREAMER_BIN / TUESDAYS_WATCHER_BIN at prebuilt ones to skip that.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServerHandle;
+use actix_web::{App, HttpServer};
+use serde_json::Value;
+use transmitter::SignalingServer;
+use tuesdays_protocol::version::VERSIONS_HEADER;
+use tuesdays_protocol::{StreamInfo, StreamState};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::{HeaderMap, HeaderName, HeaderValue};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+// How long a client waits for a message it expects
+const EXPECT_TIMEOUT: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, thiserror::Error)]
+pub enum HarnessError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid /streams response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Signaling server did not start")]
+    ServerStart,
+    #[error("Unexpected response: {0}")]
+    Http(String),
+    #[error("WebSocket: {0}")]
+    WebSocket(#[from] Box<tungstenite::Error>),
+    #[error("Cannot start {binary}: {source}")]
+    Spawn {
+        binary: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Building {name} failed ({status})")]
+    Build { name: String, status: ExitStatus },
+    #[error("{0} within {1:?}")]
+    Timeout(String, Duration),
+}
+
+// Transmitter routes served from a background actix system
+pub struct TestServer {
+    pub port: u16,
+    handle: ServerHandle,
+    thread: Option<thread::JoinHandle<std::io::Result<()>>>,
+}
+
+impl TestServer {
+    // ✅ Bind 127.0.0.1:0 and wait until the server accepts connections
+    pub fn start() -> Result<Self, HarnessError> {
+        TestServer::start_with(SignalingServer::new)
+    }
+
+    // ✅ The same, serving the routes of a server of the test's own: `server` is
+    // called inside the server's actix System, which `SignalingServer` wants
+    pub fn start_with<F>(server: F) -> Result<Self, HarnessError>
+    where
+        F: FnOnce() -> SignalingServer + Send + 'static,
+    {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                let signaling = server();
+                let server = HttpServer::new(move || {
+                    let signaling = signaling.clone();
+                    App::new().configure(move |cfg| signaling.configure(cfg))
+                })
+                .workers(1)
+                .bind(("127.0.0.1", 0))?;
+                let port = server.addrs()[0].port();
+                let server = server.run();
+                let _ = ready_tx.send((port, server.handle()));
+                server.await
+            })
+        });
+
+        let (port, handle) = ready_rx
+            .recv_timeout(Duration::from_secs(10))
+            .map_err(|_| HarnessError::ServerStart)?;
+        Ok(TestServer {
+            port,
+            handle,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("ws://127.0.0.1:{}", self.port)
+    }
+
+    // ✅ GET /streams
+    pub fn streams(&self) -> Result<Vec<StreamInfo>, HarnessError> {
+        match self.request("GET", "/streams", &[])? {
+            (200, body) => Ok(serde_json::from_str(&body)?),
+            (status, body) => Err(HarnessError::Http(format!("{} {}", status, body))),
+        }
+    }
+
+    // ✅ An HTTP request with `headers`, e.g. ("Authorization", "Bearer ..."):
+    // the response's status and body
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<(u16, String), HarnessError> {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        write!(
+            stream,
+            "{} {} HTTP/1.0\r\nHost: 127.0.0.1:{}\r\n",
+            method, path, self.port
+        )?;
+        for (name, value) in headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| HarnessError::Http(response.clone()))?;
+        let status = head
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| HarnessError::Http(head.to_string()))?;
+        Ok((status, body.to_string()))
+    }
+
+    // ✅ Connect to a WebSocket route, e.g. `/watcher?streamer_id=cam1&id=w1`
+    pub fn connect(&self, path: &str) -> Result<Client, HarnessError> {
+        self.upgrade(path, &[])?
+            .map_err(|refusal| HarnessError::Http(format!("{:?}", refusal)))
+    }
+
+    // ✅ Try to connect with `headers` (Origin, Authorization, ...); the server
+    // may accept the upgrade or refuse it
+    pub fn upgrade(
+        &self,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Result<Client, Refusal>, HarnessError> {
+        let mut request = format!("{}{}", self.ws_url(), path)
+            .into_client_request()
+            .map_err(Box::new)?;
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| HarnessError::Http(err.to_string()))?;
+            let value =
+                HeaderValue::from_str(value).map_err(|err| HarnessError::Http(err.to_string()))?;
+            request.headers_mut().append(name, value);
+        }
+        match tungstenite::connect(request) {
+            Ok((socket, response)) => {
+                if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+                    stream.set_read_timeout(Some(POLL))?;
+                }
+                Ok(Ok(Client {
+                    socket,
+                    versions: versions(response.headers()),
+                }))
+            }
+            Err(tungstenite::Error::Http(response)) => Ok(Err(Refusal {
+                status: response.status().as_u16(),
+                versions: versions(response.headers()),
+                error: serde_json::from_slice(response.body().as_deref().unwrap_or_default())?,
+            })),
+            Err(err) => Err(Box::new(err).into()),
+        }
+    }
+
+    // ✅ Poll the directory until `id` shows up as a live stream
+    pub fn wait_for_stream(&self, id: &str, timeout: Duration) -> Result<(), HarnessError> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if self
+                .streams()?
+                .iter()
+                .any(|stream| stream.id == id && stream.state == StreamState::Live)
+            {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(200));
+        }
+        Err(HarnessError::Timeout(
+            format!("Stream '{}' did not appear", id),
+            timeout,
+        ))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        actix_web::rt::System::new().block_on(self.handle.stop(false));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn versions(headers: &HeaderMap) -> Option<String> {
+    let versions = headers.get(VERSIONS_HEADER)?;
+    versions.to_str().ok().map(str::to_string)
+}
+
+// An upgrade the server turned down
+#[derive(Debug)]
+pub struct Refusal {
+    pub status: u16,
+    // Its Tuesdays-Protocol-Versions header
+    pub versions: Option<String>,
+    // The body, `{"error":{"code":...}}`
+    pub error: Value,
+}
+
+impl Refusal {
+    // The error's `code`, e.g. "unauthorized"
+    pub fn code(&self) -> &str {
+        self.error["error"]["code"].as_str().unwrap_or_default()
+    }
+}
+
+// A WebSocket connection to the test server, as a streamer, watcher or member
+pub struct Client {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    // The upgrade's Tuesdays-Protocol-Versions header
+    pub versions: Option<String>,
+}
+
+impl Client {
+    // ✅ Send a command, e.g. `json!({"command": "list"})`
+    pub fn send(&mut self, command: Value) -> Result<(), HarnessError> {
+        self.send_text(&command.to_string())
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<(), HarnessError> {
+        self.socket.send(Message::text(text)).map_err(Box::new)?;
+        Ok(())
+    }
+
+    // ✅ The next message within `timeout`, None if none came: JSON as it was
+    // sent, plain text (the greeting) as a JSON string
+    pub fn next(&mut self, timeout: Duration) -> Result<Option<Value>, HarnessError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => {
+                    return Ok(Some(
+                        serde_json::from_str(&text).unwrap_or_else(|_| Value::from(text.as_str())),
+                    ));
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(err))
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if Instant::now() >= deadline {
+                        return Ok(None);
+                    }
+                }
+                Err(err) => return Err(Box::new(err).into()),
+            }
+        }
+    }
+
+    // ✅ Read until a message matching `wanted` arrives, and return it
+    pub fn expect(
+        &mut self,
+        what: &str,
+        wanted: impl Fn(&Value) -> bool,
+    ) -> Result<Value, HarnessError> {
+        let deadline = Instant::now() + EXPECT_TIMEOUT;
+        while let Some(message) = self.next(deadline.saturating_duration_since(Instant::now()))? {
+            if wanted(&message) {
+                return Ok(message);
+            }
+        }
+        Err(HarnessError::Timeout(
+            format!("No {}", what),
+            EXPECT_TIMEOUT,
+        ))
+    }
+
+    // ✅ Read until a signal of this `type` arrives
+    pub fn expect_signal(&mut self, kind: &str) -> Result<Value, HarnessError> {
+        self.expect(&format!("'{}' signal", kind), |message| {
+            message["type"] == kind
+        })
+    }
+
+    // ✅ Read until an error with this `code` arrives
+    pub fn expect_error(&mut self, code: &str) -> Result<Value, HarnessError> {
+        self.expect(&format!("'{}' error", code), |message| {
+            message["error"]["code"] == code
+        })
+    }
+
+    // ✅ Everything that arrives within `timeout`
+    pub fn drain(&mut self, timeout: Duration) -> Result<Vec<Value>, HarnessError> {
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::new();
+        while let Some(message) = self.next(deadline.saturating_duration_since(Instant::now()))? {
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    // ✅ Whether the server closes the connection within `timeout`, whatever it
+    // sends first
+    pub fn closed(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.next(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Some(_)) => {}
+                Ok(None) => return false,
+                Err(_) => return true,
+            }
+        }
+    }
+}
+
+// A child process that is killed when the test is done with it
+pub struct Process {
+    name: String,
+    child: Child,
+}
+
+impl Process {
+    pub fn spawn(name: &str, binary: &Path, args: &[&str]) -> Result<Self, HarnessError> {
+        let child = Command::new(binary)
+            .args(args)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|source| HarnessError::Spawn {
+                binary: binary.to_path_buf(),
+                source,
+            })?;
+        Ok(Process {
+            name: name.to_string(),
+            child,
+        })
+    }
+
+    // ✅ Wait for the process to exit on its own, killing it after `timeout`
+    pub fn wait(mut self, timeout: Duration) -> Result<ExitStatus, HarnessError> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Err(HarnessError::Timeout(
+            format!("{} did not finish", self.name),
+            timeout,
+        ))
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ✅ Path to a workspace binary (streamer, watcher), building it if needed
+pub fn binary(name: &str) -> Result<PathBuf, HarnessError> {
+    let env_var = format!("TUESDAYS_{}_BIN", name.to_uppercase());
+    if let Ok(path) = std::env::var(&env_var) {
+        return Ok(PathBuf::from(path));
+    }
+
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let status = Command::new(env!("CARGO"))
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(workspace.join(name).join("Cargo.toml"))
+        .status()?;
+    if !status.success() {
+        return Err(HarnessError::Build {
+            name: name.to_string(),
+            status,
+        });
+    }
+    // The crates share the workspace's target directory unless it's moved
+    let target = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| workspace.join("target"));
+    Ok(target.join("debug").join(name))
+}
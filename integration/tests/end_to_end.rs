@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use tuesdays_integration::{Process, TestServer, binary};
+
+#[test]
+fn test_server_starts_with_an_empty_directory() {
+    let server = TestServer::start().expect("signaling server");
+    assert!(server.streams().expect("GET /streams").is_empty());
+}
+
+// ✅ streamer (videotestsrc) → transmitter → headless watcher, asserting frames
+// arrive and their watermarks come through in order, none dropped, in good time
+#[test]
+#[ignore = "needs GStreamer with the vpx plugins; run with `cargo test -- --ignored`"]
+fn frames_flow_from_streamer_to_watcher() {
+    let server = TestServer::start().expect("signaling server");
+    let url = server.ws_url();
+
+    let streamer = binary("streamer").expect("streamer binary");
+    let watcher = binary("watcher").expect("watcher binary");
+
+    let _streamer = Process::spawn(
+        "streamer",
+        &streamer,
+        &[
+            "--server",
+            &url,
+            "--id",
+            "e2e-cam",
+            "--source",
+            "test",
+            "--watermark",
+        ],
+    )
+    .expect("start streamer");
+    server
+        .wait_for_stream("e2e-cam", Duration::from_secs(30))
+        .expect("streamer registered");
+
+    let status = Process::spawn(
+        "watcher",
+        &watcher,
+        &[
+            "e2e-cam",
+            "--server",
+            &url,
+            "--id",
+            "e2e-watcher",
+            "--headless",
+            "--assert-frames",
+            "--duration",
+            "5",
+            "--min-fps",
+            "5",
+            "--assert-watermark",
+            "--max-latency-ms",
+            "1000",
+            "--no-reconnect",
+        ],
+    )
+    .expect("start watcher")
+    .wait(Duration::from_secs(90))
+    .expect("watcher finished");
+
+    assert!(
+        status.success(),
+        "watcher frame or watermark check failed: {}",
+        status
+    );
+}
@@ -0,0 +1,527 @@
+// Signaling over real sockets, against the in-process test server: no GStreamer,
+// so these run with every `cargo test`.
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::hmac;
+use serde_json::{Value, json};
+use transmitter::{AdminToken, AllowedOrigin, JwtAuth, Origins, SignalingConfig, SignalingServer};
+use tuesdays_integration::{Client, TestServer};
+use tuesdays_protocol::{Deprecation, PROTOCOL_VERSION};
+
+const SECRET: &[u8] = b"integration-secret";
+const ADMIN_TOKEN: &str = "integration-admin-token";
+// How long to listen for something that shouldn't arrive
+const QUIET: Duration = Duration::from_millis(300);
+
+fn streamer(server: &TestServer, id: &str) -> Client {
+    let mut streamer = server
+        .connect(&format!("/streamer?id={}", id))
+        .expect("streamer connects");
+    streamer.expect_signal("session").expect("streamer session");
+    streamer
+}
+
+fn watcher(server: &TestServer, streamer_id: &str, id: &str) -> Client {
+    let mut watcher = server
+        .connect(&format!("/watcher?streamer_id={}&id={}", streamer_id, id))
+        .expect("watcher connects");
+    watcher.expect_signal("session").expect("watcher session");
+    watcher
+}
+
+// ✅ An HS256 token with these claims, as an identity provider would sign it
+fn token(claims: Value) -> String {
+    signed(claims, SECRET)
+}
+
+fn signed(claims: Value, secret: &[u8]) -> String {
+    let header = URL_SAFE_NO_PAD.encode(json!({"alg": "HS256", "typ": "JWT"}).to_string());
+    let claims = URL_SAFE_NO_PAD.encode(claims.to_string());
+    let signed = format!("{}.{}", header, claims);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let signature = URL_SAFE_NO_PAD.encode(hmac::sign(&key, signed.as_bytes()));
+    format!("{}.{}", signed, signature)
+}
+
+fn in_an_hour() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    now.as_secs() + 3600
+}
+
+#[test]
+fn watchers_join_hear_broadcasts_and_leave() {
+    let server = TestServer::start().expect("signaling server");
+    let mut cam = streamer(&server, "cam1");
+    server
+        .wait_for_stream("cam1", Duration::from_secs(5))
+        .expect("cam1 in the directory");
+
+    let mut w1 = watcher(&server, "cam1", "w1");
+    let joined = cam.expect_signal("watcher_joined").unwrap();
+    assert_eq!(joined["watcher_id"], "w1");
+    let audience = cam.expect("audience of one", |message| {
+        message["type"] == "audience" && message["watchers"] == 1
+    });
+    assert!(audience.is_ok());
+
+    // Relayed verbatim to everyone, the sender included
+    let note = json!({"type": "note", "text": "hello"});
+    cam.send(json!({"command": "broadcast", "message": note.to_string()}))
+        .unwrap();
+    // (and stamped with the room's session)
+    let session_id = cam.expect_signal("note").unwrap()["session_id"].clone();
+    assert!(session_id.is_string());
+    assert_eq!(
+        w1.expect_signal("note").unwrap(),
+        json!({"type": "note", "text": "hello", "session_id": session_id})
+    );
+
+    // Only to the one watcher
+    let mut w2 = watcher(&server, "cam1", "w2");
+    let private = json!({"type": "private", "text": "just you"});
+    cam.send(json!({"command": "send", "to": "w2", "message": private.to_string()}))
+        .unwrap();
+    assert_eq!(w2.expect_signal("private").unwrap()["text"], "just you");
+    let heard = w1.drain(QUIET).unwrap();
+    assert!(
+        !heard.iter().any(|message| message["type"] == "private"),
+        "{:?}",
+        heard
+    );
+
+    w1.send(json!({"command": "list"})).unwrap();
+    let members = w1.expect("member list", Value::is_array).unwrap();
+    let mut members: Vec<_> = members
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    members.sort();
+    assert_eq!(members, ["cam1", "w1", "w2"]);
+
+    drop(w2);
+    let left = cam.expect_signal("watcher_left").unwrap();
+    assert_eq!(left["watcher_id"], "w2");
+    assert_eq!(w1.expect_signal("left").unwrap()["member_id"], "w2");
+}
+
+#[test]
+fn bad_commands_are_answered_with_error_codes() {
+    let server = TestServer::start().expect("signaling server");
+    let mut cam = streamer(&server, "cam1");
+    let mut w1 = watcher(&server, "cam1", "w1");
+
+    w1.send_text("not json").unwrap();
+    w1.expect_error("invalid_json").unwrap();
+    w1.send(json!({"command": "teleport"})).unwrap();
+    let unknown = w1.expect_error("unknown_command").unwrap();
+    assert!(
+        unknown["error"]["details"]
+            .as_str()
+            .unwrap()
+            .contains("broadcast")
+    );
+    // A viewer only negotiates; it doesn't talk to the room
+    w1.send(json!({"command": "broadcast", "message": "{}"}))
+        .unwrap();
+    w1.expect_error("forbidden").unwrap();
+
+    cam.send(json!({"command": "send", "to": "ghost", "message": "{}"}))
+        .unwrap();
+    cam.expect_error("unknown_member").unwrap();
+
+    // And the connections carry on
+    w1.send(json!({"command": "whois"})).unwrap();
+    let whois = w1.expect("whois", |message| message["member_id"] == "w1");
+    assert!(whois.is_ok());
+}
+
+#[test]
+fn joins_are_refused_before_the_upgrade() {
+    let server = TestServer::start().expect("signaling server");
+    let refusal = server
+        .upgrade("/watcher?streamer_id=nobody&id=w1", &[])
+        .unwrap()
+        .err()
+        .expect("no stream to watch");
+    assert_eq!((refusal.status, refusal.code()), (400, "stream_not_live"));
+
+    let refusal = server
+        .upgrade("/streamer", &[])
+        .unwrap()
+        .err()
+        .expect("no streamer id");
+    assert_eq!((refusal.status, refusal.code()), (400, "missing_parameter"));
+}
+
+#[test]
+fn pages_from_other_origins_are_refused() {
+    let server = TestServer::start_with(|| {
+        let allowed: AllowedOrigin = "https://app.example.com".parse().unwrap();
+        SignalingServer::new().with_origins(Origins::new(vec![allowed]))
+    })
+    .expect("signaling server");
+
+    let refusal = server
+        .upgrade(
+            "/streamer?id=cam1",
+            &[("Origin", "https://elsewhere.example")],
+        )
+        .unwrap()
+        .err()
+        .expect("another site's page");
+    assert_eq!((refusal.status, refusal.code()), (403, "forbidden"));
+    assert_eq!(
+        refusal.error["error"]["details"],
+        "origin 'https://elsewhere.example' is not allowed"
+    );
+
+    let allowed = server.upgrade(
+        "/streamer?id=cam1",
+        &[("Origin", "https://app.example.com")],
+    );
+    assert!(allowed.unwrap().is_ok());
+    // Native clients send no Origin at all
+    let native = server.upgrade("/streamer?id=cam2", &[]);
+    assert!(native.unwrap().is_ok());
+}
+
+#[test]
+fn connections_want_a_valid_token() {
+    let server = TestServer::start_with(|| SignalingServer::new().with_jwt(JwtAuth::hmac(SECRET)))
+        .expect("signaling server");
+    let streamer_token = token(json!({
+        "role": "streamer", "stream_id": "cam1", "sub": "cam1", "exp": in_an_hour(),
+    }));
+
+    // Seated first: a watcher of a stream that isn't live is turned away before
+    // its token is looked at
+    let bearer = format!("Bearer {}", streamer_token);
+    let cam = server.upgrade("/streamer?id=cam1", &[("Authorization", &bearer)]);
+    let _cam = cam.unwrap().expect("streamer with its token");
+
+    for (path, headers) in [
+        ("/streamer?id=cam1".to_string(), vec![]),
+        (
+            "/streamer?id=cam1".to_string(),
+            vec![("Authorization", "Bearer not.a.token")],
+        ),
+        // Signed for another stream, another role, or with another secret
+        (
+            format!("/streamer?id=cam2&token={}", streamer_token),
+            vec![],
+        ),
+        (
+            format!("/watcher?streamer_id=cam1&id=cam1&token={}", streamer_token),
+            vec![],
+        ),
+        (
+            format!(
+                "/streamer?id=cam1&token={}",
+                signed(
+                    json!({"role": "streamer", "stream_id": "cam1", "exp": in_an_hour()}),
+                    b"someone else's secret"
+                )
+            ),
+            vec![],
+        ),
+        // Expired
+        (
+            format!(
+                "/streamer?id=cam1&token={}",
+                token(json!({"role": "streamer", "stream_id": "cam1", "exp": 1}))
+            ),
+            vec![],
+        ),
+    ] {
+        let refusal = server.upgrade(&path, &headers).unwrap().err();
+        let refusal = refusal.unwrap_or_else(|| panic!("{} let in", path));
+        assert_eq!(
+            (refusal.status, refusal.code()),
+            (401, "unauthorized"),
+            "{}",
+            path
+        );
+    }
+
+    let watcher_token = token(json!({
+        "role": "watcher", "stream_id": "cam1", "exp": in_an_hour(),
+    }));
+    let w1 = server.upgrade(
+        &format!("/watcher?streamer_id=cam1&id=w1&token={}", watcher_token),
+        &[],
+    );
+    assert!(w1.unwrap().is_ok());
+}
+
+#[test]
+fn moderation_wants_the_admin_token() {
+    let server = TestServer::start_with(|| {
+        let config = SignalingConfig {
+            admin: true,
+            ..SignalingConfig::default()
+        };
+        SignalingServer::new()
+            .with_config(config)
+            .with_admin_token(AdminToken::new(ADMIN_TOKEN).unwrap())
+    })
+    .expect("signaling server");
+    let mut cam = streamer(&server, "cam1");
+
+    let wrong = format!("Bearer {}-not", ADMIN_TOKEN);
+    for headers in [vec![], vec![("Authorization", wrong.as_str())]] {
+        let (status, body) = server
+            .request("DELETE", "/api/streamers/cam1", &headers)
+            .unwrap();
+        assert_eq!(status, 401, "{}", body);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["code"], "unauthorized");
+    }
+    assert!(!cam.closed(QUIET));
+
+    let right = format!("Bearer {}", ADMIN_TOKEN);
+    let headers = [("Authorization", right.as_str())];
+    let (status, _) = server
+        .request("DELETE", "/api/streamers/cam1", &headers)
+        .unwrap();
+    assert_eq!(status, 204);
+    assert!(cam.closed(Duration::from_secs(5)));
+    let (status, _) = server
+        .request("DELETE", "/api/streamers/cam1", &headers)
+        .unwrap();
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn protocol_versions_are_negotiated() {
+    let server = TestServer::start().expect("signaling server");
+    let supported = PROTOCOL_VERSION.to_string();
+
+    // Asked for, or taken to be the first
+    for path in [
+        format!("/streamer?id=cam1&version={}", PROTOCOL_VERSION),
+        "/streamer?id=cam2".to_string(),
+    ] {
+        let mut cam = server.connect(&path).expect("a version we speak");
+        assert_eq!(cam.versions.as_deref(), Some(supported.as_str()));
+        cam.send(json!({"command": "whois"})).unwrap();
+        let whois = cam.expect("whois", |message| message["version"].is_u64());
+        assert_eq!(whois.unwrap()["version"], PROTOCOL_VERSION);
+    }
+
+    for version in ["999", "one"] {
+        let refusal = server
+            .upgrade(&format!("/streamer?id=cam3&version={}", version), &[])
+            .unwrap()
+            .err()
+            .expect("a version we don't speak");
+        assert_eq!(
+            (refusal.status, refusal.code()),
+            (400, "unsupported_version")
+        );
+        assert_eq!(refusal.versions.as_deref(), Some(supported.as_str()));
+        assert_eq!(
+            refusal.error["error"]["details"],
+            format!("version {} is not one of {}", version, supported)
+        );
+    }
+}
+
+#[test]
+fn deprecated_commands_are_warned_about_once() {
+    const DEPRECATED: &[Deprecation] = &[Deprecation {
+        command: "list",
+        sunset_version: 2,
+        replacement: Some("whois"),
+    }];
+    let server = TestServer::start_with(|| SignalingServer::new().with_deprecations(DEPRECATED))
+        .expect("signaling server");
+    let mut cam = streamer(&server, "cam1");
+
+    cam.send(json!({"command": "list"})).unwrap();
+    let warning = cam.expect("deprecation warning", |message| {
+        message["warning"] == "deprecated"
+    });
+    assert_eq!(
+        warning.unwrap(),
+        json!({
+            "warning": "deprecated",
+            "command": "list",
+            "sunset_version": 2,
+            "replacement": "whois",
+        })
+    );
+    // It still works until its sunset
+    assert_eq!(
+        cam.expect("member list", Value::is_array).unwrap(),
+        json!(["cam1"])
+    );
+
+    cam.send(json!({"command": "list"})).unwrap();
+    cam.send(json!({"command": "whois"})).unwrap();
+    let heard = cam.drain(QUIET).unwrap();
+    assert!(heard.contains(&json!(["cam1"])), "{:?}", heard);
+    assert!(
+        !heard.iter().any(|message| message.get("warning").is_some()),
+        "{:?}",
+        heard
+    );
+}
+
+// ✅ A server whose streamers and watchers may resume within `grace`
+fn resumable(grace: Duration) -> TestServer {
+    TestServer::start_with(move || {
+        SignalingServer::new().with_config(SignalingConfig {
+            resume_grace: Some(grace),
+            ..SignalingConfig::default()
+        })
+    })
+    .expect("signaling server")
+}
+
+// A watcher joined with a resume token; the token
+fn resumable_watcher(server: &TestServer, id: &str) -> (Client, String) {
+    let mut watcher = server
+        .connect(&format!("/watcher?streamer_id=cam1&id={}", id))
+        .expect("watcher connects");
+    let resume = watcher.expect_signal("resume").unwrap();
+    let token = resume["token"].as_str().unwrap().to_string();
+    (watcher, token)
+}
+
+fn note(text: &str) -> Value {
+    let note = json!({"type": "note", "text": text});
+    json!({"command": "broadcast", "message": note.to_string()})
+}
+
+fn resume_path(id: &str, token: &str, last_seq: u64) -> String {
+    format!(
+        "/watcher?streamer_id=cam1&id={}&resume={}&last_seq={}",
+        id, token, last_seq
+    )
+}
+
+#[test]
+fn a_dropped_watcher_resumes_with_what_it_missed() {
+    let server = resumable(Duration::from_secs(30));
+    let mut cam = streamer(&server, "cam1");
+    let (mut w1, token) = resumable_watcher(&server, "w1");
+
+    cam.send(note("before")).unwrap();
+    let seen = w1.expect_signal("note").unwrap()["seq"].as_u64().unwrap();
+    drop(w1);
+    thread::sleep(QUIET);
+    cam.send(note("while away")).unwrap();
+
+    let mut w1 = server
+        .connect(&resume_path("w1", &token, seen))
+        .expect("w1 resumes");
+    let missed = w1.expect_signal("note").unwrap();
+    assert_eq!(missed["text"], "while away");
+    assert_eq!(missed["seq"], seen + 1);
+    let resume = w1.expect_signal("resume").unwrap();
+    assert_ne!(resume["token"], token.as_str());
+
+    // Its streamer kept it all along
+    let heard = cam.drain(QUIET).unwrap();
+    assert!(
+        !heard
+            .iter()
+            .any(|message| message["type"] == "watcher_left"),
+        "{:?}",
+        heard
+    );
+}
+
+#[test]
+fn stale_and_unknown_tokens_have_nothing_to_resume() {
+    let server = resumable(Duration::from_secs(30));
+    let _cam = streamer(&server, "cam1");
+    let (w1, token) = resumable_watcher(&server, "w1");
+    drop(w1);
+    thread::sleep(QUIET);
+
+    let refused = |path: String| {
+        let refusal = server.upgrade(&path, &[]).unwrap().err();
+        let refusal = refusal.unwrap_or_else(|| panic!("{} let in", path));
+        assert_eq!(
+            (refusal.status, refusal.code()),
+            (410, "nothing_to_resume"),
+            "{}",
+            path
+        );
+    };
+    refused(resume_path("w1", "not-the-token", 0));
+    refused(resume_path("w2", &token, 0));
+    // Nothing was ever sent past it
+    refused(resume_path("w1", &token, 1000));
+
+    let w1 = server.connect(&resume_path("w1", &token, 0));
+    assert!(w1.is_ok());
+    // Used up
+    refused(resume_path("w1", &token, 0));
+}
+
+#[test]
+fn a_watcher_away_past_its_grace_has_left() {
+    let server = resumable(Duration::from_secs(1));
+    let mut cam = streamer(&server, "cam1");
+    let (w1, token) = resumable_watcher(&server, "w1");
+    cam.expect_signal("watcher_joined").unwrap();
+    drop(w1);
+
+    // Not straight away: it may yet come back
+    let heard = cam.drain(QUIET).unwrap();
+    assert!(
+        !heard
+            .iter()
+            .any(|message| message["type"] == "watcher_left"),
+        "{:?}",
+        heard
+    );
+    let left = cam.expect_signal("watcher_left").unwrap();
+    assert_eq!(left["watcher_id"], "w1");
+
+    let refusal = server
+        .upgrade(&resume_path("w1", &token, 0), &[])
+        .unwrap()
+        .err()
+        .expect("nothing kept for w1");
+    assert_eq!((refusal.status, refusal.code()), (410, "nothing_to_resume"));
+}
+
+#[test]
+fn broadcasts_within_a_window_arrive_as_one_batch() {
+    let server = TestServer::start_with(|| {
+        SignalingServer::new().with_config(SignalingConfig {
+            batch_window: Some(Duration::from_millis(100)),
+            ..SignalingConfig::default()
+        })
+    })
+    .expect("signaling server");
+    let mut cam = streamer(&server, "cam1");
+    let mut w1 = watcher(&server, "cam1", "w1");
+
+    let telemetry = |x: u32| json!({"x": x}).to_string();
+    for x in 1..=3 {
+        cam.send(json!({"command": "broadcast", "message": telemetry(x)}))
+            .unwrap();
+    }
+    // Sent once the window is out, with nothing more coming
+    let batch = w1.expect_signal("batch").unwrap();
+    assert_eq!(
+        batch["messages"],
+        json!([telemetry(1), telemetry(2), telemetry(3)])
+    );
+
+    // A lone message goes as it is
+    cam.send(json!({"command": "broadcast", "message": telemetry(4)}))
+        .unwrap();
+    let lone = w1.expect("a lone message", |message| message["x"] == 4);
+    assert!(lone.is_ok());
+}
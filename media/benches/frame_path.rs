@@ -0,0 +1,135 @@
+// Streamer frame path: an encoded buffer handed to the appsink callback until
+// `write_sample` has packetized and sent it on a connected track.
+//
+//   cargo bench --bench frame_path      (needs the GStreamer runtime)
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gstreamer as gst;
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tuesdays_media::pipeline::frame_sample;
+use webrtc::api::APIBuilder;
+use webrtc::api::media_engine::{MIME_TYPE_VP8, MediaEngine};
+use webrtc::media::Sample;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+// Encoded frame sizes: a small P-frame, a busy P-frame, a 720p keyframe
+const FRAME_SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+async fn peer_connection() -> Arc<RTCPeerConnection> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs().unwrap();
+    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    Arc::new(
+        api.new_peer_connection(RTCConfiguration::default())
+            .await
+            .unwrap(),
+    )
+}
+
+// ✅ Offer/answer over loopback until the track is bound; an unbound track
+// drops samples without doing any work
+async fn connected_track() -> (Arc<TrackLocalStaticSample>, [Arc<RTCPeerConnection>; 2]) {
+    let track = Arc::new(TrackLocalStaticSample::new(
+        RTCRtpCodecCapability {
+            mime_type: MIME_TYPE_VP8.to_owned(),
+            clock_rate: 90000,
+            ..Default::default()
+        },
+        "video".to_owned(),
+        "bench".to_owned(),
+    ));
+    let publisher = peer_connection().await;
+    let watcher = peer_connection().await;
+    publisher.add_track(track.clone()).await.unwrap();
+
+    // The watcher keeps reading so RTP never backs up
+    let (track_tx, mut track_rx) = mpsc::unbounded_channel();
+    watcher.on_track(Box::new(move |remote, _, _| {
+        let _ = track_tx.send(());
+        tokio::spawn(async move { while remote.read_rtp().await.is_ok() {} });
+        Box::pin(async {})
+    }));
+
+    // Gather everything up front instead of trickling
+    let offer = publisher.create_offer(None).await.unwrap();
+    let mut gathered = publisher.gathering_complete_promise().await;
+    publisher.set_local_description(offer).await.unwrap();
+    let _ = gathered.recv().await;
+    watcher
+        .set_remote_description(publisher.local_description().await.unwrap())
+        .await
+        .unwrap();
+
+    let answer = watcher.create_answer(None).await.unwrap();
+    let mut gathered = watcher.gathering_complete_promise().await;
+    watcher.set_local_description(answer).await.unwrap();
+    let _ = gathered.recv().await;
+    publisher
+        .set_remote_description(watcher.local_description().await.unwrap())
+        .await
+        .unwrap();
+
+    while publisher.connection_state() != RTCPeerConnectionState::Connected {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    // Bound once media reaches the other side
+    let probe = Sample {
+        data: vec![0; 100].into(),
+        duration: Duration::from_millis(33),
+        ..Default::default()
+    };
+    while track_rx.try_recv().is_err() {
+        track.write_sample(&probe).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    (track, [publisher, watcher])
+}
+
+fn encoded_frame(size: usize) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_slice(vec![0x9d_u8; size]);
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_duration(gst::ClockTime::from_mseconds(33));
+    buffer
+}
+
+fn frame_path(c: &mut Criterion) {
+    gst::init().unwrap();
+    let runtime = Runtime::new().unwrap();
+    let (track, _peers) = runtime.block_on(connected_track());
+
+    let mut group = c.benchmark_group("frame_path");
+    for size in FRAME_SIZES {
+        let buffer = encoded_frame(size);
+        group.throughput(Throughput::Bytes(size as u64));
+
+        // The part that runs on GStreamer's streaming thread
+        group.bench_with_input(BenchmarkId::new("callback", size), &buffer, |b, buffer| {
+            b.iter(|| frame_sample(buffer).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("callback_to_write_sample", size),
+            &buffer,
+            |b, buffer| {
+                b.to_async(&runtime).iter(|| async {
+                    let sample = frame_sample(buffer).unwrap();
+                    track.write_sample(&sample).await.unwrap();
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, frame_path);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+// Clock drift compensation for long-running streams. The track's RTP timeline
+// advances by each sample's duration, as the encoder states it, while the frames
+// really span what their capture timestamps say. A camera whose 30 fps is really
+// 29.97, a sound card whose 48 kHz isn't quite, frames dropped on the way to the
+// track: each opens a gap between the two that grows for as long as the stream
+// runs, until after a day watchers' buffers, and audio against video, are seconds
+// off.
+//
+// `DriftCorrector` keeps the timeline on the capture clock. Once the gap passes
+// TOLERANCE, each sample's duration is stretched or shrunk by at most MAX_SLEW
+// of itself until the gap is closed, too little for anyone to notice. A gap past
+// RESYNC isn't drift but a discontinuity (a stall, a restarted source): the
+// timeline carries on from there instead.
+
+use std::time::Duration;
+
+const TOLERANCE: Duration = Duration::from_millis(20);
+const RESYNC: Duration = Duration::from_secs(1);
+// Fraction of a sample's duration it may be stretched or shrunk by
+const MAX_SLEW: f64 = 0.01;
+
+#[derive(Debug, Default)]
+pub struct DriftCorrector {
+    // Capture timestamp the timeline started at
+    origin: Option<Duration>,
+    // Where the timeline is: the total duration written so far
+    timeline: Duration,
+}
+
+impl DriftCorrector {
+    // ✅ How long to write a sample captured at `pts` (running time) for, given
+    // the `duration` the encoder says it lasts
+    pub fn duration(&mut self, pts: Option<Duration>, duration: Duration) -> Duration {
+        let Some(pts) = pts else {
+            // Nothing to go by; the timeline trusts the encoder
+            self.timeline += duration;
+            return duration;
+        };
+        let origin = *self.origin.get_or_insert(pts);
+        let captured = pts.saturating_sub(origin);
+        // Positive: the timeline is behind the capture clock
+        let gap = captured.as_secs_f64() - self.timeline.as_secs_f64();
+
+        let corrected = if gap.abs() > RESYNC.as_secs_f64() {
+            println!(
+                "⏱️ Capture clock jumped {:+.3}s; the stream's timeline carries on from here",
+                gap
+            );
+            self.origin = Some(pts.saturating_sub(self.timeline));
+            duration
+        } else if gap.abs() > TOLERANCE.as_secs_f64() {
+            let slew = duration.as_secs_f64() * MAX_SLEW;
+            Duration::from_secs_f64(duration.as_secs_f64() + gap.clamp(-slew, slew))
+        } else {
+            duration
+        };
+        self.timeline += corrected;
+        corrected
+    }
+}
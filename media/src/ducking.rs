@@ -0,0 +1,81 @@
+// Ducking: with a loopback source (system audio, a music bed) mixed under the
+// microphone, turn the loopback down while the presenter speaks and back up
+// when they stop. The microphone's level is measured buffer by buffer, after
+// push-to-talk (a closed mic ducks nothing); above `threshold_db` the loopback
+// fades down by `depth_db` over `attack`, and below it fades back over
+// `release`, long enough that the gaps between words don't pump the music.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use crate::level;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ducking {
+    // Microphone level (dBFS) that counts as speaking
+    pub threshold_db: f64,
+    // How far the loopback is turned down while speaking (dB)
+    pub depth_db: f64,
+    pub attack: Duration,
+    pub release: Duration,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Ducking {
+            threshold_db: -40.0,
+            depth_db: 15.0,
+            attack: Duration::from_millis(50),
+            release: Duration::from_millis(500),
+        }
+    }
+}
+
+impl Ducking {
+    // ✅ Turn `volume` (the loopback's) down while S16LE audio leaving `mic` is
+    // above the threshold
+    pub(crate) fn attach(self, mic: &gst::Pad, volume: gst::Element) {
+        // Current gain in dB: 0 (not ducked) down to -depth_db
+        let gain = Mutex::new(0.0);
+        mic.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(map) = buffer.map_readable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let (sum, count) = level::power_of(&map);
+            let Some(db) = level::db(sum, count) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let elapsed = buffer
+                .duration()
+                .map_or(0.0, |duration| duration.nseconds() as f64 / 1e9);
+            let mut gain = gain.lock().unwrap_or_else(PoisonError::into_inner);
+            let next = self.step(*gain, db > self.threshold_db, elapsed);
+            if next != *gain {
+                *gain = next;
+                volume.set_property("volume", 10f64.powf(next / 20.0));
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    // The gain `elapsed` seconds on from `gain`, fading toward ducked or not
+    fn step(&self, gain: f64, speaking: bool, elapsed: f64) -> f64 {
+        let (target, over) = if speaking {
+            (-self.depth_db, self.attack)
+        } else {
+            (0.0, self.release)
+        };
+        let rate = self.depth_db / over.as_secs_f64().max(f64::EPSILON);
+        if gain > target {
+            (gain - rate * elapsed).max(target)
+        } else {
+            (gain + rate * elapsed).min(target)
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use gstreamer as gst;
+use gstreamer::glib;
+
+// Why a capture/encode pipeline couldn't be built or run
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("GStreamer: {0}")]
+    Gst(#[from] glib::Error),
+    // Usually a missing plugin, or elements that can't be linked
+    #[error("GStreamer: {0}")]
+    Element(#[from] glib::BoolError),
+    #[error("GStreamer: {0}")]
+    StateChange(#[from] gst::StateChangeError),
+    #[error("GStreamer: {0}")]
+    Flow(#[from] gst::FlowError),
+    #[error("Unsupported codec '{0}' (use vp8, vp9 or h264)")]
+    UnsupportedCodec(String),
+    #[error("{what} is not enabled in this build (cargo feature `{feature}`)")]
+    NotEnabled { what: String, feature: &'static str },
+    #[error("MediaPipeline must be built inside a Tokio runtime")]
+    NoRuntime,
+    #[error("GStreamer: {0}")]
+    PadLink(#[from] gst::PadLinkError),
+    #[error("{0} has no {1} pad")]
+    MissingPad(&'static str, &'static str),
+    #[error(
+        "Source '{0}' has no audio (use camera for the default microphone, test or a launch description)"
+    )]
+    NoAudio(String),
+    #[error("{0} needs video; not available with an audio-only pipeline")]
+    NeedsVideo(&'static str),
+    #[error("{0} needs audio; only available with an audio-only pipeline")]
+    NeedsAudio(&'static str),
+    #[error("Encoder {0} has no bitrate setting")]
+    NoBitrateControl(String),
+    #[error("Encoder {0} ignored the keyframe request")]
+    NoKeyframeControl(String),
+    #[error(
+        "The microphone can't be opened or closed on this pipeline (MediaPipelineBuilder::push_to_talk or ::controls)"
+    )]
+    NoPushToTalk,
+    #[error("The standby scene is not enabled on this pipeline (MediaPipelineBuilder::controls)")]
+    NoStandby,
+    #[error("Ducking needs a loopback source (MediaPipelineBuilder::loopback)")]
+    NoLoopback,
+    #[error("Recording is not enabled on this pipeline (MediaPipelineBuilder::recordable)")]
+    NotRecordable,
+}
+
+impl From<PipelineError> for tuesdays_protocol::Error {
+    fn from(err: PipelineError) -> Self {
+        tuesdays_protocol::Error::Pipeline(err.into())
+    }
+}
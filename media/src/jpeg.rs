@@ -0,0 +1,69 @@
+// JPEG frames pushed in by the application instead of captured (`Source::Jpeg`),
+// for devices that can only take a picture now and then and send it somewhere:
+//
+//   appsrc [image/jpeg] → jpegdec → videorate → [FRAMERATE] → (the usual chain)
+//
+// Frames are timestamped as they arrive. videorate repeats the latest one up to a
+// steady rate, so the encoder's keyframe interval (in frames) stays short in time
+// and late joiners don't wait for a picture.
+
+use bytes::Bytes;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::AppSrc;
+
+use crate::PipelineError;
+
+// Output frame rate, however rarely frames are pushed
+const FRAMERATE: i32 = 5;
+
+pub(crate) const ELEMENTS: &[&str] = &["appsrc", "jpegdec", "videorate", "capsfilter"];
+
+// Feeds frames into a `Source::Jpeg` pipeline; cheap to clone
+#[derive(Clone)]
+pub struct JpegInput {
+    src: AppSrc,
+}
+
+impl JpegInput {
+    // ✅ Queue one JPEG image as the next frame
+    pub fn push(&self, jpeg: Bytes) -> Result<(), PipelineError> {
+        self.src.push_buffer(gst::Buffer::from_slice(jpeg))?;
+        Ok(())
+    }
+}
+
+// ✅ The source bin, and the input that feeds it
+pub(crate) fn source() -> Result<(gst::Element, JpegInput), PipelineError> {
+    let src = AppSrc::builder()
+        .caps(&gst::Caps::builder("image/jpeg").build())
+        .is_live(true)
+        .format(gst::Format::Time)
+        .do_timestamp(true)
+        .build();
+    let rate = gst::ElementFactory::make("capsfilter")
+        .property(
+            "caps",
+            gst::Caps::builder("video/x-raw")
+                .field("framerate", gst::Fraction::new(FRAMERATE, 1))
+                .build(),
+        )
+        .build()?;
+    let elements = [
+        src.clone().upcast(),
+        gst::ElementFactory::make("jpegdec").build()?,
+        gst::ElementFactory::make("videorate").build()?,
+        rate.clone(),
+    ];
+
+    let bin = gst::Bin::new();
+    bin.add_many(&elements)?;
+    gst::Element::link_many(&elements)?;
+    let ghost = gst::GhostPad::with_target(
+        &rate
+            .static_pad("src")
+            .ok_or(PipelineError::MissingPad("capsfilter", "src"))?,
+    )?;
+    bin.add_pad(&ghost)?;
+    Ok((bin.upcast(), JpegInput { src }))
+}
@@ -0,0 +1,66 @@
+// Audio level of an audio-only pipeline, measured on the raw samples before
+// they're encoded: the RMS in dBFS (0 is full scale) of everything captured
+// since it was last read, for the room's active speaker.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+// Quieter than this is silence
+const FLOOR_DB: f64 = -90.0;
+
+// Sum of squared samples (scaled to ±1) and how many, since the last read
+#[derive(Clone, Default)]
+pub(crate) struct AudioMeter {
+    power: Arc<Mutex<(f64, u64)>>,
+}
+
+impl AudioMeter {
+    // ✅ Measure every S16LE buffer leaving `pad`
+    pub fn attach(pad: &gst::Pad) -> Self {
+        let meter = AudioMeter::default();
+        let power = meter.power.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(map) = buffer.map_readable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let (sum, count) = power_of(&map);
+            let mut power = power.lock().unwrap_or_else(PoisonError::into_inner);
+            power.0 += sum;
+            power.1 += count;
+            gst::PadProbeReturn::Ok
+        });
+        meter
+    }
+
+    // ✅ The level since the last call; None if nothing was captured meanwhile
+    pub fn take_db(&self) -> Option<f64> {
+        let (sum, count) =
+            std::mem::take(&mut *self.power.lock().unwrap_or_else(PoisonError::into_inner));
+        db(sum, count)
+    }
+}
+
+// ✅ Sum of squared S16LE samples, scaled to ±1, and how many there are
+pub(crate) fn power_of(bytes: &[u8]) -> (f64, u64) {
+    bytes
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / 32768.0)
+        .fold((0.0, 0), |(sum, count), sample| {
+            (sum + sample * sample, count + 1)
+        })
+}
+
+// The RMS level of `count` samples whose squares add up to `sum`, in dBFS; None
+// for no samples
+pub(crate) fn db(sum: f64, count: u64) -> Option<f64> {
+    if count == 0 {
+        return None;
+    }
+    let rms = (sum / count as f64).sqrt();
+    Some((20.0 * rms.log10()).max(FLOOR_DB))
+}
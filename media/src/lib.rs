@@ -0,0 +1,33 @@
+// Capture → encode → WebRTC track machinery shared by the streamer and anyone
+// embedding a Tuesdays publisher:
+//
+//   let media = MediaPipeline::builder()
+//       .source(Source::Camera)
+//       .codec(Codec::Vp8)
+//       .build()?;
+//   peer_connection.add_track(media.track()).await?;
+//   media.start()?;
+
+pub mod drift;
+pub mod ducking;
+pub mod error;
+pub mod jpeg;
+mod level;
+pub mod pacing;
+mod picture;
+pub mod pipeline;
+pub mod recording;
+pub mod rendition;
+pub mod throughput;
+pub mod watermark;
+
+pub use drift::DriftCorrector;
+pub use ducking::Ducking;
+pub use error::PipelineError;
+pub use jpeg::JpegInput;
+pub use pacing::Pacer;
+pub use picture::Picture;
+pub use pipeline::{Codec, MediaPipeline, MediaPipelineBuilder, Source};
+pub use recording::Recording;
+pub use rendition::Rendition;
+pub use throughput::Throughput;
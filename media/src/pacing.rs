@@ -0,0 +1,57 @@
+// Frame pacing: hand video frames to the track on an even cadence, one every
+// 1/fps of the negotiated frame rate, even when the source delivers them in
+// bursts (USB cameras that batch frames, network sources, JPEG ingest). Sent as
+// they come, a burst reaches watchers as a burst too, and their jitter buffers
+// grow to absorb it, adding latency that never goes away.
+//
+// A frame arriving ahead of its turn waits for it; one that's late goes at once,
+// and a source that's fallen MAX_LAG behind starts the cadence over rather than
+// be rushed to catch up. Pacing never holds more than BACKLOG frames back: past
+// that, the source is simply faster than its rate, and waiting would only add
+// latency until frames are dropped.
+
+use std::time::Duration;
+
+use gstreamer as gst;
+use tokio::time::Instant;
+
+const MAX_LAG: Duration = Duration::from_millis(200);
+const BACKLOG: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct Pacer {
+    // When the next frame is due
+    next: Option<Instant>,
+}
+
+impl Pacer {
+    // ✅ Wait for the next frame's turn, `interval` after the last one's; `backlog`
+    // is how many more are waiting behind it
+    pub async fn wait(&mut self, interval: Duration, backlog: usize) {
+        let now = Instant::now();
+        let due = match self.next {
+            // Not its turn yet: wait for it, unless frames are piling up behind
+            Some(due) if due > now => {
+                if backlog >= BACKLOG {
+                    now
+                } else {
+                    tokio::time::sleep_until(due).await;
+                    due
+                }
+            }
+            // Late, but not by much: keep the cadence
+            Some(due) if now - due < MAX_LAG => due,
+            // The first frame, or the source fell behind: start over
+            _ => now,
+        };
+        self.next = Some(due + interval);
+    }
+}
+
+// ✅ The time between frames at the rate in `caps`; None for audio, or a variable
+// frame rate (0/1), which can't be paced
+pub fn frame_interval(caps: &gst::CapsRef) -> Option<Duration> {
+    let framerate = caps.structure(0)?.get::<gst::Fraction>("framerate").ok()?;
+    let (numer, denom) = (framerate.numer(), framerate.denom());
+    (numer > 0 && denom > 0).then(|| Duration::from_secs(denom as u64) / numer as u32)
+}
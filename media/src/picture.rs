@@ -0,0 +1,112 @@
+// What the outgoing video looks like, cheaply, for the failure flaky HDMI capture
+// dongles have most: the pipeline runs and frames keep coming, but they're black,
+// or the same frame over and over. Every raw frame's luma plane is sampled on a
+// GRID_W x GRID_H grid before it's encoded (or watermarked): black when no sample
+// is brighter than BLACK_LUMA, frozen when the samples differ from the previous
+// frame's by less than FROZEN_DIFF on average, which a live camera's sensor noise
+// alone exceeds.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+const GRID_W: usize = 32;
+const GRID_H: usize = 18;
+// Limited-range black is 16; a little noise above it is still black
+const BLACK_LUMA: u8 = 32;
+const FROZEN_DIFF: f64 = 0.5;
+
+// How long the picture has been black, or frozen, as of the last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Picture {
+    pub black_for: Option<Duration>,
+    pub frozen_for: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Samples {
+    previous: Vec<u8>,
+    black_since: Option<Instant>,
+    frozen_since: Option<Instant>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct PictureMonitor {
+    samples: Arc<Mutex<Samples>>,
+}
+
+impl PictureMonitor {
+    // ✅ Sample every I420 frame reaching `pad`
+    pub fn attach(pad: &gst::Pad) -> Self {
+        let monitor = PictureMonitor::default();
+        let samples = monitor.samples.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some((width, height)) = pad.current_caps().and_then(|caps| {
+                let structure = caps.structure(0)?;
+                let width = structure.get::<i32>("width").ok()?;
+                let height = structure.get::<i32>("height").ok()?;
+                Some((width as usize, height as usize))
+            }) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(map) = buffer.map_readable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            // I420's Y plane comes first, rows padded to 4 bytes
+            let stride = (width + 3) & !3;
+            if width == 0 || height == 0 || map.len() < stride * height {
+                return gst::PadProbeReturn::Ok;
+            }
+            let grid: Vec<u8> = (0..GRID_H)
+                .flat_map(|gy| {
+                    let y = (2 * gy + 1) * height / (2 * GRID_H);
+                    (0..GRID_W).map(move |gx| y * stride + (2 * gx + 1) * width / (2 * GRID_W))
+                })
+                .map(|at| map[at])
+                .collect();
+            let mut samples = samples.lock().unwrap_or_else(PoisonError::into_inner);
+            samples.sample(grid);
+            gst::PadProbeReturn::Ok
+        });
+        monitor
+    }
+
+    pub fn picture(&self) -> Picture {
+        let samples = self.samples.lock().unwrap_or_else(PoisonError::into_inner);
+        Picture {
+            black_for: samples.black_since.map(|since| since.elapsed()),
+            frozen_for: samples.frozen_since.map(|since| since.elapsed()),
+        }
+    }
+}
+
+impl Samples {
+    fn sample(&mut self, grid: Vec<u8>) {
+        let now = Instant::now();
+        let black = grid.iter().all(|&luma| luma <= BLACK_LUMA);
+        let frozen = grid.len() == self.previous.len() && {
+            let diff: u32 = grid
+                .iter()
+                .zip(&self.previous)
+                .map(|(&a, &b)| a.abs_diff(b) as u32)
+                .sum();
+            (diff as f64 / grid.len() as f64) < FROZEN_DIFF
+        };
+        self.black_since = if black {
+            Some(self.black_since.unwrap_or(now))
+        } else {
+            None
+        };
+        self.frozen_since = if frozen {
+            Some(self.frozen_since.unwrap_or(now))
+        } else {
+            None
+        };
+        self.previous = grid;
+    }
+}
@@ -0,0 +1,1222 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+#[cfg(feature = "watermark")]
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use futures_util::StreamExt;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::{AppSink, AppSinkCallbacks};
+#[cfg(feature = "watermark")]
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use webrtc::api::media_engine::{MIME_TYPE_H264, MIME_TYPE_OPUS, MIME_TYPE_VP8, MIME_TYPE_VP9};
+use webrtc::media::Sample;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+use crate::PipelineError;
+use crate::drift::DriftCorrector;
+use crate::ducking::Ducking;
+use crate::jpeg::{self, JpegInput};
+use crate::level::AudioMeter;
+use crate::pacing::{self, Pacer};
+use crate::picture::{Picture, PictureMonitor};
+use crate::recording::Recording;
+use crate::rendition::{LowRendition, Rendition};
+use crate::throughput::{Throughput, ThroughputCounter};
+#[cfg(feature = "watermark")]
+use crate::watermark::{self, Watermark};
+
+// Encoded frames waiting to be written to the track; newer ones are dropped beyond this
+const SAMPLE_QUEUE: usize = 8;
+// Used when the encoder doesn't say how long a frame lasts
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(33);
+// Opus always runs at 48 kHz in WebRTC; stereo so music survives
+const OPUS_CLOCK_RATE: u32 = 48000;
+const OPUS_CHANNELS: u16 = 2;
+// EBU R128 loudness normalization, from gst-plugins-rs (audiofx)
+const LOUDNESS_NORMALIZER: &str = "audioloudnorm";
+
+// Where raw video comes from (raw audio for audio-only pipelines: the default
+// microphone, a test tone, or a launch description producing audio); written in
+// config files the way it's given on the command line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Source {
+    // First available camera (autovideosrc)
+    Camera,
+    // Moving test pattern, for headless runs and tests
+    Test,
+    // The whole primary display (needs the `screen-capture` feature)
+    #[cfg(feature = "screen-capture")]
+    Screen,
+    // JPEG frames pushed through `MediaPipeline::jpeg_input` (see `jpeg`)
+    Jpeg,
+    // Any gst-launch description producing raw video, e.g. "v4l2src device=/dev/video2"
+    Launch(String),
+}
+
+// "camera", "test", "screen", "jpeg", or a gst-launch description
+impl FromStr for Source {
+    type Err = PipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "camera" => Source::Camera,
+            "test" => Source::Test,
+            "jpeg" => Source::Jpeg,
+            #[cfg(feature = "screen-capture")]
+            "screen" => Source::Screen,
+            #[cfg(not(feature = "screen-capture"))]
+            "screen" => {
+                return Err(PipelineError::NotEnabled {
+                    what: "Screen capture".to_string(),
+                    feature: "screen-capture",
+                });
+            }
+            launch => Source::Launch(launch.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Camera => write!(f, "camera"),
+            Source::Test => write!(f, "test"),
+            Source::Jpeg => write!(f, "jpeg"),
+            #[cfg(feature = "screen-capture")]
+            Source::Screen => write!(f, "screen"),
+            Source::Launch(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+impl From<Source> for String {
+    fn from(source: Source) -> Self {
+        source.to_string()
+    }
+}
+
+impl TryFrom<String> for Source {
+    type Error = PipelineError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Source {
+    // The GStreamer elements this source is built from (launch descriptions name their own)
+    fn factories(&self) -> &'static [&'static str] {
+        match self {
+            Source::Camera => &["autovideosrc"],
+            Source::Test => &["videotestsrc"],
+            #[cfg(feature = "screen-capture")]
+            Source::Screen => &[SCREEN_SOURCE],
+            Source::Jpeg => jpeg::ELEMENTS,
+            Source::Launch(_) => &[],
+        }
+    }
+
+    // Audio-only counterparts of `factories` and `element`
+    fn audio_factories(&self) -> &'static [&'static str] {
+        match self {
+            Source::Camera => &["autoaudiosrc"],
+            Source::Test => &["audiotestsrc"],
+            #[cfg(feature = "screen-capture")]
+            Source::Screen => &[],
+            Source::Jpeg => &[],
+            Source::Launch(_) => &[],
+        }
+    }
+
+    fn audio_element(&self) -> Result<gst::Element, PipelineError> {
+        let element = match self {
+            Source::Camera => gst::ElementFactory::make("autoaudiosrc").build()?,
+            Source::Test => gst::ElementFactory::make("audiotestsrc")
+                .property("is-live", true)
+                .build()?,
+            #[cfg(feature = "screen-capture")]
+            Source::Screen => return Err(PipelineError::NoAudio(self.to_string())),
+            Source::Jpeg => return Err(PipelineError::NoAudio(self.to_string())),
+            Source::Launch(description) => {
+                gst::parse::bin_from_description(description, true)?.upcast()
+            }
+        };
+        Ok(element)
+    }
+
+    // ✅ The source element, plus the input to push frames into for `Jpeg`
+    fn element(&self) -> Result<(gst::Element, Option<JpegInput>), PipelineError> {
+        let element = match self {
+            Source::Camera => gst::ElementFactory::make("autovideosrc").build()?,
+            Source::Test => gst::ElementFactory::make("videotestsrc")
+                .property("is-live", true)
+                .build()?,
+            #[cfg(feature = "screen-capture")]
+            Source::Screen => screen_source()?,
+            Source::Jpeg => {
+                let (element, input) = jpeg::source()?;
+                return Ok((element, Some(input)));
+            }
+            Source::Launch(description) => {
+                gst::parse::bin_from_description(description, true)?.upcast()
+            }
+        };
+        Ok((element, None))
+    }
+}
+
+#[cfg(all(feature = "screen-capture", target_os = "macos"))]
+const SCREEN_SOURCE: &str = "avfvideosrc";
+#[cfg(all(feature = "screen-capture", target_os = "windows"))]
+const SCREEN_SOURCE: &str = "d3d11screencapturesrc";
+#[cfg(all(
+    feature = "screen-capture",
+    not(any(target_os = "macos", target_os = "windows"))
+))]
+const SCREEN_SOURCE: &str = "ximagesrc";
+
+// ✅ Platform screen grabber (ximagesrc / avfvideosrc / d3d11screencapturesrc)
+#[cfg(feature = "screen-capture")]
+fn screen_source() -> Result<gst::Element, PipelineError> {
+    #[cfg(target_os = "macos")]
+    let source = gst::ElementFactory::make(SCREEN_SOURCE)
+        .property("capture-screen", true)
+        .property("is-live", true)
+        .build()?;
+    #[cfg(target_os = "windows")]
+    let source = gst::ElementFactory::make(SCREEN_SOURCE).build()?;
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let source = gst::ElementFactory::make(SCREEN_SOURCE)
+        .property("use-damage", false)
+        .build()?;
+    Ok(source)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Codec {
+    Vp8,
+    Vp9,
+    H264,
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Codec::Vp8 => "vp8",
+            Codec::Vp9 => "vp9",
+            Codec::H264 => "h264",
+        })
+    }
+}
+
+impl From<Codec> for String {
+    fn from(codec: Codec) -> Self {
+        codec.to_string()
+    }
+}
+
+impl TryFrom<String> for Codec {
+    type Error = PipelineError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for Codec {
+    type Err = PipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let codec = match s.to_lowercase().as_str() {
+            "vp8" => Codec::Vp8,
+            "vp9" => Codec::Vp9,
+            "h264" => Codec::H264,
+            other => return Err(PipelineError::UnsupportedCodec(other.to_string())),
+        };
+        codec.ensure_enabled()?;
+        Ok(codec)
+    }
+}
+
+impl Codec {
+    // Whether this build can encode the codec; see the features in Cargo.toml
+    pub fn enabled(self) -> bool {
+        match self {
+            Codec::Vp8 => cfg!(feature = "vp8"),
+            Codec::Vp9 => cfg!(feature = "vp9"),
+            Codec::H264 => cfg!(feature = "h264"),
+        }
+    }
+
+    fn ensure_enabled(self) -> Result<(), PipelineError> {
+        if self.enabled() {
+            return Ok(());
+        }
+        let feature = match self {
+            Codec::Vp8 => "vp8",
+            Codec::Vp9 => "vp9",
+            Codec::H264 => "h264",
+        };
+        Err(PipelineError::NotEnabled {
+            what: format!("{:?}", self),
+            feature,
+        })
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Codec::Vp8 => MIME_TYPE_VP8,
+            Codec::Vp9 => MIME_TYPE_VP9,
+            Codec::H264 => MIME_TYPE_H264,
+        }
+    }
+
+    // Hardware encoders to try before the software one, best first
+    #[cfg(feature = "hw-encoders")]
+    fn hardware_encoders(self) -> &'static [&'static str] {
+        match self {
+            Codec::Vp8 => &["vaapivp8enc", "v4l2vp8enc"],
+            Codec::Vp9 => &["vaapivp9enc", "qsvvp9enc"],
+            Codec::H264 => &[
+                "vtenc_h264",
+                "nvh264enc",
+                "vaapih264enc",
+                "qsvh264enc",
+                "v4l2h264enc",
+            ],
+        }
+    }
+
+    // The best installed hardware encoder, if any
+    #[cfg(feature = "hw-encoders")]
+    fn hardware_encoder(self) -> Option<&'static str> {
+        self.hardware_encoders()
+            .iter()
+            .find(|name| gst::ElementFactory::find(name).is_some())
+            .copied()
+    }
+
+    fn software_encoder(self) -> &'static str {
+        match self {
+            Codec::Vp8 => "vp8enc",
+            Codec::Vp9 => "vp9enc",
+            Codec::H264 => "x264enc",
+        }
+    }
+
+    // ✅ Realtime encoder settings: no lookahead, frequent keyframes for late joiners
+    pub(crate) fn encoder(self) -> Result<Vec<gst::Element>, PipelineError> {
+        self.ensure_enabled()?;
+
+        #[cfg(feature = "hw-encoders")]
+        if let Some(name) = self.hardware_encoder() {
+            println!("⚡ Using hardware encoder {}", name);
+            let mut elements = vec![gst::ElementFactory::make(name).build()?];
+            if self == Codec::H264 {
+                elements.extend(h264_output()?);
+            }
+            return Ok(elements);
+        }
+
+        let elements = match self {
+            Codec::Vp8 | Codec::Vp9 => {
+                vec![
+                    gst::ElementFactory::make(self.software_encoder())
+                        .property_from_str("deadline", "1")
+                        .property_from_str("keyframe-max-dist", "60")
+                        .build()?,
+                ]
+            }
+            Codec::H264 => {
+                let mut elements = vec![
+                    gst::ElementFactory::make(self.software_encoder())
+                        .property_from_str("tune", "zerolatency")
+                        .property_from_str("speed-preset", "ultrafast")
+                        .property_from_str("key-int-max", "60")
+                        .build()?,
+                ];
+                elements.extend(h264_output()?);
+                elements
+            }
+        };
+        Ok(elements)
+    }
+}
+
+// ✅ Repeat SPS/PPS with every keyframe and hand out whole access units
+fn h264_output() -> Result<[gst::Element; 2], PipelineError> {
+    Ok([
+        gst::ElementFactory::make("h264parse")
+            .property_from_str("config-interval", "-1")
+            .build()?,
+        gst::ElementFactory::make("capsfilter")
+            .property(
+                "caps",
+                gst::Caps::builder("video/x-h264")
+                    .field("stream-format", "byte-stream")
+                    .field("alignment", "au")
+                    .build(),
+            )
+            .build()?,
+    ])
+}
+
+pub struct MediaPipelineBuilder {
+    source: Source,
+    codec: Codec,
+    watermark: bool,
+    preview: bool,
+    recordable: bool,
+    audio_only: bool,
+    pacing: bool,
+    loudness: Option<f64>,
+    push_to_talk: bool,
+    controls: bool,
+    loopback: Option<Source>,
+    ducking: Option<Ducking>,
+    rendition: Option<Rendition>,
+    track_id: String,
+    stream_id: String,
+}
+
+impl Default for MediaPipelineBuilder {
+    fn default() -> Self {
+        MediaPipelineBuilder {
+            source: Source::Camera,
+            codec: Codec::Vp8,
+            watermark: false,
+            preview: false,
+            recordable: false,
+            audio_only: false,
+            pacing: true,
+            loudness: None,
+            push_to_talk: false,
+            controls: false,
+            loopback: None,
+            ducking: None,
+            rendition: None,
+            track_id: "video".to_string(),
+            stream_id: "webrtc-rs".to_string(),
+        }
+    }
+}
+
+impl MediaPipelineBuilder {
+    pub fn source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    // Stamp a frame sequence number and timestamp barcode into every frame (see `watermark`)
+    pub fn watermark(mut self, enabled: bool) -> Self {
+        self.watermark = enabled;
+        self
+    }
+
+    // Also show the frames being encoded (watermark included) in a local window
+    pub fn preview(mut self, enabled: bool) -> Self {
+        self.preview = enabled;
+        self
+    }
+
+    // Allow `MediaPipeline::start_recording` while live (see `recording`)
+    pub fn recordable(mut self, enabled: bool) -> Self {
+        self.recordable = enabled;
+        self
+    }
+
+    // Capture audio from the source and send it as Opus instead of video; the
+    // codec is ignored, and there's nothing to watermark or preview
+    pub fn audio_only(mut self, enabled: bool) -> Self {
+        self.audio_only = enabled;
+        self
+    }
+
+    // Release video frames to the track at the negotiated frame rate, however
+    // bursty the source (see `pacing`); on by default
+    pub fn pacing(mut self, enabled: bool) -> Self {
+        self.pacing = enabled;
+        self
+    }
+
+    // Normalize audio-only pipelines to this integrated loudness (LUFS, e.g.
+    // -16), so presenters and microphones all come out equally loud; EBU R128
+    // measurement needs a few seconds of lookahead, which adds as much latency
+    pub fn loudness(mut self, target_lufs: Option<f64>) -> Self {
+        self.loudness = target_lufs;
+        self
+    }
+
+    // Start audio-only pipelines with the microphone closed, opened and closed
+    // again with `MediaPipeline::set_mic_open`
+    pub fn push_to_talk(mut self, enabled: bool) -> Self {
+        self.push_to_talk = enabled;
+        self
+    }
+
+    // Let the operator close the microphone (audio-only) or cut to a black
+    // standby scene (video) while live, with `MediaPipeline::set_mic_open` and
+    // `MediaPipeline::set_standby`
+    pub fn controls(mut self, enabled: bool) -> Self {
+        self.controls = enabled;
+        self
+    }
+
+    // Mix a second audio source (system audio, a music bed) under the
+    // microphone of an audio-only pipeline
+    pub fn loopback(mut self, source: Option<Source>) -> Self {
+        self.loopback = source;
+        self
+    }
+
+    // Turn the loopback down while the microphone is speaking (see `ducking`)
+    pub fn ducking(mut self, ducking: Option<Ducking>) -> Self {
+        self.ducking = ducking;
+        self
+    }
+
+    // Also encode a lower-bitrate rendition of the video, for `low_track` and
+    // recordings, if the machine can afford it (see `rendition`)
+    pub fn rendition(mut self, rendition: Option<Rendition>) -> Self {
+        self.rendition = rendition;
+        self
+    }
+
+    pub fn track_id(mut self, track_id: impl Into<String>) -> Self {
+        self.track_id = track_id.into();
+        self
+    }
+
+    pub fn stream_id(mut self, stream_id: impl Into<String>) -> Self {
+        self.stream_id = stream_id.into();
+        self
+    }
+
+    // ✅ GStreamer elements this configuration needs that aren't installed (for --check)
+    pub fn missing_elements(&self) -> Result<Vec<String>, PipelineError> {
+        gst::init()?;
+        let mut needed = vec!["capsfilter", "appsink"];
+        if self.audio_only {
+            needed.extend(["audioconvert", "audioresample", "opusenc"]);
+            needed.extend(self.source.audio_factories());
+            if self.loudness.is_some() {
+                needed.push(LOUDNESS_NORMALIZER);
+            }
+            if self.push_to_talk || self.controls || self.loopback.is_some() {
+                needed.push("volume");
+            }
+            if let Some(loopback) = &self.loopback {
+                needed.push("audiomixer");
+                needed.extend(loopback.audio_factories());
+            }
+        } else {
+            needed.extend(["videoconvert", "videoscale"]);
+            needed.extend(self.source.factories());
+            if self.controls {
+                needed.push("videobalance");
+            }
+
+            #[cfg(feature = "hw-encoders")]
+            let encoder = self
+                .codec
+                .hardware_encoder()
+                .unwrap_or(self.codec.software_encoder());
+            #[cfg(not(feature = "hw-encoders"))]
+            let encoder = self.codec.software_encoder();
+            needed.push(encoder);
+            if self.codec == Codec::H264 {
+                needed.push("h264parse");
+            }
+        }
+        if self.preview {
+            needed.extend(["tee", "queue", "autovideosink"]);
+        }
+        if self.rendition.is_some() && !self.audio_only {
+            needed.extend(["tee", "queue", "valve", "videoscale"]);
+        }
+        if self.recordable {
+            needed.extend(["tee", "queue", "matroskamux", "filesink"]);
+        }
+
+        needed.sort_unstable();
+        needed.dedup();
+        let mut missing: Vec<String> = needed
+            .into_iter()
+            .filter(|name| gst::ElementFactory::find(name).is_none())
+            .map(String::from)
+            .collect();
+        // A launch description names its own elements; parsing it creates them
+        for source in [Some(&self.source), self.loopback.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if let Source::Launch(description) = source
+                && let Err(err) = gst::parse::bin_from_description(description, true)
+            {
+                missing.push(format!("'{}' ({})", description, err));
+            }
+        }
+        Ok(missing)
+    }
+
+    // ✅ Build the pipeline (stopped) and its track; must run inside a Tokio runtime
+    pub fn build(self) -> Result<MediaPipeline, PipelineError> {
+        if self.audio_only && (self.watermark || self.preview) {
+            let what = if self.watermark {
+                "Watermarking"
+            } else {
+                "Previewing"
+            };
+            return Err(PipelineError::NeedsVideo(what));
+        }
+        if !self.audio_only {
+            let needs_audio = [
+                (self.loudness.is_some(), "Loudness normalization"),
+                (self.push_to_talk, "Push-to-talk"),
+                (self.loopback.is_some(), "A loopback source"),
+            ];
+            if let Some((_, what)) = needs_audio.iter().find(|(wanted, _)| *wanted) {
+                return Err(PipelineError::NeedsAudio(what));
+            }
+        }
+        if self.audio_only && self.rendition.is_some() {
+            return Err(PipelineError::NeedsVideo("A second rendition"));
+        }
+        if self.ducking.is_some() && self.loopback.is_none() {
+            return Err(PipelineError::NoLoopback);
+        }
+        if self.watermark && !cfg!(feature = "watermark") {
+            return Err(PipelineError::NotEnabled {
+                what: "Watermarking".to_string(),
+                feature: "watermark",
+            });
+        }
+        gst::init()?;
+        let runtime =
+            tokio::runtime::Handle::try_current().map_err(|_| PipelineError::NoRuntime)?;
+
+        let capability = if self.audio_only {
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_OPUS.to_owned(),
+                clock_rate: OPUS_CLOCK_RATE,
+                channels: OPUS_CHANNELS,
+                ..Default::default()
+            }
+        } else {
+            RTCRtpCodecCapability {
+                mime_type: self.codec.mime_type().to_owned(),
+                clock_rate: 90000,
+                ..Default::default()
+            }
+        };
+        let rendition = self.rendition.filter(|_| Rendition::affordable());
+        let low_track = rendition.map(|_| {
+            Arc::new(TrackLocalStaticSample::new(
+                capability.clone(),
+                format!("{}-low", self.track_id),
+                self.stream_id.clone(),
+            ))
+        });
+        let track = Arc::new(TrackLocalStaticSample::new(
+            capability,
+            self.track_id,
+            self.stream_id,
+        ));
+
+        // ✅ source → videoconvert → videoscale → [I420] → (videobalance)
+        //    → (tee → queue) → encoder
+        //    → (tee → queue) → appsink; the first tee feeds the preview and the
+        //    second rendition
+        // The watermark is drawn into the luma plane, and every encoder takes I420.
+        // Audio-only: source → audioconvert → audioresample
+        //    → ([48 kHz stereo S16LE] → volume → (audiomixer, with a loopback))
+        //    → (audioloudnorm → audioconvert → audioresample) → [48 kHz stereo S16LE]
+        //    → opusenc → (tee → queue) → appsink, metered before the encoder
+        let raw_caps = if self.audio_only {
+            gst::Caps::builder("audio/x-raw")
+                .field("format", "S16LE")
+                .field("rate", OPUS_CLOCK_RATE as i32)
+                .field("channels", OPUS_CHANNELS as i32)
+                .build()
+        } else {
+            gst::Caps::builder("video/x-raw")
+                .field("format", "I420")
+                .build()
+        };
+        let raw = gst::ElementFactory::make("capsfilter")
+            .property("caps", raw_caps.clone())
+            .build()?;
+        let mut jpeg_input = None;
+        let mut mic = None;
+        let mut standby = None;
+        let mut mixer = None;
+        let mut elements = if self.audio_only {
+            let mut elements = vec![
+                self.source.audio_element()?,
+                gst::ElementFactory::make("audioconvert").build()?,
+                gst::ElementFactory::make("audioresample").build()?,
+            ];
+            // The microphone's own volume, to open and close it and to mix it
+            if self.push_to_talk || self.controls || self.loopback.is_some() {
+                let volume = gst::ElementFactory::make("volume")
+                    .property("mute", self.push_to_talk)
+                    .build()?;
+                elements.extend([
+                    gst::ElementFactory::make("capsfilter")
+                        .property("caps", raw_caps.clone())
+                        .build()?,
+                    volume.clone(),
+                ]);
+                mic = Some(volume);
+            }
+            if self.loopback.is_some() {
+                let audiomixer = gst::ElementFactory::make("audiomixer").build()?;
+                elements.push(audiomixer.clone());
+                mixer = Some(audiomixer);
+            }
+            // The normalizer works at its own format and rate; converted back after
+            if let Some(target) = self.loudness {
+                elements.extend([
+                    gst::ElementFactory::make(LOUDNESS_NORMALIZER)
+                        .property("loudness-target", target)
+                        .build()?,
+                    gst::ElementFactory::make("audioconvert").build()?,
+                    gst::ElementFactory::make("audioresample").build()?,
+                ]);
+                println!("🔊 Normalizing loudness to {} LUFS", target);
+            }
+            elements.push(raw.clone());
+            elements
+        } else {
+            let (source, input) = self.source.element()?;
+            jpeg_input = input;
+            let mut elements = vec![
+                source,
+                gst::ElementFactory::make("videoconvert").build()?,
+                gst::ElementFactory::make("videoscale").build()?,
+                raw.clone(),
+            ];
+            // Passes frames through untouched until it's turned to black
+            if self.controls {
+                let balance = gst::ElementFactory::make("videobalance").build()?;
+                elements.push(balance.clone());
+                standby = Some(balance);
+            }
+            elements
+        };
+        let tee = (self.preview || rendition.is_some())
+            .then(|| gst::ElementFactory::make("tee").build())
+            .transpose()?;
+        if let Some(tee) = &tee {
+            elements.push(tee.clone());
+            elements.push(gst::ElementFactory::make("queue").build()?);
+        }
+        let encoder_at = elements.len();
+        if self.audio_only {
+            elements.push(gst::ElementFactory::make("opusenc").build()?);
+        } else {
+            elements.extend(self.codec.encoder()?);
+        }
+        let encoder = elements[encoder_at].clone();
+        // Recordings branch off the encoded frames, see `recording`
+        let record_tee = self
+            .recordable
+            .then(|| gst::ElementFactory::make("tee").build())
+            .transpose()?;
+        if let Some(tee) = &record_tee {
+            elements.push(tee.clone());
+            elements.push(gst::ElementFactory::make("queue").build()?);
+        }
+
+        let audio_meter = self
+            .audio_only
+            .then(|| {
+                raw.static_pad("src")
+                    .map(|pad| AudioMeter::attach(&pad))
+                    .ok_or(PipelineError::MissingPad("capsfilter", "src"))
+            })
+            .transpose()?;
+        // On the way in, so it sees the frames as captured, before the watermark
+        let picture_monitor = (!self.audio_only)
+            .then(|| {
+                raw.static_pad("sink")
+                    .map(|pad| PictureMonitor::attach(&pad))
+                    .ok_or(PipelineError::MissingPad("capsfilter", "sink"))
+            })
+            .transpose()?;
+
+        let sink = AppSink::builder().build();
+        elements.push(sink.clone().upcast());
+
+        let pipeline = gst::Pipeline::new();
+        pipeline.add_many(&elements)?;
+        gst::Element::link_many(&elements)?;
+
+        // ✅ loopback → audioconvert → audioresample → [48 kHz stereo S16LE] → volume
+        //    → the mixer, beside the microphone
+        if let (Some(source), Some(mixer), Some(mic)) = (&self.loopback, &mixer, &mic) {
+            let volume = gst::ElementFactory::make("volume").build()?;
+            let branch = [
+                source.audio_element()?,
+                gst::ElementFactory::make("audioconvert").build()?,
+                gst::ElementFactory::make("audioresample").build()?,
+                gst::ElementFactory::make("capsfilter")
+                    .property("caps", raw_caps.clone())
+                    .build()?,
+                volume.clone(),
+            ];
+            pipeline.add_many(&branch)?;
+            gst::Element::link_many(&branch)?;
+            volume.link(mixer)?;
+            println!("🎚️ Mixing in '{}' under the microphone", source);
+            if let Some(ducking) = self.ducking {
+                ducking.attach(
+                    &mic.static_pad("src")
+                        .ok_or(PipelineError::MissingPad("volume", "src"))?,
+                    volume,
+                );
+                println!(
+                    "🦆 Ducking it by {} dB while the microphone is above {} dBFS",
+                    ducking.depth_db, ducking.threshold_db
+                );
+            }
+        }
+
+        // ✅ tee → ... → appsink → the low track; see `rendition`
+        let low = match (rendition, &tee, low_track) {
+            (Some(rendition), Some(tee), Some(low_track)) => {
+                let (low, low_sink) = rendition.build(
+                    &pipeline,
+                    tee,
+                    self.codec,
+                    self.recordable,
+                    low_track.clone(),
+                )?;
+                feed_track(
+                    &low_sink,
+                    low_track,
+                    self.pacing,
+                    &runtime,
+                    ThroughputCounter::default(),
+                );
+                Some(low)
+            }
+            _ => None,
+        };
+
+        // ✅ tee → queue → videoconvert → window; a slow window must not hold up the encoder
+        if let Some(tee) = tee.filter(|_| self.preview) {
+            let preview = [
+                gst::ElementFactory::make("queue")
+                    .property_from_str("leaky", "downstream")
+                    .property("max-size-buffers", 2u32)
+                    .build()?,
+                gst::ElementFactory::make("videoconvert").build()?,
+                gst::ElementFactory::make("autovideosink")
+                    .property("sync", false)
+                    .build()?,
+            ];
+            pipeline.add_many(&preview)?;
+            gst::Element::link_many(&preview)?;
+            tee.link(&preview[0])?;
+            println!("🖥️ Previewing the broadcast in a local window");
+        }
+
+        // Stamped before the tee, so the preview shows exactly what is encoded
+        #[cfg(feature = "watermark")]
+        if self.watermark {
+            stamp_frames(
+                &raw.static_pad("src")
+                    .ok_or(PipelineError::MissingPad("capsfilter", "src"))?,
+            );
+            println!("🔖 Watermarking frames with sequence numbers and timestamps");
+        }
+
+        // Frames are counted as they leave for the encoder
+        let throughput = ThroughputCounter::default();
+        if !self.audio_only {
+            throughput.count_frames(
+                &raw.static_pad("src")
+                    .ok_or(PipelineError::MissingPad("capsfilter", "src"))?,
+            );
+        }
+        feed_track(
+            &sink,
+            track.clone(),
+            self.pacing && !self.audio_only,
+            &runtime,
+            throughput.clone(),
+        );
+
+        Ok(MediaPipeline {
+            pipeline,
+            track,
+            codec: self.codec,
+            audio_only: self.audio_only,
+            encoder,
+            record_tee,
+            jpeg_input,
+            audio_meter,
+            picture_monitor,
+            mic: mic.filter(|_| self.push_to_talk || self.controls),
+            standby,
+            throughput,
+            low,
+            frames: (!self.audio_only).then(|| raw.static_pad("src")).flatten(),
+        })
+    }
+}
+
+// ✅ Hand encoded frames to a Tokio task writing them to `track`; the streaming
+// thread never blocks on the network
+fn feed_track(
+    sink: &AppSink,
+    track: Arc<TrackLocalStaticSample>,
+    pacing: bool,
+    runtime: &tokio::runtime::Handle,
+    throughput: ThroughputCounter,
+) {
+    let (sample_tx, mut sample_rx) = mpsc::channel::<Frame>(SAMPLE_QUEUE);
+    let queue = throughput.clone();
+    sink.set_callbacks(
+        AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                throughput.encoded(buffer.size());
+                let frame = Frame {
+                    sample: frame_sample(buffer)?,
+                    pts: buffer.pts().map(|t| Duration::from_nanos(t.nseconds())),
+                    interval: sample
+                        .caps()
+                        .filter(|_| pacing)
+                        .and_then(pacing::frame_interval),
+                };
+                // Falling behind: drop the frame rather than stall capture
+                let _ = sample_tx.try_send(frame);
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+
+    runtime.spawn(async move {
+        // Keeps the track's RTP timeline on the capture clock (see drift.rs)
+        let mut drift = DriftCorrector::default();
+        // Evens out bursts from the source (see pacing.rs)
+        let mut pacer = Pacer::default();
+        while let Some(Frame {
+            mut sample,
+            pts,
+            interval,
+        }) = sample_rx.recv().await
+        {
+            queue.queued(sample_rx.len());
+            if let Some(interval) = interval {
+                pacer.wait(interval, sample_rx.len()).await;
+            }
+            sample.duration = drift.duration(pts, sample.duration);
+            let _ = track.write_sample(&sample).await;
+        }
+    });
+}
+
+// An encoded frame on its way to the track, with its capture timestamp (for
+// drift compensation) and the time between frames at the negotiated rate (for
+// pacing, if the frames are paced)
+struct Frame {
+    sample: Sample,
+    pts: Option<Duration>,
+    interval: Option<Duration>,
+}
+
+// ✅ Set an encoder's target bitrate; libvpx takes bits per second, x264 and the
+// hardware encoders kbit/s
+pub(crate) fn set_encoder_bitrate(encoder: &gst::Element, kbps: u32) -> Result<(), PipelineError> {
+    if encoder.has_property("target-bitrate", None) {
+        encoder.set_property_from_str("target-bitrate", &(kbps * 1000).to_string());
+    } else if encoder.has_property("bitrate", None) {
+        encoder.set_property_from_str("bitrate", &kbps.to_string());
+    } else {
+        let name = encoder.factory().map(|factory| factory.name().to_string());
+        return Err(PipelineError::NoBitrateControl(name.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+// ✅ Copy an encoded buffer out of GStreamer into a sample for the track
+pub fn frame_sample(buffer: &gst::BufferRef) -> Result<Sample, gst::FlowError> {
+    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+    Ok(Sample {
+        data: Bytes::copy_from_slice(&map),
+        duration: buffer
+            .duration()
+            .map(|d| Duration::from_nanos(d.nseconds()))
+            .unwrap_or(DEFAULT_FRAME_DURATION),
+        timestamp: SystemTime::now(),
+        ..Default::default()
+    })
+}
+
+// A running (or ready) capture/encode pipeline feeding one WebRTC track
+pub struct MediaPipeline {
+    pipeline: gst::Pipeline,
+    track: Arc<TrackLocalStaticSample>,
+    codec: Codec,
+    audio_only: bool,
+    encoder: gst::Element,
+    record_tee: Option<gst::Element>,
+    jpeg_input: Option<JpegInput>,
+    audio_meter: Option<AudioMeter>,
+    picture_monitor: Option<PictureMonitor>,
+    // The microphone's volume, with push-to-talk or controls
+    mic: Option<gst::Element>,
+    // Blacks out the picture, with controls
+    standby: Option<gst::Element>,
+    throughput: ThroughputCounter,
+    // The second rendition, if it was started; see `rendition`
+    low: Option<LowRendition>,
+    // Where raw video frames leave for the encoder, for their size
+    frames: Option<gst::Pad>,
+}
+
+impl MediaPipeline {
+    pub fn builder() -> MediaPipelineBuilder {
+        MediaPipelineBuilder::default()
+    }
+
+    // Add this to a peer connection before creating the offer
+    pub fn track(&self) -> Arc<TrackLocalStaticSample> {
+        self.track.clone()
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn audio_only(&self) -> bool {
+        self.audio_only
+    }
+
+    // ✅ The audio level (dBFS) captured since the last call, for audio-only
+    // pipelines; see `level`
+    pub fn audio_level(&self) -> Option<f64> {
+        self.audio_meter.as_ref()?.take_db()
+    }
+
+    // ✅ How long the outgoing picture has been black or frozen, for video
+    // pipelines; see `picture`
+    pub fn picture(&self) -> Option<Picture> {
+        Some(self.picture_monitor.as_ref()?.picture())
+    }
+
+    // ✅ Open or close the microphone, with push-to-talk or controls
+    pub fn set_mic_open(&self, open: bool) -> Result<(), PipelineError> {
+        let mic = self.mic.as_ref().ok_or(PipelineError::NoPushToTalk)?;
+        mic.set_property("mute", !open);
+        Ok(())
+    }
+
+    // ✅ Cut to a black picture, or back to the source, with controls; watchers
+    // stay connected either way
+    pub fn set_standby(&self, on: bool) -> Result<(), PipelineError> {
+        let balance = self.standby.as_ref().ok_or(PipelineError::NoStandby)?;
+        let (brightness, contrast, saturation) = if on {
+            (-1.0, 0.0, 0.0)
+        } else {
+            (0.0, 1.0, 1.0)
+        };
+        balance.set_property("brightness", brightness);
+        balance.set_property("contrast", contrast);
+        balance.set_property("saturation", saturation);
+        Ok(())
+    }
+
+    // ✅ Frames, bytes and queue so far; see `throughput`
+    pub fn throughput(&self) -> Throughput {
+        self.throughput.read()
+    }
+
+    // ✅ The second rendition's track, to send instead of `track` to watchers
+    // asking for the low layer; None if there's none, or it stopped for want of CPU
+    pub fn low_track(&self) -> Option<Arc<TrackLocalStaticSample>> {
+        self.low
+            .as_ref()
+            .filter(|low| low.running())
+            .map(|low| low.track.clone())
+    }
+
+    // ✅ The size of the frames being encoded, once they flow; None for audio-only
+    // pipelines
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        // Plain caps fields: gstreamer-video only comes with the watermark feature
+        let caps = self.frames.as_ref()?.current_caps()?;
+        let structure = caps.structure(0)?;
+        let width = structure.get::<i32>("width").ok()?;
+        let height = structure.get::<i32>("height").ok()?;
+        Some((u32::try_from(width).ok()?, u32::try_from(height).ok()?))
+    }
+
+    // Where to push frames for `Source::Jpeg`
+    pub fn jpeg_input(&self) -> Option<JpegInput> {
+        self.jpeg_input.clone()
+    }
+
+    pub fn pipeline(&self) -> &gst::Pipeline {
+        &self.pipeline
+    }
+
+    pub fn start(&self) -> Result<(), PipelineError> {
+        self.pipeline.set_state(gst::State::Playing)?;
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), PipelineError> {
+        self.pipeline.set_state(gst::State::Null)?;
+        Ok(())
+    }
+
+    // ✅ Change the encoder's target bitrate while it runs, e.g. on the transmitter's
+    // recommendation
+    pub fn set_bitrate(&self, kbps: u32) -> Result<(), PipelineError> {
+        if self.audio_only {
+            return Err(PipelineError::NeedsVideo("Bitrate control"));
+        }
+        set_encoder_bitrate(&self.encoder, kbps)
+    }
+
+    // ✅ Have the encoder emit a keyframe (with SPS/PPS) now, for a watcher that just
+    // joined or lost the picture
+    pub fn request_keyframe(&self) -> Result<(), PipelineError> {
+        if self.audio_only {
+            return Err(PipelineError::NeedsVideo("Keyframe requests"));
+        }
+        let request = gst::Structure::builder("GstForceKeyUnit")
+            .field("all-headers", true)
+            .build();
+        // The low rendition's watchers lose the picture just the same
+        if let Some(low) = &self.low {
+            low.encoder
+                .send_event(gst::event::CustomUpstream::new(request.clone()));
+        }
+        if !self
+            .encoder
+            .send_event(gst::event::CustomUpstream::new(request))
+        {
+            let name = self
+                .encoder
+                .factory()
+                .map(|factory| factory.name().to_string());
+            return Err(PipelineError::NoKeyframeControl(name.unwrap_or_default()));
+        }
+        Ok(())
+    }
+
+    // ✅ Start writing the encoded stream to a Matroska file, without interrupting the track
+    pub fn start_recording(&self, path: &Path) -> Result<Recording, PipelineError> {
+        let tee = self
+            .record_tee
+            .as_ref()
+            .ok_or(PipelineError::NotRecordable)?;
+        let mut recording =
+            Recording::start(&self.pipeline, tee, self.codec, self.audio_only, path)?;
+        // ✅ The low rendition alongside, as <name>-low.mkv
+        if let Some(low) = &self.low
+            && low.running()
+            && let Some(low_tee) = &low.record_tee
+        {
+            let low_path = path.with_file_name(format!(
+                "{}-low.mkv",
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            ));
+            recording.low = Some(Box::new(Recording::start(
+                &self.pipeline,
+                low_tee,
+                self.codec,
+                false,
+                &low_path,
+            )?));
+            println!(
+                "⏺️ Also recording the second rendition to {}",
+                low_path.display()
+            );
+        }
+        Ok(recording)
+    }
+
+    // ✅ Finish the file and detach it; returns its size in bytes
+    pub async fn stop_recording(&self, mut recording: Recording) -> Result<u64, PipelineError> {
+        let tee = self
+            .record_tee
+            .as_ref()
+            .ok_or(PipelineError::NotRecordable)?;
+        if let (Some(low), Some(low_tee)) = (
+            recording.low.take(),
+            self.low.as_ref().and_then(|low| low.record_tee.as_ref()),
+        ) {
+            low.finish(&self.pipeline, low_tee).await?;
+        }
+        recording.finish(&self.pipeline, tee).await
+    }
+
+    // ✅ Resolves with the first error the pipeline posts (device unplugged, encoder
+    // failure...); capture doesn't recover from these, so treat it as fatal
+    pub async fn failed(&self) -> PipelineError {
+        let Some(bus) = self.pipeline.bus() else {
+            return std::future::pending().await;
+        };
+        let mut messages = bus.stream_filtered(&[gst::MessageType::Error]);
+        while let Some(msg) = messages.next().await {
+            if let gst::MessageView::Error(err) = msg.view() {
+                return PipelineError::Gst(err.error());
+            }
+        }
+        std::future::pending().await
+    }
+}
+
+impl Drop for MediaPipeline {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+    }
+}
+
+// ✅ Stamp sequence number + time into the top-left corner of each raw frame
+#[cfg(feature = "watermark")]
+fn stamp_frames(pad: &gst::Pad) {
+    let sequence = AtomicU32::new(0);
+    pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+        let Some(video) = pad
+            .current_caps()
+            .and_then(|caps| gst_video::VideoInfo::from_caps(&caps).ok())
+        else {
+            return gst::PadProbeReturn::Ok;
+        };
+        if let Some(gst::PadProbeData::Buffer(buffer)) = &mut info.data
+            && let Ok(mut map) = buffer.make_mut().map_writable()
+        {
+            let mark = Watermark::new(sequence.fetch_add(1, Ordering::Relaxed), SystemTime::now());
+            watermark::stamp(
+                map.as_mut_slice(),
+                video.stride()[0] as usize,
+                video.height() as usize,
+                mark,
+            );
+        }
+        gst::PadProbeReturn::Ok
+    });
+}
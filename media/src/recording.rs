@@ -0,0 +1,150 @@
+// Recording the encoded stream on the streamer's side, started and stopped while
+// it's live (see `MediaPipelineBuilder::recordable`):
+//
+//   encoder → tee ─┬→ queue → appsink                                (the track)
+//                  └→ queue → [h264parse] → matroskamux → filesink   (per recording)
+//
+// Frames are written exactly as they're sent, so recording costs no encoding;
+// Matroska takes VP8, VP9, H.264 and (audio-only streams) Opus alike. Playback
+// starts at the first keyframe.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use tokio::sync::mpsc;
+
+use crate::PipelineError;
+use crate::pipeline::Codec;
+
+// How long the muxer gets to write its index when a recording stops
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// One file being written; finish it with `MediaPipeline::stop_recording`
+pub struct Recording {
+    bin: gst::Bin,
+    sink: gst::Element,
+    tee_pad: gst::Pad,
+    path: PathBuf,
+    // The second rendition's file, recorded alongside; see `rendition`
+    pub(crate) low: Option<Box<Recording>>,
+}
+
+impl Recording {
+    // ✅ Attach a queue → muxer → file branch to the running tee
+    pub(crate) fn start(
+        pipeline: &gst::Pipeline,
+        tee: &gst::Element,
+        codec: Codec,
+        audio_only: bool,
+        path: &Path,
+    ) -> Result<Self, PipelineError> {
+        let mut elements = vec![gst::ElementFactory::make("queue").build()?];
+        // Matroska stores H.264 length-prefixed, not as the byte-stream we send
+        if codec == Codec::H264 && !audio_only {
+            elements.push(gst::ElementFactory::make("h264parse").build()?);
+        }
+        let muxer = gst::ElementFactory::make("matroskamux").build()?;
+        let sink = gst::ElementFactory::make("filesink")
+            .property("location", path.to_string_lossy().as_ref())
+            // Don't hold the live pipeline up waiting for this sink to preroll
+            .property("async", false)
+            .build()?;
+
+        let bin = gst::Bin::new();
+        bin.add_many(&elements)?;
+        bin.add_many([&muxer, &sink])?;
+        gst::Element::link_many(&elements)?;
+        // Nothing flows yet to tell the muxer what's coming, so pick its pad ourselves
+        let template = if audio_only { "audio_%u" } else { "video_%u" };
+        let mux_pad = muxer
+            .request_pad_simple(template)
+            .ok_or(PipelineError::MissingPad("matroskamux", "sink"))?;
+        elements
+            .last()
+            .and_then(|last| last.static_pad("src"))
+            .ok_or(PipelineError::MissingPad("queue", "src"))?
+            .link(&mux_pad)?;
+        muxer.link(&sink)?;
+        let ghost = gst::GhostPad::with_target(
+            &elements[0]
+                .static_pad("sink")
+                .ok_or(PipelineError::MissingPad("queue", "sink"))?,
+        )?;
+        bin.add_pad(&ghost)?;
+
+        pipeline.add(&bin)?;
+        bin.sync_state_with_parent()?;
+        let tee_pad = tee
+            .request_pad_simple("src_%u")
+            .ok_or(PipelineError::MissingPad("tee", "src"))?;
+        tee_pad.link(&ghost)?;
+
+        Ok(Recording {
+            bin,
+            sink,
+            tee_pad,
+            path: path.to_path_buf(),
+            low: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Where the second rendition is recorded, if it is
+    pub fn low_path(&self) -> Option<&Path> {
+        self.low.as_ref().map(|low| low.path())
+    }
+
+    // Bytes written to the file so far
+    pub fn bytes(&self) -> u64 {
+        fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0)
+    }
+
+    // ✅ Cut the branch loose at a frame boundary, push EOS through the muxer so the
+    // file gets its index, then drop the branch; returns the file size
+    pub(crate) async fn finish(
+        self,
+        pipeline: &gst::Pipeline,
+        tee: &gst::Element,
+    ) -> Result<u64, PipelineError> {
+        let (eos_tx, mut eos_rx) = mpsc::unbounded_channel();
+        self.sink
+            .static_pad("sink")
+            .ok_or(PipelineError::MissingPad("filesink", "sink"))?
+            .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                if let Some(gst::PadProbeData::Event(event)) = &info.data
+                    && event.type_() == gst::EventType::Eos
+                {
+                    let _ = eos_tx.send(());
+                }
+                gst::PadProbeReturn::Ok
+            });
+
+        self.tee_pad.add_probe(gst::PadProbeType::IDLE, |pad, _| {
+            if let Some(peer) = pad.peer() {
+                let _ = pad.unlink(&peer);
+                peer.send_event(gst::event::Eos::new());
+            }
+            gst::PadProbeReturn::Remove
+        });
+        if tokio::time::timeout(FINISH_TIMEOUT, eos_rx.recv())
+            .await
+            .is_err()
+        {
+            eprintln!(
+                "⚠️ Recording {} did not finish in time; the file may lack its index",
+                self.path.display()
+            );
+        }
+
+        self.bin.set_state(gst::State::Null)?;
+        pipeline.remove(&self.bin)?;
+        tee.release_request_pad(&self.tee_pad);
+        Ok(self.bytes())
+    }
+}
@@ -0,0 +1,152 @@
+// A second, lower-bitrate rendition of the video, encoded in parallel from the
+// same raw frames (see `MediaPipelineBuilder::rendition`), for watchers asking
+// for the low layer and for recordings at both bitrates:
+//
+//   raw → tee ─┬→ queue → encoder → ...                                 (the track)
+//              └→ queue (leaky) → valve → videoscale → [height] → encoder
+//                   → (tee → queue) → appsink                           (the low track)
+//
+// A second encoder costs real CPU. It isn't started on a machine with fewer than
+// MIN_CORES cores, and once running, a queue in front of it that keeps
+// overflowing (OVERRUNS within OVERRUN_WINDOW) means the machine can't keep up:
+// the valve closes for good, so the main stream doesn't suffer for it.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::AppSink;
+use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+use crate::PipelineError;
+use crate::pipeline::{Codec, set_encoder_bitrate};
+
+const MIN_CORES: usize = 4;
+// Frames waiting for the second encoder before the oldest are dropped
+const QUEUE_FRAMES: u32 = 5;
+const OVERRUNS: u32 = 30;
+const OVERRUN_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rendition {
+    // Frame height; the width follows the aspect ratio
+    pub height: u32,
+    pub kbps: u32,
+}
+
+impl Default for Rendition {
+    fn default() -> Self {
+        Rendition {
+            height: 360,
+            kbps: 300,
+        }
+    }
+}
+
+// The second rendition, running in the pipeline
+pub(crate) struct LowRendition {
+    pub track: Arc<TrackLocalStaticSample>,
+    pub encoder: gst::Element,
+    // Recordings of this rendition branch off here, like the main one's
+    pub record_tee: Option<gst::Element>,
+    valve: gst::Element,
+}
+
+impl LowRendition {
+    // False once the valve has closed for want of CPU
+    pub fn running(&self) -> bool {
+        !self.valve.property::<bool>("drop")
+    }
+}
+
+impl Rendition {
+    // ✅ Whether this machine has the cores for a second encoder; says why not
+    pub(crate) fn affordable() -> bool {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        if cores < MIN_CORES {
+            println!(
+                "⚠️ Not encoding a second rendition: {} CPU cores, {} wanted",
+                cores, MIN_CORES
+            );
+            return false;
+        }
+        true
+    }
+
+    // ✅ Add the branch from `tee` to an appsink, whose frames go to `track`
+    pub(crate) fn build(
+        self,
+        pipeline: &gst::Pipeline,
+        tee: &gst::Element,
+        codec: Codec,
+        recordable: bool,
+        track: Arc<TrackLocalStaticSample>,
+    ) -> Result<(LowRendition, AppSink), PipelineError> {
+        let queue = gst::ElementFactory::make("queue")
+            .property_from_str("leaky", "downstream")
+            .property("max-size-buffers", QUEUE_FRAMES)
+            .property("max-size-bytes", 0u32)
+            .property("max-size-time", 0u64)
+            .build()?;
+        let valve = gst::ElementFactory::make("valve").build()?;
+        let mut elements = vec![
+            queue.clone(),
+            valve.clone(),
+            gst::ElementFactory::make("videoscale").build()?,
+            gst::ElementFactory::make("capsfilter")
+                .property(
+                    "caps",
+                    gst::Caps::builder("video/x-raw")
+                        .field("height", self.height as i32)
+                        .build(),
+                )
+                .build()?,
+        ];
+        let encoder_at = elements.len();
+        elements.extend(codec.encoder()?);
+        let encoder = elements[encoder_at].clone();
+        set_encoder_bitrate(&encoder, self.kbps)?;
+        let record_tee = recordable
+            .then(|| gst::ElementFactory::make("tee").build())
+            .transpose()?;
+        if let Some(tee) = &record_tee {
+            elements.push(tee.clone());
+            elements.push(gst::ElementFactory::make("queue").build()?);
+        }
+        let sink = AppSink::builder().build();
+        elements.push(sink.clone().upcast());
+
+        pipeline.add_many(&elements)?;
+        gst::Element::link_many(&elements)?;
+        tee.link(&queue)?;
+
+        // ✅ Close the valve for good once the encoder keeps falling behind
+        let overruns = Mutex::new((Instant::now(), 0));
+        let closing = valve.clone();
+        queue.connect("overrun", false, move |_| {
+            let mut overruns = overruns.lock().unwrap_or_else(PoisonError::into_inner);
+            if overruns.0.elapsed() > OVERRUN_WINDOW {
+                *overruns = (Instant::now(), 0);
+            }
+            overruns.1 += 1;
+            if overruns.1 == OVERRUNS {
+                closing.set_property("drop", true);
+                println!("⚠️ The second rendition can't keep up with the source; stopped it");
+            }
+            None
+        });
+        println!(
+            "🪜 Encoding a second rendition at {}p, {} kbps",
+            self.height, self.kbps
+        );
+
+        let low = LowRendition {
+            track,
+            encoder,
+            record_tee,
+            valve,
+        };
+        Ok((low, sink))
+    }
+}
@@ -0,0 +1,84 @@
+// What the pipeline moves, for a live view of its health (the streamer's --tui):
+// video frames captured, bytes encoded, and encoded frames queued for the track.
+// The counts only grow; rates come from two readings:
+//
+//   let before = media.throughput();
+//   tokio::time::sleep(Duration::from_secs(1)).await;
+//   let fps = media.throughput().fps_since(&before, Duration::from_secs(1));
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Throughput {
+    // Raw video frames on their way to the encoder; none for audio-only pipelines
+    pub frames: u64,
+    pub encoded_bytes: u64,
+    // Encoded frames waiting to be written to the track; a growing queue means
+    // the network side can't keep up
+    pub queued: usize,
+}
+
+impl Throughput {
+    pub fn fps_since(&self, before: &Throughput, elapsed: Duration) -> f64 {
+        per_second(self.frames.saturating_sub(before.frames), elapsed)
+    }
+
+    pub fn kbps_since(&self, before: &Throughput, elapsed: Duration) -> f64 {
+        let bytes = self.encoded_bytes.saturating_sub(before.encoded_bytes);
+        per_second(bytes * 8, elapsed) / 1000.0
+    }
+}
+
+fn per_second(count: u64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    count as f64 / elapsed.as_secs_f64()
+}
+
+#[derive(Default)]
+struct Counts {
+    frames: AtomicU64,
+    encoded_bytes: AtomicU64,
+    queued: AtomicUsize,
+}
+
+// Shared with the streaming threads and the task feeding the track
+#[derive(Clone, Default)]
+pub(crate) struct ThroughputCounter {
+    counts: Arc<Counts>,
+}
+
+impl ThroughputCounter {
+    // ✅ Count every buffer passing `pad` as a frame
+    pub fn count_frames(&self, pad: &gst::Pad) {
+        let counts = self.counts.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            counts.frames.fetch_add(1, Ordering::Relaxed);
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    pub fn encoded(&self, bytes: usize) {
+        self.counts
+            .encoded_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn queued(&self, frames: usize) {
+        self.counts.queued.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn read(&self) -> Throughput {
+        Throughput {
+            frames: self.counts.frames.load(Ordering::Relaxed),
+            encoded_bytes: self.counts.encoded_bytes.load(Ordering::Relaxed),
+            queued: self.counts.queued.load(Ordering::Relaxed),
+        }
+    }
+}
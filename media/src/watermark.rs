@@ -0,0 +1,208 @@
+// Pixel-corner barcode stamped into the luma plane of every outgoing frame.
+//
+// Layout: MARKER (8 bits) + sequence number (32 bits) + capture time in Unix
+// milliseconds truncated to 32 bits, MSB first. Each bit is a CELL x CELL block
+// that is either black (0) or white (1), laid out in rows of CELLS_PER_ROW
+// starting at the top-left corner. Blocks are large enough to survive lossy
+// encoding, so an automated watcher can decode them after the full round trip.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CELL: usize = 8;
+pub const CELLS_PER_ROW: usize = 24;
+const MARKER: u8 = 0b1011_0010;
+const BITS: usize = 8 + 32 + 32;
+
+const BLACK: u8 = 16;
+const WHITE: u8 = 235;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watermark {
+    pub sequence: u32,
+    pub timestamp_ms: u32,
+}
+
+impl Watermark {
+    pub fn new(sequence: u32, timestamp: SystemTime) -> Self {
+        let millis = timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        Watermark {
+            sequence,
+            timestamp_ms: millis as u32,
+        }
+    }
+
+    // Milliseconds between the stamped capture time and `now`, wrapping-safe
+    pub fn latency_ms(&self, now: SystemTime) -> u32 {
+        Watermark::new(0, now)
+            .timestamp_ms
+            .wrapping_sub(self.timestamp_ms)
+    }
+
+    fn bits(&self) -> u128 {
+        ((MARKER as u128) << 64) | ((self.sequence as u128) << 32) | self.timestamp_ms as u128
+    }
+}
+
+// Size of the barcode area in pixels (width, height)
+pub fn area() -> (usize, usize) {
+    let rows = BITS.div_ceil(CELLS_PER_ROW);
+    (CELLS_PER_ROW * CELL, rows * CELL)
+}
+
+// ✅ Stamp the barcode into an 8-bit luma plane (e.g. the Y plane of I420)
+pub fn stamp(luma: &mut [u8], stride: usize, height: usize, mark: Watermark) -> bool {
+    if !fits(luma, stride, height) {
+        return false;
+    }
+
+    let bits = mark.bits();
+    for bit in 0..BITS {
+        let on = (bits >> (BITS - 1 - bit)) & 1 == 1;
+        let (x0, y0) = cell_origin(bit);
+        for y in y0..y0 + CELL {
+            let row = &mut luma[y * stride + x0..y * stride + x0 + CELL];
+            row.fill(if on { WHITE } else { BLACK });
+        }
+    }
+    true
+}
+
+// Decode a barcode previously written by `stamp`, if present
+pub fn read(luma: &[u8], stride: usize, height: usize) -> Option<Watermark> {
+    if !fits(luma, stride, height) {
+        return None;
+    }
+
+    let mut bits: u128 = 0;
+    for bit in 0..BITS {
+        let (x0, y0) = cell_origin(bit);
+        // Sample the centre of the cell to stay clear of codec ringing at the edges
+        let quarter = CELL / 4;
+        let mut sum = 0u32;
+        for y in y0 + quarter..y0 + CELL - quarter {
+            for x in x0 + quarter..x0 + CELL - quarter {
+                sum += luma[y * stride + x] as u32;
+            }
+        }
+        let samples = ((CELL - 2 * quarter) * (CELL - 2 * quarter)) as u32;
+        bits = (bits << 1) | u128::from(sum / samples > 128);
+    }
+
+    if (bits >> 64) as u8 != MARKER {
+        return None;
+    }
+    Some(Watermark {
+        sequence: (bits >> 32) as u32,
+        timestamp_ms: bits as u32,
+    })
+}
+
+fn fits(luma: &[u8], stride: usize, height: usize) -> bool {
+    let (w, h) = area();
+    stride >= w && height >= h && luma.len() >= stride * h
+}
+
+fn cell_origin(bit: usize) -> (usize, usize) {
+    ((bit % CELLS_PER_ROW) * CELL, (bit / CELLS_PER_ROW) * CELL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const WIDTH: usize = 320;
+    const HEIGHT: usize = 240;
+
+    fn frame() -> Vec<u8> {
+        vec![128; WIDTH * HEIGHT]
+    }
+
+    fn mark() -> Watermark {
+        Watermark::new(
+            0xDEAD_BEEF,
+            UNIX_EPOCH + Duration::from_millis(1_700_000_123_456),
+        )
+    }
+
+    #[test]
+    fn stamped_frames_read_back() {
+        let mut luma = frame();
+        assert!(stamp(&mut luma, WIDTH, HEIGHT, mark()));
+        assert_eq!(read(&luma, WIDTH, HEIGHT), Some(mark()));
+        // Only the barcode's corner is touched
+        let (w, h) = area();
+        assert!(luma[h * WIDTH..].iter().all(|&y| y == 128));
+        assert!((0..h).all(|y| {
+            luma[y * WIDTH + w..(y + 1) * WIDTH]
+                .iter()
+                .all(|&y| y == 128)
+        }));
+    }
+
+    #[test]
+    fn stamps_survive_noise_at_the_cell_edges() {
+        let mut luma = frame();
+        assert!(stamp(&mut luma, WIDTH, HEIGHT, mark()));
+        // Codec ringing: each cell's border pulled halfway to grey, its centre a little off
+        for (i, y) in luma.iter_mut().enumerate() {
+            let (x, row) = (i % WIDTH % CELL, i / WIDTH % CELL);
+            let edge = x == 0 || row == 0 || x == CELL - 1 || row == CELL - 1;
+            *y = if edge {
+                (*y / 2).saturating_add(64)
+            } else {
+                y.saturating_add(((i * 7) % 41) as u8).saturating_sub(20)
+            };
+        }
+        assert_eq!(read(&luma, WIDTH, HEIGHT), Some(mark()));
+    }
+
+    #[test]
+    fn the_stride_may_exceed_the_width() {
+        let stride = WIDTH + 32;
+        let mut luma = vec![128; stride * HEIGHT];
+        assert!(stamp(&mut luma, stride, HEIGHT, mark()));
+        assert_eq!(read(&luma, stride, HEIGHT), Some(mark()));
+        assert_eq!(read(&luma, WIDTH, HEIGHT), None);
+    }
+
+    #[test]
+    fn unstamped_frames_read_nothing() {
+        assert_eq!(read(&frame(), WIDTH, HEIGHT), None);
+        assert_eq!(read(&vec![WHITE; WIDTH * HEIGHT], WIDTH, HEIGHT), None);
+    }
+
+    #[test]
+    fn frames_too_small_for_the_barcode_are_left_alone() {
+        let (w, h) = area();
+        for (stride, height, len) in [
+            (w - 1, HEIGHT, (w - 1) * HEIGHT),
+            (WIDTH, h - 1, WIDTH * (h - 1)),
+            (WIDTH, HEIGHT, WIDTH * h - 1),
+        ] {
+            let mut luma = vec![128; len];
+            assert!(
+                !stamp(&mut luma, stride, height, mark()),
+                "{}x{}",
+                stride,
+                height
+            );
+            assert!(luma.iter().all(|&y| y == 128));
+            assert_eq!(read(&luma, stride, height), None);
+        }
+        // Exactly the barcode's size is enough
+        let mut luma = vec![128; w * h];
+        assert!(stamp(&mut luma, w, h, mark()));
+        assert_eq!(read(&luma, w, h), Some(mark()));
+    }
+
+    #[test]
+    fn latency_wraps_with_the_truncated_clock() {
+        let captured = UNIX_EPOCH + Duration::from_millis(u64::from(u32::MAX) - 10);
+        let mark = Watermark::new(1, captured);
+        assert_eq!(mark.latency_ms(captured + Duration::from_millis(40)), 40);
+    }
+}
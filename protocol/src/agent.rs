@@ -0,0 +1,67 @@
+// Control channel for streamer agents: camera boxes that register with the
+// transmitter as available (`/agent?id=...`) and only capture when told to.
+//
+//   transmitter → agent   {"type":"start","stream_id":"lobby","source":"camera","codec":"vp8"}
+//                         {"type":"stop","stream_id":"lobby"}
+//   agent → transmitter   {"type":"status","streams":["lobby"]}
+//                         {"type":"failed","stream_id":"lobby","error":"..."}
+//
+// A started stream is published like any other streamer's, under `stream_id`.
+// Operators command agents through the transmitter's HTTP API:
+//
+//   GET  /agents              → [AgentInfo]
+//   POST /agents/{id}/start   StartStream
+//   POST /agents/{id}/stop    StopStream
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AgentCommand {
+    Start(StartStream),
+    Stop(StopStream),
+}
+
+// Source and codec are given the way the streamer's flags take them; the
+// agent's own configuration fills in what's left out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartStream {
+    pub stream_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StopStream {
+    pub stream_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AgentEvent {
+    // Everything the agent is streaming now; sent on connect and after every change
+    Status { streams: Vec<String> },
+    // A stream couldn't start, or stopped on its own
+    Failed { stream_id: String, error: String },
+}
+
+// Entry of the transmitter's `GET /agents`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentInfo {
+    pub id: String,
+    pub streams: Vec<String>,
+}
+
+impl AgentCommand {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("agent commands always serialize")
+    }
+}
+
+impl AgentEvent {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("agent events always serialize")
+    }
+}
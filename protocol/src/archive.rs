@@ -0,0 +1,32 @@
+// Stream archives: a stream's chat and lifecycle events, kept by the transmitter
+// with --archive-dir and exported by `GET /streams/{id}/archive`, one record per
+// line (NDJSON) or as a JSON array:
+//
+//   {"at_ms":1760510629000,"stream_id":"cam","event":"stream","state":"live","session_id":"6f1c..."}
+//   {"at_ms":1760510641250,"stream_id":"cam","event":"chat","member_id":"w1","message":"hi!","session_id":"6f1c..."}
+//   {"at_ms":1760512204000,"stream_id":"cam","event":"stream","state":"paused","session_id":"6f1c..."}
+//
+// Chat is every watcher's `chat`, and every room `broadcast` that isn't a
+// `Signal`. `at_ms` is Unix time in milliseconds; records are dropped once
+// they're older than the stream's retention.
+
+use serde::{Deserialize, Serialize};
+
+use crate::lifecycle::StreamState;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRecord {
+    pub at_ms: u64,
+    pub stream_id: String,
+    #[serde(flatten)]
+    pub event: ArchiveEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum ArchiveEvent {
+    Chat { member_id: String, message: String },
+    Stream { state: StreamState },
+}
@@ -0,0 +1,32 @@
+// Signaling captures: everything one room's members sent and received, in
+// order, as JSON Lines (written by the transmitter with --capture-dir, fed back
+// by the `replay` tool in tuesdays-integration):
+//
+//   {"at_ms":0,"member_id":"cam","event":"joined","request":"/streamer?id=cam"}
+//   {"at_ms":3,"member_id":"cam","event":"sent","text":"{\"type\":\"session\",...}"}
+//   {"at_ms":412,"member_id":"w1","event":"received","text":"{\"command\":\"broadcast\",...}"}
+//   {"at_ms":9120,"member_id":"w1","event":"left","reason":"closed by client (Normal)"}
+//
+// `received` is what the transmitter got from the member, `sent` what it sent
+// back (errors included); `at_ms` counts from the room's first record.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    pub at_ms: u64,
+    pub member_id: String,
+    #[serde(flatten)]
+    pub event: CaptureEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum CaptureEvent {
+    // The WebSocket upgrade, path and query as requested, tokens and stream keys
+    // blanked out
+    Joined { request: String },
+    Received { text: String },
+    Sent { text: String },
+    Left { reason: String },
+}
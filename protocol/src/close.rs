@@ -0,0 +1,21 @@
+// WebSocket close codes the transmitter uses (4000-4999 is the private range)
+
+// Another connection joined the room with the same member id; don't reconnect
+pub const REPLACED: u16 = 4000;
+pub const REPLACED_REASON: &str = "Replaced by new connection";
+
+// The stream ended a while ago and its room is gone (see `lifecycle`); don't reconnect
+pub const EXPIRED: u16 = 4001;
+pub const EXPIRED_REASON: &str = "Stream expired";
+
+// The connection sent more than its quota allows (see `quota`)
+pub const QUOTA_EXCEEDED: u16 = 4002;
+pub const QUOTA_EXCEEDED_REASON: &str = "Quota exceeded";
+
+// An operator disconnected the member through the admin API; don't reconnect
+pub const KICKED: u16 = 4003;
+pub const KICKED_REASON: &str = "Disconnected by an operator";
+
+// Sent to another instance, with a `redirect` signal first; reconnect there
+pub const REDIRECTED: u16 = 4004;
+pub const REDIRECTED_REASON: &str = "Connect to another instance";
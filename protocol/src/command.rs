@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::directory::StreamDescriptor;
+use crate::error::{ErrorCode, Rejection};
+use crate::roles::AudienceRole;
+use crate::signal::IceCandidate;
+
+// Client → transmitter messages, e.g. `{"command":"broadcast","message":"..."}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Command {
+    // List the member ids in the room; answered with a JSON array
+    List,
+    // Ask for our own member id; answered with a `WhoisResponse`
+    Whois,
+    // Relay `message` verbatim to every member of the room (including the sender);
+    // a viewer's only if it's its side of the negotiation, see `roles`
+    Broadcast {
+        message: String,
+    },
+    // Viewer-side QoE telemetry (bitrate, fps, jitter, loss, ...)
+    Stats {
+        report: Value,
+    },
+    // A streamer's audio level over the last moment, in dBFS (0 is the loudest,
+    // silence around -90), for the room's active speaker; see `Signal::Speaker`
+    Level {
+        db: f64,
+    },
+    // Send an SDP offer to one member of the room rather than all of them; it
+    // arrives as an `offer` signal with `from` and `negotiation_id` added (see
+    // `Signal::to_routed_json`). `negotiation_id` is the offerer's to pick, so
+    // concurrent negotiations with several members don't get mixed up
+    Offer {
+        to: String,
+        negotiation_id: String,
+        sdp: String,
+    },
+    // The answer to a routed offer, sent back to its `from` with its `negotiation_id`
+    Answer {
+        to: String,
+        negotiation_id: String,
+        sdp: String,
+    },
+    // Trickle a candidate to the other side of a routed negotiation; it arrives
+    // as a `candidate` signal with `from` and `negotiation_id` added. Candidates
+    // for a member who hasn't joined yet are held until it does
+    #[serde(rename = "ice-candidate")]
+    IceCandidate {
+        to: String,
+        negotiation_id: String,
+        candidate: IceCandidate,
+    },
+    // Streamers only: relay `message` verbatim to the one watcher `to`, e.g. a
+    // private reply; the streamer hears back `Unknown member` if there's no such
+    // watcher in the room
+    Send {
+        to: String,
+        message: String,
+    },
+    // Watchers only: say `message` to the stream's audience; everyone in the room,
+    // streamers included, hears it as a `chat` signal from the sender (see
+    // `Signal::Chat`). Refused as `Forbidden` while a streamer has audience chat off
+    Chat {
+        message: String,
+    },
+    // Say which client build this is (e.g. "tuesdays-watcher/0.1.0"), for the
+    // transmitter's /clients API and access log; not answered
+    Hello {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_version: Option<String>,
+    },
+    // Streamers only: what the stream is and how it can be played, for the
+    // directory (see `StreamDescriptor`); sent again whenever that changes, each
+    // replacing the last. Not answered
+    Describe {
+        media: StreamDescriptor,
+    },
+    // Send again the numbered messages `from` through `to` that we missed (see
+    // `resume`); they arrive as they were first sent, numbers and all. Refused as
+    // `Replay unavailable` once some of them are no longer kept
+    Replay {
+        from: u64,
+        to: u64,
+    },
+    // Streamers, and watchers ranking at least `role`: give the watcher
+    // `watcher_id` an audience role (see `roles`); answered `Unknown member` if
+    // there's no such watcher, `Forbidden` if it ranks above the sender
+    Assign {
+        watcher_id: String,
+        role: AudienceRole,
+    },
+    // Measure the round trip to the transmitter: `sent_ms` is our clock's time,
+    // Unix milliseconds; answered with a `latency::Pong`
+    Ping {
+        sent_ms: u64,
+    },
+}
+
+impl Command {
+    // Every command's name, as sent in the `command` field
+    pub const NAMES: &[&str] = &[
+        "list",
+        "whois",
+        "broadcast",
+        "stats",
+        "level",
+        "offer",
+        "answer",
+        "ice-candidate",
+        "send",
+        "chat",
+        "hello",
+        "describe",
+        "replay",
+        "assign",
+        "ping",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::List => "list",
+            Command::Whois => "whois",
+            Command::Broadcast { .. } => "broadcast",
+            Command::Stats { .. } => "stats",
+            Command::Level { .. } => "level",
+            Command::Offer { .. } => "offer",
+            Command::Answer { .. } => "answer",
+            Command::IceCandidate { .. } => "ice-candidate",
+            Command::Send { .. } => "send",
+            Command::Chat { .. } => "chat",
+            Command::Hello { .. } => "hello",
+            Command::Describe { .. } => "describe",
+            Command::Replay { .. } => "replay",
+            Command::Assign { .. } => "assign",
+            Command::Ping { .. } => "ping",
+        }
+    }
+
+    // ✅ The `hello` a client sends once connected, announcing e.g. "tuesdays-watcher/0.1.0"
+    pub fn hello(client: &str, version: &str) -> Self {
+        Command::Hello {
+            client_version: Some(format!("{}/{}", client, version)),
+        }
+    }
+
+    // ✅ Parse a WebSocket text frame, telling apart the ways it can be wrong and
+    // saying what exactly (the serde error, or the commands there are)
+    pub fn parse(text: &str) -> Result<Self, Rejection> {
+        let json: Value = serde_json::from_str(text)
+            .map_err(|err| Rejection::new(ErrorCode::InvalidJson, err.to_string()))?;
+        let name = match json.get("command") {
+            Some(Value::String(name)) => name.as_str(),
+            Some(_) => {
+                return Err(Rejection::new(
+                    ErrorCode::InvalidCommandFormat,
+                    "`command` must be a string",
+                ));
+            }
+            None => {
+                return Err(Rejection::new(
+                    ErrorCode::InvalidCommandFormat,
+                    "missing field `command`",
+                ));
+            }
+        };
+        if !Command::NAMES.contains(&name) {
+            return Err(Rejection::new(
+                ErrorCode::UnknownCommand,
+                format!("'{}' is not one of {}", name, Command::NAMES.join(", ")),
+            ));
+        }
+        let name = name.to_string();
+        serde_json::from_value(json).map_err(|err| {
+            Rejection::new(
+                ErrorCode::InvalidCommandFormat,
+                format!("{}: {}", name, err),
+            )
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("commands always serialize")
+    }
+}
+
+// Reply to `whois`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhoisResponse {
+    pub member_id: String,
+    // The protocol version the connection speaks, as negotiated (see `version`)
+    pub version: u32,
+}
@@ -0,0 +1,54 @@
+// Deprecations: a command slated for removal keeps working until its sunset,
+// the `PROTOCOL_VERSION` that drops it, but a client that sends it is warned,
+// once per connection, with what to send instead:
+//
+//   {"warning":"deprecated","command":"broadcast","sunset_version":2,"replacement":"..."}
+//
+// The transmitter counts every command it's sent, by name and whether it's
+// deprecated, in its --metrics (`tuesdays_commands_total`), so a deprecated
+// command goes once nobody sends it any more.
+
+use crate::command::Command;
+use crate::quota::Warning;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deprecation {
+    pub command: &'static str,
+    // The first protocol version without it
+    pub sunset_version: u32,
+    pub replacement: Option<&'static str>,
+}
+
+// Nothing yet. Once typed messages land, say, untyped broadcasts would be:
+//
+//   Deprecation { command: "broadcast", sunset_version: 2, replacement: Some("...") }
+pub const DEPRECATED: &[Deprecation] = &[];
+
+impl Deprecation {
+    // ✅ The warning for a client that sent the command
+    pub fn warning(&self) -> Warning {
+        Warning::Deprecated {
+            command: self.command.to_string(),
+            sunset_version: self.sunset_version,
+            replacement: self.replacement.map(str::to_string),
+        }
+    }
+}
+
+impl Command {
+    // Whether this command is on its way out, and until when
+    pub fn deprecation(&self) -> Option<&'static Deprecation> {
+        self.deprecation_in(DEPRECATED)
+    }
+
+    // The same, by a list of one's own (the transmitter's, say, which may warn
+    // ahead of the protocol)
+    pub fn deprecation_in(
+        &self,
+        deprecations: &'static [Deprecation],
+    ) -> Option<&'static Deprecation> {
+        deprecations
+            .iter()
+            .find(|deprecation| deprecation.command == self.name())
+    }
+}
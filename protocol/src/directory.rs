@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lifecycle::StreamState;
+
+// Entry of the transmitter's `GET /streams` directory: a room a streamer has
+// published into, from when it goes live until it expires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub id: String,
+    pub watchers: usize,
+    // The stream's current session (see `session`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    // Every streamer publishes only audio (streamer --no-video)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub audio_only: bool,
+    // The streamers publishing into the room, by member id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub streamers: Vec<String>,
+    // Where the stream is in its lifecycle (see `lifecycle`)
+    #[serde(default)]
+    pub state: StreamState,
+    // What each streamer sends and how it can be played, by member id, for those
+    // that described it (see `Command::Describe`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub media: BTreeMap<String, StreamDescriptor>,
+}
+
+// What a streamer publishes, as it advertises it on connecting and then as
+// negotiation settles it, so a client can pick a playback path it supports
+// before connecting:
+//
+//   {"codec":"h264","width":1280,"height":720,
+//    "delivery":[{"type":"webrtc"},{"type":"hls","url":"https://cdn.example/cam1.m3u8"}]}
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamDescriptor {
+    // "vp8", "vp9", "h264", or "opus" for audio-only streams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codec: Option<String>,
+    // Frame size, once frames are flowing; none for audio-only streams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    // Carries audio
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub audio: bool,
+    // Ways to play it, the streamer's own WebRTC first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delivery: Vec<Delivery>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Delivery {
+    // Negotiate with the streamer through the transmitter's /watcher
+    Webrtc,
+    // An HLS rendition published elsewhere (a packager, a CDN), at `url`
+    Hls { url: String },
+}
+
+impl StreamDescriptor {
+    // ✅ "h264 1280x720, also HLS", for listings
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(codec) = &self.codec {
+            parts.push(codec.clone());
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+        if self.audio {
+            parts.push("audio".to_string());
+        }
+        let mut summary = parts.join(" ");
+        if self.hls().is_some() {
+            summary.push_str(", also HLS");
+        }
+        summary
+    }
+
+    // The HLS playlist, if it's offered
+    pub fn hls(&self) -> Option<&str> {
+        self.delivery.iter().find_map(|delivery| match delivery {
+            Delivery::Hls { url } => Some(url.as_str()),
+            Delivery::Webrtc => None,
+        })
+    }
+}
+
+// Entry of the transmitter's `GET /api/streamers`: a streamer connected to this
+// instance, for a channel directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamerInfo {
+    pub id: String,
+    // The room it publishes into; its own id, unless it joined another's
+    pub room_id: String,
+    // Watchers in that room
+    pub watchers: usize,
+    // How long it has been connected
+    pub uptime_secs: u64,
+}
+
+// Entry of the transmitter's `GET /clients` API: one connection and the client
+// behind it, to see which builds are in the field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub room_id: String,
+    pub member_id: String,
+    // member, streamer or watcher
+    pub role: String,
+    // The WebSocket upgrade's User-Agent header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    // From the client's `hello`, if it sent one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+}
+
+// The transmitter's `GET /admin/drain`: whether it's draining, and what's left
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrainStatus {
+    pub draining: bool,
+    // Where new connections are redirected and members are told to migrate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrate_to: Option<String>,
+    // Members still connected to rooms
+    pub connections: usize,
+    // Draining and nobody left: safe to shut down
+    pub empty: bool,
+}
@@ -0,0 +1,92 @@
+// Binary signaling: a client may ask for CBOR instead of JSON with a WebSocket
+// subprotocol,
+//
+//   Sec-WebSocket-Protocol: tuesdays.cbor
+//
+// and once the transmitter picks it (echoing the header back), every message
+// both ways is a binary frame holding the CBOR of what would have been JSON,
+// `{"command":"broadcast",...}` as a CBOR map and so on: smaller for what's sent
+// all the time, ICE candidates and (mostly numbers) stats reports, and nothing
+// to escape. Plain text that isn't JSON (the greeting, say) travels as a CBOR
+// text string.
+//
+// A client that asks for nothing, or `tuesdays.json`, gets JSON text frames as
+// before. A transmitter from before binary signaling echoes nothing back, and
+// the client's handshake fails rather than go on in the wrong encoding.
+// `Encoding` is only the negotiation; the transcoding needs the `cbor` feature.
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "cbor")]
+use serde_json::Value;
+
+#[cfg(feature = "cbor")]
+use crate::error::{ErrorCode, Rejection};
+
+pub const JSON_SUBPROTOCOL: &str = "tuesdays.json";
+pub const CBOR_SUBPROTOCOL: &str = "tuesdays.cbor";
+// Every subprotocol the transmitter speaks
+pub const SUBPROTOCOLS: &[&str] = &[CBOR_SUBPROTOCOL, JSON_SUBPROTOCOL];
+
+// How signaling messages are framed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    // JSON text frames
+    #[default]
+    Json,
+    // CBOR binary frames
+    Cbor,
+}
+
+impl Encoding {
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            Encoding::Json => JSON_SUBPROTOCOL,
+            Encoding::Cbor => CBOR_SUBPROTOCOL,
+        }
+    }
+
+    pub fn from_subprotocol(name: &str) -> Option<Self> {
+        match name.trim() {
+            JSON_SUBPROTOCOL => Some(Encoding::Json),
+            CBOR_SUBPROTOCOL => Some(Encoding::Cbor),
+            _ => None,
+        }
+    }
+
+    // ✅ The encoding for a Sec-WebSocket-Protocol header: the first of the
+    // client's subprotocols that's ours, as the WebSocket handshake picks it;
+    // JSON if none is
+    pub fn negotiate(offered: Option<&str>) -> Self {
+        offered
+            .into_iter()
+            .flat_map(|offered| offered.split(','))
+            .find_map(Encoding::from_subprotocol)
+            .unwrap_or_default()
+    }
+
+    pub fn binary(self) -> bool {
+        self == Encoding::Cbor
+    }
+}
+
+// ✅ A JSON message, as CBOR
+#[cfg(feature = "cbor")]
+pub fn to_cbor(text: &str) -> Vec<u8> {
+    let value = serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()));
+    let mut bytes = Vec::with_capacity(text.len());
+    ciborium::into_writer(&value, &mut bytes).expect("JSON values always encode");
+    bytes
+}
+
+// ✅ A CBOR message, as the JSON it stands for; a text string as its text
+#[cfg(feature = "cbor")]
+pub fn from_cbor(bytes: &[u8]) -> Result<String, Rejection> {
+    let value: Value = ciborium::from_reader(bytes)
+        .map_err(|err| Rejection::new(ErrorCode::InvalidCbor, err.to_string()))?;
+    Ok(match value {
+        Value::String(text) => text,
+        value => value.to_string(),
+    })
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// Why the transmitter rejected a WebSocket message or HTTP request; the `code`
+// of its `ErrorResponse`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // Not JSON at all
+    InvalidJson,
+    // A binary frame that isn't CBOR, on a connection that asked for it (see
+    // `encoding`)
+    InvalidCbor,
+    // JSON without a `command`, or a known command with missing/mistyped fields
+    InvalidCommandFormat,
+    // A `command` the transmitter doesn't know
+    UnknownCommand,
+    // A command the sender's role may not send (see the transmitter's --policy),
+    // or a connection its auth hook or --allowed-origin turns away
+    Forbidden,
+    // An offer, answer or candidate that isn't well-formed, or an SDP over the
+    // transmitter's --max-sdp-bytes (see its `sanitize`); it isn't relayed
+    MalformedSignal,
+    // A routed offer or answer, or a `send`, for a member who isn't in the room
+    UnknownMember,
+    // The connection sent more than its quota allows (see `quota`); it's closed
+    QuotaExceeded,
+    // A command over its rate or size limit for the sender's role (see the
+    // transmitter's --policy); it's dropped, but the connection stays
+    LimitExceeded,
+    // A `replay` of messages that are no longer kept, or were never numbered
+    ReplayUnavailable,
+    // HTTP only, from here on: no valid token, stream key or admin token
+    Unauthorized,
+    // A required query parameter that's missing
+    MissingParameter,
+    // A query parameter, or request body, with a value that isn't allowed
+    InvalidRequest,
+    // A `?version=` of the protocol the transmitter doesn't speak (see `version`)
+    UnsupportedVersion,
+    // No such stream, streamer, watcher, key or archive, or a route that's off
+    NotFound,
+    // A streamer id that's already streaming (--duplicate-streamer reject)
+    AlreadyConnected,
+    // Too many connections, or watchers joining at once; see Retry-After
+    Busy,
+    // A `resume` token for a session that's gone
+    NothingToResume,
+    // Watching a stream that isn't live (--require-streamer)
+    StreamNotLive,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn message(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidJson => "Invalid JSON",
+            ErrorCode::InvalidCbor => "Invalid CBOR",
+            ErrorCode::InvalidCommandFormat => "Invalid command format",
+            ErrorCode::UnknownCommand => "Unknown command",
+            ErrorCode::Forbidden => "Not allowed",
+            ErrorCode::MalformedSignal => "Malformed signaling message",
+            ErrorCode::UnknownMember => "Unknown member",
+            ErrorCode::QuotaExceeded => "Quota exceeded",
+            ErrorCode::LimitExceeded => "Limit exceeded",
+            ErrorCode::ReplayUnavailable => "Replay unavailable",
+            ErrorCode::Unauthorized => "Unauthorized",
+            ErrorCode::MissingParameter => "Missing query parameter",
+            ErrorCode::InvalidRequest => "Invalid request",
+            ErrorCode::UnsupportedVersion => "Unsupported protocol version",
+            ErrorCode::NotFound => "Not found",
+            ErrorCode::AlreadyConnected => "Already connected",
+            ErrorCode::Busy => "Busy",
+            ErrorCode::NothingToResume => "Nothing to resume",
+            ErrorCode::StreamNotLive => "Stream not live",
+            ErrorCode::Internal => "Internal error",
+        }
+    }
+
+    // ✅ The `ErrorResponse` sent back to the client
+    pub fn to_json(self) -> String {
+        ErrorResponse::from(Rejection::from(self)).to_json()
+    }
+}
+
+// An `ErrorCode` with what exactly was wrong, where there's more to say
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    pub code: ErrorCode,
+    pub detail: Option<String>,
+}
+
+impl Rejection {
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        Rejection {
+            code,
+            detail: Some(detail.into()),
+        }
+    }
+
+    // ✅ The `ErrorResponse` sent back to the client
+    pub fn to_json(&self) -> String {
+        ErrorResponse::from(self.clone()).to_json()
+    }
+}
+
+impl From<ErrorCode> for Rejection {
+    fn from(code: ErrorCode) -> Self {
+        Rejection { code, detail: None }
+    }
+}
+
+// Every error the transmitter sends, as a WebSocket reply or an HTTP response's
+// body, in the same envelope:
+//
+//   {"error":{"code":"unknown_command","message":"Unknown command",
+//             "details":"'lst' is not one of list, whois, ..."}}
+//
+// Clients branch on `code`; `message` is only its prose, and `details`, where
+// there's more to say, what exactly was wrong.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    error: T,
+}
+
+impl ErrorResponse {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Envelope { error: self }).expect("errors always serialize")
+    }
+
+    // ✅ The error in a message from the transmitter, if it is one
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str::<Envelope<Self>>(text)
+            .ok()
+            .map(|envelope| envelope.error)
+    }
+}
+
+impl fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{} ({})", self.message, details),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl From<Rejection> for ErrorResponse {
+    fn from(rejection: Rejection) -> Self {
+        ErrorResponse {
+            code: rejection.code,
+            message: rejection.code.message().to_string(),
+            details: rejection.detail,
+        }
+    }
+}
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Anything that can go wrong streaming or watching, by category, so callers can
+// tell a signaling outage from a broken pipeline or a failed ICE negotiation
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Signaling(#[from] SignalingError),
+    // Capture, encode, decode or playback; see tuesdays-media's PipelineError
+    #[error(transparent)]
+    Pipeline(BoxError),
+    #[error(transparent)]
+    Ice(#[from] IceError),
+}
+
+// Talking to the transmitter
+#[derive(Debug, thiserror::Error)]
+pub enum SignalingError {
+    #[error("Cannot connect to {url}: {source}")]
+    Connect {
+        url: String,
+        #[source]
+        source: BoxError,
+    },
+    #[error("Signaling connection failed: {0}")]
+    Transport(#[source] BoxError),
+    #[error("Replaced by another connection with the same id")]
+    Replaced,
+    #[error("Disconnected by the transmitter's operator")]
+    Kicked,
+    // Too many watchers joining the stream at once (HTTP 503)
+    #[error("Transmitter is busy; retry in {}s", .retry_after.as_secs())]
+    Busy { retry_after: Duration },
+    #[error("Rejected by the transmitter: {}", .0.message())]
+    Rejected(ErrorCode),
+    #[error("Invalid signaling message: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl SignalingError {
+    // For `map_err` on WebSocket sends and reads, whatever the client library
+    pub fn transport(err: impl Into<BoxError>) -> Self {
+        SignalingError::Transport(err.into())
+    }
+}
+
+// Negotiating or keeping up the peer connection
+#[derive(Debug, thiserror::Error)]
+pub enum IceError {
+    #[error("Peer connection failed")]
+    Failed,
+    #[cfg(feature = "webrtc")]
+    #[error("WebRTC: {0}")]
+    WebRtc(#[from] webrtc::Error),
+}
+
+#[cfg(feature = "webrtc")]
+impl From<webrtc::Error> for Error {
+    fn from(err: webrtc::Error) -> Self {
+        Error::Ice(err.into())
+    }
+}
@@ -0,0 +1,76 @@
+// Signaling latency: how long a round trip to the transmitter takes, and how far
+// our clock is off from its. A client sends `ping` with its own clock's time,
+// Unix milliseconds, on its room connection or on the `/latency` WebSocket,
+// which does nothing else (and echoes anything but a `ping` as it is):
+//
+//   {"command":"ping","sent_ms":1760000000000}
+//
+// and is answered, at once, with the transmitter's clock's times of receiving
+// and of replying:
+//
+//   {"sent_ms":1760000000000,"received_ms":1760000000013,"replied_ms":1760000000013}
+//
+// Together with when the reply came back, that makes an NTP-style measurement;
+// see `Pong::measure`. The watcher overlays it on its --stats, and the
+// streamer's `preflight` checks it before going live.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+// The answer to `ping`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pong {
+    // The client's, echoed
+    pub sent_ms: u64,
+    // The transmitter's
+    pub received_ms: u64,
+    pub replied_ms: u64,
+}
+
+// One round trip's worth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    // There and back, the transmitter's own time aside
+    pub rtt_ms: u64,
+    // How far the transmitter's clock is ahead of ours (behind if negative)
+    pub offset_ms: i64,
+}
+
+impl Pong {
+    // ✅ Answer a ping sent at `sent_ms`, received at `received_ms`
+    pub fn reply(sent_ms: u64, received_ms: u64) -> Self {
+        Pong {
+            sent_ms,
+            received_ms,
+            replied_ms: now_ms(),
+        }
+    }
+
+    // ✅ The round trip and clock offset, given when the pong came back
+    // (`back_ms`, our clock)
+    pub fn measure(&self, back_ms: u64) -> Measurement {
+        let (sent, received, replied, back) = (
+            self.sent_ms as i64,
+            self.received_ms as i64,
+            self.replied_ms as i64,
+            back_ms as i64,
+        );
+        Measurement {
+            rtt_ms: ((back - sent) - (replied - received)).max(0) as u64,
+            offset_ms: ((received - sent) + (replied - back)) / 2,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("pongs always serialize")
+    }
+}
+
+// Unix milliseconds, by our clock
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_millis() as u64)
+}
@@ -0,0 +1,50 @@
+// Wire protocol shared by the transmitter, streamer and watcher.
+//
+// Clients talk to the transmitter over WebSocket with JSON `Command`s; the
+// transmitter answers with plain JSON values or an `ErrorResponse`.
+// Streamers and watchers negotiate WebRTC with `Signal`s routed to each other
+// through their room, one negotiation per watcher (see `Command::Offer`).
+
+pub mod agent;
+pub mod archive;
+pub mod capture;
+pub mod close;
+pub mod command;
+pub mod deprecation;
+pub mod directory;
+pub mod encoding;
+pub mod error;
+pub mod latency;
+pub mod lifecycle;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod quota;
+pub mod resume;
+pub mod roles;
+pub mod session;
+pub mod signal;
+pub mod version;
+
+pub use agent::{AgentCommand, AgentEvent, AgentInfo};
+pub use archive::{ArchiveEvent, ArchiveRecord};
+pub use capture::{CaptureEvent, CaptureRecord};
+pub use command::{Command, WhoisResponse};
+pub use deprecation::Deprecation;
+pub use directory::{
+    ClientInfo, Delivery, DrainStatus, StreamDescriptor, StreamInfo, StreamerInfo,
+};
+pub use encoding::Encoding;
+pub use error::{BoxError, Error, ErrorCode, ErrorResponse, IceError, Rejection, SignalingError};
+pub use latency::{Measurement, Pong};
+pub use lifecycle::StreamState;
+pub use quota::{Quota, Warning};
+pub use roles::AudienceRole;
+pub use signal::{
+    DeliveryMode, IceCandidate, Layer, RecordAction, RecordingState, RecordingStatus,
+    RedirectReason, Route, Signal,
+};
+pub use version::Codec;
+
+// Bumped whenever a change breaks existing clients; the transmitter goes on
+// speaking the old versions too (see `version`)
+pub const PROTOCOL_VERSION: u32 = 1;
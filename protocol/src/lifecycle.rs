@@ -0,0 +1,58 @@
+// Stream lifecycle, as the transmitter tracks it for every room:
+//
+//   registered ──▶ live ──▶ paused ──▶ ended ──▶ expired
+//                   ▲         │          │
+//                   └─────────┴──────────┘  a streamer (re)connects
+//
+//   registered  the room exists, no streamer has connected yet
+//   live        at least one streamer is connected
+//   paused      the last streamer dropped; it may come back for a while
+//   ended       it didn't; the room is kept so latecomers learn it's over
+//   expired     the room is gone, and whoever was left in it disconnected
+//
+// Every transition is announced to the room as `{"type":"stream","state":"paused"}`,
+// and members get the current state when they join; the `GET /streams` directory
+// and `GET /streams/{id}` report it too.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamState {
+    Registered,
+    // Directories from before the lifecycle only listed live streams
+    #[default]
+    Live,
+    Paused,
+    Ended,
+    Expired,
+}
+
+impl StreamState {
+    // ✅ Whether the diagram above allows going from this state to `next`
+    pub fn can_become(self, next: StreamState) -> bool {
+        use StreamState::*;
+        matches!(
+            (self, next),
+            (Registered | Paused | Ended, Live)
+                | (Live, Paused)
+                | (Paused, Ended)
+                | (Ended, Expired)
+        )
+    }
+
+    // Watchers can join: the stream is on, or its streamer is expected back
+    pub fn watchable(self) -> bool {
+        matches!(self, StreamState::Live | StreamState::Paused)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StreamState::Registered => "registered",
+            StreamState::Live => "live",
+            StreamState::Paused => "paused",
+            StreamState::Ended => "ended",
+            StreamState::Expired => "expired",
+        }
+    }
+}
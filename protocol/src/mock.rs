@@ -0,0 +1,219 @@
+// In-memory stand-in for a transmitter room, for unit tests that exercise
+// signaling without sockets or actix:
+//
+//   let room = MockRoom::new("cam");
+//   let mut streamer = room.join("cam");
+//   let mut watcher = room.join("w1");
+//   streamer.send(&Signal::Offer { sdp }.to_routed_command("w1", "n1").unwrap());
+//   let offer = watcher.recv().await;
+//
+// Commands are handled like the transmitter does: `broadcast` and `chat` reach
+// every member (the sender included), `offer`/`answer`/`ice-candidate`/`send`
+// only the member they're for, `list`/`whois` are answered, `replay` is refused
+// (the mock numbers nothing, like a transmitter without --resume-grace-secs),
+// `assign` is told to the member it's for whoever sends it, `ping` is answered
+// with a `Pong`, and anything else gets the same `ErrorResponse`. Unlike the
+// transmitter, the mock drops candidates for members who haven't joined instead of holding them.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+use crate::command::{Command, WhoisResponse};
+use crate::latency::{self, Pong};
+use crate::{ErrorCode, PROTOCOL_VERSION, Signal, close};
+
+// Member id → (connection id, sender); the connection id tells a replaced
+// connection apart from the one that replaced it
+type Members = Arc<Mutex<BTreeMap<String, (u64, UnboundedSender<Frame>)>>>;
+
+// A panicking test thread shouldn't take the whole room down with it
+fn lock(members: &Members) -> MutexGuard<'_, BTreeMap<String, (u64, UnboundedSender<Frame>)>> {
+    members.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(0);
+
+// What a member's connection receives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    // Closed by the room, with a close code
+    Close(u16),
+}
+
+#[derive(Clone)]
+pub struct MockRoom {
+    room_id: String,
+    members: Members,
+}
+
+impl MockRoom {
+    pub fn new(room_id: &str) -> Self {
+        MockRoom {
+            room_id: room_id.to_string(),
+            members: Arc::default(),
+        }
+    }
+
+    // ✅ Connect a member; an existing member with the same id is replaced
+    pub fn join(&self, member_id: &str) -> MockConnection {
+        let (tx, rx) = unbounded_channel();
+        let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+        let _ = tx.send(Frame::Text(format!(
+            "Connected as Member: {} to Room: {}",
+            member_id, self.room_id
+        )));
+        if let Some(old) = lock(&self.members).insert(member_id.to_string(), (connection, tx)) {
+            let _ = old.1.send(Frame::Close(close::REPLACED));
+        }
+        MockConnection {
+            member_id: member_id.to_string(),
+            connection,
+            members: self.members.clone(),
+            incoming: rx,
+        }
+    }
+
+    pub fn members(&self) -> Vec<String> {
+        lock(&self.members).keys().cloned().collect()
+    }
+}
+
+// One member's end of the duplex channel
+pub struct MockConnection {
+    member_id: String,
+    connection: u64,
+    members: Members,
+    incoming: UnboundedReceiver<Frame>,
+}
+
+impl MockConnection {
+    // ✅ Send a text frame to the room, as a WebSocket client would
+    pub fn send(&self, text: &str) {
+        let members = lock(&self.members);
+        let reply = |frame: String| {
+            if let Some((_, me)) = members.get(&self.member_id) {
+                let _ = me.send(Frame::Text(frame));
+            }
+        };
+        match Command::parse(text) {
+            Ok(Command::Broadcast { message }) => {
+                for (_, member) in members.values() {
+                    let _ = member.send(Frame::Text(message.clone()));
+                }
+            }
+            Ok(Command::Offer {
+                to,
+                negotiation_id,
+                sdp,
+            }) => match members.get(&to) {
+                Some((_, member)) => {
+                    let offer =
+                        Signal::Offer { sdp }.to_routed_json(&self.member_id, &negotiation_id);
+                    let _ = member.send(Frame::Text(offer));
+                }
+                None => reply(ErrorCode::UnknownMember.to_json()),
+            },
+            Ok(Command::Answer {
+                to,
+                negotiation_id,
+                sdp,
+            }) => match members.get(&to) {
+                Some((_, member)) => {
+                    let answer =
+                        Signal::Answer { sdp }.to_routed_json(&self.member_id, &negotiation_id);
+                    let _ = member.send(Frame::Text(answer));
+                }
+                None => reply(ErrorCode::UnknownMember.to_json()),
+            },
+            Ok(Command::IceCandidate {
+                to,
+                negotiation_id,
+                candidate,
+            }) => {
+                if let Some((_, member)) = members.get(&to) {
+                    let candidate = Signal::Candidate(candidate)
+                        .to_routed_json(&self.member_id, &negotiation_id);
+                    let _ = member.send(Frame::Text(candidate));
+                }
+            }
+            Ok(Command::Send { to, message }) => match members.get(&to) {
+                Some((_, member)) => {
+                    let _ = member.send(Frame::Text(message));
+                }
+                None => reply(ErrorCode::UnknownMember.to_json()),
+            },
+            Ok(Command::Chat { message }) => {
+                let chat = Signal::Chat {
+                    from: self.member_id.clone(),
+                    message,
+                };
+                for (_, member) in members.values() {
+                    let _ = member.send(Frame::Text(chat.to_json()));
+                }
+            }
+            Ok(Command::List) => {
+                let ids: Vec<&String> = members.keys().collect();
+                reply(serde_json::to_string(&ids).unwrap_or_default());
+            }
+            Ok(Command::Whois) => reply(
+                serde_json::to_string(&WhoisResponse {
+                    member_id: self.member_id.clone(),
+                    version: PROTOCOL_VERSION,
+                })
+                .unwrap_or_default(),
+            ),
+            Ok(
+                Command::Stats { .. }
+                | Command::Level { .. }
+                | Command::Hello { .. }
+                | Command::Describe { .. },
+            ) => {}
+            Ok(Command::Replay { .. }) => reply(ErrorCode::ReplayUnavailable.to_json()),
+            Ok(Command::Ping { sent_ms }) => {
+                reply(Pong::reply(sent_ms, latency::now_ms()).to_json())
+            }
+            Ok(Command::Assign { watcher_id, role }) => match members.get(&watcher_id) {
+                Some((_, member)) => {
+                    let signal = Signal::Role { watcher_id, role };
+                    let _ = member.send(Frame::Text(signal.to_json()));
+                }
+                None => reply(ErrorCode::UnknownMember.to_json()),
+            },
+            Err(rejection) => reply(rejection.to_json()),
+        }
+    }
+
+    // Next frame for this member; `None` once it has left or been replaced
+    pub async fn recv(&mut self) -> Option<Frame> {
+        self.incoming.recv().await
+    }
+
+    // Next text frame, skipping the join notice and anything else that isn't `pred`
+    pub async fn recv_matching(&mut self, pred: impl Fn(&str) -> bool) -> Option<String> {
+        while let Some(frame) = self.recv().await {
+            match frame {
+                Frame::Text(text) if pred(&text) => return Some(text),
+                Frame::Text(_) => {}
+                Frame::Close(_) => return None,
+            }
+        }
+        None
+    }
+}
+
+impl Drop for MockConnection {
+    fn drop(&mut self) {
+        let mut members = lock(&self.members);
+        // Only remove ourselves, not a connection that replaced us
+        if members
+            .get(&self.member_id)
+            .is_some_and(|(connection, _)| *connection == self.connection)
+        {
+            members.remove(&self.member_id);
+        }
+    }
+}
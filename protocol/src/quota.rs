@@ -0,0 +1,91 @@
+// Quotas on what one connection may send the transmitter (--max-messages-per-sec,
+// --max-bytes-per-sec), counted over windows of `window_ms`. At 80% of either,
+// the client is warned, once per window and quota, with the counters so far:
+//
+//   {"warning":"quota","quota":"messages","used":41,"limit":50,"window_ms":5000}
+//
+// Over 100% it gets an error, `{"error":{"code":"quota_exceeded",...}}`, and the
+// connection is closed with `close::QUOTA_EXCEEDED`. A client that slows down
+// when warned never gets there.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// Warnings from the transmitter: not errors, the command was carried out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "warning", rename_all = "lowercase")]
+pub enum Warning {
+    Quota {
+        quota: Quota,
+        used: u64,
+        limit: u64,
+        window_ms: u64,
+    },
+    // A command on its way out; see `deprecation`
+    Deprecated {
+        command: String,
+        sunset_version: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replacement: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quota {
+    // Frames received, whatever they hold
+    Messages,
+    // Their payload bytes
+    Bytes,
+}
+
+impl Quota {
+    pub fn name(self) -> &'static str {
+        match self {
+            Quota::Messages => "messages",
+            Quota::Bytes => "bytes",
+        }
+    }
+}
+
+impl Warning {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("warnings always serialize")
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Quota {
+                quota,
+                used,
+                limit,
+                window_ms,
+            } => write!(
+                f,
+                "{} of {} {} used in {}ms",
+                used,
+                limit,
+                quota.name(),
+                window_ms
+            ),
+            Warning::Deprecated {
+                command,
+                sunset_version,
+                replacement,
+            } => {
+                write!(
+                    f,
+                    "'{}' is deprecated, and gone in protocol version {}",
+                    command, sunset_version
+                )?;
+                match replacement {
+                    Some(replacement) => write!(f, "; use {}", replacement),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
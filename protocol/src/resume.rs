@@ -0,0 +1,139 @@
+// Resuming after a dropped connection (transmitter --resume-grace-secs): one flow
+// for streamers and watchers alike, which web clients follow the same way:
+//
+//   1. On joining, the member is given a token of its own, and how long it may
+//      be away:
+//        {"type":"resume","token":"9b2e...","grace_secs":30}
+//   2. From then on, every message the room sends it carries a sequence number,
+//      counting up from 1 and carried on across resumes:
+//        {"type":"candidate","candidate":"...","session_id":"6f1c...","seq":42}
+//      Replies to its own commands (`list`, `whois`, errors) and the join notice
+//      carry none, and aren't replayed.
+//   3. If the connection drops, it reconnects to the same URL within
+//      `grace_secs`, adding the token and the last number it saw:
+//        /watcher?id=w1&streamer_id=cam1&resume=9b2e...&last_seq=42
+//      The room sends it what came after (it keeps the last REPLAY_WINDOW
+//      messages, those sent while the member was away included), then a new
+//      token, and the member carries on as if it had never left: a streamer's
+//      watchers stay attached, a watcher keeps its peer connection, and neither
+//      renegotiates.
+//   4. A token that's stale, used, or from a session since replaced, or a
+//      `last_seq` whose successors have fallen out of the window, is refused with
+//      410 Gone: the member starts over with a fresh connection.
+//   5. Numbers also show what went missing on a connection that stayed up (a
+//      message dropped or held up on the way): a number skipped means the ones
+//      in between are missing, and the member asks for them again:
+//        {"command":"replay","from":43,"to":45}
+//      They arrive as first sent, from the same window, or the reply is
+//      {"error":{"code":"replay_unavailable",...}} once they've fallen out of it. A number
+//      already seen is a copy, and ignored.
+//
+// `Resumption` keeps track of 1, 2 and 5 for a client, and builds the URL for 3.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use serde_json::Value;
+
+pub const SEQ_KEY: &str = "seq";
+
+// Messages the transmitter keeps for each member to replay
+pub const REPLAY_WINDOW: usize = 256;
+
+// ✅ Number a message for its member; None for anything but a JSON object,
+// which has nowhere to carry it
+pub fn number(message: &str, seq: u64) -> Option<String> {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Object(mut object)) => {
+            object.insert(SEQ_KEY.to_string(), seq.into());
+            Some(Value::Object(object).to_string())
+        }
+        _ => None,
+    }
+}
+
+// What a message from the room turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    // Not seen before, or not numbered: handle it
+    New,
+    // Not seen before, but some before it are missing: handle it, and ask for
+    // them with `Command::Replay`
+    Gap { from: u64, to: u64 },
+    // Seen before: ignore it
+    Copy,
+}
+
+// A client's side of the flow
+#[derive(Debug, Clone, Default)]
+pub struct Resumption {
+    // The last token given, and the grace period that came with it
+    token: Option<(String, Duration)>,
+    // The highest number seen
+    last_seq: Option<u64>,
+    // Numbers below it not seen yet, at most REPLAY_WINDOW of them
+    missing: BTreeSet<u64>,
+}
+
+impl Resumption {
+    // ✅ Take note of a message from the room: a new token, or a sequence number
+    pub fn observe(&mut self, text: &str) -> Arrival {
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(text) else {
+            return Arrival::New;
+        };
+        let arrival = match object.get(SEQ_KEY).and_then(Value::as_u64) {
+            Some(seq) => self.number(seq),
+            None => Arrival::New,
+        };
+        if arrival != Arrival::Copy
+            && object.get("type").and_then(Value::as_str) == Some("resume")
+            && let (Some(token), Some(grace_secs)) = (
+                object.get("token").and_then(Value::as_str),
+                object.get("grace_secs").and_then(Value::as_u64),
+            )
+        {
+            self.token = Some((token.to_string(), Duration::from_secs(grace_secs)));
+        }
+        arrival
+    }
+
+    // ✅ Where `seq` falls among the numbers seen so far
+    fn number(&mut self, seq: u64) -> Arrival {
+        let next = self.last_seq.map_or(1, |last_seq| last_seq + 1);
+        if seq < next {
+            // Late, or a copy
+            return if self.missing.remove(&seq) {
+                Arrival::New
+            } else {
+                Arrival::Copy
+            };
+        }
+        self.last_seq = Some(seq);
+        if seq == next {
+            return Arrival::New;
+        }
+        // Whatever fell out of the window can't be had again anyway
+        let from = next.max(seq.saturating_sub(REPLAY_WINDOW as u64));
+        self.missing.extend(from..seq);
+        while self.missing.len() > REPLAY_WINDOW {
+            self.missing.pop_first();
+        }
+        Arrival::Gap { from, to: seq - 1 }
+    }
+
+    // Whether there's a session to resume
+    pub fn resumable(&self) -> bool {
+        self.token.is_some()
+    }
+
+    // ✅ `url` (which has a query already) for resuming, and how long there is
+    // to; a token is good for one try, so it's used up
+    pub fn take(&mut self, url: &str) -> Option<(String, Duration)> {
+        let (token, grace) = self.token.take()?;
+        let mut url = format!("{}&resume={}", url, token);
+        if let Some(last_seq) = self.last_seq {
+            url.push_str(&format!("&last_seq={}", last_seq));
+        }
+        Some((url, grace))
+    }
+}
@@ -0,0 +1,137 @@
+// Audience roles: what a watcher may do in the room beyond watching. A watcher
+// joins as a viewer unless its join request asks for more:
+//
+//   /watcher?id=w1&streamer_id=cam1&role=moderator
+//
+// if something vouches for it: with transmitter --jwt-*, its token, up to the
+// `audience_role` claim; otherwise a stream key for the stream (`&key=...`).
+// Asked for without either, it joins as a viewer all the same. A streamer, or a
+// watcher ranking above, changes it at runtime:
+//
+//   {"command":"assign","watcher_id":"w1","role":"cohost"}
+//
+// The watcher and the room's streamers then hear
+// {"type":"role","watcher_id":"w1","role":"cohost"}. Each role may do what the
+// one before it may, and:
+//
+//   viewer     chat, and broadcast only its own side of the negotiation
+//              (answer, candidate, quality, mode, record)
+//   moderator  broadcast anything, and assign viewers and moderators
+//   cohost     `send` to one watcher, like a streamer, and assign cohosts
+//
+// Anything else is refused as `Forbidden`. Streamers, and room members, have no
+// audience role; they may do everything as before.
+
+use serde::{Deserialize, Serialize};
+
+use crate::command::Command;
+use crate::signal::Signal;
+
+// Ordered: a role may do everything the ones before it may
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum AudienceRole {
+    #[default]
+    Viewer,
+    Moderator,
+    Cohost,
+}
+
+impl AudienceRole {
+    pub fn name(self) -> &'static str {
+        match self {
+            AudienceRole::Viewer => "viewer",
+            AudienceRole::Moderator => "moderator",
+            AudienceRole::Cohost => "cohost",
+        }
+    }
+
+    // ✅ Whether a watcher with this role may send `command`; see above
+    pub fn may(self, command: &Command) -> bool {
+        match command {
+            Command::Broadcast { message } => {
+                self >= AudienceRole::Moderator || negotiates(message)
+            }
+            Command::Send { .. } => self == AudienceRole::Cohost,
+            Command::Assign { role, .. } => self >= AudienceRole::Moderator && *role <= self,
+            _ => true,
+        }
+    }
+}
+
+// What a watcher broadcasts to play the stream
+fn negotiates(message: &str) -> bool {
+    matches!(
+        serde_json::from_str(message),
+        Ok(Signal::Answer { .. }
+            | Signal::Candidate(_)
+            | Signal::Quality { .. }
+            | Signal::Mode { .. }
+            | Signal::Record { .. })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broadcast(message: &str) -> Command {
+        Command::Broadcast {
+            message: message.to_string(),
+        }
+    }
+
+    fn assign(role: AudienceRole) -> Command {
+        Command::Assign {
+            watcher_id: "w2".to_string(),
+            role,
+        }
+    }
+
+    #[test]
+    fn viewers_only_broadcast_their_side_of_the_negotiation() {
+        let viewer = AudienceRole::Viewer;
+        assert!(viewer.may(&broadcast(r#"{"type":"answer","sdp":"v=0"}"#)));
+        assert!(viewer.may(&broadcast(
+            r#"{"type":"quality","watcher_id":"w1","layer":"low"}"#
+        )));
+        assert!(!viewer.may(&broadcast(r#"{"type":"offer","sdp":"v=0"}"#)));
+        assert!(!viewer.may(&broadcast("hello everyone")));
+        assert!(AudienceRole::Moderator.may(&broadcast("hello everyone")));
+    }
+
+    #[test]
+    fn only_cohosts_send_to_one_watcher() {
+        let send = Command::Send {
+            to: "w2".to_string(),
+            message: "hi".to_string(),
+        };
+        assert!(!AudienceRole::Viewer.may(&send));
+        assert!(!AudienceRole::Moderator.may(&send));
+        assert!(AudienceRole::Cohost.may(&send));
+    }
+
+    #[test]
+    fn roles_are_assigned_only_up_to_the_assigners_own() {
+        assert!(!AudienceRole::Viewer.may(&assign(AudienceRole::Viewer)));
+        assert!(AudienceRole::Moderator.may(&assign(AudienceRole::Viewer)));
+        assert!(AudienceRole::Moderator.may(&assign(AudienceRole::Moderator)));
+        assert!(!AudienceRole::Moderator.may(&assign(AudienceRole::Cohost)));
+        assert!(AudienceRole::Cohost.may(&assign(AudienceRole::Cohost)));
+    }
+
+    #[test]
+    fn everything_else_is_anyones() {
+        for role in [
+            AudienceRole::Viewer,
+            AudienceRole::Moderator,
+            AudienceRole::Cohost,
+        ] {
+            assert!(role.may(&Command::Whois));
+            assert!(role.may(&Command::Ping { sent_ms: 0 }));
+        }
+    }
+}
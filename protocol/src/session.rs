@@ -0,0 +1,44 @@
+// Session correlation ids. The transmitter mints one (a UUID) every time a
+// streamer registers and announces it to the room:
+//
+//   {"type":"session","session_id":"6f1c..."}
+//
+// New members get the current one when they join, and every JSON object the
+// room relays carries it as `session_id`. Members already in the room when a
+// streamer comes back get `rejoin_after_ms` with it: a jittered delay before
+// they renegotiate, so a restart doesn't turn into a reconnect storm. Streamer,
+// watcher and transmitter put it in their log lines, stats reports and alerts,
+// so one stream's story can be pieced together from all three.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::signal::Signal;
+
+pub const SESSION_KEY: &str = "session_id";
+
+// ✅ Stamp a relayed message with the session; anything but a JSON object is left alone
+pub fn tag(message: &str, session_id: &str) -> String {
+    match serde_json::from_str::<Value>(message) {
+        Ok(Value::Object(mut object)) => {
+            object.insert(SESSION_KEY.to_string(), session_id.into());
+            Value::Object(object).to_string()
+        }
+        _ => message.to_string(),
+    }
+}
+
+// The announcement sent to the room
+pub fn announce(session_id: &str, rejoin_after: Option<Duration>) -> String {
+    Signal::Session {
+        session_id: session_id.to_string(),
+        rejoin_after_ms: rejoin_after.map(|delay| delay.as_millis() as u64),
+    }
+    .to_json()
+}
+
+// For log lines, before a session is known
+pub fn label(session_id: Option<&str>) -> &str {
+    session_id.unwrap_or("-")
+}
@@ -0,0 +1,473 @@
+// WebRTC signaling, in the exact shapes `RTCPeerConnection` uses so browser
+// clients can hand them straight to setRemoteDescription/addIceCandidate:
+//
+//   {"type":"offer","sdp":"v=0..."}                      RTCSessionDescriptionInit
+//   {"type":"answer","sdp":"v=0..."}                     RTCSessionDescriptionInit
+//   {"type":"candidate","candidate":"candidate:...",
+//    "sdpMid":"0","sdpMLineIndex":0,"usernameFragment":"..."}  RTCIceCandidateInit
+//   {"type":"candidate","candidate":""}                  end of candidates
+//   {"type":"offer","sdp":"v=0...","from":"cam",
+//    "negotiation_id":"n1"}                              routed; see `Command::Offer`
+//   {"type":"quality","watcher_id":"w1","layer":"low"}   simulcast layer request
+//   {"type":"mode","watcher_id":"w1","mode":"realtime"}  delivery mode request
+//   {"type":"session","session_id":"6f1c..."}            from the transmitter, see `session`
+//   {"type":"record","watcher_id":"w1","action":"start"} recording request, see below
+//   {"type":"recording","watcher_id":"w1","state":"started","location":"..."}
+//   {"type":"recommendation","bitrate_kbps":800,"layer":"medium"}  from the transmitter
+//   {"type":"migrate","url":"ws://green:8080"}          from a draining transmitter
+//   {"type":"speaker","streamer_id":"cam1"}              from the transmitter, see below
+//   {"type":"stream","state":"paused"}                   from the transmitter, see `lifecycle`
+//   {"type":"stream_started","streamer_id":"cam1"}       from the transmitter, see below
+//   {"type":"resume","token":"9b2e...","grace_secs":30}   to one member, see below
+//   {"type":"left","member_id":"w1","timed_out":true}    from the transmitter, see below
+//   {"type":"audience","watchers":12}                    to streamers, see below
+//   {"type":"watcher_joined","watcher_id":"w1","at_ms":1760000000000}  to streamers
+//   {"type":"watcher_left","watcher_id":"w1","at_ms":1760000000000}    to streamers
+//   {"type":"chat","from":"w1","message":"hi!"}          audience chat, see below
+//   {"type":"redirect","url":"wss://us.example/watcher?...","reason":"region"}  see below
+//   {"type":"role","watcher_id":"w1","role":"moderator"}  see `roles`
+//   {"type":"batch","messages":["...","..."]}            several broadcasts, see below
+//
+// The streamer is always the offerer (sendonly), watchers answer (recvonly), and
+// both sides trickle candidates as soon as they're gathered. Payloads travel as
+// the `message` of a room `broadcast` command, or, for an offer, answer or
+// candidate meant for one member, as an `offer`/`answer`/`ice-candidate` command
+// the room routes to it alone.
+//
+// Recording on demand: a watcher sends `record`, the streamer decides (by policy
+// or by asking its operator) and records on its own side, answering with
+// `recording` statuses: pending → started → progress… → stopped, or denied /
+// failed. They go to the whole room, so every watcher knows when it's recorded.
+//
+// Departures: the room hears `left` whenever a member's connection goes, closed
+// or, with `timed_out`, dropped for not answering the transmitter's pings, so the
+// other side can let go of whatever it kept for that member.
+//
+// Active speaker: when several streamers share a room, those sending their audio
+// `level` (streamer --report-level) are compared, and the room hears `speaker`
+// whenever a different one is talking, or no one (no `streamer_id`) is.
+//
+// Waiting room (transmitter --waiting-room): a watcher connecting before the
+// stream is live is parked instead of turned away, and hears `stream_started`
+// the moment a streamer registers; from then on it's in the room like any other.
+//
+// Resuming (transmitter --resume-grace-secs): streamers and watchers are each
+// sent a `resume` token when they join. If the connection drops, reconnecting
+// with it within `grace_secs` picks up where it left off, replaying what was
+// missed, instead of renegotiating from scratch; see `resume`.
+//
+// Audience: the room's streamers are told how many watchers it has whenever one
+// joins or leaves, and on joining themselves, for the presenter to see. They're
+// also told who: `watcher_joined` and `watcher_left` as each comes and goes, with
+// when (Unix milliseconds), and on joining a `watcher_joined` for each watcher
+// already there, so a streamer can keep a live audience list without `list`.
+//
+// Audience chat: watchers `chat` with each other through the room, and everyone
+// in it, streamers included, hears each message as `chat` with its sender's id.
+// A streamer connecting with `&chat=off` (streamer --no-audience-chat) turns it
+// off for the room while it's there.
+//
+// Redirects (transmitter --region, --presence-redis): a member connecting to an
+// instance that isn't the one for it hears `redirect` first thing, with the URL
+// to connect to instead (same path and query), and is closed with
+// `close::REDIRECTED`. It comes over the WebSocket rather than as an HTTP
+// redirect so browsers, which never see an upgrade's response, can follow it.
+//
+// Batching (transmitter --batch-window-ms): broadcasts that aren't signals, such
+// as a streamer's telemetry, may arrive several to a frame as `batch`, each
+// message verbatim and in the order they were sent.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::command::Command;
+use crate::lifecycle::StreamState;
+use crate::roles::AudienceRole;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Signal {
+    Offer {
+        sdp: String,
+    },
+    Answer {
+        sdp: String,
+    },
+    Candidate(IceCandidate),
+    // Ask the streamer (or an SFU) to send a specific simulcast layer, or pick one itself
+    Quality {
+        watcher_id: String,
+        layer: Layer,
+    },
+    // How a watcher wants the stream delivered, for whatever forwards it (an SFU
+    // tunes its buffers and retransmissions per watcher)
+    Mode {
+        watcher_id: String,
+        mode: DeliveryMode,
+    },
+    // The stream's current session, announced by the transmitter
+    Session {
+        session_id: String,
+        // When a restarted streamer replaces the session: wait this long before
+        // renegotiating, so watchers don't all hit it at once
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rejoin_after_ms: Option<u64>,
+    },
+    // Ask the streamer to start or stop recording the stream on its side
+    Record {
+        watcher_id: String,
+        action: RecordAction,
+    },
+    // The streamer's answer to `Record`, then progress until the recording stops
+    Recording(RecordingStatus),
+    // What the transmitter makes of the watchers' `stats` reports (transmitter
+    // --recommend-quality): the streamer caps its encoder at `bitrate_kbps`,
+    // watchers that pick their own layer can follow `layer`
+    Recommendation {
+        bitrate_kbps: u32,
+        layer: Layer,
+    },
+    // The transmitter is draining for a deploy: the session carries on here, but
+    // reconnect to `url` (same path and query) once it's convenient
+    Migrate {
+        url: String,
+    },
+    // Which of the room's streamers is talking now; none while everyone is quiet
+    Speaker {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        streamer_id: Option<String>,
+    },
+    // The stream moved on in its lifecycle, announced by the transmitter
+    Stream {
+        state: StreamState,
+    },
+    // The stream a watcher was parked waiting for has started; see above
+    #[serde(rename = "stream_started")]
+    StreamStarted {
+        streamer_id: String,
+    },
+    // For one member alone: how to pick up where it left off after a dropped
+    // connection; see `resume`
+    Resume {
+        token: String,
+        grace_secs: u64,
+    },
+    // A member's connection went; `timed_out` if it stopped answering pings
+    Left {
+        member_id: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        timed_out: bool,
+    },
+    // For streamers: the watchers in the room now
+    Audience {
+        watchers: usize,
+    },
+    // For streamers: a watcher joined the room at `at_ms` (Unix milliseconds)
+    #[serde(rename = "watcher_joined")]
+    WatcherJoined {
+        watcher_id: String,
+        at_ms: u64,
+    },
+    // For streamers: a watcher left the room at `at_ms`
+    #[serde(rename = "watcher_left")]
+    WatcherLeft {
+        watcher_id: String,
+        at_ms: u64,
+    },
+    // A watcher's chat message, from the transmitter
+    Chat {
+        from: String,
+        message: String,
+    },
+    // Connect to `url` instead; see above
+    Redirect {
+        url: String,
+        reason: RedirectReason,
+    },
+    // To a watcher and the room's streamers: the watcher's audience role changed
+    Role {
+        watcher_id: String,
+        role: AudienceRole,
+    },
+    // Broadcasts sent within one batching window, oldest first; see above
+    Batch {
+        messages: Vec<String>,
+    },
+}
+
+// RTCIceCandidateInit; an empty `candidate` marks the end of candidates
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IceCandidate {
+    pub candidate: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdp_mid: Option<String>,
+    #[serde(
+        default,
+        rename = "sdpMLineIndex",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sdp_mline_index: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_fragment: Option<String>,
+}
+
+// Simulcast layer a watcher wants to receive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    Auto,
+    High,
+    Medium,
+    Low,
+}
+
+// Latency tier a watcher picks: interactive viewers want it live, lean-back
+// viewers want it smooth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    // As live as possible: late packets are dropped, lost ones aren't waited for
+    Realtime,
+    // Close to live, riding out the odd loss
+    Low,
+    // Smooth on a bad network, seconds behind
+    Resilient,
+}
+
+// Why a member was sent to another instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedirectReason {
+    // The stream is live there
+    Stream,
+    // The instance is in the member's region
+    Region,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordAction {
+    Start,
+    Stop,
+}
+
+// A recording as requested by `watcher_id`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub watcher_id: String,
+    #[serde(flatten)]
+    pub state: RecordingState,
+}
+
+// `location` is the file on the streamer's machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum RecordingState {
+    // Waiting for the streamer's operator to consent
+    Pending,
+    Started { location: String },
+    Progress { location: String, bytes: u64 },
+    Stopped { location: String, bytes: u64 },
+    // Finished early, the streamer's disk running low; resumes, in a new file
+    // (`started` again), once there's room
+    Paused { location: String, reason: String },
+    Denied { reason: String },
+    Failed { reason: String },
+}
+
+impl fmt::Display for RecordingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let megabytes = |bytes: &u64| *bytes as f64 / 1_000_000.0;
+        match &self.state {
+            RecordingState::Pending => write!(
+                f,
+                "Recording for '{}' is waiting for the streamer's consent",
+                self.watcher_id
+            ),
+            RecordingState::Started { location } => {
+                write!(
+                    f,
+                    "Recording for '{}' started: {}",
+                    self.watcher_id, location
+                )
+            }
+            RecordingState::Progress { location, bytes } => write!(
+                f,
+                "Recording for '{}': {:.1} MB in {}",
+                self.watcher_id,
+                megabytes(bytes),
+                location
+            ),
+            RecordingState::Stopped { location, bytes } => write!(
+                f,
+                "Recording for '{}' stopped: {:.1} MB in {}",
+                self.watcher_id,
+                megabytes(bytes),
+                location
+            ),
+            RecordingState::Paused { location, reason } => write!(
+                f,
+                "Recording for '{}' paused, {} finished: {}",
+                self.watcher_id, location, reason
+            ),
+            RecordingState::Denied { reason } => {
+                write!(f, "Recording for '{}' denied: {}", self.watcher_id, reason)
+            }
+            RecordingState::Failed { reason } => {
+                write!(f, "Recording for '{}' failed: {}", self.watcher_id, reason)
+            }
+        }
+    }
+}
+
+impl IceCandidate {
+    pub fn is_end_of_candidates(&self) -> bool {
+        self.candidate.is_empty()
+    }
+}
+
+#[cfg(feature = "webrtc")]
+impl From<webrtc::ice_transport::ice_candidate::RTCIceCandidateInit> for IceCandidate {
+    fn from(init: webrtc::ice_transport::ice_candidate::RTCIceCandidateInit) -> Self {
+        IceCandidate {
+            candidate: init.candidate,
+            sdp_mid: init.sdp_mid,
+            sdp_mline_index: init.sdp_mline_index,
+            username_fragment: init.username_fragment,
+        }
+    }
+}
+
+#[cfg(feature = "webrtc")]
+impl From<IceCandidate> for webrtc::ice_transport::ice_candidate::RTCIceCandidateInit {
+    fn from(candidate: IceCandidate) -> Self {
+        webrtc::ice_transport::ice_candidate::RTCIceCandidateInit {
+            candidate: candidate.candidate,
+            sdp_mid: candidate.sdp_mid,
+            sdp_mline_index: candidate.sdp_mline_index,
+            username_fragment: candidate.username_fragment,
+        }
+    }
+}
+
+// Who a routed signal came from and the negotiation it's part of; a broadcast
+// one carries neither
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Route {
+    pub from: String,
+    pub negotiation_id: String,
+}
+
+impl Route {
+    pub fn of(text: &str) -> Option<Self> {
+        serde_json::from_str(text).ok()
+    }
+}
+
+impl Signal {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("signals always serialize")
+    }
+
+    // ✅ Wrap the payload in the transmitter command that delivers it to the room
+    pub fn to_command(&self) -> String {
+        Command::Broadcast {
+            message: self.to_json(),
+        }
+        .to_json()
+    }
+
+    // ✅ Wrap an offer, answer or candidate in the command that routes it to the
+    // one member `to` as part of negotiation `negotiation_id`; other signals
+    // aren't routed
+    pub fn to_routed_command(&self, to: &str, negotiation_id: &str) -> Option<String> {
+        let (to, negotiation_id) = (to.to_string(), negotiation_id.to_string());
+        let command = match self {
+            Signal::Offer { sdp } => Command::Offer {
+                to,
+                negotiation_id,
+                sdp: sdp.clone(),
+            },
+            Signal::Answer { sdp } => Command::Answer {
+                to,
+                negotiation_id,
+                sdp: sdp.clone(),
+            },
+            Signal::Candidate(candidate) => Command::IceCandidate {
+                to,
+                negotiation_id,
+                candidate: candidate.clone(),
+            },
+            _ => return None,
+        };
+        Some(command.to_json())
+    }
+
+    // ✅ The signal as the recipient of a routed `offer`/`answer` command gets it:
+    // the same shape, so existing parsers take it as is, plus who sent it and the
+    // negotiation it belongs to, to answer it
+    pub fn to_routed_json(&self, from: &str, negotiation_id: &str) -> String {
+        let mut json = serde_json::to_value(self).expect("signals always serialize");
+        if let Some(object) = json.as_object_mut() {
+            object.insert("from".to_string(), from.into());
+            object.insert("negotiation_id".to_string(), negotiation_id.into());
+        }
+        json.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routed_signals_say_where_they_came_from() {
+        let offer = Signal::Offer {
+            sdp: "v=0".to_string(),
+        };
+        let routed = offer.to_routed_json("cam", "n1");
+        assert_eq!(serde_json::from_str::<Signal>(&routed).unwrap(), offer);
+        assert_eq!(
+            Route::of(&routed),
+            Some(Route {
+                from: "cam".to_string(),
+                negotiation_id: "n1".to_string(),
+            })
+        );
+        assert_eq!(Route::of(&offer.to_json()), None);
+    }
+
+    #[test]
+    fn only_negotiation_signals_are_routed() {
+        let candidate = Signal::Candidate(IceCandidate {
+            candidate: "candidate:1 1 udp 1 10.0.0.1 5000 typ host".to_string(),
+            ..IceCandidate::default()
+        });
+        let command = candidate.to_routed_command("w1", "n2").unwrap();
+        assert_eq!(
+            Command::parse(&command).unwrap(),
+            Command::IceCandidate {
+                to: "w1".to_string(),
+                negotiation_id: "n2".to_string(),
+                candidate: IceCandidate {
+                    candidate: "candidate:1 1 udp 1 10.0.0.1 5000 typ host".to_string(),
+                    ..IceCandidate::default()
+                },
+            }
+        );
+        let answer = Signal::Answer {
+            sdp: "v=0".to_string(),
+        };
+        assert!(matches!(
+            Command::parse(&answer.to_routed_command("cam", "n1").unwrap()),
+            Ok(Command::Answer { to, negotiation_id, sdp })
+                if to == "cam" && negotiation_id == "n1" && sdp == "v=0"
+        ));
+        let audience = Signal::Audience { watchers: 1 };
+        assert_eq!(audience.to_routed_command("w1", "n1"), None);
+    }
+}
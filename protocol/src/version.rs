@@ -0,0 +1,89 @@
+// Protocol versions: a client says which one it speaks as it connects,
+//
+//   /watcher?streamer_id=cam1&id=w1&version=1
+//
+// (one that doesn't say is taken to speak 1, the first), and the transmitter
+// talks to it in that version's wire format: its `Codec`, which decodes the
+// client's frames into `Command`s and encodes what's sent back. A version the
+// transmitter doesn't speak is refused before the upgrade, 400 with
+//
+//   {"error":{"code":"unsupported_version","message":"Unsupported protocol version",
+//             "details":"version 3 is not one of 1"}}
+//
+// Accepted or not, the response's Tuesdays-Protocol-Versions header lists the
+// versions it speaks, e.g. `1`. A new version gets a codec of its own, so the
+// wire format can change without breaking clients of the old one.
+
+use crate::command::Command;
+use crate::error::{ErrorCode, Rejection};
+
+// The query parameter a client gives its version in
+pub const VERSION_PARAM: &str = "version";
+// The response header the transmitter lists its versions in
+pub const VERSIONS_HEADER: &str = "tuesdays-protocol-versions";
+// Every version the transmitter speaks, oldest first; the last is `PROTOCOL_VERSION`
+pub const SUPPORTED: &[u32] = &[1];
+// Clients from before versions were negotiated
+const UNSPECIFIED: u32 = 1;
+
+// One version's wire format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    V1,
+}
+
+impl Codec {
+    pub fn for_version(version: u32) -> Option<Self> {
+        match version {
+            1 => Some(Codec::V1),
+            _ => None,
+        }
+    }
+
+    pub fn version(self) -> u32 {
+        match self {
+            Codec::V1 => 1,
+        }
+    }
+
+    // ✅ The codec for the version a client asked for (`?version=`), if it's one
+    // we speak; the rejection says which we do if not
+    pub fn negotiate(requested: Option<&str>) -> Result<Self, Rejection> {
+        let Some(requested) = requested else {
+            return Ok(Codec::for_version(UNSPECIFIED).unwrap_or_default());
+        };
+        let unsupported = || {
+            Rejection::new(
+                ErrorCode::UnsupportedVersion,
+                format!("version {} is not one of {}", requested, advertised()),
+            )
+        };
+        let version: u32 = requested.parse().map_err(|_| unsupported())?;
+        Codec::for_version(version).ok_or_else(unsupported)
+    }
+
+    // ✅ A client's text frame, as a command
+    pub fn decode(self, text: &str) -> Result<Command, Rejection> {
+        match self {
+            Codec::V1 => Command::parse(text),
+        }
+    }
+
+    // ✅ A message for the client, written in `PROTOCOL_VERSION`'s format, in
+    // the client's own
+    pub fn encode(self, message: String) -> String {
+        match self {
+            Codec::V1 => message,
+        }
+    }
+}
+
+// ✅ The versions we speak, for the Tuesdays-Protocol-Versions header: "1,2"
+pub fn advertised() -> String {
+    SUPPORTED
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,149 @@
+// Agent mode: register with the transmitter as an available camera box and
+// stream only what it asks for (see `tuesdays_protocol::agent`). Each started
+// stream is the regular streamer under its own id, with this process's
+// configuration filling in whatever the command leaves out.
+
+use std::collections::HashMap;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tuesdays_media::{Codec, Source};
+use tuesdays_protocol::agent::StartStream;
+use tuesdays_protocol::{AgentCommand, AgentEvent, Error, SignalingError};
+
+use crate::{Args, start_webrtc_stream};
+
+// A running stream; the generation tells a restarted stream from the task it replaced
+struct Running {
+    generation: u64,
+    task: AbortHandle,
+}
+
+pub async fn run(args: Args) -> Result<(), Error> {
+    // ✅ Register on the control channel
+    let url = format!("{}/agent?id={}", args.server, args.id);
+    let (ws_stream, _) = connect_async(&url)
+        .await
+        .map_err(|err| SignalingError::Connect {
+            url: url.clone(),
+            source: err.into(),
+        })?;
+    let (mut write, mut read) = ws_stream.split();
+    println!(
+        "🤖 Agent '{}' waiting for commands from {}... Press Ctrl+C to stop.",
+        args.id, args.server
+    );
+
+    let mut streams: HashMap<String, Running> = HashMap::new();
+    let mut generation = 0;
+    let (ended_tx, mut ended_rx) = mpsc::unbounded_channel();
+    let mut events = vec![status(&streams)];
+
+    loop {
+        for event in events.drain(..) {
+            write
+                .send(Message::Text(event.to_json().into()))
+                .await
+                .map_err(SignalingError::transport)?;
+        }
+
+        tokio::select! {
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(AgentCommand::Start(start)) => {
+                        let stream_id = start.stream_id.clone();
+                        match stream_args(&args, &start) {
+                            Ok(stream) => {
+                                generation += 1;
+                                println!("▶️ Starting stream '{}'", stream_id);
+                                let ended = ended_tx.clone();
+                                let id = stream_id.clone();
+                                let task = tokio::spawn(async move {
+                                    let result = start_webrtc_stream(stream).await;
+                                    let _ = ended.send((id, generation, result));
+                                });
+                                let running = Running {
+                                    generation,
+                                    task: task.abort_handle(),
+                                };
+                                if let Some(old) = streams.insert(stream_id, running) {
+                                    old.task.abort();
+                                }
+                            }
+                            Err(error) => {
+                                eprintln!("❌ Cannot start stream '{}': {}", stream_id, error);
+                                events.push(AgentEvent::Failed { stream_id, error });
+                            }
+                        }
+                        events.push(status(&streams));
+                    }
+                    Ok(AgentCommand::Stop(stop)) => {
+                        if let Some(running) = streams.remove(&stop.stream_id) {
+                            running.task.abort();
+                            println!("⏹️ Stopped stream '{}'", stop.stream_id);
+                        }
+                        events.push(status(&streams));
+                    }
+                    Err(_) => println!("💬 {}", text),
+                },
+                Some(Ok(Message::Close(reason))) => {
+                    println!("👋 Control connection closed: {:?}", reason);
+                    break;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(SignalingError::transport(err).into()),
+                None => return Err(SignalingError::Transport("connection lost".into()).into()),
+            },
+            // ✅ A stream ended on its own: report it, it's not restarted without a new command
+            Some((stream_id, ended, result)) = ended_rx.recv() => {
+                if streams.get(&stream_id).is_some_and(|running| running.generation == ended) {
+                    streams.remove(&stream_id);
+                    let error = match result {
+                        Ok(()) => "Stream ended".to_string(),
+                        Err(err) => err.to_string(),
+                    };
+                    eprintln!("⚠️ Stream '{}' stopped: {}", stream_id, error);
+                    events.push(AgentEvent::Failed { stream_id, error });
+                    events.push(status(&streams));
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    for running in streams.values() {
+        running.task.abort();
+    }
+    Ok(())
+}
+
+fn status(streams: &HashMap<String, Running>) -> AgentEvent {
+    let mut streams: Vec<String> = streams.keys().cloned().collect();
+    streams.sort();
+    AgentEvent::Status { streams }
+}
+
+// ✅ This agent's configuration with the command's stream id, source and codec
+fn stream_args(args: &Args, start: &StartStream) -> Result<Args, String> {
+    if start.stream_id.is_empty() {
+        return Err("stream_id must not be empty".to_string());
+    }
+    let mut stream = args.clone();
+    stream.id = start.stream_id.clone();
+    stream.mode = None;
+    if let Some(source) = &start.source {
+        stream.source = source.parse::<Source>().map_err(|err| err.to_string())?;
+        // Launch descriptions can do anything GStreamer can (write files, open
+        // devices); only take them from the agent's own configuration
+        if matches!(stream.source, Source::Launch(_)) && stream.source != args.source {
+            return Err(format!("source '{}' is not allowed remotely", source));
+        }
+    }
+    if let Some(codec) = &start.codec {
+        stream.codec = codec.parse::<Codec>().map_err(|err| err.to_string())?;
+    }
+    Ok(stream)
+}
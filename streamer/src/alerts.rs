@@ -0,0 +1,145 @@
+// Picture alerts on the outgoing stream (--alerts): a capture dongle that lost
+// its HDMI signal keeps the pipeline running, on black frames or the last frame
+// over and over, and nothing else notices. Structured like the watcher's alerts,
+// one JSON line per raised or cleared alert:
+//
+//   🚨 {"kind":"black_frames","state":"raised","streamer_id":"cam1",...}
+//
+// The recordings' disk guard (see `disk`) raises its alerts the same way.
+
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tuesdays_media::Picture;
+
+// Thresholds for picture alerts
+pub struct AlertRules {
+    // Picture black for this long
+    pub black: Duration,
+    // Picture unchanged for this long
+    pub frozen: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    BlackFrames,
+    FrozenFrames,
+    // Little room left for recordings; see `disk`
+    LowDiskSpace,
+    LowInodes,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Raised,
+    Cleared,
+}
+
+// One alert event, as logged
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub state: AlertState,
+    pub streamer_id: String,
+    // Correlates with the transmitter's and watchers' logs; unknown until announced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub message: String,
+    // Seconds black or frozen, or MB or inodes free
+    pub value: f64,
+    // Unix time in milliseconds
+    pub timestamp: u64,
+}
+
+// Raises an alert when the picture has been black or frozen long enough and
+// clears it once it's back
+pub struct AlertMonitor {
+    rules: AlertRules,
+    alerts: AlertLog,
+}
+
+impl AlertMonitor {
+    pub fn new(rules: AlertRules, streamer_id: &str) -> Self {
+        AlertMonitor {
+            rules,
+            alerts: AlertLog::new(streamer_id),
+        }
+    }
+
+    // ✅ Check the picture against the rules and log whatever changed; call about
+    // once a second
+    pub fn check(&mut self, picture: Picture, session_id: Option<&str>) {
+        let black = picture.black_for.unwrap_or_default();
+        let frozen = picture.frozen_for.unwrap_or_default();
+        self.alerts.update(
+            session_id,
+            AlertKind::BlackFrames,
+            black >= self.rules.black,
+            black.as_secs_f64(),
+            format!("Picture black for {:.1}s", black.as_secs_f64()),
+        );
+        self.alerts.update(
+            session_id,
+            AlertKind::FrozenFrames,
+            frozen >= self.rules.frozen,
+            frozen.as_secs_f64(),
+            format!("Picture unchanged for {:.1}s", frozen.as_secs_f64()),
+        );
+    }
+}
+
+// The alerts raised and not yet cleared, so each is logged once either way
+pub struct AlertLog {
+    streamer_id: String,
+    active: HashSet<AlertKind>,
+}
+
+impl AlertLog {
+    pub fn new(streamer_id: &str) -> Self {
+        AlertLog {
+            streamer_id: streamer_id.to_string(),
+            active: HashSet::new(),
+        }
+    }
+
+    // ✅ Log an event when a condition flips
+    pub fn update(
+        &mut self,
+        session_id: Option<&str>,
+        kind: AlertKind,
+        firing: bool,
+        value: f64,
+        message: String,
+    ) {
+        let state = match (firing, self.active.contains(&kind)) {
+            (true, false) => {
+                self.active.insert(kind);
+                AlertState::Raised
+            }
+            (false, true) => {
+                self.active.remove(&kind);
+                AlertState::Cleared
+            }
+            _ => return,
+        };
+        let alert = Alert {
+            kind,
+            state,
+            streamer_id: self.streamer_id.clone(),
+            session_id: session_id.map(str::to_string),
+            message,
+            value,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        };
+        match serde_json::to_string(&alert) {
+            Ok(line) => println!("🚨 {}", line),
+            Err(err) => eprintln!("⚠️ Cannot serialize alert: {}", err),
+        }
+    }
+}
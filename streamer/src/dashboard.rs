@@ -0,0 +1,191 @@
+// A live dashboard (--tui) for presenters, pinned to the top of the terminal
+// while the usual log lines scroll beneath it:
+//
+//   🎬 'cam1' · session 6f1c... · up 3m02s
+//   📷 29.9 fps captured · 🎚️ 1210 kbps encoded · 📦 0 frames queued
+//   🧊 ICE connected · 👀 3 watching
+//   🎙️ Microphone open · 🎥 Live
+//   ⌨️ m: mute · s: standby · q: quit (then Enter)
+//   ─────────────────────────────────────────────
+//
+// Keys are read a line at a time, like push-to-talk's Enter, so the terminal
+// stays in its normal mode. Standby cuts to a black picture (video) and mute
+// closes the microphone (audio-only); watchers stay connected through both.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use tuesdays_media::{MediaPipeline, PipelineError, Throughput};
+use tuesdays_protocol::session;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+
+pub const REFRESH: Duration = Duration::from_secs(1);
+// Lines the header takes, rule included
+const HEADER_ROWS: usize = 6;
+const RULE: &str = "─────────────────────────────────────────────";
+
+pub enum Key {
+    Mute,
+    Standby,
+    Quit,
+}
+
+impl Key {
+    pub fn parse(line: &str) -> Option<Key> {
+        match line.trim() {
+            "m" => Some(Key::Mute),
+            "s" => Some(Key::Standby),
+            "q" => Some(Key::Quit),
+            _ => None,
+        }
+    }
+}
+
+pub struct Dashboard {
+    id: String,
+    started: Instant,
+    // The last reading, for rates
+    previous: Option<(Instant, Throughput)>,
+    pub ice: RTCIceConnectionState,
+    pub watchers: Option<usize>,
+    muted: bool,
+    standby: bool,
+}
+
+impl Dashboard {
+    // ✅ Keep the top of the terminal for the dashboard; output scrolls below it
+    pub fn open(id: &str) -> Self {
+        // Set the scrolling region under the header, and start writing there
+        print!(
+            "\x1b[2J\x1b[{};r\x1b[{};1H",
+            HEADER_ROWS + 1,
+            HEADER_ROWS + 1
+        );
+        let _ = io::stdout().flush();
+        Dashboard {
+            id: id.to_string(),
+            started: Instant::now(),
+            previous: None,
+            ice: RTCIceConnectionState::New,
+            watchers: None,
+            muted: false,
+            standby: false,
+        }
+    }
+
+    // ✅ Close or open the microphone
+    pub fn toggle_mute(&mut self, media: &MediaPipeline) -> Result<(), PipelineError> {
+        media.set_mic_open(self.muted)?;
+        self.muted = !self.muted;
+        println!(
+            "{}",
+            if self.muted {
+                "🔇 Microphone muted"
+            } else {
+                "🎙️ Microphone open"
+            }
+        );
+        Ok(())
+    }
+
+    // ✅ Cut to black, or back to the source
+    pub fn toggle_standby(&mut self, media: &MediaPipeline) -> Result<(), PipelineError> {
+        media.set_standby(!self.standby)?;
+        self.standby = !self.standby;
+        println!(
+            "{}",
+            if self.standby {
+                "🌙 Standby: watchers see black"
+            } else {
+                "🎥 Live"
+            }
+        );
+        Ok(())
+    }
+
+    // ✅ Redraw the header in place, leaving the cursor where the log is
+    pub fn draw(&mut self, media: &MediaPipeline, session_id: Option<&str>) {
+        let now = Instant::now();
+        let throughput = media.throughput();
+        let rates = self
+            .previous
+            .map(|(at, before)| (now - at, before))
+            .filter(|(elapsed, _)| !elapsed.is_zero());
+        let fps = match rates {
+            Some((elapsed, before)) if !media.audio_only() => {
+                format!("{:.1} fps", throughput.fps_since(&before, elapsed))
+            }
+            _ => "- fps".to_string(),
+        };
+        let kbps = match rates {
+            Some((elapsed, before)) => {
+                format!("{:.0} kbps", throughput.kbps_since(&before, elapsed))
+            }
+            None => "- kbps".to_string(),
+        };
+        self.previous = Some((now, throughput));
+
+        let watchers = self
+            .watchers
+            .map_or("-".to_string(), |count| count.to_string());
+        let mic = if media.audio_only() {
+            if self.muted {
+                "🔇 Microphone muted"
+            } else {
+                "🎙️ Microphone open"
+            }
+        } else {
+            "🎙️ No audio"
+        };
+        let scene = if self.standby {
+            "🌙 Standby"
+        } else {
+            "🎥 Live"
+        };
+        let lines: [String; HEADER_ROWS] = [
+            format!(
+                "🎬 '{}' · session {} · up {}",
+                self.id,
+                session::label(session_id),
+                clock(self.started.elapsed())
+            ),
+            format!(
+                "📷 {} captured · 🎚️ {} encoded · 📦 {} frames queued",
+                fps, kbps, throughput.queued
+            ),
+            format!("🧊 ICE {} · 👀 {} watching", self.ice, watchers),
+            format!("{} · {}", mic, scene),
+            "⌨️ m: mute · s: standby · q: quit (then Enter)".to_string(),
+            RULE.to_string(),
+        ];
+
+        // Save the cursor, write each header line over the last, and restore it
+        let mut screen = String::from("\x1b7");
+        for (row, line) in lines.iter().enumerate() {
+            screen.push_str(&format!("\x1b[{};1H{}\x1b[K", row + 1, line));
+        }
+        screen.push_str("\x1b8");
+        let mut stdout = io::stdout().lock();
+        let _ = stdout
+            .write_all(screen.as_bytes())
+            .and_then(|_| stdout.flush());
+    }
+}
+
+// ✅ Give the whole terminal back
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        print!("\x1b[r\x1b[999;1H");
+        let _ = io::stdout().flush();
+    }
+}
+
+// 1h02m03s, 2m03s or 3s
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
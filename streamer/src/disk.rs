@@ -0,0 +1,189 @@
+// Disk guard for recordings: a muxer that runs out of disk space (or inodes)
+// mid-file fails with the file cut short and no index, and the recording won't
+// play. So the filesystem --record-dir is on is checked before a recording
+// starts, which is refused if it's already low, and every
+// `recorder::PROGRESS_INTERVAL` while it runs:
+//
+//   streamer --record-requests allow --record-min-free-mb 500 --record-low-disk rotate
+//
+// Below --record-min-free-mb or --record-min-free-inodes, `pause` (the default)
+// finishes the file properly and resumes in a new one once there's room again;
+// `rotate` first deletes this stream's oldest recordings to make room, and
+// pauses only if that isn't enough. Either way an alert is raised, and cleared
+// once there's room:
+//
+//   🚨 {"kind":"low_disk_space","state":"raised","streamer_id":"cam1","value":412.3,...}
+//
+// Only Unix says what's free on a filesystem; elsewhere recordings go unguarded.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{AlertKind, AlertLog};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LowDisk {
+    // Finish the file; resume in a new one once there's room
+    Pause,
+    // Delete the stream's oldest recordings to make room
+    Rotate,
+}
+
+// What to keep free for recordings
+pub struct DiskRules {
+    pub min_free_bytes: u64,
+    pub min_free_inodes: u64,
+}
+
+// What's free on a filesystem
+#[derive(Debug, Clone, Copy)]
+pub struct Space {
+    // To unprivileged users, that is; root's reserve doesn't count
+    pub free_bytes: u64,
+    // None where the filesystem doesn't count inodes (btrfs, some network ones)
+    pub free_inodes: Option<u64>,
+}
+
+// ✅ What's free on the filesystem `path` is on, or would be once it's created
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)] // statvfs's field widths differ between platforms
+pub fn space(path: &Path) -> io::Result<Space> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let existing = CString::new(existing.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    // SAFETY: `existing` is NUL-terminated and `stat` is plain data for statvfs to fill
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(existing.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Space {
+        free_bytes: u64::from(stat.f_bavail) * u64::from(stat.f_frsize),
+        free_inodes: (stat.f_files > 0).then(|| u64::from(stat.f_favail)),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn space(_: &Path) -> io::Result<Space> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free space is only known on Unix",
+    ))
+}
+
+// Checks the recordings' filesystem against the rules, alerting while it's low
+pub struct DiskGuard {
+    rules: DiskRules,
+    low_disk: LowDisk,
+    alerts: AlertLog,
+    // Said once that we can't tell, rather than every check
+    unknown: bool,
+}
+
+impl DiskGuard {
+    pub fn new(rules: DiskRules, low_disk: LowDisk, streamer_id: &str) -> Self {
+        DiskGuard {
+            rules,
+            low_disk,
+            alerts: AlertLog::new(streamer_id),
+            unknown: false,
+        }
+    }
+
+    pub fn low_disk(&self) -> LowDisk {
+        self.low_disk
+    }
+
+    // ✅ Check the filesystem `dir` is on, raising or clearing alerts; why there's
+    // too little room, if there is
+    pub fn check(&mut self, dir: &Path, session_id: Option<&str>) -> Option<String> {
+        let space = match space(dir) {
+            Ok(space) => space,
+            Err(err) => {
+                if !self.unknown {
+                    eprintln!(
+                        "⚠️ Cannot tell how much room {} has; recordings go unguarded: {}",
+                        dir.display(),
+                        err
+                    );
+                    self.unknown = true;
+                }
+                return None;
+            }
+        };
+        let megabytes = space.free_bytes as f64 / 1_000_000.0;
+        let low_space = space.free_bytes < self.rules.min_free_bytes;
+        self.alerts.update(
+            session_id,
+            AlertKind::LowDiskSpace,
+            low_space,
+            megabytes,
+            format!("{:.1} MB free for recordings", megabytes),
+        );
+        let inodes = space.free_inodes.unwrap_or(u64::MAX);
+        let low_inodes = inodes < self.rules.min_free_inodes;
+        self.alerts.update(
+            session_id,
+            AlertKind::LowInodes,
+            low_inodes,
+            inodes as f64,
+            format!("{} inodes free for recordings", inodes),
+        );
+
+        if low_space {
+            Some(format!(
+                "only {:.1} MB free for {}, under {:.1} MB",
+                megabytes,
+                dir.display(),
+                self.rules.min_free_bytes as f64 / 1_000_000.0
+            ))
+        } else if low_inodes {
+            Some(format!(
+                "only {} inodes free for {}, under {}",
+                inodes,
+                dir.display(),
+                self.rules.min_free_inodes
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// ✅ The oldest of the stream's recordings in `dir`, `<stem>-<started>.mkv` and
+// its second rendition's `-low.mkv`, but any in `except`, still being written
+pub fn oldest_recording(dir: &Path, stem: &str, except: &[PathBuf]) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| recording_of(&entry.file_name().to_string_lossy(), stem))
+        .map(|entry| entry.path())
+        .filter(|path| !except.contains(path))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .min()
+        .map(|(_, path)| path)
+}
+
+// Whether `name` is one of the stream's recordings, and not another stream's
+// whose id starts the same, `<stem>-2-<started>.mkv`
+fn recording_of(name: &str, stem: &str) -> bool {
+    let started = name
+        .strip_prefix(stem)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(".mkv"))
+        .map(|rest| rest.strip_suffix("-low").unwrap_or(rest));
+    started.is_some_and(|started| {
+        !started.is_empty() && started.bytes().all(|byte| byte.is_ascii_digit())
+    })
+}
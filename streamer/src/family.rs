@@ -0,0 +1,72 @@
+// Address families for ICE (--ip-family). Both are gathered by default (dual
+// stack); `ipv4` or `ipv6` gathers one alone, and `prefer-ipv4`/`prefer-ipv6`
+// both but gives the preferred one a head start: our candidates of the other
+// family are trickled, and the watcher's are tried, only FALLBACK_DELAY later,
+// so a connection over the preferred family is in place before the other is
+// even attempted (the Happy Eyeballs idea). An IPv6-only venue can't use IPv4
+// host candidates at all, so `ipv6` or `prefer-ipv6` spares it the useless checks.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use webrtc::ice::network_type::NetworkType;
+
+pub const FALLBACK_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpFamily {
+    #[default]
+    Dual,
+    PreferIpv4,
+    PreferIpv6,
+    Ipv4,
+    Ipv6,
+}
+
+impl IpFamily {
+    // What to gather candidates on
+    pub fn network_types(self) -> Vec<NetworkType> {
+        match self {
+            IpFamily::Ipv4 => vec![NetworkType::Udp4],
+            IpFamily::Ipv6 => vec![NetworkType::Udp6],
+            IpFamily::Dual | IpFamily::PreferIpv4 | IpFamily::PreferIpv6 => {
+                vec![NetworkType::Udp4, NetworkType::Udp6]
+            }
+        }
+    }
+
+    pub fn allows(self, address: IpAddr) -> bool {
+        match self {
+            IpFamily::Ipv4 => address.is_ipv4(),
+            IpFamily::Ipv6 => address.is_ipv6(),
+            _ => true,
+        }
+    }
+
+    fn preferred(self) -> Option<bool> {
+        match self {
+            IpFamily::PreferIpv4 => Some(false),
+            IpFamily::PreferIpv6 => Some(true),
+            _ => None,
+        }
+    }
+
+    // ✅ How long to hold a candidate (its `candidate:` line) back: FALLBACK_DELAY
+    // if it's of the family we don't prefer, or the end of candidates, which
+    // follows them
+    pub fn delay(self, candidate: &str) -> Option<Duration> {
+        let ipv6 = self.preferred()?;
+        if candidate.is_empty() {
+            return Some(FALLBACK_DELAY);
+        }
+        // candidate:<foundation> <component> <transport> <priority> <address> ...
+        let address = candidate
+            .split_whitespace()
+            .nth(4)?
+            .parse::<IpAddr>()
+            .ok()?;
+        (address.is_ipv6() != ipv6).then_some(FALLBACK_DELAY)
+    }
+}
@@ -0,0 +1,166 @@
+// RTCP feedback from watchers, folded into what the encoder should do, so a crowd
+// asking at once doesn't keep it busy:
+//
+//   PLI / FIR   one keyframe per KEYFRAME_INTERVAL, however many asked; asks that
+//               come in between are answered together at the end of it
+//   REMB        the bitrate is capped at the lowest receiver estimate heard over
+//               BITRATE_INTERVAL (and the transmitter's recommendation, if lower),
+//               changed only when it moves by 10% or more
+//   NACK        answered by webrtc's responder from its send buffer
+//   probe       the uplink probe's result (see `probe`) stands in for receiver
+//               estimates until the first of them is heard
+//   quality     a watcher's layer request caps the bitrate too (low 300 kbps,
+//               medium 1000 kbps, high or auto: whatever it can take); a switch
+//               starts on a fresh keyframe encoded at the new bitrate. With a
+//               second rendition running (--low-rendition), low is that
+//               rendition instead, and the main encoder is left alone
+//
+// Transport-free: the WebSocket loop feeds it packets and ticks it.
+
+use std::time::{Duration, Instant};
+
+use tuesdays_protocol::Layer;
+use webrtc::rtcp::packet::Packet;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate as remb;
+
+pub const KEYFRAME_INTERVAL: Duration = Duration::from_millis(500);
+const BITRATE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct Feedback {
+    // Keyframe requests not yet answered
+    keyframe_requests: u32,
+    last_keyframe: Option<Instant>,
+    // Lowest receiver estimate since the bitrate was last looked at
+    lowest_estimate_kbps: Option<u32>,
+    estimated_kbps: Option<u32>,
+    estimated_at: Option<Instant>,
+    // The transmitter's recommendation (transmitter --recommend-quality)
+    recommended_kbps: Option<u32>,
+    // What the uplink probe found room for
+    probed_kbps: Option<u32>,
+    // The layer the watcher asked for
+    layer: Option<Layer>,
+    // What the encoder was last set to
+    bitrate_kbps: Option<u32>,
+    // A second rendition is running, and whether it's the one being sent
+    low_available: bool,
+    sending_low: bool,
+}
+
+// What the encoder should do about the feedback so far
+pub enum Action {
+    Keyframe { requests: u32 },
+    Bitrate { kbps: u32 },
+    // Send the second rendition (or the main one again)
+    Rendition { low: bool },
+}
+
+impl Feedback {
+    // ✅ Take one batch of RTCP packets from a watcher
+    pub fn take(&mut self, packets: &[Box<dyn Packet + Send + Sync>]) {
+        for packet in packets {
+            let packet = packet.as_any();
+            if packet.is::<PictureLossIndication>() || packet.is::<FullIntraRequest>() {
+                self.keyframe_requests += 1;
+            } else if let Some(remb) =
+                packet.downcast_ref::<remb::ReceiverEstimatedMaximumBitrate>()
+            {
+                let kbps = (remb.bitrate / 1000.0) as u32;
+                self.lowest_estimate_kbps =
+                    Some(self.lowest_estimate_kbps.map_or(kbps, |k| k.min(kbps)));
+            }
+        }
+    }
+
+    // A watcher answered our offer: it starts decoding at the next keyframe
+    pub fn ask_keyframe(&mut self) {
+        self.keyframe_requests += 1;
+    }
+
+    pub fn recommend(&mut self, kbps: u32) {
+        self.recommended_kbps = Some(kbps);
+    }
+
+    pub fn probed(&mut self, kbps: u32) {
+        self.probed_kbps = Some(kbps);
+    }
+
+    // Whether the second rendition is running (it stops if the machine can't keep up)
+    pub fn set_low_available(&mut self, available: bool) {
+        self.low_available = available;
+    }
+
+    // ✅ Take a watcher's layer request; true if it changes what's sent
+    pub fn request_layer(&mut self, layer: Layer) -> bool {
+        let previous = self.layer.replace(layer);
+        if previous.and_then(layer_kbps) == layer_kbps(layer) {
+            return false;
+        }
+        self.keyframe_requests += 1;
+        true
+    }
+
+    // ✅ What to do now; call at least every KEYFRAME_INTERVAL. A new bitrate
+    // comes before the keyframe, so a layer switch starts on one of its own
+    pub fn tick(&mut self) -> Vec<Action> {
+        let now = Instant::now();
+        let mut actions = Vec::new();
+        let low = self.low_available && self.layer == Some(Layer::Low);
+        if low != self.sending_low {
+            self.sending_low = low;
+            self.keyframe_requests += 1;
+            actions.push(Action::Rendition { low });
+        }
+        if self
+            .estimated_at
+            .is_none_or(|at| now.duration_since(at) >= BITRATE_INTERVAL)
+        {
+            self.estimated_kbps = self.lowest_estimate_kbps.take();
+            self.estimated_at = Some(now);
+        }
+        // Once a receiver has estimated, the probe is out of date
+        if self.estimated_kbps.is_some() {
+            self.probed_kbps = None;
+        }
+        let target = [
+            self.estimated_kbps.or(self.probed_kbps),
+            self.recommended_kbps,
+            self.layer.filter(|_| !low).and_then(layer_kbps),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        if let Some(target) = target
+            && self
+                .bitrate_kbps
+                .is_none_or(|current| target.abs_diff(current) * 10 >= current)
+        {
+            self.bitrate_kbps = Some(target);
+            actions.push(Action::Bitrate { kbps: target });
+        }
+
+        if self.keyframe_requests > 0
+            && self
+                .last_keyframe
+                .is_none_or(|at| now.duration_since(at) >= KEYFRAME_INTERVAL)
+        {
+            actions.push(Action::Keyframe {
+                requests: std::mem::take(&mut self.keyframe_requests),
+            });
+            self.last_keyframe = Some(now);
+        }
+        actions
+    }
+}
+
+// The bitrate a layer is capped at; None for as much as the watcher can take
+fn layer_kbps(layer: Layer) -> Option<u32> {
+    match layer {
+        Layer::Low => Some(300),
+        Layer::Medium => Some(1000),
+        Layer::High | Layer::Auto => None,
+    }
+}
@@ -0,0 +1,98 @@
+// JPEG ingest for devices without GStreamer or WebRTC (a microcontroller camera,
+// a cron job with curl): with --source jpeg, every image POSTed to
+//
+//   curl --data-binary @frame.jpg http://<streamer>:8090/frame
+//
+// becomes the stream's next frame, at whatever rate they come (see
+// `tuesdays_media::jpeg`). 204 when taken, 413/415 for what isn't a frame.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tuesdays_media::{JpegInput, MediaPipeline};
+use tuesdays_protocol::Error;
+
+// Anything bigger isn't a frame from a small device
+const MAX_FRAME_BYTES: usize = 4 * 1024 * 1024;
+
+pub type Server = Pin<Box<dyn Future<Output = Error> + Send>>;
+
+// ✅ Start taking frames if the pipeline wants them; the server resolves only if it breaks
+pub async fn start(bind: SocketAddr, media: &MediaPipeline) -> Result<Server, Error> {
+    let Some(input) = media.jpeg_input() else {
+        return Ok(Box::pin(std::future::pending()));
+    };
+    let listener = TcpListener::bind(bind)
+        .await
+        .map_err(|err| ingest_error(bind, err))?;
+    println!("📷 Taking JPEG frames at http://{}/frame", bind);
+    Ok(Box::pin(serve(listener, bind, input)))
+}
+
+async fn serve(listener: TcpListener, bind: SocketAddr, input: JpegInput) -> Error {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => return ingest_error(bind, err),
+        };
+        let input = input.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle(req, input.clone()));
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    input: JpegInput,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.uri().path() != "/frame" {
+        return Ok(reply(StatusCode::NOT_FOUND, "POST frames to /frame"));
+    }
+    if req.method() != Method::POST {
+        return Ok(reply(StatusCode::METHOD_NOT_ALLOWED, "POST a JPEG image"));
+    }
+    let frame = match Limited::new(req.into_body(), MAX_FRAME_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(err) if err.is::<LengthLimitError>() => {
+            return Ok(reply(StatusCode::PAYLOAD_TOO_LARGE, "Frame too large"));
+        }
+        Err(_) => return Ok(reply(StatusCode::BAD_REQUEST, "Incomplete frame")),
+    };
+    // Every JPEG starts with an SOI marker
+    if !frame.starts_with(&[0xff, 0xd8]) {
+        return Ok(reply(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Not a JPEG image",
+        ));
+    }
+    Ok(match input.push(frame) {
+        Ok(()) => reply(StatusCode::NO_CONTENT, ""),
+        Err(err) => reply(StatusCode::SERVICE_UNAVAILABLE, &err.to_string()),
+    })
+}
+
+fn reply(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+}
+
+fn ingest_error(bind: SocketAddr, err: std::io::Error) -> Error {
+    Error::Pipeline(format!("JPEG ingest on {}: {}", bind, err).into())
+}
@@ -1,30 +1,613 @@
-use bytes::Bytes;
+mod agent;
+mod alerts;
+mod dashboard;
+mod disk;
+mod family;
+mod feedback;
+mod ingest;
+mod preflight;
+mod probe;
+mod publisher;
+mod recorder;
+mod selftest;
+
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::task;
-use webrtc::api::APIBuilder;
+use std::time::Duration;
+use webrtc::api::{API, APIBuilder};
+use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
-use webrtc::media::Sample;
+use webrtc::api::setting_engine::SettingEngine;
 use webrtc::peer_connection::configuration::RTCConfiguration;
-// Removed unused import: use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
-use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::Message;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::ice_transport::ice_connection_state::RTCIceConnectionState;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::interceptor::registry::Registry;
+use webrtc::rtcp::packet::Packet;
+use webrtc::rtp_transceiver::rtp_sender::RTCRtpSender;
+use webrtc::track::track_local::TrackLocal;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use tokio_tungstenite::tungstenite::{self, Message};
 use futures_util::{StreamExt, SinkExt};
 // Removed unused import: use url::Url;
-use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
-use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 
-use gstreamer as gst;
-use gstreamer::prelude::*;
-use gstreamer_app::{AppSink, AppSinkCallbacks};
+use serde::{Deserialize, Serialize};
+use tuesdays_config::{Config, exit};
+use tuesdays_media::{Codec, Ducking, MediaPipeline, MediaPipelineBuilder, Rendition, Source};
+use alerts::{AlertMonitor, AlertRules};
+use dashboard::{Dashboard, Key};
+use disk::{DiskGuard, DiskRules, LowDisk};
+use family::IpFamily;
+use feedback::{Action, Feedback};
+use publisher::{Event, Publisher};
+use recorder::{RecordPolicy, Recorder};
+use tuesdays_protocol::resume::{Arrival, Resumption};
+use tuesdays_protocol::{
+    Command, Delivery, Error, ErrorResponse, IceCandidate, PROTOCOL_VERSION, Signal, SignalingError,
+    StreamDescriptor, Warning, session,
+};
+
+// How often an offer is repeated until its watcher answers it
+const REOFFER_INTERVAL: Duration = Duration::from_secs(2);
+// How often the audio level is reported, with --report-level
+const LEVEL_INTERVAL: Duration = Duration::from_millis(250);
+// Loudness targets the normalizer accepts, in LUFS
+const MIN_LOUDNESS: f64 = -70.0;
+const MAX_LOUDNESS: f64 = -5.0;
+// How often the picture is checked, with --alerts
+const ALERT_INTERVAL: Duration = Duration::from_secs(1);
+// How often reconnecting is tried while resuming a session
+const RESUME_RETRY: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Serialize, Deserialize, Debug, Clone)]
+#[command(about = "Capture video and stream it over WebRTC")]
+#[serde(deny_unknown_fields)]
+struct Args {
+    /// Signaling server base URL
+    #[arg(long, default_value = "ws://localhost:8080")]
+    server: String,
+
+    /// Streamer id watchers use to find this stream
+    #[arg(long, default_value = "streamer")]
+    id: String,
+
+    /// Stream key from the transmitter's operator, for a transmitter run with
+    /// --stream-keys; best kept out of the configuration file with `{ env = "..." }`
+    #[arg(long, value_name = "KEY")]
+    stream_key: Option<String>,
+
+    /// Room to publish into alongside other streamers (co-streaming); by
+    /// default one of its own, named after --id
+    #[arg(long)]
+    room: Option<String>,
+
+    /// Video source: "camera", "test" (test pattern), "screen" (with the
+    /// screen-capture feature), "jpeg" (images POSTed to --ingest-bind), or a
+    /// gst-launch description. With --no-video,
+    /// "camera" is the default microphone and "test" a test tone
+    #[arg(long, default_value = "camera")]
+    source: Source,
+
+    /// Video codec: vp8, vp9 or h264
+    #[arg(long, default_value = "vp8")]
+    codec: Codec,
+
+    /// Stamp a frame sequence number and capture timestamp barcode into the
+    /// top-left corner of every frame (for automated QoE testing)
+    #[arg(long)]
+    watermark: bool,
+
+    /// Show the video being broadcast (framing, watermark) in a local window
+    #[arg(long)]
+    preview: bool,
+
+    /// Address to take POSTed JPEG frames on, with --source jpeg
+    #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:8090")]
+    ingest_bind: SocketAddr,
+
+    /// Broadcast audio only, as Opus (radio, podcast, intercom); --codec is ignored
+    #[arg(long, conflicts_with_all = ["watermark", "preview"])]
+    no_video: bool,
+
+    /// Report the audio level to the room (with --no-video), so that when
+    /// several streamers share it everyone is told who is speaking
+    #[arg(long, requires = "no_video")]
+    report_level: bool,
+
+    /// Normalize the audio (with --no-video) to this loudness in LUFS, e.g. -16,
+    /// so every presenter and microphone comes out equally loud; adds a few
+    /// seconds of latency for the EBU R128 lookahead
+    #[arg(long, value_name = "LUFS", requires = "no_video", allow_negative_numbers = true)]
+    loudness: Option<f64>,
+
+    /// Start with the microphone closed (with --no-video); Enter on this
+    /// terminal opens it, and Enter again closes it
+    #[arg(long, requires = "no_video")]
+    push_to_talk: bool,
+
+    /// Mix a second audio source under the microphone (with --no-video), e.g.
+    /// system audio: "pulsesrc device=alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"
+    #[arg(long, value_name = "SOURCE", requires = "no_video")]
+    loopback: Option<Source>,
+
+    /// Turn the --loopback audio down while the presenter speaks
+    #[arg(long, requires = "loopback")]
+    duck: bool,
+
+    /// Microphone level, in dBFS, above which the presenter counts as speaking
+    #[arg(long, value_name = "DB", default_value_t = -40.0, allow_negative_numbers = true)]
+    duck_threshold: f64,
+
+    /// How far to turn the loopback down while speaking, in dB
+    #[arg(long, value_name = "DB", default_value_t = 15.0)]
+    duck_depth: f64,
+
+    /// Milliseconds to fade the loopback down once speaking starts
+    #[arg(long, value_name = "MS", default_value_t = 50)]
+    duck_attack_ms: u64,
+
+    /// Milliseconds to fade it back up once speaking stops; long enough that
+    /// the gaps between words don't pump it
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    duck_release_ms: u64,
+
+    /// Keep the encoder's bitrate instead of following the transmitter's
+    /// recommendations (transmitter --recommend-quality) and watchers' estimates
+    #[arg(long)]
+    fixed_bitrate: bool,
+
+    /// Seconds to probe the uplink for once the first watcher is connected, to
+    /// start the encoder at what it can carry (0: don't probe)
+    #[arg(long, value_name = "SECS", default_value_t = 3)]
+    probe_secs: u64,
+
+    /// Send video frames as the source delivers them, instead of evenly at the
+    /// negotiated frame rate (pacing holds frames of a burst back by a frame or
+    /// two, so watchers' jitter buffers don't grow to absorb it)
+    #[arg(long)]
+    no_pacing: bool,
+
+    /// Also encode a lower-bitrate rendition in parallel, sent to watchers asking
+    /// for the low layer and recorded alongside; skipped, or stopped, on a
+    /// machine without the CPU for a second encoder
+    #[arg(long, conflicts_with = "no_video")]
+    low_rendition: bool,
+
+    /// Height of the --low-rendition, in pixels
+    #[arg(long, value_name = "PIXELS", default_value_t = 360)]
+    low_height: u32,
+
+    /// Bitrate of the --low-rendition, in kbps
+    #[arg(long, value_name = "KBPS", default_value_t = 300)]
+    low_kbps: u32,
+
+    /// Watch the outgoing picture for black or frozen frames (a capture dongle
+    /// that lost its signal) and emit structured alerts
+    #[arg(long, conflicts_with = "no_video")]
+    alerts: bool,
+
+    /// Raise a black-frames alert after this many seconds of black picture
+    #[arg(long, default_value_t = 3.0)]
+    alert_black: f64,
+
+    /// Raise a frozen-frames alert after this many seconds of unchanged picture
+    #[arg(long, default_value_t = 5.0)]
+    alert_frozen: f64,
+
+    /// Gather ICE candidates only on this network interface (e.g. eth1), so a
+    /// capture server with several NICs streams from the intended one
+    #[arg(long, value_name = "NAME")]
+    bind_interface: Option<String>,
+
+    /// Gather ICE candidates only on this local address
+    #[arg(long, value_name = "IP")]
+    bind_address: Option<IpAddr>,
+
+    /// Address families to gather ICE candidates on: dual (both), ipv4 or ipv6
+    /// alone (e.g. at an IPv6-only venue), or both with prefer-ipv4 or
+    /// prefer-ipv6, the other tried only if the preferred one hasn't connected
+    #[arg(long, value_enum, default_value = "dual")]
+    ip_family: IpFamily,
+
+    /// Capture and encode without connecting to a signaling server, e.g. to
+    /// check a source or encoder offline (with --preview, to frame the shot);
+    /// frames are not sent anywhere
+    #[arg(long)]
+    no_signaling: bool,
+
+    /// What to do when a watcher asks to record the stream: deny, allow, or
+    /// ask (prompt on this terminal for each request)
+    #[arg(long, value_enum, default_value = "deny")]
+    record_requests: RecordPolicy,
+
+    /// Directory for recordings made at watchers' request
+    #[arg(long, value_name = "DIR", default_value = "recordings")]
+    record_dir: PathBuf,
+
+    /// Disk space to keep free on --record-dir's filesystem, in MB: below it no
+    /// recording starts, and a running one is finished before the disk fills up
+    #[arg(long, value_name = "MB", default_value_t = 500)]
+    record_min_free_mb: u64,
+
+    /// Inodes to keep free on --record-dir's filesystem, likewise
+    #[arg(long, default_value_t = 1000)]
+    record_min_free_inodes: u64,
+
+    /// What to do when --record-dir runs low while recording: pause (finish the
+    /// file, and go on in a new one once there's room) or rotate (delete this
+    /// stream's oldest recordings to make room, pausing if that isn't enough)
+    #[arg(long, value_enum, default_value = "pause")]
+    record_low_disk: LowDisk,
+
+    /// Show a live dashboard at the top of the terminal (capture, encoder, ICE,
+    /// watchers), with keys to mute, cut to standby and quit; the log scrolls below
+    #[arg(long, conflicts_with_all = ["push_to_talk", "no_signaling"])]
+    tui: bool,
+
+    /// HLS playlist the stream is also published at (by a packager or CDN),
+    /// listed in the transmitter's directory for players without WebRTC
+    #[arg(long, value_name = "URL")]
+    hls_url: Option<String>,
+
+    /// Turn off audience chat (watchers' `chat`) in the room while streaming
+    #[arg(long)]
+    no_audience_chat: bool,
+
+    // Command line only; not part of the configuration file
+    #[command(subcommand)]
+    #[serde(skip)]
+    mode: Option<Mode>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Mode {
+    /// Stream the test pattern to an in-process receiver over a real WebRTC
+    /// connection, decode it and check the frames; exits non-zero on failure
+    Selftest {
+        /// Seconds to stream for
+        #[arg(long, default_value_t = 5)]
+        duration: u64,
+
+        /// Fail if fewer frames per second are decoded
+        #[arg(long, default_value_t = 10.0)]
+        min_fps: f64,
+    },
+    /// Register with the transmitter (run with --agents) as an available camera
+    /// box under --id, and stream whatever it asks for; --source and --codec
+    /// are the defaults for streams that don't name their own
+    Agent,
+    /// Ping the transmitter's /latency endpoint and print the signaling round
+    /// trip and clock offset; exits non-zero if it's unreachable or too slow
+    Preflight {
+        /// Pings to send
+        #[arg(long, default_value_t = 5)]
+        count: u32,
+
+        /// Fail if the average round trip is longer, in milliseconds
+        #[arg(long)]
+        max_rtt_ms: Option<u64>,
+    },
+}
+
+impl Config for Args {
+    const SECTION: &'static str = "streamer";
+
+    fn command_line_only(&mut self, cli: Self) {
+        self.mode = cli.mode;
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.server.starts_with("ws://") && !self.server.starts_with("wss://") {
+            return Err(format!("server must be a ws:// or wss:// URL, not '{}'", self.server));
+        }
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        if matches!(self.mode, Some(Mode::Preflight { count: 0, .. })) {
+            return Err("preflight needs a count of at least 1".to_string());
+        }
+        if self.no_video && (self.watermark || self.preview) {
+            return Err("watermark and preview need video; not with no_video".to_string());
+        }
+        if self.report_level && !self.no_video {
+            return Err("report_level needs no_video".to_string());
+        }
+        if let Some(target) = self.loudness {
+            if !self.no_video {
+                return Err("loudness needs no_video".to_string());
+            }
+            if !(MIN_LOUDNESS..=MAX_LOUDNESS).contains(&target) {
+                return Err(format!(
+                    "loudness must be between {} and {} LUFS, not {}",
+                    MIN_LOUDNESS, MAX_LOUDNESS, target
+                ));
+            }
+        }
+        if (self.push_to_talk || self.loopback.is_some()) && !self.no_video {
+            return Err("push_to_talk and loopback need no_video".to_string());
+        }
+        if self.push_to_talk && self.record_requests == RecordPolicy::Ask {
+            return Err(
+                "push_to_talk and record_requests = \"ask\" both read the terminal".to_string()
+            );
+        }
+        if self.tui && (self.push_to_talk || self.record_requests == RecordPolicy::Ask) {
+            return Err(
+                "tui reads the terminal; not with push_to_talk or record_requests = \"ask\""
+                    .to_string()
+            );
+        }
+        if self.tui && self.no_signaling {
+            return Err("tui needs signaling; not with no_signaling".to_string());
+        }
+        if self.duck && self.loopback.is_none() {
+            return Err("duck needs loopback".to_string());
+        }
+        if !self.duck_threshold.is_finite() || self.duck_threshold > 0.0 {
+            return Err(format!(
+                "duck_threshold must be at most 0 dBFS, not {}",
+                self.duck_threshold
+            ));
+        }
+        if !self.duck_depth.is_finite() || self.duck_depth <= 0.0 {
+            return Err(format!("duck_depth must be above 0 dB, not {}", self.duck_depth));
+        }
+        if self.low_rendition && self.no_video {
+            return Err("low_rendition needs video; not with no_video".to_string());
+        }
+        if self.low_height == 0 || self.low_kbps == 0 {
+            return Err("low_height and low_kbps must be above 0".to_string());
+        }
+        if self.alerts && self.no_video {
+            return Err("alerts need video; not with no_video".to_string());
+        }
+        let thresholds = [("alert_black", self.alert_black), ("alert_frozen", self.alert_frozen)];
+        if let Some((name, value)) = thresholds
+            .into_iter()
+            .find(|(_, value)| !value.is_finite() || *value < 0.0)
+        {
+            return Err(format!("{} must be a non-negative number, not {}", name, value));
+        }
+        if let Some(url) = &self.hls_url
+            && !url.starts_with("http://")
+            && !url.starts_with("https://")
+        {
+            return Err(format!("hls_url must be an http:// or https:// URL, not '{}'", url));
+        }
+        if let Some(address) = self.bind_address
+            && !self.ip_family.allows(address)
+        {
+            return Err(format!("bind_address {} is not gathered with ip_family", address));
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<String, String> {
+        let missing = media_pipeline(self)
+            .missing_elements()
+            .map_err(|err| err.to_string())?;
+        if !missing.is_empty() {
+            return Err(format!("missing GStreamer elements: {}", missing.join(", ")));
+        }
+        Ok(format!(
+            "GStreamer elements for '{}' as {} are installed",
+            self.source,
+            encoding(self)
+        ))
+    }
+}
+
+// ✅ Capture + encode pipeline feeding a WebRTC track
+fn media_pipeline(args: &Args) -> MediaPipelineBuilder {
+    MediaPipeline::builder()
+        .source(args.source.clone())
+        .codec(args.codec)
+        .watermark(args.watermark)
+        .preview(args.preview)
+        .recordable(args.record_requests != RecordPolicy::Deny)
+        .audio_only(args.no_video)
+        .pacing(!args.no_pacing)
+        .loudness(args.loudness)
+        .push_to_talk(args.push_to_talk)
+        .controls(args.tui)
+        .loopback(args.loopback.clone())
+        .rendition(args.low_rendition.then_some(Rendition {
+            height: args.low_height,
+            kbps: args.low_kbps,
+        }))
+        .ducking(args.duck.then(|| Ducking {
+            threshold_db: args.duck_threshold,
+            depth_db: args.duck_depth,
+            attack: Duration::from_millis(args.duck_attack_ms),
+            release: Duration::from_millis(args.duck_release_ms),
+        }))
+}
+
+// What the stream is sent as, for log lines
+fn encoding(args: &Args) -> String {
+    if args.no_video {
+        "opus (audio only)".to_string()
+    } else {
+        args.codec.to_string()
+    }
+}
+
+// ✅ What the stream is and how to play it, for the transmitter's directory; the
+// codec as negotiated once it is, and the frame size once frames flow
+fn describe(args: &Args, media: &MediaPipeline, negotiated: Option<String>) -> Command {
+    let mut delivery = vec![Delivery::Webrtc];
+    if let Some(url) = &args.hls_url {
+        delivery.push(Delivery::Hls { url: url.clone() });
+    }
+    let codec = if media.audio_only() { "opus".to_string() } else { media.codec().to_string() };
+    let resolution = media.resolution();
+    Command::Describe {
+        media: StreamDescriptor {
+            codec: Some(negotiated.unwrap_or(codec)),
+            width: resolution.map(|(width, _)| width),
+            height: resolution.map(|(_, height)| height),
+            audio: media.audio_only(),
+            delivery,
+        },
+    }
+}
+
+// ✅ The watchers' peer connections, each sending the stream: their RTCP is pooled
+// for `feedback`, their candidates and ICE states say which watcher they're for
+struct Connections {
+    api: API,
+    config: RTCConfiguration,
+    family: IpFamily,
+    // By watcher id
+    senders: HashMap<String, Arc<RTCRtpSender>>,
+    // Whether the low rendition is being sent instead, to watchers joining too
+    low: bool,
+    rtcp_tx: tokio::sync::mpsc::UnboundedSender<Vec<Box<dyn Packet + Send + Sync>>>,
+    candidate_tx: tokio::sync::mpsc::UnboundedSender<(String, String, IceCandidate)>,
+    ice_tx: tokio::sync::mpsc::UnboundedSender<(String, RTCIceConnectionState)>,
+}
+
+impl Connections {
+    // ✅ A peer connection for a watcher's negotiation, with the track added, so
+    // the offer carries it
+    async fn open(
+        &mut self,
+        watcher_id: &str,
+        negotiation_id: &str,
+        media: &MediaPipeline,
+    ) -> Result<Arc<RTCPeerConnection>, Error> {
+        let peer_connection = Arc::new(self.api.new_peer_connection(self.config.clone()).await?);
+        let track = match media.low_track().filter(|_| self.low) {
+            Some(track) => track as Arc<dyn TrackLocal + Send + Sync>,
+            None => media.track() as Arc<dyn TrackLocal + Send + Sync>,
+        };
+        let sender = peer_connection.add_track(track).await?;
+        tokio::spawn(read_rtcp(sender.clone(), self.rtcp_tx.clone()));
+        self.senders.insert(watcher_id.to_string(), sender);
+
+        // ✅ Trickle our candidates to the watcher as soon as they're gathered
+        let (watcher, negotiation) = (watcher_id.to_string(), negotiation_id.to_string());
+        let (candidate_tx, family) = (self.candidate_tx.clone(), self.family);
+        peer_connection.on_ice_candidate(Box::new(move |candidate| {
+            let init = match candidate.map(|c| c.to_json()) {
+                Some(Ok(init)) => init,
+                Some(Err(err)) => {
+                    eprintln!("⚠️ Cannot serialize ICE candidate: {}", err);
+                    return Box::pin(async {});
+                }
+                // Gathering finished: an empty candidate signals end-of-candidates
+                None => RTCIceCandidateInit::default(),
+            };
+            // The family we don't prefer waits its turn; see `family`
+            let candidate = (watcher.clone(), negotiation.clone(), IceCandidate::from(init));
+            let candidate_tx = candidate_tx.clone();
+            Box::pin(async move {
+                if let Some(delay) = family.delay(&candidate.2.candidate) {
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        let _ = candidate_tx.send(candidate);
+                    });
+                    return;
+                }
+                let _ = candidate_tx.send(candidate);
+            })
+        }));
+
+        // ✅ How the connection to the watcher is doing, for the dashboard
+        let (watcher, ice_tx) = (watcher_id.to_string(), self.ice_tx.clone());
+        peer_connection.on_ice_connection_state_change(Box::new(move |state| {
+            let _ = ice_tx.send((watcher.clone(), state));
+            Box::pin(async {})
+        }));
+        Ok(peer_connection)
+    }
+
+    // ✅ The codec negotiation with a watcher settled on, e.g. "vp8" for video/VP8
+    async fn negotiated_codec(&self, watcher_id: &str) -> Option<String> {
+        let parameters = self.senders.get(watcher_id)?.get_parameters().await;
+        let codec = parameters.rtp_parameters.codecs.first()?;
+        let mime_type = &codec.capability.mime_type;
+        Some(mime_type.rsplit('/').next().unwrap_or(mime_type).to_lowercase())
+    }
+}
+
+// ✅ Keep ICE to the chosen NIC on a multi-homed host (--bind-interface, --bind-address)
+// and address families (--ip-family)
+fn setting_engine(args: &Args) -> SettingEngine {
+    let mut settings = SettingEngine::default();
+    settings.set_network_types(args.ip_family.network_types());
+    if let Some(name) = args.bind_interface.clone() {
+        settings.set_interface_filter(Box::new(move |interface| interface == name));
+    }
+    if let Some(address) = args.bind_address {
+        settings.set_ip_filter(Box::new(move |ip| ip == address));
+    }
+    settings
+}
+
+// ✅ Run the same media path with nobody to send to; the unbound track drops every frame
+async fn run_without_signaling(args: Args) -> Result<(), Error> {
+    let media = media_pipeline(&args).build()?;
+    let ingest = ingest::start(args.ingest_bind, &media).await?;
+    media.start()?;
+    println!(
+        "🎬 Capturing '{}' as {} without signaling... Press Ctrl+C to stop.",
+        args.source,
+        encoding(&args)
+    );
+    tokio::select! {
+        err = media.failed() => return Err(err.into()),
+        err = ingest => return Err(err),
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    media.stop()?;
+    Ok(())
+}
 
-async fn start_webrtc_stream() -> Result<(), Box<dyn std::error::Error>> {
-    // ✅ Initialize GStreamer
-    gst::init()?;
+async fn start_webrtc_stream(args: Args) -> Result<(), Error> {
+    let media = media_pipeline(&args).build()?;
 
     // ✅ Connect to Signaling Server
-    let signaling_server_url = "ws://localhost:8080/ws";
-    let (ws_stream, _) = connect_async(signaling_server_url).await?;
-    let (mut write, _read) = ws_stream.split(); // Prefix unused variable with underscore
+    let mut signaling_server_url = format!(
+        "{}/streamer?id={}&version={}",
+        args.server, args.id, PROTOCOL_VERSION
+    );
+    if let Some(room) = &args.room {
+        signaling_server_url.push_str(&format!("&room_id={}", room));
+    }
+    // Lets the directory tell watchers there's no picture
+    if args.no_video {
+        signaling_server_url.push_str("&media=audio");
+    }
+    if args.no_audience_chat {
+        signaling_server_url.push_str("&chat=off");
+    }
+    if let Some(key) = &args.stream_key {
+        signaling_server_url.push_str(&format!("&key={}", key));
+    }
+    let (ws_stream, _) = connect_async(&signaling_server_url)
+        .await
+        .map_err(|err| SignalingError::Connect {
+            url: signaling_server_url.clone(),
+            source: err.into(),
+        })?;
+    let (mut write, mut read) = ws_stream.split();
+
+    // ✅ Say which build we are, for the transmitter's /clients API
+    let hello = Command::hello("tuesdays-streamer", env!("CARGO_PKG_VERSION"));
+    write
+        .send(Message::Text(hello.to_json().into()))
+        .await
+        .map_err(SignalingError::transport)?;
+    // ✅ And what we're about to send, for the directory; again once it's negotiated
+    write
+        .send(Message::Text(describe(&args, &media, None).to_json().into()))
+        .await
+        .map_err(SignalingError::transport)?;
 
     // ✅ Define WebRTC configuration (ICE servers for NAT traversal can be added later)
     let config = RTCConfiguration {
@@ -32,102 +615,466 @@ async fn start_webrtc_stream() -> Result<(), Box<dyn std::error::Error>> {
         ..Default::default()
     };
 
-    let api = APIBuilder::new().with_media_engine(MediaEngine::default()).build();
-    // ✅ Create a WebRTC PeerConnection
-    let peer_connection = Arc::new(api.new_peer_connection(config).await?);
-    let offer = peer_connection.create_offer(None).await?;
-    peer_connection.set_local_description(offer.clone()).await?;
-
-    // ✅ Send offer to the signaling server
-    let offer_json = serde_json::to_string(&offer)?;
-    println!("📡 Sending WebRTC Offer: {}", offer_json);
-    write.send(Message::Text(offer_json.into())).await?;
-
-    // ✅ Create a WebRTC video track (VP8 Codec, 90kHz clock rate)
-    let video_track = Arc::new(TrackLocalStaticSample::new(
-        RTCRtpCodecCapability {
-            mime_type: "video/vp8".to_owned(),
-            clock_rate: 90000,
-            ..Default::default()
+    // ✅ Register codecs so the track's codec can be negotiated, and RTCP interceptors
+    // (NACK responses from a send buffer, reports)
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let registry = register_default_interceptors(Registry::new(), &mut media_engine)?;
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .with_setting_engine(setting_engine(&args))
+        .build();
+    // ✅ A peer connection per watcher, opened as each joins; see `Connections`
+    let (rtcp_tx, mut rtcp_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (candidate_tx, mut candidate_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (ice_tx, mut ice_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut connections = Connections {
+        api,
+        config,
+        family: args.ip_family,
+        senders: HashMap::new(),
+        low: false,
+        rtcp_tx,
+        candidate_tx,
+        ice_tx,
+    };
+
+    // ✅ The uplink probe's channel goes with the first watcher's, with video whose
+    // bitrate follows; see `probe`
+    let (probe_open_tx, mut probe_open_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut probe_wanted = args.probe_secs > 0 && !args.no_video && !args.fixed_bitrate;
+    let mut probe_channel = None;
+    let (probed_tx, mut probed_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut probe_started = None;
+
+    // ✅ Watchers' keyframe requests and bandwidth estimates, aggregated; see `feedback`
+    let mut feedback = Feedback::default();
+    let mut feedback_ticker = tokio::time::interval(feedback::KEYFRAME_INTERVAL);
+
+    // ✅ Start the GStreamer pipeline (and take frames from devices, for --source jpeg)
+    let mut ingest = ingest::start(args.ingest_bind, &media).await?;
+    media.start()?;
+
+    let kind = if args.no_video { "audio" } else { "video" };
+    println!("🚀 Streaming {}... Press Ctrl+C to stop.", kind);
+
+    // ✅ Offer to each watcher the room says is there, until it answers
+    let mut publisher = Publisher::new().with_family(args.ip_family);
+    let mut reoffer = tokio::time::interval(REOFFER_INTERVAL);
+
+    // ✅ Watchers' recording requests, and the operator's answers with `ask`
+    let guard = DiskGuard::new(
+        DiskRules {
+            min_free_bytes: args.record_min_free_mb * 1_000_000,
+            min_free_inodes: args.record_min_free_inodes,
         },
-        "video".to_owned(),
-        "webrtc-rs".to_owned(),
-    ));
-
-    // ✅ Add the video track to the PeerConnection
-    peer_connection.add_track(video_track.clone()).await?;
-
-    // ✅ Manually Create GStreamer Elements
-    let pipeline = gst::Pipeline::new(); // Pipeline contains the entire flow of elements
-    let source = gst::ElementFactory::make("autovideosrc").build()?; // Video source (webcam)
-    let convert = gst::ElementFactory::make("videoconvert").build()?; // Converts video format
-    let scale = gst::ElementFactory::make("videoscale").build()?; // Adjusts video scaling
-    let sink_element = gst::ElementFactory::make("appsink").build()?; // AppSink receives frames
-
-    // ✅ Convert `sink_element` into `AppSink`
-    let sink = sink_element
-        .clone()
-        .downcast::<AppSink>()
-        .expect("Sink element is not an AppSink");
-
-    // ✅ Add elements to pipeline
-    pipeline.add_many(&[&source, &convert, &scale, &sink_element])?;
-
-    // ✅ Link elements manually (Data flow: source -> convert -> scale -> appsink)
-    source.link(&convert)?;
-    convert.link(&scale)?;
-    scale.link(&sink_element)?;
-
-    let video_track_clone = video_track.clone();
-
-    // ✅ Set up GStreamer AppSink to handle video frames
-    sink.set_callbacks(
-        AppSinkCallbacks::builder()
-            .new_sample(move |sink| {
-                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
-                let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
-
-                // ✅ Convert buffer into readable format
-                let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
-
-                // ✅ Convert buffer to Bytes format (WebRTC compatible)
-                let sample_data: Bytes = map.to_vec().into();
-
-                let video_track_clone = video_track_clone.clone();
-                let timestamp = std::time::SystemTime::now(); // ✅ Set frame timestamp
-
-                let runtime = tokio::runtime::Runtime::new().unwrap();
-                runtime.block_on(async move {  // ✅ Fix: Use Tokio runtime
-                    let _ = video_track_clone
-                        .write_sample(&Sample {
-                            data: sample_data,
-                            duration: std::time::Duration::from_millis(33),
-                            timestamp,
-                            prev_dropped_packets: 0,
-                            prev_padding_packets: 0,
-                            packet_timestamp: 0,
-                        })
-                        .await;
-                });
+        args.record_low_disk,
+        &args.id,
+    );
+    let mut recorder =
+        Recorder::new(args.record_requests, args.record_dir.clone(), args.id.clone(), guard);
+    let mut answers = match args.record_requests {
+        RecordPolicy::Ask => recorder::operator_answers(),
+        _ => tokio::sync::mpsc::unbounded_channel().1,
+    };
+    let mut progress = tokio::time::interval(recorder::PROGRESS_INTERVAL);
+    let mut level = tokio::time::interval(LEVEL_INTERVAL);
 
-                Ok(gst::FlowSuccess::Ok)
-            })
-            .build(),
+    // ✅ Picture alerts on what's being sent; see `alerts`
+    let mut alerts = AlertMonitor::new(
+        AlertRules {
+            black: Duration::from_secs_f64(args.alert_black),
+            frozen: Duration::from_secs_f64(args.alert_frozen),
+        },
+        &args.id,
     );
+    let mut alert_ticker = tokio::time::interval(ALERT_INTERVAL);
+
+    // ✅ Push-to-talk: every Enter opens or closes the microphone
+    let mut talk = if args.push_to_talk {
+        println!("🔇 Microphone closed; press Enter to talk, and Enter again to stop");
+        recorder::operator_answers()
+    } else {
+        tokio::sync::mpsc::unbounded_channel().1
+    };
+    let mut talking = false;
+
+    // ✅ The dashboard, and the keys that drive it; see `dashboard`
+    let mut dashboard = args.tui.then(|| Dashboard::open(&args.id));
+    let mut keys = if args.tui {
+        recorder::operator_answers()
+    } else {
+        tokio::sync::mpsc::unbounded_channel().1
+    };
+    let mut redraw = tokio::time::interval(dashboard::REFRESH);
 
-    // ✅ Start the GStreamer pipeline
-    pipeline.set_state(gst::State::Playing)?;
+    // ✅ What we'd need to resume after a dropped connection (transmitter
+    // --resume-grace-secs); see `tuesdays_protocol::resume`
+    let mut resumption = Resumption::default();
 
-    println!("🚀 Streaming video... Press Ctrl+C to stop.");
+    loop {
+        tokio::select! {
+            _ = reoffer.tick(), if publisher.unanswered() => {
+                for offer in publisher.offer_commands() {
+                    write
+                        .send(Message::Text(offer.into()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                }
+            }
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    // ✅ Skip copies; ask again for what went missing on the way
+                    match resumption.observe(&text) {
+                        Arrival::Copy => continue,
+                        Arrival::Gap { from, to } => {
+                            println!("🕳️ Missed messages {}-{}; asking again", from, to);
+                            write
+                                .send(Message::Text(Command::Replay { from, to }.to_json().into()))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+                        Arrival::New => {}
+                    }
+                    match publisher.handle(&text).await? {
+                        Some(Event::Joined(watcher_id)) => {
+                            let negotiation_id = publisher.next_negotiation_id();
+                            let peer_connection =
+                                connections.open(&watcher_id, &negotiation_id, &media).await?;
+                            if std::mem::take(&mut probe_wanted) {
+                                let channel = probe::channel(&peer_connection).await?;
+                                let probe_open_tx = probe_open_tx.clone();
+                                channel.on_open(Box::new(move || {
+                                    let _ = probe_open_tx.send(());
+                                    Box::pin(async {})
+                                }));
+                                probe_channel = Some(channel);
+                            }
+                            let offer =
+                                publisher.offer(&watcher_id, &negotiation_id, peer_connection);
+                            write
+                                .send(Message::Text(offer.await?.into()))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+                        // A new watcher starts decoding now, not at the next scheduled
+                        // keyframe; the first also settles the codec, for the directory
+                        Some(Event::Answered(watcher_id)) => {
+                            feedback.ask_keyframe();
+                            if publisher.answered().count() == 1 {
+                                let codec = connections.negotiated_codec(&watcher_id).await;
+                                let describe = describe(&args, &media, codec).to_json();
+                                write
+                                    .send(Message::Text(describe.into()))
+                                    .await
+                                    .map_err(SignalingError::transport)?;
+                            }
+                        }
+                        Some(Event::Left(watcher_id)) => {
+                            connections.senders.remove(&watcher_id);
+                        }
+                        None => {}
+                    }
+                    match serde_json::from_str(&text) {
+                        Ok(Signal::Recommendation { bitrate_kbps, .. }) => {
+                            feedback.recommend(bitrate_kbps);
+                        }
+                        Ok(Signal::Quality { watcher_id, layer })
+                            if feedback.request_layer(layer) =>
+                        {
+                            println!("🎞️ Watcher '{}' asked for {:?}", watcher_id, layer);
+                        }
+                        Ok(Signal::Left { member_id, timed_out: true }) => {
+                            println!("💔 '{}' stopped answering and was dropped", member_id);
+                        }
+                        Ok(Signal::Chat { from, message }) => {
+                            println!("💬 {}: {}", from, message);
+                        }
+                        Ok(Signal::WatcherJoined { watcher_id, .. }) => {
+                            println!("👋 Watcher '{}' joined", watcher_id);
+                        }
+                        Ok(Signal::WatcherLeft { watcher_id, .. }) => {
+                            println!("🚪 Watcher '{}' left", watcher_id);
+                        }
+                        Ok(Signal::Role { watcher_id, role }) => {
+                            println!("🎖️ Watcher '{}' is now a {}", watcher_id, role.name());
+                        }
+                        Ok(Signal::Audience { watchers }) => {
+                            if let Some(dashboard) = &mut dashboard {
+                                dashboard.watchers = Some(watchers);
+                            }
+                        }
+                        // ✅ Not the instance for us: start over on the one we're sent to
+                        Ok(Signal::Redirect { url, .. }) => {
+                            println!("🧭 Sent to {}", url);
+                            let (ws_stream, _) = connect_async(&url).await.map_err(|err| {
+                                SignalingError::Connect { url: url.clone(), source: err.into() }
+                            })?;
+                            (write, read) = ws_stream.split();
+                            for command in [
+                                Command::hello("tuesdays-streamer", env!("CARGO_PKG_VERSION")),
+                                describe(&args, &media, None),
+                            ] {
+                                write
+                                    .send(Message::Text(command.to_json().into()))
+                                    .await
+                                    .map_err(SignalingError::transport)?;
+                            }
+                            // A new instance numbers its messages afresh
+                            resumption = Resumption::default();
+                            signaling_server_url = url;
+                            continue;
+                        }
+                        Err(_) => {
+                            if let Some(error) = ErrorResponse::parse(&text) {
+                                eprintln!("⚠️ Transmitter refused: {}", error);
+                            } else if let Ok(warning) = serde_json::from_str::<Warning>(&text) {
+                                eprintln!("⚠️ Transmitter warns: {}", warning);
+                            }
+                        }
+                        _ => {}
+                    }
+                    recorder.set_session(publisher.session_id());
+                    for status in recorder.handle(&text, &media).await {
+                        write
+                            .send(Message::Text(status.to_command().into()))
+                            .await
+                            .map_err(SignalingError::transport)?;
+                    }
+                }
+                Some(Ok(Message::Close(reason))) => {
+                    println!(
+                        "👋 Signaling connection closed (session {}): {:?}",
+                        session::label(publisher.session_id()),
+                        reason
+                    );
+                    break;
+                }
+                Some(Ok(_)) => {}
+                // ✅ Pick up the session on a new connection, watchers still attached
+                Some(Err(_)) | None if resumption.resumable() => {
+                    let Some((url, grace)) = resumption.take(&signaling_server_url) else {
+                        continue;
+                    };
+                    println!(
+                        "🔌 Signaling connection lost; resuming session {} within {}s",
+                        session::label(publisher.session_id()),
+                        grace.as_secs()
+                    );
+                    (write, read) = resume_signaling(&url, grace).await?.split();
+                    let codec = match publisher.answered().next() {
+                        Some(watcher_id) => connections.negotiated_codec(watcher_id).await,
+                        None => None,
+                    };
+                    for command in [
+                        Command::hello("tuesdays-streamer", env!("CARGO_PKG_VERSION")),
+                        describe(&args, &media, codec),
+                    ] {
+                        write
+                            .send(Message::Text(command.to_json().into()))
+                            .await
+                            .map_err(SignalingError::transport)?;
+                    }
+                    println!("🔁 Resumed");
+                }
+                Some(Err(err)) => return Err(SignalingError::transport(err).into()),
+                None => return Err(SignalingError::Transport("connection lost".into()).into()),
+            },
+            Some((watcher_id, negotiation_id, candidate)) = candidate_rx.recv() => {
+                let command = publisher.local_candidate(&watcher_id, &negotiation_id, candidate);
+                let Some(command) = command else { continue };
+                write
+                    .send(Message::Text(command.into()))
+                    .await
+                    .map_err(SignalingError::transport)?;
+            }
+            Some(packets) = rtcp_rx.recv() => feedback.take(&packets),
+            Some(()) = probe_open_rx.recv() => {
+                let Some(channel) = probe_channel.take() else { continue };
+                println!("📶 Probing the uplink for {}s", args.probe_secs);
+                probe_started = Some(media.throughput());
+                let probed_tx = probed_tx.clone();
+                let duration = Duration::from_secs(args.probe_secs);
+                tokio::spawn(async move {
+                    let _ = probed_tx.send(probe::run(channel, duration).await);
+                });
+            }
+            Some(probed) = probed_rx.recv() => match probed {
+                Ok(probed) => {
+                    // The video sent meanwhile took its share of the uplink too
+                    let now = media.throughput();
+                    let video = probe_started
+                        .take()
+                        .map_or(0.0, |before| now.kbps_since(&before, probed.elapsed));
+                    let uplink = probed.kbps() + video;
+                    let kbps = (uplink * probe::SHARE) as u32;
+                    println!(
+                        "📶 The uplink carries about {:.0} kbps; starting the encoder at {} kbps",
+                        uplink, kbps
+                    );
+                    feedback.probed(kbps);
+                }
+                Err(err) => eprintln!("⚠️ Cannot probe the uplink: {}", err),
+            },
+            _ = feedback_ticker.tick() => {
+                feedback.set_low_available(media.low_track().is_some());
+                for action in feedback.tick() {
+                    follow_feedback(&args, &media, &mut connections, action).await;
+                }
+            }
+            answer = answers.recv(), if recorder.asking() => {
+                for status in recorder.answer(answer.as_deref(), &media) {
+                    write
+                        .send(Message::Text(status.to_command().into()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                }
+            }
+            _ = progress.tick(), if recorder.recording() => {
+                let mut statuses = recorder.guard(&media).await;
+                statuses.extend(recorder.progress());
+                for status in statuses {
+                    write
+                        .send(Message::Text(status.to_command().into()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                }
+            }
+            _ = level.tick(), if args.report_level => {
+                if let Some(db) = media.audio_level() {
+                    write
+                        .send(Message::Text(Command::Level { db }.to_json().into()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                }
+            }
+            Some(_) = talk.recv() => {
+                talking = !talking;
+                media.set_mic_open(talking)?;
+                println!("{}", if talking { "🎙️ On air" } else { "🔇 Microphone closed" });
+            }
+            Some((watcher_id, state)) = ice_rx.recv() => {
+                println!("🧊 ICE {} ('{}')", state, watcher_id);
+                if let Some(dashboard) = &mut dashboard {
+                    dashboard.ice = state;
+                }
+            }
+            _ = redraw.tick(), if dashboard.is_some() => {
+                if let Some(dashboard) = &mut dashboard {
+                    dashboard.draw(&media, publisher.session_id());
+                }
+            }
+            Some(line) = keys.recv() => {
+                let Some(dashboard) = &mut dashboard else { continue };
+                let done = match Key::parse(&line) {
+                    Some(Key::Mute) => dashboard.toggle_mute(&media),
+                    Some(Key::Standby) => dashboard.toggle_standby(&media),
+                    Some(Key::Quit) => break,
+                    None => {
+                        println!("⌨️ m: mute · s: standby · q: quit (then Enter)");
+                        Ok(())
+                    }
+                };
+                if let Err(err) = done {
+                    eprintln!("⚠️ {}", err);
+                }
+            }
+            _ = alert_ticker.tick(), if args.alerts => {
+                if let Some(picture) = media.picture() {
+                    alerts.check(picture, publisher.session_id());
+                }
+            }
+            // A broken capture pipeline won't recover; exit so a supervisor can restart us
+            err = media.failed() => return Err(err.into()),
+            err = &mut ingest => return Err(err),
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
 
-    // ✅ Keep the app running until user stops it
-    tokio::signal::ctrl_c().await?;
-    pipeline.set_state(gst::State::Null)?;
-    peer_connection.close().await?;
+    // ✅ Finish any recording before the pipeline stops; the connection may be gone already
+    if let Some(status) = recorder.finish(&media).await {
+        let _ = write.send(Message::Text(status.to_command().into())).await;
+    }
+    drop(dashboard);
+    media.stop()?;
+    publisher.close().await;
 
     Ok(())
 }
 
+// ✅ Reconnect until the grace period is up, or the transmitter says there's no
+// session left to resume (410 Gone)
+async fn resume_signaling(
+    url: &str,
+    grace: Duration,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, SignalingError> {
+    let deadline = tokio::time::Instant::now() + grace;
+    loop {
+        let err = match connect_async(url).await {
+            Ok((ws_stream, _)) => return Ok(ws_stream),
+            Err(err) => err,
+        };
+        let gone = matches!(&err, tungstenite::Error::Http(response)
+            if response.status() == tungstenite::http::StatusCode::GONE);
+        if gone || tokio::time::Instant::now() + RESUME_RETRY > deadline {
+            return Err(SignalingError::Connect { url: url.to_string(), source: err.into() });
+        }
+        println!("🔄 Couldn't reconnect yet: {}", err);
+        tokio::time::sleep(RESUME_RETRY).await;
+    }
+}
+
+// ✅ Pass on the RTCP the watchers send; ends with the connection
+async fn read_rtcp(
+    sender: Arc<RTCRtpSender>,
+    packets: tokio::sync::mpsc::UnboundedSender<Vec<Box<dyn Packet + Send + Sync>>>,
+) {
+    while let Ok((batch, _)) = sender.read_rtcp().await {
+        if packets.send(batch).is_err() {
+            return;
+        }
+    }
+}
+
+// ✅ Make the keyframe watchers asked for; follow the bitrate unless told not
+// to; send the rendition they asked for
+async fn follow_feedback(
+    args: &Args,
+    media: &MediaPipeline,
+    connections: &mut Connections,
+    action: Action,
+) {
+    if media.audio_only() {
+        return;
+    }
+    match action {
+        Action::Keyframe { requests } => match media.request_keyframe() {
+            Ok(()) => println!("🔑 Sending a keyframe ({} requests)", requests),
+            Err(err) => eprintln!("⚠️ Cannot send a keyframe: {}", err),
+        },
+        Action::Bitrate { .. } if args.fixed_bitrate => {}
+        Action::Bitrate { kbps } => match media.set_bitrate(kbps) {
+            Ok(()) => println!("🎚️ Encoding at {} kbps, as watchers can take", kbps),
+            Err(err) => eprintln!("⚠️ Cannot follow the watchers' bitrate: {}", err),
+        },
+        Action::Rendition { low } => {
+            let (track, which) = match media.low_track().filter(|_| low) {
+                Some(track) => (track as Arc<dyn TrackLocal + Send + Sync>, "the low rendition"),
+                None => (media.track() as Arc<dyn TrackLocal + Send + Sync>, "the main rendition"),
+            };
+            connections.low = low && media.low_track().is_some();
+            for sender in connections.senders.values() {
+                if let Err(err) = sender.replace_track(Some(track.clone())).await {
+                    eprintln!("⚠️ Cannot switch to {}: {}", which, err);
+                    return;
+                }
+            }
+            println!("🪜 Sending {}", which);
+        }
+    }
+}
+
 #[cfg(target_os = "macos")]
 extern crate cocoa;
 
@@ -148,7 +1095,34 @@ fn initialize_macos_ui() {
 async fn main() {
     initialize_macos_ui(); // 🛠️ Ensure NSApplication is running
 
-    if let Err(err) = start_webrtc_stream().await {
+    let args: Args = tuesdays_config::load();
+
+    if let Some(Mode::Selftest { duration, min_fps }) = args.mode {
+        let result = selftest::run(args.codec, Duration::from_secs(duration), min_fps).await;
+        if let Err(err) = result {
+            eprintln!("❌ Self-test failed: {}", err);
+            std::process::exit(exit::FAILURE);
+        }
+        return;
+    }
+
+    if let Some(Mode::Preflight { count, max_rtt_ms }) = args.mode {
+        if let Err(err) = preflight::run(&args.server, count, max_rtt_ms).await {
+            eprintln!("❌ Preflight failed: {}", err);
+            std::process::exit(exit::FAILURE);
+        }
+        return;
+    }
+
+    let result = if matches!(args.mode, Some(Mode::Agent)) {
+        agent::run(args).await
+    } else if args.no_signaling {
+        run_without_signaling(args).await
+    } else {
+        start_webrtc_stream(args).await
+    };
+    if let Err(err) = result {
         eprintln!("❌ Error: {}", err);
+        std::process::exit(exit::FAILURE);
     }
 }
\ No newline at end of file
@@ -0,0 +1,90 @@
+// Preflight: before going live, check the way to the transmitter. Pings its
+// /latency WebSocket a few times and prints the signaling round trip and how
+// far our clock is off from its; see `tuesdays_protocol::latency`.
+//
+//   streamer --server wss://tx.example.com preflight --count 10 --max-rtt-ms 150
+//
+// Fails (exits non-zero) if the transmitter can't be reached, stops answering,
+// or the average round trip is above --max-rtt-ms.
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tuesdays_protocol::latency::{self, Measurement, Pong};
+use tuesdays_protocol::{Command, SignalingError};
+
+// How long to wait for each answer
+const PONG_TIMEOUT: Duration = Duration::from_secs(5);
+// Between pings, so they don't queue behind each other
+const PING_GAP: Duration = Duration::from_millis(100);
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreflightError {
+    #[error(transparent)]
+    Signaling(#[from] SignalingError),
+    #[error("No answer to a ping within {0:?}")]
+    NoAnswer(Duration),
+    #[error("Signaling round trip averages {rtt_ms} ms (at most {max_ms} ms allowed)")]
+    TooSlow { rtt_ms: u64, max_ms: u64 },
+}
+
+pub async fn run(server: &str, count: u32, max_rtt_ms: Option<u64>) -> Result<(), PreflightError> {
+    let url = format!("{}/latency", server);
+    println!("🛫 Preflight: pinging {} {} times", url, count);
+    let (ws_stream, _) = connect_async(&url).await.map_err(|err| SignalingError::Connect {
+        url: url.clone(),
+        source: err.into(),
+    })?;
+    let (mut write, mut read) = ws_stream.split();
+
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(PING_GAP).await;
+        }
+        let ping = Command::Ping { sent_ms: latency::now_ms() };
+        write
+            .send(Message::Text(ping.to_json().into()))
+            .await
+            .map_err(SignalingError::transport)?;
+        let pong = tokio::time::timeout(PONG_TIMEOUT, async {
+            while let Some(msg) = read.next().await {
+                let pong = match msg.map_err(SignalingError::transport)? {
+                    Message::Text(text) => serde_json::from_str::<Pong>(&text).ok(),
+                    _ => None,
+                };
+                if pong.is_some() {
+                    return Ok(pong);
+                }
+            }
+            Ok::<_, SignalingError>(None)
+        })
+        .await
+        .map_err(|_| PreflightError::NoAnswer(PONG_TIMEOUT))??
+        .ok_or(PreflightError::NoAnswer(PONG_TIMEOUT))?;
+        measurements.push(pong.measure(latency::now_ms()));
+    }
+    let _ = write.send(Message::Close(None)).await;
+
+    let rtts: Vec<u64> = measurements.iter().map(|m| m.rtt_ms).collect();
+    let min = rtts.iter().copied().min().unwrap_or(0);
+    let max = rtts.iter().copied().max().unwrap_or(0);
+    let avg = rtts.iter().sum::<u64>() / rtts.len().max(1) as u64;
+    // The quickest round trip had the least room for asymmetry, so trust its offset
+    let offset_ms = measurements
+        .iter()
+        .min_by_key(|m| m.rtt_ms)
+        .map_or(0, |m| m.offset_ms);
+    println!("🏓 Signaling round trip: min {} ms, avg {} ms, max {} ms", min, avg, max);
+    println!("🕰️ Transmitter's clock is {:+} ms off ours", offset_ms);
+
+    match max_rtt_ms {
+        Some(max_ms) if avg > max_ms => Err(PreflightError::TooSlow { rtt_ms: avg, max_ms }),
+        _ => {
+            println!("✅ Preflight passed");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// Uplink probe (--probe-secs): rather than start the encoder blind, fill a
+// throwaway data channel with padding for the first few seconds the peer
+// connection is up. SCTP's congestion control lets through only what the path
+// carries, so the padding the watcher acknowledged, plus the video sent
+// alongside it, is the uplink's bandwidth now; the encoder starts at SHARE of
+// it (see `Feedback::probed`) until watchers' own estimates take over.
+//
+// The channel is negotiated with the track, in the first offer; watchers need
+// not do anything with it, and it's closed once the probe is over.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
+use webrtc::peer_connection::RTCPeerConnection;
+
+const LABEL: &str = "probe";
+const CHUNK: usize = 16 * 1024;
+// Padding kept queued, enough that the congestion window is never starved
+const HIGH_WATER: usize = 1024 * 1024;
+const POLL: Duration = Duration::from_millis(5);
+// The rest is headroom for audio, RTCP and retransmissions
+pub const SHARE: f64 = 0.8;
+
+// What got through
+pub struct Probed {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Probed {
+    pub fn kbps(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        (self.bytes * 8) as f64 / self.elapsed.as_secs_f64() / 1000.0
+    }
+}
+
+// ✅ The channel to probe over; create it before the offer
+pub async fn channel(
+    peer_connection: &RTCPeerConnection,
+) -> Result<Arc<RTCDataChannel>, webrtc::Error> {
+    let init = RTCDataChannelInit {
+        ordered: Some(false),
+        ..Default::default()
+    };
+    peer_connection.create_data_channel(LABEL, Some(init)).await
+}
+
+// ✅ Keep the open channel full of padding for `duration`, then close it
+pub async fn run(
+    channel: Arc<RTCDataChannel>,
+    duration: Duration,
+) -> Result<Probed, webrtc::Error> {
+    let padding = Bytes::from(vec![0u8; CHUNK]);
+    let started = Instant::now();
+    let mut sent = 0;
+    while started.elapsed() < duration {
+        if channel.buffered_amount().await < HIGH_WATER {
+            sent += channel.send(&padding).await? as u64;
+        } else {
+            tokio::time::sleep(POLL).await;
+        }
+    }
+    // Whatever is still queued didn't make it in time
+    let queued = channel.buffered_amount().await as u64;
+    let elapsed = started.elapsed();
+    let _ = channel.close().await;
+    Ok(Probed {
+        bytes: sent.saturating_sub(queued),
+        elapsed,
+    })
+}
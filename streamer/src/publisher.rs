@@ -0,0 +1,518 @@
+// Streamer side of the signaling protocol (see `tuesdays_protocol::signal`): a
+// peer connection per watcher, each negotiated over offers routed to that
+// watcher alone (see `Command::Offer`), so watchers joining together don't
+// answer each other's offers. The room says who's watching (`watcher_joined`,
+// `watcher_left`); every watcher that joins gets a negotiation of its own, and
+// its offer is repeated until it answers.
+// Transport-free so it can be driven by the WebSocket loop or a MockRoom.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::family::IpFamily;
+
+use tuesdays_protocol::{IceCandidate, IceError, Route, Signal, session};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+// What a message from the room changed for the watchers
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    // A watcher is there to be offered a peer connection; one it had is closed
+    Joined(String),
+    // A watcher answered: media flows to it once ICE connects
+    Answered(String),
+    // A watcher has gone, and its peer connection with it
+    Left(String),
+}
+
+// One watcher's peer connection and how far along it is
+struct Negotiation {
+    id: String,
+    peer_connection: Arc<RTCPeerConnection>,
+    offer: Signal,
+    answered: bool,
+    // Its candidates that arrived before its answer
+    pending: Vec<RTCIceCandidateInit>,
+}
+
+#[derive(Default)]
+pub struct Publisher {
+    // By watcher id
+    watchers: HashMap<String, Negotiation>,
+    negotiations: u64,
+    // Assigned by the transmitter when we joined the room
+    session_id: Option<String>,
+    // Watcher candidates of the family we don't prefer are tried late
+    family: IpFamily,
+}
+
+impl Publisher {
+    pub fn new() -> Self {
+        Publisher::default()
+    }
+
+    pub fn with_family(mut self, family: IpFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    // ✅ A fresh negotiation id, for a watcher's next peer connection; its own
+    // candidates carry it too, so pick it before they're gathered
+    pub fn next_negotiation_id(&mut self) -> String {
+        self.negotiations += 1;
+        format!("n{}", self.negotiations)
+    }
+
+    // ✅ Create and apply the offer for a watcher's peer connection, replacing any
+    // it had; add the tracks to the connection first. Returns the command that
+    // routes the offer to it
+    pub async fn offer(
+        &mut self,
+        watcher_id: &str,
+        negotiation_id: &str,
+        peer_connection: Arc<RTCPeerConnection>,
+    ) -> Result<String, IceError> {
+        let offer = peer_connection.create_offer(None).await?;
+        peer_connection.set_local_description(offer.clone()).await?;
+
+        let offer = Signal::Offer { sdp: offer.sdp };
+        println!(
+            "📡 Sending WebRTC Offer to '{}' ({})",
+            watcher_id, negotiation_id
+        );
+        let negotiation = Negotiation {
+            id: negotiation_id.to_string(),
+            peer_connection,
+            offer,
+            answered: false,
+            pending: Vec::new(),
+        };
+        let command = negotiation
+            .offer
+            .to_routed_command(watcher_id, negotiation_id)
+            .expect("offers are routed");
+        if let Some(replaced) = self.watchers.insert(watcher_id.to_string(), negotiation) {
+            close(replaced.peer_connection);
+        }
+        Ok(command)
+    }
+
+    // The offers to send again, to the watchers that haven't answered them yet
+    pub fn offer_commands(&self) -> Vec<String> {
+        self.watchers
+            .iter()
+            .filter(|(_, negotiation)| !negotiation.answered)
+            .filter_map(|(watcher_id, negotiation)| {
+                negotiation
+                    .offer
+                    .to_routed_command(watcher_id, &negotiation.id)
+            })
+            .collect()
+    }
+
+    pub fn unanswered(&self) -> bool {
+        self.watchers
+            .values()
+            .any(|negotiation| !negotiation.answered)
+    }
+
+    // The watchers that have answered, whose peer connections carry the stream
+    pub fn answered(&self) -> impl Iterator<Item = &str> {
+        self.watchers
+            .iter()
+            .filter(|(_, negotiation)| negotiation.answered)
+            .map(|(watcher_id, _)| watcher_id.as_str())
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    // ✅ The command that trickles one of our candidates to its watcher, unless
+    // that negotiation has since been replaced or the watcher has gone
+    pub fn local_candidate(
+        &self,
+        watcher_id: &str,
+        negotiation_id: &str,
+        candidate: IceCandidate,
+    ) -> Option<String> {
+        self.watchers
+            .get(watcher_id)
+            .filter(|negotiation| negotiation.id == negotiation_id)?;
+        Signal::Candidate(candidate).to_routed_command(watcher_id, negotiation_id)
+    }
+
+    // ✅ Close every watcher's peer connection, when we stop streaming
+    pub async fn close(&mut self) {
+        for (_, negotiation) in self.watchers.drain() {
+            if let Err(err) = negotiation.peer_connection.close().await {
+                eprintln!("⚠️ Cannot close peer connection: {}", err);
+            }
+        }
+    }
+
+    // ✅ Try a watcher's candidate now, or after the head start of the family we prefer
+    async fn add_remote(
+        &self,
+        peer_connection: &Arc<RTCPeerConnection>,
+        candidate: RTCIceCandidateInit,
+    ) -> Result<(), IceError> {
+        let Some(delay) = self.family.delay(&candidate.candidate) else {
+            return Ok(peer_connection.add_ice_candidate(candidate).await?);
+        };
+        let peer_connection = peer_connection.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(err) = peer_connection.add_ice_candidate(candidate).await {
+                eprintln!("⚠️ Cannot add ICE candidate: {}", err);
+            }
+        });
+        Ok(())
+    }
+
+    // ✅ Handle one incoming text frame from the room
+    pub async fn handle(&mut self, text: &str) -> Result<Option<Event>, IceError> {
+        let signal = match serde_json::from_str::<Signal>(text) {
+            Ok(signal) => signal,
+            Err(_) => {
+                println!("💬 {}", text);
+                return Ok(None);
+            }
+        };
+        match signal {
+            // A watcher joining anew needs a new peer connection, whatever it had
+            Signal::WatcherJoined { watcher_id, .. } => Ok(Some(Event::Joined(watcher_id))),
+            Signal::WatcherLeft { watcher_id, .. } => {
+                let Some(negotiation) = self.watchers.remove(&watcher_id) else {
+                    return Ok(None);
+                };
+                close(negotiation.peer_connection);
+                Ok(Some(Event::Left(watcher_id)))
+            }
+            Signal::Answer { sdp } => {
+                let session = session::label(self.session_id()).to_string();
+                let Some((watcher_id, negotiation)) = self.negotiation(text) else {
+                    return Ok(None);
+                };
+                if negotiation.answered {
+                    return Ok(None);
+                }
+                println!(
+                    "📡 Received WebRTC Answer from '{}' (session {})",
+                    watcher_id, session
+                );
+                negotiation
+                    .peer_connection
+                    .set_remote_description(RTCSessionDescription::answer(sdp)?)
+                    .await?;
+                negotiation.answered = true;
+                let peer_connection = negotiation.peer_connection.clone();
+                for candidate in std::mem::take(&mut negotiation.pending) {
+                    self.add_remote(&peer_connection, candidate).await?;
+                }
+                Ok(Some(Event::Answered(watcher_id)))
+            }
+            Signal::Candidate(candidate) if !candidate.is_end_of_candidates() => {
+                let Some((_, negotiation)) = self.negotiation(text) else {
+                    return Ok(None);
+                };
+                if !negotiation.answered {
+                    negotiation.pending.push(candidate.into());
+                    return Ok(None);
+                }
+                let peer_connection = negotiation.peer_connection.clone();
+                self.add_remote(&peer_connection, candidate.into()).await?;
+                Ok(None)
+            }
+            Signal::Session { session_id, .. } => {
+                println!("🆔 Session {}", session_id);
+                self.session_id = Some(session_id);
+                Ok(None)
+            }
+            // Quality, mode and recording requests, and the room's own news
+            _ => Ok(None),
+        }
+    }
+
+    // ✅ The watcher a routed signal came from, if it's part of the negotiation
+    // we have with it; anything else is for a peer connection that's gone
+    fn negotiation(&mut self, text: &str) -> Option<(String, &mut Negotiation)> {
+        let route = Route::of(text)?;
+        let negotiation = self
+            .watchers
+            .get_mut(&route.from)
+            .filter(|negotiation| negotiation.id == route.negotiation_id)?;
+        Some((route.from, negotiation))
+    }
+}
+
+// Let a peer connection go without holding up the room's messages
+fn close(peer_connection: Arc<RTCPeerConnection>) {
+    tokio::spawn(async move {
+        if let Err(err) = peer_connection.close().await {
+            eprintln!("⚠️ Cannot close peer connection: {}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuesdays_protocol::mock::{MockConnection, MockRoom};
+    use webrtc::api::APIBuilder;
+    use webrtc::api::media_engine::{MIME_TYPE_VP8, MediaEngine};
+    use webrtc::peer_connection::configuration::RTCConfiguration;
+    use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
+    use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
+
+    async fn peer_connection() -> Arc<RTCPeerConnection> {
+        let mut media_engine = MediaEngine::default();
+        media_engine.register_default_codecs().unwrap();
+        let api = APIBuilder::new().with_media_engine(media_engine).build();
+        Arc::new(
+            api.new_peer_connection(RTCConfiguration::default())
+                .await
+                .unwrap(),
+        )
+    }
+
+    // A peer connection carrying a track, as the streamer offers them
+    async fn sending() -> Arc<RTCPeerConnection> {
+        let peer_connection = peer_connection().await;
+        let track = Arc::new(TrackLocalStaticSample::new(
+            RTCRtpCodecCapability {
+                mime_type: MIME_TYPE_VP8.to_owned(),
+                clock_rate: 90000,
+                ..Default::default()
+            },
+            "video".to_owned(),
+            "test".to_owned(),
+        ));
+        peer_connection.add_track(track).await.unwrap();
+        peer_connection
+    }
+
+    fn watcher_joined(watcher_id: &str) -> String {
+        Signal::WatcherJoined {
+            watcher_id: watcher_id.to_string(),
+            at_ms: 0,
+        }
+        .to_json()
+    }
+
+    fn remote_candidate() -> IceCandidate {
+        IceCandidate {
+            candidate: "candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host".to_string(),
+            sdp_mid: Some("0".to_string()),
+            sdp_mline_index: Some(0),
+            username_fragment: None,
+        }
+    }
+
+    // ✅ What a watcher does with the offer routed to it: answer it, to whoever sent it
+    async fn answer(watcher: &mut MockConnection) -> Route {
+        let text = watcher
+            .recv_matching(|text| text.contains(r#""type":"offer""#))
+            .await
+            .unwrap();
+        let route = Route::of(&text).unwrap();
+        let Ok(Signal::Offer { sdp }) = serde_json::from_str(&text) else {
+            panic!("expected an offer, got {}", text);
+        };
+        let answerer = peer_connection().await;
+        answerer
+            .set_remote_description(RTCSessionDescription::offer(sdp).unwrap())
+            .await
+            .unwrap();
+        let answer = answerer.create_answer(None).await.unwrap();
+        answerer
+            .set_local_description(answer.clone())
+            .await
+            .unwrap();
+        let answer = Signal::Answer { sdp: answer.sdp };
+        watcher.send(
+            &answer
+                .to_routed_command(&route.from, &route.negotiation_id)
+                .unwrap(),
+        );
+        route
+    }
+
+    async fn join(publisher: &mut Publisher, streamer: &mut MockConnection, watcher_id: &str) {
+        let event = publisher.handle(&watcher_joined(watcher_id)).await.unwrap();
+        assert_eq!(event, Some(Event::Joined(watcher_id.to_string())));
+        let negotiation_id = publisher.next_negotiation_id();
+        let offer = publisher
+            .offer(watcher_id, &negotiation_id, sending().await)
+            .await
+            .unwrap();
+        streamer.send(&offer);
+    }
+
+    #[tokio::test]
+    async fn each_watcher_gets_and_answers_its_own_offer() {
+        let room = MockRoom::new("cam");
+        let mut streamer = room.join("cam");
+        let mut first = room.join("w1");
+        let mut second = room.join("w2");
+        let mut publisher = Publisher::new();
+
+        join(&mut publisher, &mut streamer, "w1").await;
+        join(&mut publisher, &mut streamer, "w2").await;
+        assert_eq!(publisher.offer_commands().len(), 2);
+
+        // Both answer at once; each answer reaches the peer connection it's for
+        let (one, other) = tokio::join!(answer(&mut first), answer(&mut second));
+        assert_eq!(one.from, "cam");
+        assert_ne!(one.negotiation_id, other.negotiation_id);
+        for _ in 0..2 {
+            let text = streamer
+                .recv_matching(|text| text.contains(r#""type":"answer""#))
+                .await
+                .unwrap();
+            assert!(matches!(
+                publisher.handle(&text).await.unwrap(),
+                Some(Event::Answered(_))
+            ));
+        }
+
+        assert!(!publisher.unanswered());
+        assert!(publisher.offer_commands().is_empty());
+        let mut answered: Vec<_> = publisher.answered().collect();
+        answered.sort();
+        assert_eq!(answered, ["w1", "w2"]);
+        for negotiation in publisher.watchers.values() {
+            assert!(
+                negotiation
+                    .peer_connection
+                    .remote_description()
+                    .await
+                    .is_some()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_for_another_negotiation_are_ignored() {
+        let room = MockRoom::new("cam");
+        let mut streamer = room.join("cam");
+        let mut watcher = room.join("w1");
+        let mut publisher = Publisher::new();
+
+        join(&mut publisher, &mut streamer, "w1").await;
+        let _ = answer(&mut watcher).await;
+        // The watcher rejoined meanwhile: the answer is to an offer since replaced
+        join(&mut publisher, &mut streamer, "w1").await;
+        let text = streamer
+            .recv_matching(|text| text.contains(r#""type":"answer""#))
+            .await
+            .unwrap();
+
+        assert_eq!(publisher.handle(&text).await.unwrap(), None);
+        assert!(publisher.unanswered());
+        assert_eq!(publisher.offer_commands().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn candidates_before_the_answer_are_buffered() {
+        let mut publisher = Publisher::new();
+        let negotiation_id = publisher.next_negotiation_id();
+        publisher
+            .offer("w1", &negotiation_id, sending().await)
+            .await
+            .unwrap();
+
+        let candidate = Signal::Candidate(remote_candidate());
+        for text in [
+            candidate.to_routed_json("w1", &negotiation_id),
+            // Not routed, or for a negotiation that isn't the watcher's
+            candidate.to_json(),
+            candidate.to_routed_json("w1", "n0"),
+            candidate.to_routed_json("w2", &negotiation_id),
+        ] {
+            publisher.handle(&text).await.unwrap();
+        }
+
+        assert_eq!(publisher.watchers["w1"].pending.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn candidates_go_only_to_their_current_negotiation() {
+        let mut publisher = Publisher::new();
+        let stale = publisher.next_negotiation_id();
+        publisher
+            .offer("w1", &stale, sending().await)
+            .await
+            .unwrap();
+        let current = publisher.next_negotiation_id();
+        publisher
+            .offer("w1", &current, sending().await)
+            .await
+            .unwrap();
+
+        let command = publisher.local_candidate("w1", &current, remote_candidate());
+        assert!(command.is_some_and(|command| command.contains(r#""to":"w1""#)));
+        assert_eq!(
+            publisher.local_candidate("w1", &stale, remote_candidate()),
+            None
+        );
+        assert_eq!(
+            publisher.local_candidate("w2", &current, remote_candidate()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn a_watcher_that_leaves_is_let_go() {
+        let mut publisher = Publisher::new();
+        let negotiation_id = publisher.next_negotiation_id();
+        publisher
+            .offer("w1", &negotiation_id, sending().await)
+            .await
+            .unwrap();
+
+        let left = Signal::WatcherLeft {
+            watcher_id: "w1".to_string(),
+            at_ms: 0,
+        }
+        .to_json();
+        assert_eq!(
+            publisher.handle(&left).await.unwrap(),
+            Some(Event::Left("w1".to_string()))
+        );
+        assert_eq!(publisher.handle(&left).await.unwrap(), None);
+        assert!(publisher.offer_commands().is_empty());
+    }
+
+    #[tokio::test]
+    async fn session_announced_by_the_room_is_kept() {
+        let mut publisher = Publisher::new();
+
+        publisher
+            .handle(&session::announce("6f1c-test", None))
+            .await
+            .unwrap();
+
+        assert_eq!(publisher.session_id(), Some("6f1c-test"));
+        assert!(!publisher.unanswered());
+    }
+
+    #[tokio::test]
+    async fn unrelated_messages_are_ignored() {
+        let mut publisher = Publisher::new();
+
+        for text in [
+            "Connected as Member: cam to Room: cam".to_string(),
+            Signal::Answer {
+                sdp: "v=0".to_string(),
+            }
+            .to_routed_json("w1", "n1"),
+            Signal::Candidate(IceCandidate::default()).to_routed_json("w1", "n1"),
+        ] {
+            assert_eq!(publisher.handle(&text).await.unwrap(), None);
+        }
+        assert!(publisher.watchers.is_empty());
+    }
+}
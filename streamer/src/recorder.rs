@@ -0,0 +1,370 @@
+// Recording on demand (see `tuesdays_protocol::signal`): watchers ask with a
+// `record` signal, --record-requests decides (or the operator at this terminal,
+// with `ask`), and the stream is recorded here. Statuses go to the whole room.
+// One recording at a time; only the watcher who asked for it can stop it. The
+// disk it's written to is guarded; see `disk`.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tuesdays_media::{MediaPipeline, Recording};
+use tuesdays_protocol::{RecordAction, RecordingState, RecordingStatus, Signal};
+
+use crate::disk::{self, DiskGuard, LowDisk};
+
+// How often watchers hear how far a recording has got
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordPolicy {
+    Deny,
+    Allow,
+    // Prompt on this terminal for every request
+    Ask,
+}
+
+struct Active {
+    watcher_id: String,
+    recording: Recording,
+}
+
+// A recording finished early for want of room, until there's room again
+struct Paused {
+    watcher_id: String,
+    location: String,
+    bytes: u64,
+}
+
+pub struct Recorder {
+    policy: RecordPolicy,
+    dir: PathBuf,
+    stream_id: String,
+    active: Option<Active>,
+    paused: Option<Paused>,
+    // Requests waiting for the operator, oldest first; the first one is being asked
+    asking: VecDeque<String>,
+    guard: DiskGuard,
+    // For the disk alerts; unknown until announced
+    session_id: Option<String>,
+}
+
+impl Recorder {
+    pub fn new(policy: RecordPolicy, dir: PathBuf, stream_id: String, guard: DiskGuard) -> Self {
+        Recorder {
+            policy,
+            dir,
+            stream_id,
+            active: None,
+            paused: None,
+            asking: VecDeque::new(),
+            guard,
+            session_id: None,
+        }
+    }
+
+    // Whether a recording is running, or paused until there's room
+    pub fn recording(&self) -> bool {
+        self.active.is_some() || self.paused.is_some()
+    }
+
+    pub fn set_session(&mut self, session_id: Option<&str>) {
+        self.session_id = session_id.map(str::to_string);
+    }
+
+    // Whether the operator owes an answer
+    pub fn asking(&self) -> bool {
+        !self.asking.is_empty()
+    }
+
+    // ✅ Handle one incoming text frame from the room; returns the statuses to send
+    pub async fn handle(&mut self, text: &str, media: &MediaPipeline) -> Vec<Signal> {
+        let Ok(Signal::Record { watcher_id, action }) = serde_json::from_str::<Signal>(text) else {
+            return Vec::new();
+        };
+        let reply = match action {
+            RecordAction::Start => {
+                println!("⏺️ Watcher '{}' asks to record the stream", watcher_id);
+                self.request(watcher_id, media)
+            }
+            RecordAction::Stop => {
+                println!("⏺️ Watcher '{}' asks to stop recording", watcher_id);
+                self.stop(watcher_id, media).await
+            }
+        };
+        reply.into_iter().map(Signal::Recording).collect()
+    }
+
+    // ✅ The operator's answer to the current prompt; `None` when nobody can answer
+    pub fn answer(&mut self, line: Option<&str>, media: &MediaPipeline) -> Vec<Signal> {
+        let mut statuses = Vec::new();
+        match line {
+            Some(line) => {
+                if let Some(watcher_id) = self.asking.pop_front() {
+                    statuses.push(match line.trim().to_lowercase().as_str() {
+                        "y" | "yes" => self.start(watcher_id, media),
+                        _ => denied(watcher_id, "declined by the streamer"),
+                    });
+                }
+            }
+            None => {
+                for watcher_id in self.asking.drain(..) {
+                    statuses.push(denied(watcher_id, "nobody at the streamer to consent"));
+                }
+            }
+        }
+        if let Some(next) = self.asking.front() {
+            prompt(next);
+        }
+        statuses.into_iter().map(Signal::Recording).collect()
+    }
+
+    pub fn progress(&self) -> Option<Signal> {
+        self.active.as_ref().map(|active| {
+            Signal::Recording(status(
+                &active.watcher_id,
+                RecordingState::Progress {
+                    location: active.recording.path().display().to_string(),
+                    bytes: active.recording.bytes(),
+                },
+            ))
+        })
+    }
+
+    // ✅ Check there's still room for the recording, pausing it if not and
+    // resuming it once there is; returns the statuses to send
+    pub async fn guard(&mut self, media: &MediaPipeline) -> Vec<Signal> {
+        let status = match self.room() {
+            Some(reason) => self.pause(reason, media).await,
+            None => match self.paused.take() {
+                Some(paused) => {
+                    println!("⏺️ There's room again; recording resumes");
+                    Some(self.start(paused.watcher_id, media))
+                }
+                None => None,
+            },
+        };
+        status.into_iter().map(Signal::Recording).collect()
+    }
+
+    // ✅ The stream is ending: close the file properly
+    pub async fn finish(&mut self, media: &MediaPipeline) -> Option<Signal> {
+        let watcher_id = self.active.as_ref()?.watcher_id.clone();
+        self.stop(watcher_id, media).await.map(Signal::Recording)
+    }
+
+    fn request(&mut self, watcher_id: String, media: &MediaPipeline) -> Option<RecordingStatus> {
+        if self.active.is_some() {
+            return Some(self.start(watcher_id, media));
+        }
+        match self.policy {
+            RecordPolicy::Deny => Some(denied(watcher_id, "recording is disabled on this stream")),
+            RecordPolicy::Allow => Some(self.start(watcher_id, media)),
+            RecordPolicy::Ask => {
+                if !self.asking.contains(&watcher_id) {
+                    if self.asking.is_empty() {
+                        prompt(&watcher_id);
+                    }
+                    self.asking.push_back(watcher_id.clone());
+                }
+                Some(status(&watcher_id, RecordingState::Pending))
+            }
+        }
+    }
+
+    fn start(&mut self, watcher_id: String, media: &MediaPipeline) -> RecordingStatus {
+        // Someone's recording is already running; they all get the same file
+        if let Some(active) = &self.active {
+            let location = active.recording.path().display().to_string();
+            return status(&watcher_id, RecordingState::Started { location });
+        }
+        // ✅ Not on a disk that would fill up before the file's finished
+        if let Some(reason) = self.room() {
+            let reply = status(&watcher_id, RecordingState::Failed { reason });
+            println!("⏺️ {}", reply);
+            return reply;
+        }
+
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = self.dir.join(format!("{}-{}.mkv", self.stem(), started));
+        let recording = std::fs::create_dir_all(&self.dir)
+            .map_err(|err| err.to_string())
+            .and_then(|_| media.start_recording(&path).map_err(|err| err.to_string()));
+        let reply = match recording {
+            Ok(recording) => {
+                self.active = Some(Active {
+                    watcher_id: watcher_id.clone(),
+                    recording,
+                });
+                status(
+                    &watcher_id,
+                    RecordingState::Started {
+                        location: path.display().to_string(),
+                    },
+                )
+            }
+            Err(reason) => status(&watcher_id, RecordingState::Failed { reason }),
+        };
+        println!("⏺️ {}", reply);
+        reply
+    }
+
+    async fn stop(&mut self, watcher_id: String, media: &MediaPipeline) -> Option<RecordingStatus> {
+        self.asking.retain(|asking| *asking != watcher_id);
+        // Paused: stopped already, as far as the file goes
+        if self.active.is_none()
+            && let Some(paused) = self
+                .paused
+                .take_if(|paused| paused.watcher_id == watcher_id)
+        {
+            let reply = status(
+                &watcher_id,
+                RecordingState::Stopped {
+                    location: paused.location,
+                    bytes: paused.bytes,
+                },
+            );
+            println!("⏺️ {}", reply);
+            return Some(reply);
+        }
+        let requested_by = &self.active.as_ref()?.watcher_id;
+        if *requested_by != watcher_id {
+            let reason = format!("the recording was requested by '{}'", requested_by);
+            return Some(denied(watcher_id, &reason));
+        }
+
+        let active = self.active.take()?;
+        let location = active.recording.path().display().to_string();
+        let reply = match media.stop_recording(active.recording).await {
+            Ok(bytes) => status(&watcher_id, RecordingState::Stopped { location, bytes }),
+            Err(err) => status(
+                &watcher_id,
+                RecordingState::Failed {
+                    reason: err.to_string(),
+                },
+            ),
+        };
+        println!("⏺️ {}", reply);
+        Some(reply)
+    }
+
+    // The stream id comes from the command line, but keep it to one path component
+    fn stem(&self) -> String {
+        self.stream_id
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    // ✅ Why there's too little room to record, if there is, after deleting the
+    // stream's oldest recordings to make some with `rotate`
+    fn room(&mut self) -> Option<String> {
+        let session_id = self.session_id.as_deref();
+        let mut reason = self.guard.check(&self.dir, session_id)?;
+        if self.guard.low_disk() != LowDisk::Rotate {
+            return Some(reason);
+        }
+        let writing: Vec<PathBuf> = self
+            .active
+            .iter()
+            .flat_map(|active| [Some(active.recording.path()), active.recording.low_path()])
+            .flatten()
+            .map(Path::to_path_buf)
+            .collect();
+        while let Some(oldest) = disk::oldest_recording(&self.dir, &self.stem(), &writing) {
+            if let Err(err) = std::fs::remove_file(&oldest) {
+                eprintln!(
+                    "⚠️ Cannot delete {} to make room: {}",
+                    oldest.display(),
+                    err
+                );
+                break;
+            }
+            println!(
+                "🗑️ Deleted {} to make room for recordings",
+                oldest.display()
+            );
+            reason = self.guard.check(&self.dir, session_id)?;
+        }
+        Some(reason)
+    }
+
+    // ✅ Finish the file before the disk fills up, for `guard` to resume later
+    async fn pause(&mut self, reason: String, media: &MediaPipeline) -> Option<RecordingStatus> {
+        let active = self.active.take()?;
+        let location = active.recording.path().display().to_string();
+        let bytes = match media.stop_recording(active.recording).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                eprintln!("⚠️ Cannot finish {}: {}", location, err);
+                0
+            }
+        };
+        let reply = status(
+            &active.watcher_id,
+            RecordingState::Paused {
+                location: location.clone(),
+                reason,
+            },
+        );
+        println!("⏺️ {}", reply);
+        self.paused = Some(Paused {
+            watcher_id: active.watcher_id,
+            location,
+            bytes,
+        });
+        Some(reply)
+    }
+}
+
+// ✅ Operator answers (and push-to-talk presses), one line each, read on a thread of their own so a pending
+// read never holds up shutdown; the channel closes with stdin
+pub fn operator_answers() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+fn prompt(watcher_id: &str) {
+    println!(
+        "❓ Watcher '{}' wants to record this stream. Allow? [y/N]",
+        watcher_id
+    );
+}
+
+fn status(watcher_id: &str, state: RecordingState) -> RecordingStatus {
+    RecordingStatus {
+        watcher_id: watcher_id.to_string(),
+        state,
+    }
+}
+
+fn denied(watcher_id: String, reason: &str) -> RecordingStatus {
+    let reply = status(
+        &watcher_id,
+        RecordingState::Denied {
+            reason: reason.to_string(),
+        },
+    );
+    println!("⏺️ {}", reply);
+    reply
+}
@@ -0,0 +1,312 @@
+// Loopback self-test: stream the test pattern over a real WebRTC connection to
+// a second peer in this process, decode what arrives and check the frames.
+//
+//   streamer --codec h264 selftest --duration 5
+//
+// No signaling server is involved; both ends gather all their candidates and
+// exchange complete descriptions directly. With the `watermark` feature every
+// decoded frame is also checked for its sequence number, so reordered or
+// corrupted frames fail the test, not just missing ones.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::{AppSink, AppSinkCallbacks, AppSrc};
+use tuesdays_media::{Codec, MediaPipeline, PipelineError, Source};
+use tuesdays_protocol::Error;
+use webrtc::api::APIBuilder;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecParameters;
+use webrtc::track::track_remote::TrackRemote;
+use webrtc::util::Marshal;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SelfTestError {
+    #[error(transparent)]
+    Stream(#[from] Error),
+    #[error(transparent)]
+    Decode(#[from] PipelineError),
+    #[error("No video track arrived within {0:?}")]
+    NoTrack(Duration),
+    #[error("Only {decoded} frames decoded in {duration:?} (expected at least {expected})")]
+    TooFewFrames {
+        decoded: u64,
+        expected: u64,
+        duration: Duration,
+    },
+    #[error("Frame {sequence} decoded after frame {previous}")]
+    OutOfOrder { sequence: u32, previous: u32 },
+}
+
+// What the receiving end has decoded so far
+#[derive(Default)]
+struct Decoded {
+    frames: AtomicU64,
+    // Watermarked frames only
+    marked: AtomicU64,
+    #[cfg(feature = "watermark")]
+    last_sequence: Mutex<Option<u32>>,
+    out_of_order: Mutex<Option<(u32, u32)>>,
+    latency_ms: Mutex<Vec<u32>>,
+}
+
+pub async fn run(codec: Codec, duration: Duration, min_fps: f64) -> Result<(), SelfTestError> {
+    println!(
+        "🧪 Self-test: {} test pattern over a loopback WebRTC connection for {:?}",
+        codec, duration
+    );
+
+    let media = MediaPipeline::builder()
+        .source(Source::Test)
+        .codec(codec)
+        .watermark(cfg!(feature = "watermark"))
+        .build()?;
+
+    let publisher = peer_connection().await?;
+    let watcher = peer_connection().await?;
+    publisher
+        .add_track(media.track())
+        .await
+        .map_err(Error::from)?;
+
+    // ✅ Receiving end: RTP → depayloader → decoder → appsink
+    let decoded = Arc::new(Decoded::default());
+    let (track_tx, mut track_rx) = tokio::sync::mpsc::unbounded_channel();
+    watcher.on_track(Box::new(move |track, _, _| {
+        let _ = track_tx.send(track);
+        Box::pin(async {})
+    }));
+
+    connect(&publisher, &watcher).await?;
+    media.start()?;
+
+    let track = tokio::time::timeout(duration, track_rx.recv())
+        .await
+        .ok()
+        .flatten()
+        .ok_or(SelfTestError::NoTrack(duration))?;
+    let (decoder, src) = decoder(&track.codec(), decoded.clone())?;
+    decoder
+        .set_state(gst::State::Playing)
+        .map_err(PipelineError::from)?;
+    tokio::spawn(forward_rtp(track, src));
+
+    tokio::time::sleep(duration).await;
+
+    media.stop()?;
+    decoder
+        .set_state(gst::State::Null)
+        .map_err(PipelineError::from)?;
+    let _ = publisher.close().await;
+    let _ = watcher.close().await;
+
+    verify(&decoded, duration, min_fps)
+}
+
+// ✅ Pass if enough frames decoded, in order
+fn verify(decoded: &Decoded, duration: Duration, min_fps: f64) -> Result<(), SelfTestError> {
+    let frames = decoded.frames.load(Ordering::Relaxed);
+    let expected = (min_fps * duration.as_secs_f64()) as u64;
+    if frames < expected {
+        return Err(SelfTestError::TooFewFrames {
+            decoded: frames,
+            expected,
+            duration,
+        });
+    }
+    if let Some((sequence, previous)) = *decoded
+        .out_of_order
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+    {
+        return Err(SelfTestError::OutOfOrder { sequence, previous });
+    }
+
+    let fps = frames as f64 / duration.as_secs_f64();
+    let mut latency = decoded
+        .latency_ms
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    latency.sort_unstable();
+    match latency.get(latency.len() / 2) {
+        Some(median) => println!(
+            "✅ Self-test passed: {} frames decoded ({:.1} fps), {} watermarks in order, median latency {} ms",
+            frames,
+            fps,
+            decoded.marked.load(Ordering::Relaxed),
+            median
+        ),
+        None => println!(
+            "✅ Self-test passed: {} frames decoded ({:.1} fps)",
+            frames, fps
+        ),
+    }
+    Ok(())
+}
+
+async fn peer_connection() -> Result<RTCPeerConnection, Error> {
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let api = APIBuilder::new().with_media_engine(media_engine).build();
+    Ok(api.new_peer_connection(RTCConfiguration::default()).await?)
+}
+
+// ✅ Offer/answer with all candidates included, instead of trickling them
+async fn connect(publisher: &RTCPeerConnection, watcher: &RTCPeerConnection) -> Result<(), Error> {
+    let offer = publisher.create_offer(None).await?;
+    let mut gathered = publisher.gathering_complete_promise().await;
+    publisher.set_local_description(offer).await?;
+    let _ = gathered.recv().await;
+    if let Some(offer) = publisher.local_description().await {
+        watcher.set_remote_description(offer).await?;
+    }
+
+    let answer = watcher.create_answer(None).await?;
+    let mut gathered = watcher.gathering_complete_promise().await;
+    watcher.set_local_description(answer).await?;
+    let _ = gathered.recv().await;
+    if let Some(answer) = watcher.local_description().await {
+        publisher.set_remote_description(answer).await?;
+    }
+    Ok(())
+}
+
+fn decode_chain(encoding_name: &str) -> Result<&'static [&'static str], PipelineError> {
+    Ok(match encoding_name {
+        "VP8" => &["rtpvp8depay", "vp8dec"],
+        "VP9" => &["rtpvp9depay", "vp9dec"],
+        "H264" => &["rtph264depay", "h264parse", "avdec_h264"],
+        other => return Err(PipelineError::UnsupportedCodec(other.to_string())),
+    })
+}
+
+// ✅ appsrc → rtpjitterbuffer → depayloader → decoder → [I420] → appsink
+fn decoder(
+    codec: &RTCRtpCodecParameters,
+    decoded: Arc<Decoded>,
+) -> Result<(gst::Pipeline, AppSrc), PipelineError> {
+    let encoding_name = codec
+        .capability
+        .mime_type
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let src = AppSrc::builder()
+        .caps(
+            &gst::Caps::builder("application/x-rtp")
+                .field("media", "video")
+                .field("encoding-name", &encoding_name)
+                .field("payload", codec.payload_type as i32)
+                .field("clock-rate", codec.capability.clock_rate as i32)
+                .build(),
+        )
+        .is_live(true)
+        .format(gst::Format::Time)
+        .do_timestamp(true)
+        .build();
+    let sink = AppSink::builder()
+        .caps(
+            &gst::Caps::builder("video/x-raw")
+                .field("format", "I420")
+                .build(),
+        )
+        .sync(false)
+        .build();
+
+    let mut elements = vec![
+        src.clone().upcast::<gst::Element>(),
+        gst::ElementFactory::make("rtpjitterbuffer").build()?,
+    ];
+    for name in decode_chain(&encoding_name)? {
+        elements.push(gst::ElementFactory::make(name).build()?);
+    }
+    elements.push(gst::ElementFactory::make("videoconvert").build()?);
+    elements.push(sink.clone().upcast());
+
+    let pipeline = gst::Pipeline::new();
+    pipeline.add_many(&elements)?;
+    gst::Element::link_many(&elements)?;
+
+    sink.set_callbacks(
+        AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                decoded.frames.fetch_add(1, Ordering::Relaxed);
+                #[cfg(feature = "watermark")]
+                check_watermark(&sample, &decoded);
+                #[cfg(not(feature = "watermark"))]
+                let _ = sample;
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+    Ok((pipeline, src))
+}
+
+// ✅ Read the sequence number back out of the decoded luma plane
+#[cfg(feature = "watermark")]
+fn check_watermark(sample: &gst::Sample, decoded: &Decoded) {
+    use std::time::SystemTime;
+    use tuesdays_media::watermark;
+
+    let Some(structure) = sample.caps().and_then(|caps| caps.structure(0)) else {
+        return;
+    };
+    let (Ok(width), Ok(height)) = (
+        structure.get::<i32>("width"),
+        structure.get::<i32>("height"),
+    ) else {
+        return;
+    };
+    let Some(map) = sample
+        .buffer()
+        .and_then(|buffer| buffer.map_readable().ok())
+    else {
+        return;
+    };
+    // GStreamer's default I420 layout: the Y plane comes first, rows padded to 4 bytes
+    let stride = (width as usize).next_multiple_of(4);
+    let Some(mark) = watermark::read(&map, stride, height as usize) else {
+        return;
+    };
+
+    decoded.marked.fetch_add(1, Ordering::Relaxed);
+    decoded
+        .latency_ms
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(mark.latency_ms(SystemTime::now()));
+    let mut last = decoded
+        .last_sequence
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(previous) = *last
+        && mark.sequence <= previous
+    {
+        decoded
+            .out_of_order
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert((mark.sequence, previous));
+    }
+    *last = Some(mark.sequence);
+}
+
+// ✅ Pump RTP packets from the remote track into the decoder
+async fn forward_rtp(track: Arc<TrackRemote>, src: AppSrc) {
+    while let Ok((packet, _)) = track.read_rtp().await {
+        let Ok(data) = packet.marshal() else {
+            continue;
+        };
+        if src.push_buffer(gst::Buffer::from_slice(data)).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// Room broadcast fan-out: one message in, one WebSocket frame out per member.
+//
+//   cargo bench --features bench
+
+use std::time::{Duration, Instant};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use transmitter::bench::BenchRoom;
+use tuesdays_protocol::{IceCandidate, Signal};
+
+fn broadcast(c: &mut Criterion) {
+    // A trickled candidate: the most frequent broadcast in a live room
+    let message = Signal::Candidate(IceCandidate {
+        candidate: "candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host".to_string(),
+        sdp_mid: Some("0".to_string()),
+        sdp_mline_index: Some(0),
+        username_fragment: None,
+    })
+    .to_json();
+
+    let mut group = c.benchmark_group("broadcast");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(10));
+
+    for watchers in [1_000, 10_000] {
+        let system = actix::System::new();
+        let mut room = system.block_on(BenchRoom::new(watchers));
+        group.throughput(Throughput::Elements(room.members() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(watchers),
+            &message,
+            |b, message| {
+                b.iter_custom(|iters| {
+                    system.block_on(async {
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            room.broadcast(message).await;
+                        }
+                        start.elapsed()
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, broadcast);
+criterion_main!(benches);
@@ -0,0 +1,243 @@
+// Access log: one line per finished watcher session, for standard log tooling
+// (GoAccess, awk, jq...). Combined Log Format, i.e. Common Log Format plus the
+// referrer and user agent:
+//
+//   203.0.113.7 - w1 [15/Oct/2026:05:36:02 +0000] "GET /watcher?streamer_id=cam&id=w1 HTTP/1.1" 101 5120 "-" "Mozilla/5.0"
+//
+// or JSON Lines with the same facts plus the session id, the client version the
+// watcher announced in its `hello`, duration and why the session ended. Bytes
+// are the signaling bytes sent to the watcher; media goes peer-to-peer and never
+// passes through the transmitter. The file is rotated by size: `access.log` →
+// `access.log.1` → ... → `access.log.{keep}`.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime};
+
+use actix_web::HttpRequest;
+use actix_web::http::header;
+use log::info;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+
+use crate::jwt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    Combined,
+    Json,
+}
+
+// Shared by every connection; cheap to clone
+#[derive(Clone)]
+pub struct AccessLog {
+    format: AccessLogFormat,
+    writer: Arc<Mutex<Writer>>,
+}
+
+struct Writer {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    // Rotate once the file reaches this size; 0 never rotates
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl AccessLog {
+    // ✅ Append to `path`, creating it if needed
+    pub fn open(
+        path: impl Into<PathBuf>,
+        format: AccessLogFormat,
+        max_bytes: u64,
+        keep: usize,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        let file = append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(AccessLog {
+            format,
+            writer: Arc::new(Mutex::new(Writer {
+                path,
+                file,
+                size,
+                max_bytes,
+                keep,
+            })),
+        })
+    }
+
+    fn write(&self, record: &AccessRecord) {
+        let line = match self.format {
+            AccessLogFormat::Combined => record.combined(),
+            AccessLogFormat::Json => record.json(),
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(err) = writer.write_line(&line) {
+            info!(
+                "⚠️ Cannot write access log {}: {}",
+                writer.path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl Writer {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        if self.max_bytes > 0 && self.size >= self.max_bytes {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+
+    // ✅ Shift access.log.N up by one, dropping the oldest, and start a fresh file
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let _ = fs::rename(numbered(&self.path, n), numbered(&self.path, n + 1));
+            }
+            fs::rename(&self.path, numbered(&self.path, 1))?;
+            self.file = append(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+// The request's User-Agent header, if it's text
+pub(crate) fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|agent| agent.to_str().ok())
+        .map(String::from)
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(format!(".{}", n));
+    name.into()
+}
+
+// One watcher connection, from its upgrade request until it's gone
+pub(crate) struct AccessSession {
+    log: AccessLog,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    // From the watcher's `hello`
+    client_version: Option<String>,
+    request: String,
+    started: SystemTime,
+    connected: Instant,
+    bytes_sent: u64,
+    // The first reason we learn of; "connection lost" when there's none
+    close_reason: Option<String>,
+}
+
+impl AccessSession {
+    // `client` is the peer's address, or who's behind it when it came through TLS
+    pub fn new(log: AccessLog, req: &HttpRequest, client: Option<SocketAddr>) -> Self {
+        AccessSession {
+            log,
+            ip: client.map(|addr| addr.ip().to_string()),
+            user_agent: user_agent(req),
+            client_version: None,
+            request: format!(
+                "{} {} {:?}",
+                req.method(),
+                jwt::redacted(req.uri()),
+                req.version()
+            ),
+            started: SystemTime::now(),
+            connected: Instant::now(),
+            bytes_sent: 0,
+            close_reason: None,
+        }
+    }
+
+    pub fn hello(&mut self, client_version: Option<String>) {
+        self.client_version = client_version;
+    }
+
+    pub fn sent(&mut self, bytes: usize) {
+        self.bytes_sent += bytes as u64;
+    }
+
+    pub fn closed(&mut self, reason: impl Into<String>) {
+        self.close_reason.get_or_insert_with(|| reason.into());
+    }
+
+    // ✅ Write the session's record
+    pub fn finish(&self, watcher_id: &str, streamer_id: &str, session_id: Option<&str>) {
+        self.log.write(&AccessRecord {
+            session: self,
+            watcher_id,
+            streamer_id,
+            session_id,
+        });
+    }
+}
+
+struct AccessRecord<'a> {
+    session: &'a AccessSession,
+    watcher_id: &'a str,
+    streamer_id: &'a str,
+    session_id: Option<&'a str>,
+}
+
+impl AccessRecord<'_> {
+    fn combined(&self) -> String {
+        let session = self.session;
+        let started = OffsetDateTime::from(session.started)
+            .format(format_description!(
+                "[day]/[month repr:short]/[year]:[hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]"
+            ))
+            .unwrap_or_default();
+        format!(
+            r#"{} - {} [{}] "{}" 101 {} "-" "{}""#,
+            session.ip.as_deref().unwrap_or("-"),
+            self.watcher_id,
+            started,
+            session.request,
+            session.bytes_sent,
+            session
+                .user_agent
+                .as_deref()
+                .unwrap_or("-")
+                .replace('"', "'")
+        )
+    }
+
+    fn json(&self) -> String {
+        let session = self.session;
+        serde_json::json!({
+            "time": OffsetDateTime::from(session.started).format(&Rfc3339).unwrap_or_default(),
+            "streamer_id": self.streamer_id,
+            "watcher_id": self.watcher_id,
+            "session_id": self.session_id,
+            "ip": session.ip,
+            "user_agent": session.user_agent,
+            "client_version": session.client_version,
+            "request": session.request,
+            "duration_ms": session.connected.elapsed().as_millis() as u64,
+            "bytes_sent": session.bytes_sent,
+            "disconnect_reason": session.close_reason.as_deref().unwrap_or("connection lost"),
+        })
+        .to_string()
+    }
+}
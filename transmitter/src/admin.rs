@@ -0,0 +1,47 @@
+// Operator API authentication (--admin-token): with a token set, /admin/drain and
+// the moderation routes want it as `Authorization: Bearer <token>`, and answer 401
+// without it:
+//
+//   curl -X DELETE -H "Authorization: Bearer $TOKEN" http://tx:8080/api/streamers/cam1
+//
+// The moderation routes (`DELETE /api/streamers/...`) are only served with one.
+// A signaling connection carrying it is an admin to the command policy (see
+// `policy`); with --jwt-* it still needs its own token, in the query string.
+// Tokens are compared through their HMACs under a key of the process's own, so
+// how long a comparison takes says nothing about the token.
+
+use actix_web::HttpRequest;
+use actix_web::http::header;
+use ring::hmac;
+use ring::rand::SystemRandom;
+
+// Cheap to clone into every worker
+#[derive(Clone)]
+pub struct AdminToken {
+    key: hmac::Key,
+    tag: hmac::Tag,
+}
+
+impl AdminToken {
+    pub fn new(token: &str) -> Result<Self, String> {
+        if token.trim().is_empty() {
+            return Err("the admin token is empty".to_string());
+        }
+        let key = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .map_err(|_| "cannot generate a key".to_string())?;
+        let tag = hmac::sign(&key, token.as_bytes());
+        Ok(AdminToken { key, tag })
+    }
+
+    // ✅ The request must carry the token as its bearer token
+    pub(crate) fn verify(&self, req: &HttpRequest) -> Result<(), &'static str> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or("no bearer token")?;
+        hmac::verify(&self.key, token.trim().as_bytes(), self.tag.as_ref())
+            .map_err(|_| "wrong admin token")
+    }
+}
@@ -0,0 +1,139 @@
+use actix::{Actor, Addr, AsyncContext, Handler, Message, StreamHandler};
+use actix_web_actors::ws;
+use log::info;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tuesdays_protocol::{AgentCommand, AgentEvent, AgentInfo, ErrorCode, close};
+
+use crate::heartbeat::{self, Heartbeat, Heartbeating};
+use crate::room::CloseConnection;
+
+// Connected agents by id (see `tuesdays_protocol::agent`)
+pub(crate) type AgentStore = Arc<Mutex<HashMap<String, Agent>>>;
+
+pub(crate) fn lock_agents(agents: &AgentStore) -> MutexGuard<'_, HashMap<String, Agent>> {
+    agents.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) struct Agent {
+    pub addr: Addr<AgentWebSocket>,
+    // As last reported by the agent
+    pub streams: Vec<String>,
+}
+
+impl Agent {
+    pub fn info(&self, id: &str) -> AgentInfo {
+        AgentInfo {
+            id: id.to_string(),
+            streams: self.streams.clone(),
+        }
+    }
+}
+
+// Relay a start/stop command to the agent
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SendCommand(pub AgentCommand);
+
+// WebSocket Actor for agents; they never join a room themselves
+pub(crate) struct AgentWebSocket {
+    pub agent_id: String,
+    pub agents: AgentStore,
+    pub heartbeat: Option<Heartbeat>,
+    // The last frame from the agent; see `heartbeat`
+    pub last_seen: Instant,
+}
+
+impl Actor for AgentWebSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let agent = Agent {
+            addr: ctx.address(),
+            streams: Vec::new(),
+        };
+        if let Some(old) = lock_agents(&self.agents).insert(self.agent_id.clone(), agent) {
+            info!(
+                "⚠️ Agent '{}' already connected. Replacing connection.",
+                self.agent_id
+            );
+            old.addr.do_send(CloseConnection);
+        }
+        info!("🤖 Agent '{}' available", self.agent_id);
+        if let Some(heartbeat) = self.heartbeat {
+            heartbeat::start(heartbeat, ctx);
+        }
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        let mut agents = lock_agents(&self.agents);
+        // Only remove ourselves, not a connection that replaced us
+        if agents
+            .get(&self.agent_id)
+            .is_some_and(|agent| agent.addr == ctx.address())
+        {
+            agents.remove(&self.agent_id);
+        }
+        info!("❌ Agent '{}' disconnected", self.agent_id);
+    }
+}
+
+impl Heartbeating for AgentWebSocket {
+    fn last_seen(&self) -> Instant {
+        self.last_seen
+    }
+
+    fn timed_out(&mut self) {
+        info!("💔 Agent '{}' stopped answering pings", self.agent_id);
+    }
+}
+
+impl Handler<SendCommand> for AgentWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: SendCommand, ctx: &mut Self::Context) {
+        info!("📨 Agent '{}' commanded: {:?}", self.agent_id, msg.0);
+        ctx.text(msg.0.to_json());
+    }
+}
+
+impl Handler<CloseConnection> for AgentWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, _: CloseConnection, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(close::REPLACED),
+            description: Some(close::REPLACED_REASON.to_string()),
+        }));
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for AgentWebSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        if msg.is_ok() {
+            self.last_seen = Instant::now();
+        }
+        if let Ok(ws::Message::Ping(bytes)) = &msg {
+            ctx.pong(bytes);
+        }
+        let Ok(ws::Message::Text(text)) = msg else {
+            return;
+        };
+        match serde_json::from_str::<AgentEvent>(&text) {
+            Ok(AgentEvent::Status { streams }) => {
+                info!("🤖 Agent '{}' streaming: {:?}", self.agent_id, streams);
+                if let Some(agent) = lock_agents(&self.agents).get_mut(&self.agent_id) {
+                    agent.streams = streams;
+                }
+            }
+            Ok(AgentEvent::Failed { stream_id, error }) => {
+                info!(
+                    "⚠️ Agent '{}' stream '{}' failed: {}",
+                    self.agent_id, stream_id, error
+                );
+            }
+            Err(_) => ctx.text(ErrorCode::InvalidCommandFormat.to_json()),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Chat and lifecycle archive (--archive-dir), so producers can pull a stream's
+// chat transcript once it's over: every room's chat and `stream` events, as
+// `tuesdays_protocol::archive` records, one JSON Lines file per stream:
+//
+//   <dir>/<stream>.jsonl
+//
+// exported by `GET /streams/{id}/archive`. Records are kept for the retention of
+// the stream's tenant, its id up to the first '/' (--archive-retention):
+//
+//   default_hours = 720     # streams of unlisted tenants (30 days)
+//
+//   [tenants]
+//   acme = 2160             # acme/...
+//   trial = 24              # trial/...
+//
+// A purge job drops older records every PURGE_INTERVAL, and files left empty.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::info;
+use serde::Deserialize;
+use tuesdays_protocol::{ArchiveEvent, ArchiveRecord};
+
+use crate::capture;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Shared by every room and connection; cheap to clone
+#[derive(Clone)]
+pub struct Archive {
+    dir: PathBuf,
+    retention: Arc<Retention>,
+    // Held while a file is appended to or rewritten
+    files: Arc<Mutex<()>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Retention {
+    #[serde(default = "default_hours")]
+    default_hours: u64,
+    #[serde(default)]
+    tenants: HashMap<String, u64>,
+}
+
+fn default_hours() -> u64 {
+    30 * 24
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            default_hours: default_hours(),
+            tenants: HashMap::new(),
+        }
+    }
+}
+
+impl Archive {
+    // ✅ Archive into `dir`, creating it if needed, keeping records as long as the
+    // `retention` file says (30 days without one)
+    pub fn new(dir: impl Into<PathBuf>, retention: Option<&Path>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let retention = match retention {
+            Some(path) => {
+                let text = fs::read_to_string(path)?;
+                toml::from_str(&text).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {}", path.display(), err),
+                    )
+                })?
+            }
+            None => Retention::default(),
+        };
+        Ok(Archive {
+            dir,
+            retention: Arc::new(retention),
+            files: Arc::new(Mutex::new(())),
+        })
+    }
+
+    fn retention(&self, stream_id: &str) -> Duration {
+        let hours = stream_id
+            .split_once('/')
+            .and_then(|(tenant, _)| self.retention.tenants.get(tenant))
+            .unwrap_or(&self.retention.default_hours);
+        Duration::from_secs(hours * 60 * 60)
+    }
+
+    fn path(&self, stream_id: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.jsonl", capture::file_name(stream_id)))
+    }
+
+    // ✅ Append one event to the stream's archive
+    pub(crate) fn record(&self, stream_id: &str, session_id: Option<&str>, event: ArchiveEvent) {
+        let record = ArchiveRecord {
+            at_ms: now_ms(),
+            stream_id: stream_id.to_string(),
+            event,
+            session_id: session_id.map(String::from),
+        };
+        let _files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let written = serde_json::to_string(&record)
+            .map_err(io::Error::other)
+            .and_then(|line| {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.path(stream_id))?;
+                writeln!(file, "{}", line)
+            });
+        if let Err(err) = written {
+            info!("⚠️ Cannot archive stream '{}': {}", stream_id, err);
+        }
+    }
+
+    // ✅ The stream's records still within its retention, oldest first
+    pub(crate) fn read(&self, stream_id: &str) -> io::Result<Vec<ArchiveRecord>> {
+        let _files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        let text = match fs::read_to_string(self.path(stream_id)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let now = now_ms();
+        Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ArchiveRecord>(line).ok())
+            .filter(|record| record.stream_id == stream_id && !self.expired(record, now))
+            .collect())
+    }
+
+    fn expired(&self, record: &ArchiveRecord, now_ms: u64) -> bool {
+        let age = Duration::from_millis(now_ms.saturating_sub(record.at_ms));
+        age >= self.retention(&record.stream_id)
+    }
+
+    // ✅ Drop every record past its retention, and files left empty; how many went
+    pub fn purge(&self) -> io::Result<usize> {
+        let now = now_ms();
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let _files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+            let text = fs::read_to_string(&path)?;
+            // Lines we can't make sense of are left alone
+            let kept: Vec<&str> = text
+                .lines()
+                .filter(|line| {
+                    serde_json::from_str::<ArchiveRecord>(line)
+                        .map_or(true, |record| !self.expired(&record, now))
+                })
+                .collect();
+            let dropped = text.lines().count() - kept.len();
+            if dropped == 0 {
+                continue;
+            }
+            purged += dropped;
+            if kept.is_empty() {
+                fs::remove_file(&path)?;
+                continue;
+            }
+            let partial = path.with_extension("jsonl.tmp");
+            fs::write(&partial, kept.join("\n") + "\n")?;
+            fs::rename(&partial, &path)?;
+        }
+        Ok(purged)
+    }
+
+    // ✅ Purge now and every PURGE_INTERVAL, in the background
+    pub fn start_purging(&self) {
+        let archive = self.clone();
+        thread::spawn(move || {
+            loop {
+                match archive.purge() {
+                    Ok(0) => {}
+                    Ok(purged) => info!("🗄️ Purged {} archived records past retention", purged),
+                    Err(err) => info!("⚠️ Cannot purge the archive: {}", err),
+                }
+                thread::sleep(PURGE_INTERVAL);
+            }
+        });
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
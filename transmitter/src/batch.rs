@@ -0,0 +1,135 @@
+// Broadcast batching (--batch-window-ms): for data-heavy streams, whose
+// streamers broadcast many small messages (sensor overlays, positions), the
+// room holds each broadcast that isn't signaling for up to the window and sends
+// whatever came meanwhile to every member as one frame:
+//
+//   {"type":"batch","messages":["{\"x\":1}","{\"x\":2}","..."]}
+//
+// A lone message goes as it is. Signaling is never held, and sends what's held
+// ahead of it, so nothing arrives out of order; see `tuesdays_protocol::signal`.
+
+use std::time::Duration;
+
+use tuesdays_protocol::Signal;
+
+// Most messages held for one frame; a full batch goes without waiting out the window
+pub(crate) const MAX_BATCH: usize = 256;
+
+#[derive(Debug)]
+pub(crate) struct Batcher {
+    window: Duration,
+    // Oldest first
+    held: Vec<String>,
+}
+
+impl Batcher {
+    pub fn new(window: Duration) -> Self {
+        Batcher {
+            window,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    // ✅ Hold a message for the next frame; true if it's the first, and the frame
+    // is yet to be scheduled
+    pub fn hold(&mut self, message: String) -> bool {
+        self.held.push(message);
+        self.held.len() == 1
+    }
+
+    pub fn full(&self) -> bool {
+        self.held.len() >= MAX_BATCH
+    }
+
+    // ✅ The frame for what's held: the message alone if there's one, else a batch
+    pub fn take(&mut self) -> Option<String> {
+        match self.held.len() {
+            0 => None,
+            1 => self.held.pop(),
+            _ => Some(
+                Signal::Batch {
+                    messages: std::mem::take(&mut self.held),
+                }
+                .to_json(),
+            ),
+        }
+    }
+}
+
+// ✅ Whether a broadcast may wait for others: anything but a signal
+pub(crate) fn batches(message: &str) -> bool {
+    serde_json::from_str::<Signal>(message).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(x: usize) -> String {
+        format!(r#"{{"x":{}}}"#, x)
+    }
+
+    #[test]
+    fn a_lone_message_goes_as_it_is() {
+        let mut batcher = Batcher::new(Duration::from_millis(50));
+        assert_eq!(batcher.take(), None);
+        batcher.hold(message(1));
+        assert_eq!(batcher.take(), Some(message(1)));
+        assert_eq!(batcher.take(), None);
+    }
+
+    #[test]
+    fn messages_held_meanwhile_go_as_one_batch_in_order() {
+        let mut batcher = Batcher::new(Duration::from_millis(50));
+        for x in 1..=3 {
+            batcher.hold(message(x));
+        }
+        let frame = batcher.take().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Signal>(&frame).unwrap(),
+            Signal::Batch {
+                messages: vec![message(1), message(2), message(3)],
+            }
+        );
+        assert_eq!(batcher.take(), None);
+    }
+
+    #[test]
+    fn the_first_message_held_schedules_the_frame() {
+        // The room flushes a window after the message that started the batch
+        let mut batcher = Batcher::new(Duration::from_millis(50));
+        assert_eq!(batcher.window(), Duration::from_millis(50));
+        assert!(batcher.hold(message(1)));
+        assert!(!batcher.hold(message(2)));
+        batcher.take();
+        assert!(batcher.hold(message(3)));
+    }
+
+    #[test]
+    fn a_full_batch_goes_without_waiting() {
+        let mut batcher = Batcher::new(Duration::from_secs(60));
+        for x in 1..MAX_BATCH {
+            batcher.hold(message(x));
+            assert!(!batcher.full());
+        }
+        batcher.hold(message(MAX_BATCH));
+        assert!(batcher.full());
+        let Ok(Signal::Batch { messages }) = serde_json::from_str(&batcher.take().unwrap()) else {
+            panic!("a batch");
+        };
+        assert_eq!(messages.len(), MAX_BATCH);
+        assert!(!batcher.full());
+    }
+
+    #[test]
+    fn signals_are_never_held() {
+        assert!(batches(&message(1)));
+        assert!(batches("not json"));
+        let batch = Signal::Batch { messages: vec![] }.to_json();
+        assert!(!batches(&batch));
+    }
+}
@@ -0,0 +1,149 @@
+// In-process room for the broadcast benchmarks (`cargo bench --features bench`).
+//
+// Members are the real `MemberWebSocket` actors, driven through
+// `WebsocketContext::create` instead of an HTTP upgrade: each member's encoded
+// output frames are drained by a task that counts deliveries, so a broadcast is
+// measured from the room receiving it until every member has framed it.
+
+use std::time::{Duration, Instant};
+
+use actix::{Actor, Addr, Arbiter};
+use actix_web::error::PayloadError;
+use actix_web::web::Bytes;
+use actix_web_actors::ws;
+use futures_util::{StreamExt, stream};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tuesdays_protocol::deprecation::DEPRECATED;
+use tuesdays_protocol::{Codec, Encoding};
+
+use crate::drain::Drain;
+use crate::limits::LimitMeter;
+use crate::member::MemberWebSocket;
+use crate::metrics::Metrics;
+use crate::policy::Policy;
+use crate::presence::Presence;
+use crate::quota::{QuotaMeter, Quotas};
+use crate::registry::{EnsureRoom, Registry};
+use crate::room::{BroadcastMessage, GetMembers, Role, RoomActor};
+use crate::sanitize::Sanitizer;
+
+const ROOM_ID: &str = "bench";
+
+pub struct BenchRoom {
+    room: Addr<RoomActor>,
+    members: usize,
+    delivered: UnboundedReceiver<()>,
+}
+
+impl BenchRoom {
+    // ✅ One streamer plus `watchers` watchers; must run inside an actix System
+    pub async fn new(watchers: usize) -> Self {
+        let room = Registry::default()
+            .start()
+            .send(EnsureRoom {
+                room_id: ROOM_ID.to_string(),
+                throttle: None,
+                recommend: false,
+                archive: None,
+                presence: Presence::default(),
+                fanout: None,
+                metrics: Metrics::default(),
+                resume_grace: None,
+                replay_broadcasts: 0,
+                batch_window: None,
+                arbiter: Arbiter::current(),
+            })
+            .await
+            .expect("the registry is running");
+        let (delivered_tx, delivered) = unbounded_channel();
+
+        let mut bench = BenchRoom {
+            room,
+            members: 0,
+            delivered,
+        };
+
+        // The streamer first, so every member joins into the same session
+        bench.join("streamer", Role::Streamer, &delivered_tx);
+        bench.wait_for_members().await;
+        for i in 0..watchers {
+            bench.join(&format!("watcher-{}", i), Role::Watcher, &delivered_tx);
+        }
+        bench.wait_for_members().await;
+
+        // Each member has been sent its join notice and the session announcement
+        bench.wait_for_frames(2 * bench.members).await;
+        bench
+    }
+
+    pub fn members(&self) -> usize {
+        self.members
+    }
+
+    // ✅ Broadcast once and wait until every member has sent it out
+    pub async fn broadcast(&mut self, message: &str) {
+        self.room.do_send(BroadcastMessage {
+            message: message.to_string(),
+        });
+        self.wait_for_frames(self.members).await;
+    }
+
+    fn join(&mut self, member_id: &str, role: Role, delivered: &UnboundedSender<()>) {
+        let member = MemberWebSocket {
+            member_id: member_id.to_string(),
+            room_id: ROOM_ID.to_string(),
+            role,
+            audience_role: None,
+            admin: false,
+            audio_only: false,
+            no_chat: false,
+            user_agent: None,
+            waiting: false,
+            resume: None,
+            last_seq: None,
+            room: self.room.clone(),
+            session_id: None,
+            access: None,
+            capture: None,
+            archive: None,
+            heartbeat: None,
+            last_seen: Instant::now(),
+            timed_out: false,
+            quota: QuotaMeter::new(Quotas::default()),
+            policy: Policy::default(),
+            limits: LimitMeter::default(),
+            deprecations: DEPRECATED,
+            deprecated: Vec::new(),
+            codec: Codec::default(),
+            encoding: Encoding::default(),
+            sanitizer: Sanitizer::default(),
+            drain: Drain::default(),
+            metrics: Metrics::default(),
+            disconnect: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        };
+        // No client frames; the member only ever writes
+        let incoming = stream::pending::<Result<Bytes, PayloadError>>();
+        let mut outgoing = Box::pin(ws::WebsocketContext::create(member, incoming));
+        let delivered = delivered.clone();
+        actix::spawn(async move {
+            while let Some(Ok(_)) = outgoing.next().await {
+                let _ = delivered.send(());
+            }
+        });
+        self.members += 1;
+    }
+
+    async fn wait_for_members(&self) {
+        while self.room.send(GetMembers).await.map_or(0, |m| m.len()) < self.members {
+            actix::clock::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    async fn wait_for_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.delivered.recv().await;
+        }
+    }
+}
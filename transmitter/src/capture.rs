@@ -0,0 +1,261 @@
+// Opt-in signaling capture (--capture-dir) for reproducing failed handshakes:
+// every frame each room member sends and receives, in order, one JSON Lines file
+// per stream session (format in `tuesdays_protocol::capture`):
+//
+//   <dir>/<room>-<session_id>.jsonl     from the streamer registering
+//   <dir>/<room>-<unix ms>.jsonl        whatever came before the first session
+//
+// A new session starts a new file, which begins with a `joined` record for every
+// member still connected, so each file replays on its own. Files hold SDP and
+// ICE candidates, i.e. members' IP addresses; only capture to debug. Tokens and
+// stream keys are blanked out of the recorded requests.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use actix_web::HttpRequest;
+use log::info;
+use tuesdays_protocol::{CaptureEvent, CaptureRecord};
+
+use crate::jwt;
+
+// Shared by every connection; cheap to clone
+#[derive(Clone)]
+pub struct Capture {
+    dir: PathBuf,
+    rooms: Arc<Mutex<HashMap<String, RoomFile>>>,
+}
+
+struct RoomFile {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+    session_id: Option<String>,
+    // Connected members and how they joined, to open the next file with
+    members: HashMap<String, String>,
+}
+
+impl Capture {
+    // ✅ Capture into `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Capture {
+            dir,
+            rooms: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    fn record(&self, room_id: &str, member_id: &str, event: CaptureEvent) {
+        let mut rooms = self.rooms.lock().unwrap_or_else(PoisonError::into_inner);
+        if !rooms.contains_key(room_id) {
+            let started = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            match self.open(room_id, &started.to_string(), None) {
+                Ok(room) => rooms.insert(room_id.to_string(), room),
+                Err(err) => return warn(room_id, &err),
+            };
+        }
+        let Some(room) = rooms.get_mut(room_id) else {
+            return;
+        };
+        match &event {
+            CaptureEvent::Joined { request } => {
+                room.members.insert(member_id.to_string(), request.clone());
+            }
+            CaptureEvent::Left { .. } => {
+                room.members.remove(member_id);
+            }
+            _ => {}
+        }
+        if let Err(err) = room.write(member_id, event) {
+            warn(room_id, &err);
+        }
+    }
+
+    // ✅ A streamer registered: continue in a file of the session's own
+    fn start_session(&self, room_id: &str, session_id: &str) {
+        let mut rooms = self.rooms.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = rooms.get(room_id);
+        if previous.is_some_and(|room| room.session_id.as_deref() == Some(session_id)) {
+            return;
+        }
+        let members = previous
+            .map(|room| room.members.clone())
+            .unwrap_or_default();
+        let mut room = match self.open(room_id, session_id, Some(session_id)) {
+            Ok(room) => room,
+            Err(err) => return warn(room_id, &err),
+        };
+        for (member_id, request) in members {
+            if let Err(err) = room.write(&member_id, CaptureEvent::Joined { request }) {
+                return warn(room_id, &err);
+            }
+        }
+        info!(
+            "🎞️ Capturing signaling of Room '{}' to {}",
+            room_id,
+            room.path.display()
+        );
+        rooms.insert(room_id.to_string(), room);
+    }
+
+    fn open(&self, room_id: &str, suffix: &str, session_id: Option<&str>) -> io::Result<RoomFile> {
+        let path = self
+            .dir
+            .join(format!("{}-{}.jsonl", file_name(room_id), suffix));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(RoomFile {
+            path,
+            file,
+            started: Instant::now(),
+            session_id: session_id.map(String::from),
+            members: HashMap::new(),
+        })
+    }
+}
+
+impl RoomFile {
+    fn write(&mut self, member_id: &str, event: CaptureEvent) -> io::Result<()> {
+        let record = CaptureRecord {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            member_id: member_id.to_string(),
+            event,
+        };
+        let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+        writeln!(self.file, "{}", line)
+    }
+}
+
+// Room ids come from clients; keep them to one path component
+pub(crate) fn file_name(room_id: &str) -> String {
+    room_id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn warn(room_id: &str, err: &io::Error) {
+    info!("⚠️ Cannot capture signaling of Room '{}': {}", room_id, err);
+}
+
+// One member's connection, as seen by the capture
+pub(crate) struct CaptureSession {
+    capture: Capture,
+    room_id: String,
+    member_id: String,
+    request: String,
+    // The first reason we learn of; "connection lost" when there's none
+    close_reason: Option<String>,
+}
+
+impl CaptureSession {
+    pub fn new(capture: Capture, req: &HttpRequest, room_id: &str, member_id: &str) -> Self {
+        CaptureSession {
+            capture,
+            room_id: room_id.to_string(),
+            member_id: member_id.to_string(),
+            // Captures get shared as replay fixtures: keep tokens and keys out
+            request: jwt::redacted(req.uri()),
+            close_reason: None,
+        }
+    }
+
+    fn record(&self, event: CaptureEvent) {
+        self.capture.record(&self.room_id, &self.member_id, event);
+    }
+
+    pub fn joined(&self) {
+        self.record(CaptureEvent::Joined {
+            request: self.request.clone(),
+        });
+    }
+
+    pub fn received(&self, text: &str) {
+        self.record(CaptureEvent::Received {
+            text: text.to_string(),
+        });
+    }
+
+    pub fn sent(&self, text: &str) {
+        self.record(CaptureEvent::Sent {
+            text: text.to_string(),
+        });
+    }
+
+    pub fn session(&self, session_id: &str) {
+        self.capture.start_session(&self.room_id, session_id);
+    }
+
+    pub fn closed(&mut self, reason: impl Into<String>) {
+        self.close_reason.get_or_insert_with(|| reason.into());
+    }
+
+    pub fn left(&self) {
+        self.record(CaptureEvent::Left {
+            reason: self
+                .close_reason
+                .clone()
+                .unwrap_or_else(|| "connection lost".to_string()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    // Everything one member joining with `uri` leaves in a fresh capture directory
+    fn captured(name: &str, uri: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("tuesdays-capture-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let capture = Capture::new(&dir).unwrap();
+        let req = TestRequest::with_uri(uri).to_http_request();
+        let session = CaptureSession::new(capture, &req, "lobby", "m1");
+        session.joined();
+        session.left();
+
+        let mut lines = String::new();
+        for entry in fs::read_dir(&dir).unwrap() {
+            lines.push_str(&fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+        lines
+    }
+
+    #[test]
+    fn tokens_are_not_captured() {
+        let lines = captured("token", "/watcher?room=lobby&token=eyJhbGc.eyJzdWI.c2ln");
+        assert!(
+            lines.contains("/watcher?room=lobby&token=<redacted>"),
+            "{}",
+            lines
+        );
+        assert!(!lines.contains("eyJ"), "{}", lines);
+    }
+
+    #[test]
+    fn stream_keys_are_not_captured() {
+        let lines = captured("key", "/streamer?id=cam&key=sk_live_s3cret");
+        assert!(
+            lines.contains("/streamer?id=cam&key=<redacted>"),
+            "{}",
+            lines
+        );
+        assert!(!lines.contains("s3cret"), "{}", lines);
+    }
+}
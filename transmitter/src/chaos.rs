@@ -0,0 +1,45 @@
+// Fault injection for resilience tests (`--features chaos`, never in production
+// builds): every message the room relays to a member may be dropped, duplicated
+// or held back (which reorders it), and may cost the member its connection,
+// dropped without a close frame like a lost network. Streamers and watchers have
+// to reconnect and renegotiate their way out of it.
+//
+//   cargo run --features chaos -- --chaos-drop 0.05 --chaos-delay-ms 500
+
+use std::time::Duration;
+
+use rand::Rng;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chaos {
+    // Each relayed message is held back up to this long
+    pub max_delay: Duration,
+    // Probabilities (0 to 1) per relayed message
+    pub drop: f64,
+    pub duplicate: f64,
+    pub disconnect: f64,
+}
+
+// What becomes of one relayed message
+pub(crate) enum Fate {
+    Drop,
+    Deliver { delay: Duration, copies: usize },
+    Disconnect,
+}
+
+impl Chaos {
+    // ✅ Roll the dice for one message
+    pub(crate) fn fate(&self) -> Fate {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(self.disconnect) {
+            return Fate::Disconnect;
+        }
+        if rng.gen_bool(self.drop) {
+            return Fate::Drop;
+        }
+        Fate::Deliver {
+            delay: self.max_delay.mul_f64(rng.gen_range(0.0..=1.0)),
+            copies: if rng.gen_bool(self.duplicate) { 2 } else { 1 },
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// A live status console (--tui), for running the server in a terminal rather
+// than behind Grafana: redrawn every REFRESH on stdout, it shows
+//
+//   connections by role, and whether the instance is draining
+//   relays and broadcasts per second, and WebSocket errors
+//   each stream on this instance, with its state and watchers
+//   the last few abnormal disconnects (see `metrics::Disconnect`)
+//
+// The log still goes to stderr, so keep it out of the way:
+//
+//   transmitter --tui 2>transmitter.log
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime};
+
+use tuesdays_protocol::StreamInfo;
+
+use crate::SignalingServer;
+use crate::metrics::Snapshot;
+use crate::registry::ListRooms;
+use crate::room::GetStreamInfo;
+
+const REFRESH: Duration = Duration::from_secs(1);
+// Streams listed; the rest are counted
+const STREAMS_SHOWN: usize = 20;
+
+impl SignalingServer {
+    // ✅ Redraw the console until the process ends
+    pub async fn console(self, listening: String) {
+        let started = Instant::now();
+        let mut previous: Option<(Instant, Snapshot)> = None;
+        let mut interval = actix_web::rt::time::interval(REFRESH);
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            let snapshot = self.metrics.snapshot();
+            let streams = self.streams().await;
+            let screen = draw(
+                &listening,
+                started.elapsed(),
+                self.drain.target(),
+                &snapshot,
+                previous.as_ref().map(|(at, before)| (now - *at, before)),
+                &streams,
+            );
+            let mut stdout = io::stdout().lock();
+            if stdout
+                .write_all(screen.as_bytes())
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                return;
+            }
+            previous = Some((now, snapshot));
+        }
+    }
+
+    async fn streams(&self) -> Vec<StreamInfo> {
+        let mut streams = Vec::new();
+        for room in self.rooms.send(ListRooms).await.unwrap_or_default() {
+            if let Ok(info) = room.send(GetStreamInfo).await {
+                streams.push(info);
+            }
+        }
+        streams.sort_by(|a, b| b.watchers.cmp(&a.watchers).then(a.id.cmp(&b.id)));
+        streams
+    }
+}
+
+fn draw(
+    listening: &str,
+    uptime: Duration,
+    draining_to: Option<String>,
+    now: &Snapshot,
+    before: Option<(Duration, &Snapshot)>,
+    streams: &[StreamInfo],
+) -> String {
+    // Clear the screen and home the cursor
+    let mut screen = String::from("\x1b[2J\x1b[H");
+    let mut line = |text: String| {
+        screen.push_str(&text);
+        screen.push_str("\r\n");
+    };
+
+    line(format!(
+        "📡 Tuesdays transmitter on {} · up {}",
+        listening,
+        clock(uptime)
+    ));
+    if let Some(target) = draining_to {
+        line(format!("🚚 Draining to {}", target));
+    }
+    line(String::new());
+
+    let roles = now
+        .members
+        .iter()
+        .map(|(role, count)| format!("{} {}", count, role))
+        .collect::<Vec<_>>();
+    let total: i64 = now.members.values().sum();
+    line(format!(
+        "🔌 Connections: {}{}",
+        total,
+        if roles.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", roles.join(", "))
+        }
+    ));
+    // Rates need a previous snapshot to take the difference from
+    let rate = |count: fn(&Snapshot) -> u64| match before {
+        Some((elapsed, before)) if !elapsed.is_zero() => format!(
+            "{:.1}/s",
+            count(now).saturating_sub(count(before)) as f64 / elapsed.as_secs_f64()
+        ),
+        _ => "-".to_string(),
+    };
+    line(format!(
+        "📨 Relayed: {} · Broadcasts: {} ({} recipients) · WebSocket errors: {}",
+        rate(|s| s.relayed),
+        rate(|s| s.broadcasts),
+        rate(|s| s.recipients),
+        now.websocket_errors
+    ));
+    line(String::new());
+
+    line(format!("🎥 Streams: {}", streams.len()));
+    for info in streams.iter().take(STREAMS_SHOWN) {
+        line(format!(
+            "   {:<32} {:<10} {:>5} watching",
+            info.id,
+            format!("{:?}", info.state).to_lowercase(),
+            info.watchers
+        ));
+    }
+    if streams.len() > STREAMS_SHOWN {
+        line(format!("   ... and {} more", streams.len() - STREAMS_SHOWN));
+    }
+    line(String::new());
+
+    line("⚠️ Recent errors:".to_string());
+    if now.incidents.is_empty() {
+        line("   none".to_string());
+    }
+    let wall = SystemTime::now();
+    for (at, what) in now.incidents.iter().rev() {
+        let ago = wall.duration_since(*at).unwrap_or_default();
+        line(format!("   {:>8} ago  {}", clock(ago), what));
+    }
+    screen
+}
+
+// 1h02m03s, 2m03s or 3s
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m{:02}s", m, s),
+        (h, m, s) => format!("{}h{:02}m{:02}s", h, m, s),
+    }
+}
@@ -0,0 +1,690 @@
+// WebSocket compression (--deflate streamer,watcher): permessage-deflate
+// (RFC 7692) on the routes it's turned on for, when the client offers it, as
+// browsers and tungstenite with its `deflate` feature do:
+//
+//   Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits
+//
+// actix's WebSocket codec knows nothing of extensions, so compression happens
+// around it, frame by frame: compressed messages from the client are inflated
+// into plain frames before the codec parses them, and the codec's text and
+// binary frames are deflated on their way out (but for those under
+// MIN_DEFLATE_BYTES, which would hardly shrink). The compression context carries
+// over from message to message unless the client asks it not to
+// (`server_no_context_takeover`); a large SDP offer or a busy chat then compresses
+// several times over. A message that inflates past --max-frame-bytes ends the
+// connection like a frame that's too large.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use actix::{Actor, StreamHandler};
+use actix_web::body::{BodySize, BoxBody, MessageBody};
+use actix_web::error::PayloadError;
+use actix_web::http::header::{HeaderValue, SEC_WEBSOCKET_EXTENSIONS};
+use actix_web::web::{self, Bytes, BytesMut};
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use futures_core::Stream;
+
+// Messages shorter than this go uncompressed
+pub(crate) const MIN_DEFLATE_BYTES: usize = 64;
+
+const EXTENSION: &str = "permessage-deflate";
+// Ends every compressed message, and is left off on the wire
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+
+// The terms agreed with the client
+#[derive(Clone, Copy, Debug)]
+struct Deflate {
+    // Start every message we send afresh
+    no_context_takeover: bool,
+}
+
+impl Deflate {
+    fn header(self) -> &'static str {
+        if self.no_context_takeover {
+            "permessage-deflate; server_no_context_takeover"
+        } else {
+            EXTENSION
+        }
+    }
+}
+
+// ✅ The first permessage-deflate offer we can take up, if any: we keep to a full
+// window for what we send, and take whatever window the client picks
+fn negotiate(req: &HttpRequest) -> Option<Deflate> {
+    let offers = req.headers().get_all(SEC_WEBSOCKET_EXTENSIONS);
+    offers
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|offer| {
+            let mut params = offer.split(';').map(str::trim);
+            if params.next() != Some(EXTENSION) {
+                return None;
+            }
+            let mut deflate = Deflate {
+                no_context_takeover: false,
+            };
+            for param in params {
+                match param.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                    None if param == "server_no_context_takeover" => {
+                        deflate.no_context_takeover = true
+                    }
+                    None if param == "client_no_context_takeover" => {}
+                    None if param == "client_max_window_bits" => {}
+                    Some(("client_max_window_bits", _)) => {}
+                    Some(("server_max_window_bits", "15")) => {}
+                    _ => return None,
+                }
+            }
+            Some(deflate)
+        })
+}
+
+// ✅ Start a WebSocket actor, compressing its messages if `enabled` and the
+// client offered to, and agreeing to the first of the client's subprotocols
+// that's in `protocols`
+pub(crate) fn start<A>(
+    actor: A,
+    req: &HttpRequest,
+    stream: web::Payload,
+    frame_size: usize,
+    enabled: bool,
+    protocols: &[&str],
+) -> Result<HttpResponse, actix_web::Error>
+where
+    A: Actor<Context = ws::WebsocketContext<A>>
+        + StreamHandler<Result<ws::Message, ws::ProtocolError>>,
+{
+    let Some(deflate) = negotiate(req).filter(|_| enabled) else {
+        return ws::WsResponseBuilder::new(actor, req, stream)
+            .frame_size(frame_size)
+            .protocols(protocols)
+            .start();
+    };
+    let stream = Inflating::new(stream, frame_size);
+    let mut res = ws::WsResponseBuilder::new(actor, req, stream)
+        .frame_size(frame_size)
+        .protocols(protocols)
+        .start()?;
+    res.headers_mut().insert(
+        SEC_WEBSOCKET_EXTENSIONS,
+        HeaderValue::from_static(deflate.header()),
+    );
+    Ok(res.map_body(|_, body| BoxBody::new(Deflating::new(body, deflate))))
+}
+
+// A frame's header, as far as we need it
+struct Header {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    // Of the header itself
+    size: usize,
+    // Of the payload
+    len: usize,
+}
+
+// ✅ The header at the start of `buf`, once it's all there
+fn header(buf: &[u8]) -> Option<Header> {
+    let (first, second) = (*buf.first()?, *buf.get(1)?);
+    let (len, mut size) = match second & 0x7f {
+        126 => (
+            u16::from_be_bytes(buf.get(2..4)?.try_into().ok()?) as usize,
+            4,
+        ),
+        127 => (
+            u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?) as usize,
+            10,
+        ),
+        len => (len as usize, 2),
+    };
+    let mask = if second & 0x80 != 0 {
+        let mask = buf.get(size..size + 4)?.try_into().ok()?;
+        size += 4;
+        Some(mask)
+    } else {
+        None
+    };
+    Some(Header {
+        fin: first & FIN != 0,
+        rsv1: first & RSV1 != 0,
+        opcode: first & 0x0f,
+        mask,
+        size,
+        len,
+    })
+}
+
+fn write_frame(out: &mut BytesMut, first: u8, mask: Option<[u8; 4]>, payload: &[u8]) {
+    out.extend_from_slice(&[first]);
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => out.extend_from_slice(&[masked | len as u8]),
+        len @ 126..=0xffff => {
+            out.extend_from_slice(&[masked | 126]);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.extend_from_slice(&[masked | 127]);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    if let Some(mask) = mask {
+        out.extend_from_slice(&mask);
+    }
+    out.extend_from_slice(payload);
+}
+
+// What the client sends, with compressed messages inflated into plain frames.
+// Those are masked with an all-zero key, as the codec wants clients' frames masked
+struct Inflating<S> {
+    inner: S,
+    inflater: Decompress,
+    // Most a message may inflate to
+    limit: usize,
+    // Received, not yet a whole frame
+    buf: BytesMut,
+    // Ready for the codec
+    out: BytesMut,
+    // A compressed message in several frames: its opcode, and payload so far
+    partial: Option<(u8, Vec<u8>)>,
+    // Past a frame too large to hold: the rest goes through untouched, for the
+    // codec to refuse
+    passthrough: bool,
+}
+
+impl<S> Inflating<S> {
+    fn new(inner: S, limit: usize) -> Self {
+        Inflating {
+            inner,
+            inflater: Decompress::new(false),
+            limit,
+            buf: BytesMut::new(),
+            out: BytesMut::new(),
+            partial: None,
+            passthrough: false,
+        }
+    }
+
+    // ✅ Move every whole frame received on to `out`, inflating as need be
+    fn frames(&mut self) -> Result<(), PayloadError> {
+        while let Some(header) = header(&self.buf) {
+            if header.len > self.limit {
+                self.passthrough = true;
+                self.out.extend_from_slice(&self.buf.split());
+                return Ok(());
+            }
+            if self.buf.len() < header.size + header.len {
+                return Ok(());
+            }
+            let frame = self.buf.split_to(header.size + header.len);
+            let mut payload = frame[header.size..].to_vec();
+            if let Some(mask) = header.mask {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            match header.opcode {
+                TEXT | BINARY if header.rsv1 => self.partial = Some((header.opcode, payload)),
+                CONTINUATION if self.partial.is_some() => {
+                    if let Some((_, message)) = &mut self.partial {
+                        message.extend_from_slice(&payload);
+                        if message.len() > self.limit {
+                            return Err(PayloadError::Overflow);
+                        }
+                    }
+                }
+                _ => {
+                    self.out.extend_from_slice(&frame);
+                    continue;
+                }
+            }
+            if header.fin
+                && let Some((opcode, message)) = self.partial.take()
+            {
+                let message = self.inflate(&message)?;
+                write_frame(&mut self.out, FIN | opcode, Some([0; 4]), &message);
+            }
+        }
+        Ok(())
+    }
+
+    fn inflate(&mut self, message: &[u8]) -> Result<Vec<u8>, PayloadError> {
+        let input = [message, &TAIL].concat();
+        let mut out = Vec::with_capacity(input.len() * 4);
+        let mut consumed = 0;
+        loop {
+            let before = self.inflater.total_in();
+            self.inflater
+                .decompress_vec(&input[consumed..], &mut out, FlushDecompress::Sync)
+                .map_err(|_| PayloadError::EncodingCorrupted)?;
+            consumed += (self.inflater.total_in() - before) as usize;
+            if out.len() > self.limit {
+                return Err(PayloadError::Overflow);
+            }
+            if consumed == input.len() && out.len() < out.capacity() {
+                return Ok(out);
+            }
+            out.reserve(out.capacity());
+        }
+    }
+}
+
+impl<S> Stream for Inflating<S>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if !self.out.is_empty() {
+                return Poll::Ready(Some(Ok(self.out.split().freeze())));
+            }
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) if self.passthrough => {
+                    return Poll::Ready(Some(Ok(bytes)));
+                }
+                Poll::Ready(Some(Ok(bytes))) => {
+                    self.buf.extend_from_slice(&bytes);
+                    if let Err(err) = self.frames() {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                // Whatever's left is a frame cut short, for the codec to make of it
+                Poll::Ready(None) if !self.buf.is_empty() => {
+                    let rest = self.buf.split();
+                    self.out.extend_from_slice(&rest);
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// What the codec sends, with text and binary messages deflated
+struct Deflating {
+    inner: BoxBody,
+    deflater: Compress,
+    deflate: Deflate,
+    buf: BytesMut,
+    out: BytesMut,
+    // Within a message sent in several frames, which goes as it is
+    continuing: bool,
+}
+
+impl Deflating {
+    fn new(inner: BoxBody, deflate: Deflate) -> Self {
+        Deflating {
+            inner,
+            deflater: Compress::new(Compression::default(), false),
+            deflate,
+            buf: BytesMut::new(),
+            out: BytesMut::new(),
+            continuing: false,
+        }
+    }
+
+    // ✅ Move every whole frame on to `out`, deflating single-frame messages
+    fn frames(&mut self) {
+        while let Some(header) = header(&self.buf) {
+            if self.buf.len() < header.size + header.len {
+                return;
+            }
+            let frame = self.buf.split_to(header.size + header.len);
+            let data = matches!(header.opcode, TEXT | BINARY);
+            if data && header.fin && !self.continuing && header.len >= MIN_DEFLATE_BYTES {
+                let message = self.deflate(&frame[header.size..]);
+                write_frame(&mut self.out, FIN | RSV1 | header.opcode, None, &message);
+                continue;
+            }
+            if data || header.opcode == CONTINUATION {
+                self.continuing = !header.fin;
+            }
+            self.out.extend_from_slice(&frame);
+        }
+    }
+
+    fn deflate(&mut self, message: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(message.len() / 2 + 64);
+        let mut consumed = 0;
+        loop {
+            let before = self.deflater.total_in();
+            self.deflater
+                .compress_vec(&message[consumed..], &mut out, FlushCompress::Sync)
+                .expect("deflating into a growing buffer can't fail");
+            consumed += (self.deflater.total_in() - before) as usize;
+            if consumed == message.len() && out.len() < out.capacity() {
+                break;
+            }
+            out.reserve(out.capacity());
+        }
+        if out.ends_with(&TAIL) {
+            out.truncate(out.len() - TAIL.len());
+        }
+        if self.deflate.no_context_takeover {
+            self.deflater.reset();
+        }
+        out
+    }
+}
+
+impl MessageBody for Deflating {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        loop {
+            if !self.out.is_empty() {
+                return Poll::Ready(Some(Ok(self.out.split().freeze())));
+            }
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => {
+                    self.buf.extend_from_slice(&bytes);
+                    self.frames();
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) if !self.buf.is_empty() => {
+                    let rest = self.buf.split();
+                    self.out.extend_from_slice(&rest);
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const PING: u8 = 0x9;
+    const MASK: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    fn deflating(no_context_takeover: bool) -> Deflating {
+        Deflating::new(
+            BoxBody::new(()),
+            Deflate {
+                no_context_takeover,
+            },
+        )
+    }
+
+    fn inflating(limit: usize) -> Inflating<()> {
+        Inflating::new((), limit)
+    }
+
+    // A frame as a client sends it, masked
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let masked: Vec<u8> = payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ MASK[i % 4])
+            .collect();
+        let mut out = BytesMut::new();
+        write_frame(&mut out, first, Some(MASK), &masked);
+        out.to_vec()
+    }
+
+    // Each frame's first byte and unmasked payload
+    fn frames(mut bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut frames = Vec::new();
+        while let Some(header) = header(bytes) {
+            let mut payload = bytes[header.size..header.size + header.len].to_vec();
+            if let Some(mask) = header.mask {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            frames.push((bytes[0], payload));
+            bytes = &bytes[header.size + header.len..];
+        }
+        assert!(bytes.is_empty(), "a frame cut short");
+        frames
+    }
+
+    fn receive(
+        inflating: &mut Inflating<()>,
+        bytes: &[u8],
+    ) -> Result<Vec<(u8, Vec<u8>)>, PayloadError> {
+        inflating.buf.extend_from_slice(bytes);
+        inflating.frames()?;
+        Ok(frames(&inflating.out.split()))
+    }
+
+    fn send(deflating: &mut Deflating, first: u8, payload: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        let mut frame = BytesMut::new();
+        write_frame(&mut frame, first, None, payload);
+        deflating.buf.extend_from_slice(&frame);
+        deflating.frames();
+        frames(&deflating.out.split())
+    }
+
+    fn message(len: usize) -> Vec<u8> {
+        r#"{"type":"candidate","candidate":"candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host"}"#
+            .bytes()
+            .cycle()
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn compressed_message_is_inflated() {
+        let text = message(500);
+        let compressed = deflating(false).deflate(&text);
+        assert!(compressed.len() < text.len());
+
+        let mut inflating = inflating(1 << 20);
+        let received = receive(
+            &mut inflating,
+            &client_frame(FIN | RSV1 | TEXT, &compressed),
+        );
+        assert_eq!(received.unwrap(), vec![(FIN | TEXT, text)]);
+
+        // Uncompressed frames go through as they are
+        let plain = client_frame(FIN | TEXT, b"hi");
+        assert_eq!(
+            receive(&mut inflating, &plain).unwrap(),
+            vec![(FIN | TEXT, b"hi".to_vec())]
+        );
+    }
+
+    #[test]
+    fn fragmented_message_is_inflated_whole() {
+        let text = message(2000);
+        let compressed = deflating(false).deflate(&text);
+        let third = compressed.len() / 3;
+        let wire = [
+            client_frame(RSV1 | BINARY, &compressed[..third]),
+            client_frame(CONTINUATION, &compressed[third..2 * third]),
+            client_frame(FIN | CONTINUATION, &compressed[2 * third..]),
+        ]
+        .concat();
+
+        // However the bytes arrive
+        let mut inflating = inflating(1 << 20);
+        let mut received = Vec::new();
+        for chunk in wire.chunks(7) {
+            received.extend(receive(&mut inflating, chunk).unwrap());
+        }
+        assert_eq!(received, vec![(FIN | BINARY, text)]);
+    }
+
+    #[test]
+    fn control_frames_pass_within_a_fragmented_message() {
+        let text = message(300);
+        let compressed = deflating(false).deflate(&text);
+        let half = compressed.len() / 2;
+        let wire = [
+            client_frame(RSV1 | TEXT, &compressed[..half]),
+            client_frame(FIN | PING, b"are you there"),
+            client_frame(FIN | CONTINUATION, &compressed[half..]),
+        ]
+        .concat();
+
+        let received = receive(&mut inflating(1 << 20), &wire).unwrap();
+        assert_eq!(
+            received,
+            vec![(FIN | PING, b"are you there".to_vec()), (FIN | TEXT, text)]
+        );
+    }
+
+    #[test]
+    fn inflating_past_the_limit_overflows() {
+        // Deflates to a few dozen bytes
+        let bomb = vec![b'a'; 100_000];
+        let compressed = deflating(false).deflate(&bomb);
+        let wire = client_frame(FIN | RSV1 | TEXT, &compressed);
+        assert!(matches!(
+            receive(&mut inflating(10_000), &wire),
+            Err(PayloadError::Overflow)
+        ));
+
+        // Nor may its compressed fragments add up past it
+        let wire = [
+            client_frame(RSV1 | TEXT, &[0; 600]),
+            client_frame(CONTINUATION, &[0; 600]),
+        ]
+        .concat();
+        assert!(matches!(
+            receive(&mut inflating(1000), &wire),
+            Err(PayloadError::Overflow)
+        ));
+
+        // A frame too large to hold goes on untouched, for the codec to refuse
+        let mut inflating = inflating(100);
+        let wire = client_frame(FIN | RSV1 | TEXT, &[0; 200]);
+        inflating.buf.extend_from_slice(&wire);
+        inflating.frames().unwrap();
+        assert!(inflating.passthrough);
+        assert_eq!(inflating.out.split().to_vec(), wire);
+    }
+
+    #[test]
+    fn sent_messages_round_trip() {
+        let mut deflating = deflating(false);
+        let mut client = inflating(1 << 20);
+        for _ in 0..3 {
+            let text = message(1000);
+            let sent = send(&mut deflating, FIN | TEXT, &text);
+            assert_eq!(sent.len(), 1);
+            let (first, compressed) = &sent[0];
+            assert_eq!(*first, FIN | RSV1 | TEXT);
+            assert!(compressed.len() < text.len() / 4);
+
+            let mut wire = BytesMut::new();
+            write_frame(&mut wire, *first, None, compressed);
+            assert_eq!(
+                receive(&mut client, &wire).unwrap(),
+                vec![(FIN | TEXT, text)]
+            );
+        }
+
+        // Short messages, control frames and fragments go as they are
+        assert_eq!(
+            send(&mut deflating, FIN | TEXT, b"ok"),
+            vec![(FIN | TEXT, b"ok".to_vec())]
+        );
+        assert_eq!(
+            send(&mut deflating, FIN | PING, b""),
+            vec![(FIN | PING, Vec::new())]
+        );
+        let part = message(200);
+        assert_eq!(
+            send(&mut deflating, TEXT, &part),
+            vec![(TEXT, part.clone())]
+        );
+        assert_eq!(
+            send(&mut deflating, FIN | CONTINUATION, &part),
+            vec![(FIN | CONTINUATION, part)]
+        );
+    }
+
+    #[test]
+    fn context_is_taken_over_unless_the_client_asks_not_to() {
+        let text = message(1000);
+
+        let mut takeover = deflating(false);
+        let first = takeover.deflate(&text);
+        let second = takeover.deflate(&text);
+        // The second refers back to the first
+        assert!(second.len() < first.len());
+        // ...so it inflates after the first, with the same context
+        let mut client = inflating(1 << 20);
+        for compressed in [first, second] {
+            let received = receive(&mut client, &client_frame(FIN | RSV1 | TEXT, &compressed));
+            assert_eq!(received.unwrap(), vec![(FIN | TEXT, text.clone())]);
+        }
+
+        let mut no_takeover = deflating(true);
+        let first = no_takeover.deflate(&text);
+        let second = no_takeover.deflate(&text);
+        assert_eq!(first, second);
+        // Each inflates on its own
+        let received = receive(
+            &mut inflating(1 << 20),
+            &client_frame(FIN | RSV1 | TEXT, &second),
+        );
+        assert_eq!(received.unwrap(), vec![(FIN | TEXT, text)]);
+    }
+
+    #[test]
+    fn extension_offers_are_negotiated() {
+        let negotiated = |offers: &[&str]| {
+            let mut req = TestRequest::default();
+            for offer in offers {
+                req = req.append_header((SEC_WEBSOCKET_EXTENSIONS, *offer));
+            }
+            negotiate(&req.to_http_request()).map(|deflate| deflate.header())
+        };
+        assert_eq!(negotiated(&[]), None);
+        assert_eq!(negotiated(&["x-webkit-deflate-frame"]), None);
+        assert_eq!(
+            negotiated(&["permessage-deflate; client_max_window_bits"]),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiated(&[
+                "permessage-deflate; client_max_window_bits=10; client_no_context_takeover"
+            ]),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiated(&["permessage-deflate; server_no_context_takeover"]),
+            Some("permessage-deflate; server_no_context_takeover")
+        );
+        // A smaller window of ours isn't something we do; the next offer is
+        assert_eq!(
+            negotiated(&["permessage-deflate; server_max_window_bits=10"]),
+            None
+        );
+        assert_eq!(
+            negotiated(&["permessage-deflate; server_max_window_bits=10, permessage-deflate"]),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiated(&["x-unknown", "permessage-deflate; server_max_window_bits=15"]),
+            Some("permessage-deflate")
+        );
+        assert_eq!(negotiated(&["permessage-deflate; mystery"]), None);
+    }
+}
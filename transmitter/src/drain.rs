@@ -0,0 +1,79 @@
+// Draining for blue/green deploys (--admin-api):
+//
+//   POST /admin/drain {"migrate_to":"ws://green:8080"}   start draining
+//   GET  /admin/drain                                    `DrainStatus`, poll until `empty`
+//
+// Once draining, sessions already here carry on undisturbed, but every room is
+// told to `migrate` (see `tuesdays_protocol::signal`) and new connections are
+// turned away with a 307 to the same path and query on `migrate_to`. The log
+// says so when the last member leaves; then the instance can be stopped.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use log::info;
+use tuesdays_protocol::DrainStatus;
+
+#[derive(Clone, Default)]
+pub(crate) struct Drain {
+    // Where to send everyone; None until draining
+    target: Arc<Mutex<Option<String>>>,
+    // Members connected to rooms
+    connections: Arc<AtomicUsize>,
+}
+
+impl Drain {
+    pub fn start(&self, migrate_to: String) {
+        info!("🚚 Draining: new connections go to {}", migrate_to);
+        *self.target.lock().unwrap_or_else(PoisonError::into_inner) = Some(migrate_to);
+        self.report_empty();
+    }
+
+    pub fn target(&self) -> Option<String> {
+        self.target
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    // ✅ Where a new connection to `path_and_query` goes instead, if draining
+    pub fn redirect(&self, path_and_query: &str) -> Option<String> {
+        let target = self.target()?;
+        Some(format!(
+            "{}{}",
+            target.trim_end_matches('/'),
+            path_and_query
+        ))
+    }
+
+    pub fn joined(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn left(&self) {
+        if self.connections.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.report_empty();
+        }
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> DrainStatus {
+        let migrate_to = self.target();
+        let connections = self.connections.load(Ordering::Relaxed);
+        DrainStatus {
+            draining: migrate_to.is_some(),
+            empty: migrate_to.is_some() && connections == 0,
+            migrate_to,
+            connections,
+        }
+    }
+
+    fn report_empty(&self) {
+        if self.status().empty {
+            info!("🏁 Drained: no connections left, safe to shut down");
+        }
+    }
+}
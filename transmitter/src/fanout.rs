@@ -0,0 +1,133 @@
+// Room fan-out between instances (--fanout, over the --presence-redis Redis).
+// Without it, a stream is watched on the instance its streamer is connected to,
+// and watchers landing anywhere else are redirected there. With it, they stay:
+// every instance keeps its own room for the stream, and the rooms pass on to each
+// other what crosses them, over Redis pub/sub on `tuesdays:room:<id>`:
+//
+//   - broadcasts (the streamer's offers, chat, who left, the stream's state...),
+//     delivered to every member of every instance's room
+//   - routed offers, answers and candidates for a member not in the room here,
+//     delivered by whichever instance has it
+//
+// So a watcher on one instance answers a streamer on another as if they shared
+// a room. Every instance hears every room's traffic and drops what it has no
+// room for. A message published while Redis is unreachable is lost, like one to
+// a member that just disconnected; members renegotiate as they would then.
+
+use std::sync::Arc;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use actix::{Addr, Message};
+use log::info;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::redis::Redis;
+use crate::registry::Registry;
+
+const CHANNEL_PREFIX: &str = "tuesdays:room:";
+// Before subscribing again after losing the connection
+const RESUBSCRIBE_AFTER: Duration = Duration::from_secs(2);
+
+// What one instance's room passes on to the others'; already tagged with its
+// session, if it has one
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum Crossing {
+    Broadcast { message: String },
+    Relay { to: String, message: String },
+}
+
+// A crossing as published: which instance it's from, so it doesn't come back
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    instance: String,
+    #[serde(flatten)]
+    crossing: Crossing,
+}
+
+// Arrived from another instance, for the room here with that id, if any
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Arrived {
+    pub room_id: String,
+    pub crossing: Crossing,
+}
+
+// Shared by every room; cheap to clone. Published from a thread of its own, so
+// a slow Redis never holds a room up
+#[derive(Clone)]
+pub struct Fanout {
+    redis: Arc<Redis>,
+    instance: String,
+    outgoing: Sender<(String, String)>,
+}
+
+impl Fanout {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let redis = Arc::new(Redis::new(url)?);
+        let (outgoing, pending) = mpsc::channel::<(String, String)>();
+        let publisher = redis.clone();
+        thread::spawn(move || {
+            for (channel, envelope) in pending {
+                if let Err(err) = publisher.publish(&channel, &envelope) {
+                    info!("⚠️ Cannot fan out to {}: {}", channel, err);
+                }
+            }
+        });
+        Ok(Fanout {
+            redis,
+            instance: Uuid::new_v4().to_string(),
+            outgoing,
+        })
+    }
+
+    pub fn addr(&self) -> &str {
+        self.redis.addr()
+    }
+
+    // ✅ Pass a room's broadcast or relay on to the other instances
+    pub(crate) fn publish(&self, room_id: &str, crossing: Crossing) {
+        let envelope = Envelope {
+            instance: self.instance.clone(),
+            crossing,
+        };
+        if let Ok(envelope) = serde_json::to_string(&envelope) {
+            let channel = format!("{}{}", CHANNEL_PREFIX, room_id);
+            let _ = self.outgoing.send((channel, envelope));
+        }
+    }
+
+    // ✅ Hand what other instances publish to the registry, for the room it's
+    // for; subscribes again whenever the connection is lost
+    pub(crate) fn listen(&self, registry: Addr<Registry>) {
+        let redis = self.redis.clone();
+        let instance = self.instance.clone();
+        let pattern = format!("{}*", CHANNEL_PREFIX);
+        thread::spawn(move || {
+            loop {
+                let listened = redis.psubscribe(&pattern, |channel, payload| {
+                    let Some(room_id) = channel.strip_prefix(CHANNEL_PREFIX) else {
+                        return;
+                    };
+                    match serde_json::from_str::<Envelope>(&payload) {
+                        Ok(envelope) if envelope.instance != instance => {
+                            registry.do_send(Arrived {
+                                room_id: room_id.to_string(),
+                                crossing: envelope.crossing,
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(err) => info!("⚠️ Ignoring fan-out on {}: {}", channel, err),
+                    }
+                });
+                if let Err(err) = listened {
+                    info!("⚠️ Fan-out subscription lost: {}", err);
+                }
+                thread::sleep(RESUBSCRIBE_AFTER);
+            }
+        });
+    }
+}
@@ -0,0 +1,48 @@
+// Heartbeat: every connection, member or agent, is pinged every `interval`, and
+// any frame from it (its pong, or anything else) shows it's alive. One that stays
+// silent for `timeout`, like a peer whose network vanished without a FIN, is
+// stopped: it leaves its room (which tells the others it `left`, timed out) or
+// the agent registry, as if it had closed.
+//
+//   transmitter --heartbeat-interval-secs 10 --heartbeat-timeout-secs 30
+
+use std::time::{Duration, Instant};
+
+use actix::{Actor, ActorContext, AsyncContext};
+use actix_web_actors::ws;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Heartbeat {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+// A WebSocket actor that keeps track of when it last heard from its client
+pub(crate) trait Heartbeating: Actor<Context = ws::WebsocketContext<Self>> {
+    fn last_seen(&self) -> Instant;
+
+    // About to be stopped for its silence
+    fn timed_out(&mut self);
+}
+
+// ✅ Ping the client every interval, and stop the actor once it's been silent
+// for the timeout
+pub(crate) fn start<A: Heartbeating>(heartbeat: Heartbeat, ctx: &mut ws::WebsocketContext<A>) {
+    ctx.run_interval(heartbeat.interval, move |act, ctx| {
+        if act.last_seen().elapsed() >= heartbeat.timeout {
+            act.timed_out();
+            ctx.stop();
+            return;
+        }
+        ctx.ping(b"");
+    });
+}
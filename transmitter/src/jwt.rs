@@ -0,0 +1,441 @@
+// JWT authentication (--jwt-secret or --jwt-public-key): every connection shows a
+// token, as `?token=...` or `Authorization: Bearer ...`, whose claims say what it
+// may join:
+//
+//   {"role":"watcher","stream_id":"cam1","sub":"w1","exp":1767225600}
+//
+// `role` is streamer, watcher, member (/room) or agent, `stream_id` the room (an
+// agent's own id), `sub`, if there is one, the member id it must connect as. A
+// watcher's `audience_role`, viewer if there's none, is the most its `?role=` may
+// ask for (see `tuesdays_protocol::roles`). HS256
+// tokens are checked with the shared secret, RS256 ones with the issuer's RSA
+// public key; a token signed any other way is refused. Without a valid token the
+// upgrade is answered 401 with `{"error":{"code":"unauthorized",...}}`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::HttpRequest;
+use actix_web::http::{Uri, header};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::{hmac, signature};
+use serde::Deserialize;
+use tuesdays_protocol::AudienceRole;
+
+use crate::Join;
+use crate::room::Role;
+
+// Clock skew tolerated on `exp` and `nbf`, in seconds
+const LEEWAY: u64 = 30;
+// OID 1.2.840.113549.1.1.1, rsaEncryption, as DER
+const RSA_ENCRYPTION: &[u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+];
+
+#[derive(Clone)]
+enum Key {
+    Hs256(hmac::Key),
+    // PKCS#1 RSAPublicKey, DER
+    Rs256(Vec<u8>),
+}
+
+// Cheap to clone into every worker
+#[derive(Clone)]
+pub struct JwtAuth {
+    key: Key,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    role: Role,
+    stream_id: String,
+    #[serde(default)]
+    sub: Option<String>,
+    #[serde(default)]
+    audience_role: AudienceRole,
+    exp: u64,
+    #[serde(default)]
+    nbf: Option<u64>,
+}
+
+impl JwtAuth {
+    // ✅ HS256 tokens signed with `secret`
+    pub fn hmac(secret: &[u8]) -> Self {
+        JwtAuth {
+            key: Key::Hs256(hmac::Key::new(hmac::HMAC_SHA256, secret)),
+        }
+    }
+
+    // ✅ RS256 tokens, checked with a PEM public key ("PUBLIC KEY" or "RSA PUBLIC KEY")
+    pub fn rsa_pem(pem: &str) -> Result<Self, String> {
+        let pem = pem::parse(pem).map_err(|err| format!("not a PEM key: {}", err))?;
+        let der = match pem.tag() {
+            "RSA PUBLIC KEY" => pem.contents().to_vec(),
+            "PUBLIC KEY" => spki_rsa_key(pem.contents())
+                .ok_or("not an RSA public key")?
+                .to_vec(),
+            tag => return Err(format!("expected a PUBLIC KEY, not {}", tag)),
+        };
+        Ok(JwtAuth {
+            key: Key::Rs256(der),
+        })
+    }
+
+    // ✅ The request's token must be valid and allow exactly this join
+    pub(crate) fn verify(&self, req: &HttpRequest, join: &Join) -> Result<(), String> {
+        let token = token(req).ok_or("no token")?;
+        let claims = self.claims(&token)?;
+        if claims.role != join.role {
+            return Err(format!("token is for a {}", claims.role.name()));
+        }
+        if claims.stream_id != join.room_id {
+            return Err(format!("token is for '{}'", claims.stream_id));
+        }
+        if claims.sub.as_ref().is_some_and(|sub| sub != join.member_id) {
+            return Err("token is for another member id".to_string());
+        }
+        if let Some(role) = join
+            .audience_role
+            .filter(|role| *role > claims.audience_role)
+        {
+            return Err(format!("token doesn't make it a {}", role.name()));
+        }
+        Ok(())
+    }
+
+    fn claims(&self, token: &str) -> Result<Claims, String> {
+        let Some((signed, signature)) = token.rsplit_once('.') else {
+            return Err("malformed token".to_string());
+        };
+        let Some((header, payload)) = signed.split_once('.') else {
+            return Err("malformed token".to_string());
+        };
+        let header: Header = decode(header).ok_or("malformed token header")?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| "malformed token signature")?;
+        let valid = match (&self.key, header.alg.as_str()) {
+            (Key::Hs256(key), "HS256") => hmac::verify(key, signed.as_bytes(), &signature).is_ok(),
+            (Key::Rs256(der), "RS256") => {
+                signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, der)
+                    .verify(signed.as_bytes(), &signature)
+                    .is_ok()
+            }
+            (_, alg) => return Err(format!("token signed with {}", alg)),
+        };
+        if !valid {
+            return Err("bad token signature".to_string());
+        }
+
+        let claims: Claims = decode(payload).ok_or("malformed token claims")?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs());
+        if claims.exp.saturating_add(LEEWAY) <= now {
+            return Err("token expired".to_string());
+        }
+        if claims
+            .nbf
+            .is_some_and(|nbf| nbf > now.saturating_add(LEEWAY))
+        {
+            return Err("token not valid yet".to_string());
+        }
+        Ok(claims)
+    }
+}
+
+// `token` from the query string, or the bearer token
+fn token(req: &HttpRequest) -> Option<String> {
+    if let Some(token) = crate::query_params(req).remove("token") {
+        return Some(token);
+    }
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+// ✅ The URI with any token (or stream key) blanked out, for logs
+pub(crate) fn redacted(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query: Vec<&str> = query
+        .split('&')
+        .map(|pair| {
+            if pair.starts_with("token=") {
+                "token=<redacted>"
+            } else if pair.starts_with("key=") {
+                "key=<redacted>"
+            } else {
+                pair
+            }
+        })
+        .collect();
+    format!("{}?{}", uri.path(), query.join("&"))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(part: &str) -> Option<T> {
+    let json = URL_SAFE_NO_PAD.decode(part).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+// The RSAPublicKey inside a SubjectPublicKeyInfo
+fn spki_rsa_key(der: &[u8]) -> Option<&[u8]> {
+    let (0x30, spki, _) = der_element(der)? else {
+        return None;
+    };
+    let (0x30, algorithm, rest) = der_element(spki)? else {
+        return None;
+    };
+    if !algorithm.starts_with(RSA_ENCRYPTION) {
+        return None;
+    }
+    let (0x03, bits, _) = der_element(rest)? else {
+        return None;
+    };
+    // No unused bits
+    bits.strip_prefix(&[0])
+}
+
+// One DER element: its tag, contents, and what follows it
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n]
+            .iter()
+            .fold(0, |len, byte| (len << 8) | *byte as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use ring::rand::SystemRandom;
+    use serde_json::{Value, json};
+
+    const SECRET: &[u8] = b"test secret";
+    // A throwaway 2048-bit key pair, made with `openssl genpkey -algorithm RSA`
+    const RSA_PRIVATE: &str = include_str!("../testdata/jwt-rs256.key");
+    const RSA_SPKI: &str = include_str!("../testdata/jwt-rs256.pub");
+    const RSA_PKCS1: &str = include_str!("../testdata/jwt-rs256-pkcs1.pub");
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn claims() -> Value {
+        json!({"role": "watcher", "stream_id": "cam1", "sub": "w1", "exp": now() + 3600})
+    }
+
+    fn encode(value: &Value) -> String {
+        URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    fn hs256(claims: &Value) -> String {
+        let signed = format!("{}.{}", encode(&json!({"alg": "HS256"})), encode(claims));
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET);
+        let signature = hmac::sign(&key, signed.as_bytes());
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn rs256(claims: &Value) -> String {
+        let signed = format!("{}.{}", encode(&json!({"alg": "RS256"})), encode(claims));
+        let der = pem::parse(RSA_PRIVATE).unwrap();
+        let key = signature::RsaKeyPair::from_pkcs8(der.contents()).unwrap();
+        let mut signature = vec![0; key.public().modulus_len()];
+        key.sign(
+            &signature::RSA_PKCS1_SHA256,
+            &SystemRandom::new(),
+            signed.as_bytes(),
+            &mut signature,
+        )
+        .unwrap();
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    fn join(role: Role, member_id: &str, audience_role: Option<AudienceRole>) -> Join<'_> {
+        Join {
+            role,
+            room_id: "cam1",
+            member_id,
+            audience_role,
+        }
+    }
+
+    fn verify(auth: &JwtAuth, token: &str, join: &Join) -> Result<(), String> {
+        let req = TestRequest::with_uri(&format!("/watcher?token={}", token)).to_http_request();
+        auth.verify(&req, join)
+    }
+
+    #[test]
+    fn hs256_token_is_accepted() {
+        let auth = JwtAuth::hmac(SECRET);
+        let watcher = join(Role::Watcher, "w1", None);
+        assert_eq!(verify(&auth, &hs256(&claims()), &watcher), Ok(()));
+
+        let req = TestRequest::with_uri("/watcher")
+            .insert_header((
+                header::AUTHORIZATION,
+                format!("Bearer {}", hs256(&claims())),
+            ))
+            .to_http_request();
+        assert_eq!(auth.verify(&req, &watcher), Ok(()));
+    }
+
+    #[test]
+    fn rs256_token_is_accepted_with_either_public_key_format() {
+        let watcher = join(Role::Watcher, "w1", None);
+        for pem in [RSA_SPKI, RSA_PKCS1] {
+            let auth = JwtAuth::rsa_pem(pem).unwrap();
+            assert_eq!(verify(&auth, &rs256(&claims()), &watcher), Ok(()));
+        }
+        assert!(JwtAuth::rsa_pem(RSA_PRIVATE).is_err());
+    }
+
+    #[test]
+    fn unsigned_and_unknown_algorithms_are_refused() {
+        let auth = JwtAuth::hmac(SECRET);
+        let watcher = join(Role::Watcher, "w1", None);
+        for alg in ["none", "HS512", "RS256"] {
+            let token = format!("{}.{}.", encode(&json!({"alg": alg})), encode(&claims()));
+            assert_eq!(
+                verify(&auth, &token, &watcher),
+                Err(format!("token signed with {}", alg))
+            );
+        }
+        // An HS256 token "signed" with the RSA public key as its secret
+        let auth = JwtAuth::rsa_pem(RSA_SPKI).unwrap();
+        assert!(verify(&auth, &hs256(&claims()), &watcher).is_err());
+    }
+
+    #[test]
+    fn tampered_token_is_refused() {
+        let auth = JwtAuth::hmac(SECRET);
+        let watcher = join(Role::Watcher, "w1", None);
+        let token = hs256(&claims());
+        let (signed, signature) = token.rsplit_once('.').unwrap();
+        let (header, _) = signed.split_once('.').unwrap();
+        let mut moderator = claims();
+        moderator["audience_role"] = json!("moderator");
+        let forged = format!("{}.{}.{}", header, encode(&moderator), signature);
+        assert_eq!(
+            verify(&auth, &forged, &watcher),
+            Err("bad token signature".to_string())
+        );
+        assert_eq!(
+            verify(&auth, &format!("{}.AAAA", signed), &watcher),
+            Err("bad token signature".to_string())
+        );
+        assert_eq!(
+            verify(&auth, "not-a-token", &watcher),
+            Err("malformed token".to_string())
+        );
+    }
+
+    #[test]
+    fn expired_and_not_yet_valid_tokens_are_refused() {
+        let auth = JwtAuth::hmac(SECRET);
+        let watcher = join(Role::Watcher, "w1", None);
+        let mut expired = claims();
+        expired["exp"] = json!(now() - 2 * LEEWAY);
+        assert_eq!(
+            verify(&auth, &hs256(&expired), &watcher),
+            Err("token expired".to_string())
+        );
+        // Within the leeway
+        expired["exp"] = json!(now() - LEEWAY / 2);
+        assert_eq!(verify(&auth, &hs256(&expired), &watcher), Ok(()));
+        expired["exp"] = json!(u64::MAX);
+        assert_eq!(verify(&auth, &hs256(&expired), &watcher), Ok(()));
+
+        let mut early = claims();
+        early["nbf"] = json!(now() + 2 * LEEWAY);
+        assert_eq!(
+            verify(&auth, &hs256(&early), &watcher),
+            Err("token not valid yet".to_string())
+        );
+        early["nbf"] = json!(now());
+        assert_eq!(verify(&auth, &hs256(&early), &watcher), Ok(()));
+    }
+
+    #[test]
+    fn token_must_be_for_this_join() {
+        let auth = JwtAuth::hmac(SECRET);
+        let token = hs256(&claims());
+        assert_eq!(
+            verify(&auth, &token, &join(Role::Streamer, "w1", None)),
+            Err("token is for a watcher".to_string())
+        );
+        let other_room = Join {
+            room_id: "cam2",
+            ..join(Role::Watcher, "w1", None)
+        };
+        assert_eq!(
+            verify(&auth, &token, &other_room),
+            Err("token is for 'cam1'".to_string())
+        );
+        assert_eq!(
+            verify(&auth, &token, &join(Role::Watcher, "w2", None)),
+            Err("token is for another member id".to_string())
+        );
+
+        // Without `sub`, any member id will do
+        let mut anyone = claims();
+        anyone.as_object_mut().unwrap().remove("sub");
+        assert_eq!(
+            verify(&auth, &hs256(&anyone), &join(Role::Watcher, "w2", None)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn audience_role_caps_what_a_watcher_may_ask_for() {
+        let auth = JwtAuth::hmac(SECRET);
+        let viewer = hs256(&claims());
+        let mut claims = claims();
+        claims["audience_role"] = json!("moderator");
+        let moderator = hs256(&claims);
+
+        let asking = |role| join(Role::Watcher, "w1", Some(role));
+        assert_eq!(
+            verify(&auth, &viewer, &asking(AudienceRole::Viewer)),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&auth, &viewer, &asking(AudienceRole::Moderator)),
+            Err("token doesn't make it a moderator".to_string())
+        );
+        assert_eq!(
+            verify(&auth, &moderator, &asking(AudienceRole::Moderator)),
+            Ok(())
+        );
+        assert_eq!(
+            verify(&auth, &moderator, &asking(AudienceRole::Cohost)),
+            Err("token doesn't make it a cohost".to_string())
+        );
+    }
+}
@@ -0,0 +1,440 @@
+// Stream keys (--stream-keys): who may stream, for a server open beyond the LAN.
+// Each key belongs to one streamer, by display name, and lets it stream under
+// the ids it lists ("alice", "alice/*" for any of hers, "*" for any at all),
+// with quotas of its own in place of --max-messages-per-sec/--max-bytes-per-sec.
+// Streamers show theirs as `?key=...`; without a key that allows the stream, the
+// upgrade is answered 401 like a bad JWT. Watchers need no key, but one that shows
+// a key for the stream may join as the audience role it asks for (see `roles`).
+//
+// Keys are made and revoked from the command line, or with --admin-token on the
+// admin API:
+//
+//   transmitter --stream-keys keys.json keys create --name Alice --stream 'alice/*'
+//   transmitter --stream-keys keys.json keys list
+//   transmitter --stream-keys keys.json keys revoke 3f9a1c2e
+//
+//   POST   /admin/keys {"name":"Alice","stream_ids":["alice/*"]} → the key, once
+//   GET    /admin/keys
+//   DELETE /admin/keys/{id}
+//
+// The store is a JSON file, rewritten whole (through a temporary file) on every
+// change and read again whenever it changes on disk, so keys made or revoked from
+// the command line take effect on a running server. Only keys' SHA-256 digests
+// are kept; a key is shown once, when it's made.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::HttpRequest;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::digest::{SHA256, digest};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::Join;
+use crate::quota::Quotas;
+
+// Keys look like "tk_" and 32 base64url characters
+const PREFIX: &str = "tk_";
+const KEY_BYTES: usize = 24;
+
+// A key, as listed; never the key itself
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StreamKey {
+    pub id: String,
+    // Who streams with it, for the log and the operator
+    pub name: String,
+    pub stream_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_messages_per_sec: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_per_sec: Option<u64>,
+    // Seconds since the epoch
+    pub created_at: u64,
+}
+
+// What a new key is for; the body of `POST /admin/keys`
+#[derive(Deserialize, Debug)]
+pub struct NewKey {
+    pub name: String,
+    pub stream_ids: Vec<String>,
+    #[serde(default)]
+    pub max_messages_per_sec: Option<u64>,
+    #[serde(default)]
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    #[serde(flatten)]
+    key: StreamKey,
+    sha256: String,
+}
+
+#[derive(Deserialize)]
+struct StoreFile {
+    keys: Vec<Stored>,
+}
+
+#[derive(Default)]
+struct Cache {
+    // The file's modification time when it was read; None if it doesn't exist
+    modified: Option<SystemTime>,
+    keys: Vec<Stored>,
+}
+
+// Cheap to clone into every worker
+#[derive(Clone)]
+pub struct KeyStore {
+    path: Arc<PathBuf>,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl StreamKey {
+    // ✅ "alice" allows only alice, "alice/*" anything under alice/, "*" anything
+    pub fn allows(&self, stream_id: &str) -> bool {
+        self.stream_ids
+            .iter()
+            .any(|allowed| match wildcard(allowed) {
+                Some(prefix) => stream_id.starts_with(prefix),
+                None => allowed == stream_id,
+            })
+    }
+
+    // The key's own quotas, or the server's where it has none
+    pub(crate) fn quotas(&self, server: Quotas) -> Quotas {
+        Quotas {
+            messages_per_sec: self.max_messages_per_sec.or(server.messages_per_sec),
+            bytes_per_sec: self.max_bytes_per_sec.or(server.bytes_per_sec),
+        }
+    }
+}
+
+impl NewKey {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("a key needs a name".to_string());
+        }
+        if self.stream_ids.is_empty() {
+            return Err("a key needs at least one stream id".to_string());
+        }
+        for pattern in &self.stream_ids {
+            let fixed = wildcard(pattern).unwrap_or(pattern);
+            if pattern.is_empty() || fixed.contains('*') {
+                return Err(format!(
+                    "'{}': a stream id may only be '*' or end with '/*'",
+                    pattern
+                ));
+            }
+        }
+        if self.max_messages_per_sec == Some(0) || self.max_bytes_per_sec == Some(0) {
+            return Err("quotas must be above 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl KeyStore {
+    // ✅ The store in `path`; empty until the first key is made if there's no file
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let store = KeyStore {
+            path: Arc::new(path.to_path_buf()),
+            cache: Arc::default(),
+        };
+        // Refuse a file that isn't a store now, rather than every streamer later
+        drop(store.read()?);
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // ✅ Make a key; returns it with the only copy of the key itself
+    pub fn create(&self, new: NewKey) -> io::Result<(StreamKey, String)> {
+        new.validate()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let mut bytes = [0u8; KEY_BYTES];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| io::Error::other("cannot generate a key"))?;
+        let secret = format!("{}{}", PREFIX, URL_SAFE_NO_PAD.encode(bytes));
+        let key = StreamKey {
+            id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+            name: new.name.trim().to_string(),
+            stream_ids: new.stream_ids,
+            max_messages_per_sec: new.max_messages_per_sec,
+            max_bytes_per_sec: new.max_bytes_per_sec,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_secs()),
+        };
+
+        let mut cache = self.read()?;
+        cache.keys.push(Stored {
+            key: key.clone(),
+            sha256: sha256(&secret),
+        });
+        self.write(&mut cache)?;
+        Ok((key, secret))
+    }
+
+    pub fn list(&self) -> io::Result<Vec<StreamKey>> {
+        Ok(self
+            .read()?
+            .keys
+            .iter()
+            .map(|stored| stored.key.clone())
+            .collect())
+    }
+
+    // ✅ Revoke a key by id; None if there's no such key. Streamers already
+    // connected with it stay connected
+    pub fn revoke(&self, id: &str) -> io::Result<Option<StreamKey>> {
+        let mut cache = self.read()?;
+        let Some(index) = cache.keys.iter().position(|stored| stored.key.id == id) else {
+            return Ok(None);
+        };
+        let revoked = cache.keys.remove(index);
+        self.write(&mut cache)?;
+        Ok(Some(revoked.key))
+    }
+
+    // ✅ A streamer's key must allow the stream it joins
+    pub(crate) fn verify(&self, req: &HttpRequest, join: &Join) -> Result<StreamKey, String> {
+        let secret = crate::query_params(req)
+            .remove("key")
+            .ok_or("no stream key")?;
+        let cache = self
+            .read()
+            .map_err(|err| format!("stream keys unreadable: {}", err))?;
+        // Digests, not keys, are compared, so timing says nothing about a key
+        let sha256 = sha256(&secret);
+        let key = cache
+            .keys
+            .iter()
+            .find(|stored| stored.sha256 == sha256)
+            .map(|stored| &stored.key)
+            .ok_or("unknown stream key")?;
+        if !key.allows(join.room_id) {
+            return Err(format!(
+                "{}'s key doesn't allow '{}'",
+                key.name, join.room_id
+            ));
+        }
+        Ok(key.clone())
+    }
+
+    // ✅ The keys, read again if the file changed since
+    fn read(&self) -> io::Result<MutexGuard<'_, Cache>> {
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let modified = match fs::metadata(self.path.as_path()) {
+            Ok(metadata) => Some(metadata.modified()?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        if modified != cache.modified {
+            cache.keys = match modified {
+                Some(_) => {
+                    let file: StoreFile =
+                        serde_json::from_slice(&fs::read(self.path.as_path())?)
+                            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    file.keys
+                }
+                None => Vec::new(),
+            };
+            cache.modified = modified;
+        }
+        Ok(cache)
+    }
+
+    // ✅ Replace the file, so a reader never sees half of it
+    fn write(&self, cache: &mut Cache) -> io::Result<()> {
+        let json = serde_json::to_vec_pretty(&serde_json::json!({ "keys": cache.keys }))?;
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, self.path.as_path())?;
+        cache.modified = Some(fs::metadata(self.path.as_path())?.modified()?);
+        Ok(())
+    }
+}
+
+// What a pattern's stream ids start with, if it's "*" or ends with "/*"; "alice*"
+// is no pattern, so it doesn't allow alice2
+fn wildcard(pattern: &str) -> Option<&str> {
+    pattern
+        .strip_suffix('*')
+        .filter(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+}
+
+fn sha256(secret: &str) -> String {
+    digest(&SHA256, secret.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn new_key(stream_ids: &[&str]) -> NewKey {
+        NewKey {
+            name: "Alice".to_string(),
+            stream_ids: stream_ids.iter().map(|id| id.to_string()).collect(),
+            max_messages_per_sec: None,
+            max_bytes_per_sec: None,
+        }
+    }
+
+    fn store() -> (KeyStore, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("tuesdays-keys-{}.json", uuid::Uuid::new_v4()));
+        (KeyStore::open(&path).unwrap(), path)
+    }
+
+    fn streamer(room_id: &str) -> Join<'_> {
+        Join {
+            role: crate::Role::Streamer,
+            room_id,
+            member_id: room_id,
+            audience_role: None,
+        }
+    }
+
+    fn showing(secret: &str) -> HttpRequest {
+        TestRequest::with_uri(&format!("/streamer?id=alice&key={}", secret)).to_http_request()
+    }
+
+    #[test]
+    fn patterns_allow_one_stream_their_owners_or_any() {
+        let key = |stream_ids: &[&str]| StreamKey {
+            id: "3f9a1c2e".to_string(),
+            name: "Alice".to_string(),
+            stream_ids: stream_ids.iter().map(|id| id.to_string()).collect(),
+            max_messages_per_sec: None,
+            max_bytes_per_sec: None,
+            created_at: 0,
+        };
+
+        let one = key(&["alice"]);
+        assert!(one.allows("alice"));
+        assert!(!one.allows("alice2"));
+        assert!(!one.allows("alice/cam1"));
+
+        let hers = key(&["alice/*"]);
+        assert!(hers.allows("alice/cam1"));
+        assert!(!hers.allows("alice"));
+        assert!(!hers.allows("alice2/cam1"));
+
+        assert!(key(&["*"]).allows("bob"));
+        // Stored before patterns were checked: only ever the id itself
+        assert!(!key(&["alice*"]).allows("alice2"));
+    }
+
+    #[test]
+    fn only_whole_wildcards_are_accepted() {
+        for pattern in ["alice", "alice/*", "*", "alice/cams/*"] {
+            assert!(new_key(&[pattern]).validate().is_ok(), "{}", pattern);
+        }
+        for pattern in ["alice*", "", "*/alice", "a*ce", "alice/**"] {
+            assert!(new_key(&[pattern]).validate().is_err(), "{}", pattern);
+        }
+        assert!(new_key(&[]).validate().is_err());
+        let nameless = NewKey {
+            name: " ".to_string(),
+            ..new_key(&["alice"])
+        };
+        assert!(nameless.validate().is_err());
+        let no_quota = NewKey {
+            max_bytes_per_sec: Some(0),
+            ..new_key(&["alice"])
+        };
+        assert!(no_quota.validate().is_err());
+    }
+
+    #[test]
+    fn keys_are_verified_by_their_digest() {
+        let (keys, path) = store();
+        let (key, secret) = keys.create(new_key(&["alice/*"])).unwrap();
+        assert!(secret.starts_with(PREFIX));
+
+        let stored = fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains(&secret));
+        assert!(stored.contains(&sha256(&secret)));
+
+        let verified = keys.verify(&showing(&secret), &streamer("alice/cam1"));
+        assert_eq!(verified.map(|verified| verified.id), Ok(key.id));
+        assert_eq!(
+            keys.verify(&showing(&secret), &streamer("bob"))
+                .map(|key| key.id),
+            Err("Alice's key doesn't allow 'bob'".to_string())
+        );
+        assert_eq!(
+            keys.verify(&showing("tk_wrong"), &streamer("alice/cam1"))
+                .map(|key| key.id),
+            Err("unknown stream key".to_string())
+        );
+        let keyless = TestRequest::with_uri("/streamer?id=alice").to_http_request();
+        assert_eq!(
+            keys.verify(&keyless, &streamer("alice/cam1"))
+                .map(|key| key.id),
+            Err("no stream key".to_string())
+        );
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn revoked_keys_are_refused() {
+        let (keys, path) = store();
+        let (key, secret) = keys.create(new_key(&["alice"])).unwrap();
+
+        assert_eq!(
+            keys.revoke(&key.id).unwrap().map(|key| key.id),
+            Some(key.id)
+        );
+        assert!(keys.revoke("3f9a1c2e").unwrap().is_none());
+
+        assert!(keys.list().unwrap().is_empty());
+        assert!(keys.verify(&showing(&secret), &streamer("alice")).is_err());
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn changes_on_disk_are_picked_up() {
+        // The command line and the running server each have a store on one file
+        let (server, path) = store();
+        let cli = KeyStore::open(&path).unwrap();
+        assert!(server.list().unwrap().is_empty());
+
+        let (key, secret) = cli.create(new_key(&["alice"])).unwrap();
+        assert!(server.verify(&showing(&secret), &streamer("alice")).is_ok());
+
+        // A file's mtime is only as fine as the filesystem's clock tick
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        cli.revoke(&key.id).unwrap();
+        assert!(
+            server
+                .verify(&showing(&secret), &streamer("alice"))
+                .is_err()
+        );
+        fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn a_file_that_isnt_a_store_is_refused() {
+        let (_, path) = store();
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(
+            KeyStore::open(&path).err().map(|err| err.kind()),
+            Some(io::ErrorKind::InvalidData)
+        );
+        fs::remove_file(path).ok();
+    }
+}
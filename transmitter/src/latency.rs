@@ -0,0 +1,60 @@
+// Latency test endpoint: /latency is a WebSocket that joins no room and answers
+// each `ping` with the transmitter's timestamps, and echoes anything else as it
+// came, so a client can measure its signaling round trip and clock offset
+// before (or without) joining; see `tuesdays_protocol::latency`.
+//
+//   {"command":"ping","sent_ms":1760000000000}
+//   {"sent_ms":1760000000000,"received_ms":1760000000013,"replied_ms":1760000000013}
+
+use actix::{Actor, StreamHandler};
+use actix_web_actors::ws;
+use std::time::Instant;
+use tuesdays_protocol::Command;
+use tuesdays_protocol::latency::{self, Pong};
+
+use crate::heartbeat::{self, Heartbeat, Heartbeating};
+
+pub(crate) struct LatencyWebSocket {
+    pub heartbeat: Option<Heartbeat>,
+    // The last frame from the client; see `heartbeat`
+    pub last_seen: Instant,
+}
+
+impl Actor for LatencyWebSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(heartbeat) = self.heartbeat {
+            heartbeat::start(heartbeat, ctx);
+        }
+    }
+}
+
+impl Heartbeating for LatencyWebSocket {
+    fn last_seen(&self) -> Instant {
+        self.last_seen
+    }
+
+    fn timed_out(&mut self) {}
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for LatencyWebSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let received_ms = latency::now_ms();
+        if msg.is_ok() {
+            self.last_seen = Instant::now();
+        }
+        match msg {
+            Ok(ws::Message::Ping(bytes)) => ctx.pong(&bytes),
+            Ok(ws::Message::Text(text)) => match serde_json::from_str::<Command>(&text) {
+                Ok(Command::Ping { sent_ms }) => {
+                    ctx.text(Pong::reply(sent_ms, received_ms).to_json())
+                }
+                _ => ctx.text(text),
+            },
+            Ok(ws::Message::Binary(bytes)) => ctx.binary(bytes),
+            Ok(ws::Message::Close(reason)) => ctx.close(reason),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,1626 @@
+// Embeddable signaling server: mount the room/streamer/watcher routes into any
+// actix-web App.
+//
+//   let server = SignalingServer::new().with_auth(|req, join| check_token(req, join));
+//   HttpServer::new(move || {
+//       let server = server.clone();
+//       App::new().configure(move |cfg| server.configure(cfg))
+//   })
+
+mod access_log;
+mod admin;
+mod agent;
+mod archive;
+mod batch;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod capture;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod console;
+mod deflate;
+mod drain;
+mod fanout;
+mod heartbeat;
+mod jwt;
+mod keys;
+mod latency;
+mod lifecycle;
+mod limits;
+mod member;
+mod metrics;
+mod origin;
+mod policy;
+mod presence;
+mod qoe;
+mod quota;
+mod redis;
+mod region;
+mod registry;
+mod replay;
+mod room;
+mod sanitize;
+mod speaker;
+mod throttle;
+pub mod tls;
+
+use actix::{Actor, Addr, Arbiter};
+use actix_web::http::header::{self, ContentType, HeaderName, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, web};
+use log::info;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use access_log::AccessSession;
+use agent::{AgentStore, AgentWebSocket, SendCommand, lock_agents};
+use capture::CaptureSession;
+use drain::Drain;
+use latency::LatencyWebSocket;
+use limits::LimitMeter;
+use member::MemberWebSocket;
+use metrics::Metrics;
+use quota::QuotaMeter;
+use registry::{EnsureRoom, GetRoom, ListRooms, Registry};
+use room::{
+    CanResume, GetClients, GetMembers, GetStreamInfo, GetStreamState, GetStreamers, Kick, Migrate,
+    RoomActor,
+};
+use throttle::JoinLimiter;
+use tls::{ClientAddrs, TlsFront};
+use tuesdays_protocol::agent::{StartStream, StopStream};
+use tuesdays_protocol::deprecation::DEPRECATED;
+use tuesdays_protocol::encoding;
+use tuesdays_protocol::version::{self, VERSION_PARAM, VERSIONS_HEADER};
+use tuesdays_protocol::{
+    AgentCommand, AgentInfo, AudienceRole, Codec, Deprecation, Encoding, ErrorCode, RedirectReason,
+    Rejection, StreamState,
+};
+
+pub use access_log::{AccessLog, AccessLogFormat};
+pub use admin::AdminToken;
+pub use archive::Archive;
+pub use capture::Capture;
+#[cfg(feature = "chaos")]
+pub use chaos::Chaos;
+pub use fanout::Fanout;
+pub use heartbeat::Heartbeat;
+pub use jwt::JwtAuth;
+pub use keys::{KeyStore, NewKey, StreamKey};
+pub use limits::Limit;
+pub use origin::{AllowedOrigin, Origins};
+pub use policy::Policy;
+pub use presence::{MemoryStore, Presence, PresenceStore, RedisStore};
+pub use quota::Quotas;
+pub use region::{RegionUrl, Regions};
+pub use room::{DuplicateStreamer, Role};
+pub use sanitize::{DEFAULT_MAX_SDP_BYTES, Sanitizer, Transport};
+
+// Who is trying to connect, as seen by the auth hook
+#[derive(Debug)]
+pub struct Join<'a> {
+    pub role: Role,
+    pub room_id: &'a str,
+    pub member_id: &'a str,
+    // Watchers only: the audience role it asks for (`role`); see
+    // `tuesdays_protocol::roles`
+    pub audience_role: Option<AudienceRole>,
+}
+
+// Decides whether a connection may join; `Err(reason)` rejects it with 403
+pub type AuthHook = Arc<dyn Fn(&HttpRequest, &Join) -> Result<(), String> + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct SignalingConfig {
+    // Reject watchers of a stream that isn't live (or paused, its streamer expected
+    // back) instead of letting them wait
+    pub require_streamer: bool,
+    // Rather than reject them, park watchers of a stream that isn't live until a
+    // streamer registers, then seat them with a `stream_started` signal
+    pub waiting_room: bool,
+    // Serve the `GET /streams` directory, `GET /streams/{id}` and `GET /api/streamers`
+    pub directory: bool,
+    // Accept streamer agents on /agent and serve the /agents control API; the API
+    // is unauthenticated, so only enable it behind a trusted network or proxy
+    pub agents: bool,
+    // Most watchers that may join one stream per second; see `throttle`
+    pub watcher_join_rate: Option<u32>,
+    // Serve `GET /clients`: every connection's user agent and client version.
+    // Unauthenticated, like the agents API
+    pub clients: bool,
+    // Turn watchers' `stats` reports into bitrate recommendations for the
+    // streamer; see `qoe`
+    pub recommend_quality: bool,
+    // Serve the /admin API for draining the instance before a deploy; see `drain`.
+    // Unauthenticated, like the agents API
+    pub admin: bool,
+    // Ping every connection and drop those that stop answering; see `heartbeat`
+    pub heartbeat: Option<Heartbeat>,
+    // Most a member may send, warned before it's disconnected; see `quota`
+    pub quotas: Quotas,
+    // A streamer connecting with the id of one that's still connected
+    pub duplicate_streamer: DuplicateStreamer,
+    // Serve Prometheus metrics on `GET /metrics`; see `metrics`. Unauthenticated,
+    // like the agents API
+    pub metrics: bool,
+    // Keep a dropped streamer's session, or a watcher's place, this long for it to
+    // resume with its token; see `tuesdays_protocol::resume`
+    pub resume_grace: Option<Duration>,
+    // Replay each streamer's last this many broadcasts to a watcher that joins;
+    // 0: none
+    pub replay_broadcasts: usize,
+    // Hold broadcasts that aren't signaling this long, to send them to each member
+    // several to a frame; see `batch`
+    pub batch_window: Option<Duration>,
+    // Most members connected to rooms at once; more are turned away with 503
+    pub max_connections: Option<usize>,
+    // Largest WebSocket frame a client may send
+    pub max_frame_bytes: usize,
+    // Compress WebSocket messages for members joining as these, if they offer
+    // permessage-deflate; see `deflate`
+    pub deflate: Vec<Role>,
+}
+
+impl Default for SignalingConfig {
+    fn default() -> Self {
+        SignalingConfig {
+            require_streamer: true,
+            waiting_room: false,
+            directory: true,
+            agents: false,
+            watcher_join_rate: None,
+            clients: false,
+            recommend_quality: false,
+            admin: false,
+            heartbeat: Some(Heartbeat::default()),
+            quotas: Quotas::default(),
+            duplicate_streamer: DuplicateStreamer::default(),
+            metrics: false,
+            resume_grace: None,
+            replay_broadcasts: 0,
+            batch_window: None,
+            max_connections: None,
+            max_frame_bytes: 65_536,
+            deflate: Vec::new(),
+        }
+    }
+}
+
+// Rooms and settings for one set of signaling routes; cheap to clone into each worker
+#[derive(Clone)]
+pub struct SignalingServer {
+    // Every room by id; see `registry`
+    rooms: Addr<Registry>,
+    agents: AgentStore,
+    config: SignalingConfig,
+    auth: Option<AuthHook>,
+    jwt: Option<JwtAuth>,
+    // Wanted from streamers, with --stream-keys; see `keys`
+    keys: Option<KeyStore>,
+    // Wanted by the admin API, with --admin-token; see `admin`
+    admin_token: Option<AdminToken>,
+    archive: Option<Archive>,
+    // Which streams are live here, and on other instances; see `presence`
+    presence: Presence,
+    // Rooms' traffic passed between instances, with --fanout; see `fanout`
+    fanout: Option<Fanout>,
+    // Where members from other regions belong, with --region; see `region`
+    regions: Regions,
+    // Web pages allowed to connect, with --allowed-origin; see `origin`
+    origins: Origins,
+    access_log: Option<AccessLog>,
+    capture: Option<Capture>,
+    throttle: Option<JoinLimiter>,
+    policy: Policy,
+    // Commands members are warned off; see `tuesdays_protocol::deprecation`
+    deprecations: &'static [Deprecation],
+    sanitizer: Sanitizer,
+    drain: Drain,
+    // Counted whether or not they're served; see `metrics`
+    metrics: Metrics,
+    // Who's behind connections decrypted by the TLS front; see `tls`
+    clients: ClientAddrs,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Chaos>,
+}
+
+// ✅ Starts the room registry, so it must be created inside an actix System
+impl Default for SignalingServer {
+    fn default() -> Self {
+        SignalingServer {
+            rooms: Registry::default().start(),
+            agents: AgentStore::default(),
+            config: SignalingConfig::default(),
+            auth: None,
+            jwt: None,
+            keys: None,
+            admin_token: None,
+            archive: None,
+            presence: Presence::default(),
+            fanout: None,
+            regions: Regions::default(),
+            origins: Origins::default(),
+            access_log: None,
+            capture: None,
+            throttle: None,
+            policy: Policy::default(),
+            deprecations: DEPRECATED,
+            sanitizer: Sanitizer::default(),
+            drain: Drain::default(),
+            metrics: Metrics::default(),
+            clients: ClientAddrs::default(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+}
+
+impl SignalingServer {
+    pub fn new() -> Self {
+        SignalingServer::default()
+    }
+
+    pub fn with_config(mut self, config: SignalingConfig) -> Self {
+        self.throttle = config.watcher_join_rate.map(JoinLimiter::new);
+        self.config = config;
+        self
+    }
+
+    pub fn with_auth<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&HttpRequest, &Join) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.auth = Some(Arc::new(hook));
+        self
+    }
+
+    // Require a token for every connection; see `jwt`
+    pub fn with_jwt(mut self, jwt: JwtAuth) -> Self {
+        self.jwt = Some(jwt);
+        self
+    }
+
+    // Require a stream key of every streamer; see `keys`
+    pub fn with_stream_keys(mut self, keys: KeyStore) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    // Require a bearer token on the admin API, and serve the moderation routes;
+    // see `admin`
+    pub fn with_admin_token(mut self, token: AdminToken) -> Self {
+        self.admin_token = Some(token);
+        self
+    }
+
+    // Serve the routes behind `front` too, logging its clients' own addresses
+    pub fn with_tls(mut self, front: &TlsFront) -> Self {
+        self.clients = front.clients();
+        self
+    }
+
+    // Record every watcher session in an access log
+    pub fn with_access_log(mut self, log: AccessLog) -> Self {
+        self.access_log = Some(log);
+        self
+    }
+
+    // Keep every stream's chat and lifecycle events, and serve them on
+    // `GET /streams/{id}/archive`
+    pub fn with_archive(mut self, archive: Archive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    // Send members from other regions to instances of their own
+    pub fn with_regions(mut self, regions: Regions) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    // Only let pages from these origins connect; see `origin`
+    pub fn with_origins(mut self, origins: Origins) -> Self {
+        self.origins = origins;
+        self
+    }
+
+    // Share which streams are live here with other instances
+    pub fn with_presence(mut self, presence: Presence) -> Self {
+        self.presence = presence;
+        self
+    }
+
+    // Pass rooms' broadcasts and relays between instances; starts listening to
+    // the others right away
+    pub fn with_fanout(mut self, fanout: Fanout) -> Self {
+        fanout.listen(self.rooms.clone());
+        self.fanout = Some(fanout);
+        self
+    }
+
+    // Capture every room's signaling to files, for replaying failed handshakes
+    pub fn with_capture(mut self, capture: Capture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    // Limit which commands each role may send
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Warn members off these commands, rather than the protocol's deprecations;
+    // a deployment retiring a command ahead of the protocol lists them all
+    pub fn with_deprecations(mut self, deprecations: &'static [Deprecation]) -> Self {
+        self.deprecations = deprecations;
+        self
+    }
+
+    // Filter relayed candidates and validate SDP before it reaches peers
+    pub fn with_sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = sanitizer;
+        self
+    }
+
+    // Mistreat relayed messages and connections, for resilience tests
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Chaos) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    // ✅ Register the routes (and this server's state) on an App or scope
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::new(self.clone()))
+            .route("/room", web::get().to(room_ws))
+            .route("/streamer", web::get().to(streamer_ws))
+            .route("/watcher", web::get().to(watcher_ws))
+            .route("/latency", web::get().to(latency_ws));
+        if self.config.directory {
+            cfg.route("/streams", web::get().to(list_streams))
+                .route("/streams/{id}", web::get().to(get_stream))
+                .route("/api/streamers", web::get().to(list_streamers));
+        }
+        if self.config.clients {
+            cfg.route("/clients", web::get().to(list_clients));
+        }
+        if self.archive.is_some() {
+            cfg.route("/streams/{id}/archive", web::get().to(export_archive));
+        }
+        if self.config.agents {
+            cfg.route("/agent", web::get().to(agent_ws))
+                .route("/agents", web::get().to(list_agents))
+                .route("/agents/{id}/start", web::post().to(start_agent_stream))
+                .route("/agents/{id}/stop", web::post().to(stop_agent_stream));
+        }
+        if self.config.metrics {
+            cfg.route("/metrics", web::get().to(metrics));
+        }
+        if self.config.admin {
+            cfg.route("/admin/drain", web::get().to(drain_status))
+                .route("/admin/drain", web::post().to(start_draining));
+        }
+        if self.config.admin && self.admin_token.is_some() {
+            cfg.route("/api/streamers/{id}", web::delete().to(disconnect_streamer))
+                .route(
+                    "/api/streamers/{id}/watchers/{watcher_id}",
+                    web::delete().to(kick_watcher),
+                );
+        }
+        if self.config.admin && self.admin_token.is_some() && self.keys.is_some() {
+            cfg.route("/admin/keys", web::get().to(list_keys))
+                .route("/admin/keys", web::post().to(create_key))
+                .route("/admin/keys/{id}", web::delete().to(revoke_key));
+        }
+    }
+
+    // ✅ Relay a command to a connected agent; None if there's no such agent
+    fn command_agent(&self, agent_id: &str, command: AgentCommand) -> Option<()> {
+        let agents = lock_agents(&self.agents);
+        let agent = agents.get(agent_id)?;
+        agent.addr.do_send(SendCommand(command));
+        Some(())
+    }
+
+    // ✅ A draining instance sends new connections to the one replacing it
+    fn redirect(&self, req: &HttpRequest) -> Result<(), HttpResponse> {
+        let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+        match self.drain.redirect(path_and_query) {
+            Some(location) => {
+                info!("🚚 Connection redirected to {}", location);
+                Err(HttpResponse::TemporaryRedirect()
+                    .insert_header((header::LOCATION, location))
+                    .body("Draining; connect to the new instance"))
+            }
+            None => Ok(()),
+        }
+    }
+
+    // ✅ Where a member belongs instead of here, if anywhere: the instance its
+    // stream is live on, or one in its region; see `region`
+    async fn elsewhere(
+        &self,
+        req: &HttpRequest,
+        join: &Join<'_>,
+        room: Option<&Addr<RoomActor>>,
+    ) -> Option<(String, RedirectReason)> {
+        let live_here = match room {
+            Some(room) => room
+                .send(GetStreamState)
+                .await
+                .is_ok_and(StreamState::watchable),
+            None => false,
+        };
+        // A streamer joins its stream wherever it's ingested; a watcher, unless
+        // it can watch from anywhere (--fanout)
+        let follows_stream = match join.role {
+            Role::Streamer => !live_here,
+            _ => room.is_none() && self.fanout.is_none(),
+        };
+        let owner = if follows_stream {
+            self.presence.find_elsewhere(join.room_id).await
+        } else {
+            None
+        };
+        let owner = owner
+            .filter(|present| present.info.state.watchable())
+            .and_then(|present| present.url);
+        let by_region = match join.role {
+            Role::Streamer => !live_here,
+            _ => self.fanout.is_some(),
+        };
+        let redirect = match owner {
+            Some(url) => {
+                let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+                let url = format!("{}{}", url.trim_end_matches('/'), path_and_query);
+                Some((url, RedirectReason::Stream))
+            }
+            None if by_region => self
+                .regions
+                .elsewhere(req)
+                .map(|url| (url, RedirectReason::Region)),
+            None => None,
+        };
+        if let Some((url, reason)) = &redirect {
+            info!(
+                "🧭 {} '{}' of stream '{}' sent to {} ({:?})",
+                join.role.name(),
+                join.member_id,
+                join.room_id,
+                url,
+                reason
+            );
+        }
+        redirect
+    }
+
+    // ✅ With --allowed-origin, a browser's page must be from an allowed origin;
+    // 403 if not
+    fn check_origin(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        self.origins.check(req).map_err(|reason| {
+            info!(
+                "🚫 {:?} '{}' rejected from Room '{}': {}",
+                join.role, join.member_id, join.room_id, reason
+            );
+            reject(&mut HttpResponse::Forbidden(), ErrorCode::Forbidden, reason)
+        })
+    }
+
+    // ✅ The client must speak a protocol version we do; 400 if not, with the
+    // versions we speak
+    fn check_version(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        let params = query_params(req);
+        Codec::negotiate(params.get(VERSION_PARAM).map(String::as_str))
+            .map(|_| ())
+            .map_err(|rejection| {
+                info!(
+                    "🔢 {:?} '{}' rejected from Room '{}': {}",
+                    join.role,
+                    join.member_id,
+                    join.room_id,
+                    rejection.detail.as_deref().unwrap_or_default()
+                );
+                reject(
+                    HttpResponse::BadRequest()
+                        .insert_header((VERSIONS_HEADER, version::advertised())),
+                    rejection.code,
+                    rejection.detail.unwrap_or_default(),
+                )
+            })
+    }
+
+    // ✅ With --jwt-*, the connection's token must allow the join; 401 if not
+    fn authenticate(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        let Some(jwt) = &self.jwt else {
+            return Ok(());
+        };
+        jwt.verify(req, join).map_err(|reason| {
+            info!(
+                "🔑 {:?} '{}' unauthenticated for Room '{}': {}",
+                join.role, join.member_id, join.room_id, reason
+            );
+            reject(
+                HttpResponse::Unauthorized()
+                    .insert_header((header::WWW_AUTHENTICATE, r#"Bearer error="invalid_token""#)),
+                ErrorCode::Unauthorized,
+                reason,
+            )
+        })
+    }
+
+    // ✅ With --stream-keys, a streamer's key must allow the stream; 401 if not
+    fn authenticate_streamer(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        let Some(keys) = self.keys.as_ref().filter(|_| join.role == Role::Streamer) else {
+            return Ok(());
+        };
+        match keys.verify(req, join) {
+            Ok(key) => {
+                info!(
+                    "🔑 Streamer '{}' streams '{}' as {} (key {})",
+                    join.member_id, join.room_id, key.name, key.id
+                );
+                Ok(())
+            }
+            Err(reason) => {
+                info!(
+                    "🔑 Streamer '{}' unauthenticated for Room '{}': {}",
+                    join.member_id, join.room_id, reason
+                );
+                Err(reject(
+                    HttpResponse::Unauthorized()
+                        .insert_header((header::WWW_AUTHENTICATE, r#"Key realm="stream""#)),
+                    ErrorCode::Unauthorized,
+                    reason,
+                ))
+            }
+        }
+    }
+
+    // ✅ The audience role a watcher asked for, if something vouches for it: its
+    // token (held to its `audience_role` as it's admitted) or a stream key for
+    // the stream. Otherwise it joins as a viewer, and only a streamer (or a
+    // watcher it made a moderator) raises it, with `assign`
+    fn grant(&self, req: &HttpRequest, join: &Join) -> AudienceRole {
+        let asked = join.audience_role.unwrap_or_default();
+        if asked == AudienceRole::Viewer || self.jwt.is_some() {
+            return asked;
+        }
+        if let Some(key) = self
+            .keys
+            .as_ref()
+            .and_then(|keys| keys.verify(req, join).ok())
+        {
+            info!(
+                "🎭 Watcher '{}' joins '{}' as a {} (key {})",
+                join.member_id,
+                join.room_id,
+                asked.name(),
+                key.id
+            );
+            return asked;
+        }
+        info!(
+            "🎭 Watcher '{}' asked to be a {} in '{}' with nothing granting it; joins as a viewer",
+            join.member_id,
+            asked.name(),
+            join.room_id
+        );
+        AudienceRole::Viewer
+    }
+
+    // The server's quotas, or those of the streamer's key
+    fn quotas(&self, req: &HttpRequest, join: &Join) -> Quotas {
+        match &self.keys {
+            Some(keys) if join.role == Role::Streamer => keys
+                .verify(req, join)
+                .map_or(self.config.quotas, |key| key.quotas(self.config.quotas)),
+            _ => self.config.quotas,
+        }
+    }
+
+    // ✅ With --admin-token, admin requests must carry it; 401 if not
+    fn authenticate_admin(&self, req: &HttpRequest) -> Result<(), HttpResponse> {
+        let Some(token) = &self.admin_token else {
+            return Ok(());
+        };
+        token.verify(req).map_err(|reason| {
+            info!(
+                "🔑 Admin request {} {} unauthenticated: {}",
+                req.method(),
+                req.path(),
+                reason
+            );
+            reject(
+                HttpResponse::Unauthorized().insert_header((header::WWW_AUTHENTICATE, "Bearer")),
+                ErrorCode::Unauthorized,
+                reason,
+            )
+        })
+    }
+
+    fn authorize(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        match &self.auth {
+            Some(hook) => hook(req, join).map_err(|reason| {
+                info!(
+                    "🔒 {:?} '{}' rejected from Room '{}': {}",
+                    join.role, join.member_id, join.room_id, reason
+                );
+                reject(&mut HttpResponse::Forbidden(), ErrorCode::Forbidden, reason)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    async fn start_member(
+        &self,
+        req: &HttpRequest,
+        stream: web::Payload,
+        join: Join<'_>,
+    ) -> Result<HttpResponse, actix_web::Error> {
+        if let Err(response) = self.admit(req, &join) {
+            return Ok(response);
+        }
+        self.connect(req, stream, join).await
+    }
+
+    async fn room(&self, room_id: &str) -> Option<Addr<RoomActor>> {
+        self.rooms
+            .send(GetRoom(room_id.to_string()))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn admit(&self, req: &HttpRequest, join: &Join) -> Result<(), HttpResponse> {
+        self.check_origin(req, join)
+            .and_then(|()| self.check_version(req, join))
+            .and_then(|()| self.redirect(req))
+            .and_then(|()| self.authenticate(req, join))
+            .and_then(|()| self.authenticate_streamer(req, join))
+            .and_then(|()| self.authorize(req, join))
+    }
+
+    // Start the member's connection, once it's been admitted
+    async fn connect(
+        &self,
+        req: &HttpRequest,
+        stream: web::Payload,
+        join: Join<'_>,
+    ) -> Result<HttpResponse, actix_web::Error> {
+        // ✅ Full: turn the member away before it takes a worker's share of memory
+        if let Some(max) = self.config.max_connections
+            && self.drain.connections() >= max
+        {
+            info!(
+                "⛔ {:?} '{}' turned away from '{}': {} connections already",
+                join.role, join.member_id, join.room_id, max
+            );
+            return Ok(reject(
+                HttpResponse::ServiceUnavailable().insert_header((header::RETRY_AFTER, "5")),
+                ErrorCode::Busy,
+                "too many connections; try again shortly",
+            ));
+        }
+
+        // Check if the room exists, if not create it (on this worker)
+        let room = self
+            .rooms
+            .send(EnsureRoom {
+                room_id: join.room_id.to_string(),
+                throttle: self.throttle.clone(),
+                recommend: self.config.recommend_quality,
+                archive: self.archive.clone(),
+                presence: self.presence.clone(),
+                fanout: self.fanout.clone(),
+                metrics: self.metrics.clone(),
+                resume_grace: self.config.resume_grace,
+                replay_broadcasts: self.config.replay_broadcasts,
+                batch_window: self.config.batch_window,
+                arbiter: Arbiter::current(),
+            })
+            .await
+            .map_err(actix_web::error::ErrorServiceUnavailable)?;
+
+        deflate::start(
+            MemberWebSocket {
+                member_id: join.member_id.to_string(),
+                room_id: join.room_id.to_string(),
+                role: join.role,
+                audience_role: join.audience_role,
+                admin: self
+                    .admin_token
+                    .as_ref()
+                    .is_some_and(|token| token.verify(req).is_ok()),
+                // A streamer publishing no video says so, for the directory
+                audio_only: join.role == Role::Streamer
+                    && query_params(req)
+                        .get("media")
+                        .is_some_and(|media| media == "audio"),
+                // Its opt-out of audience chat, for the room
+                no_chat: join.role == Role::Streamer
+                    && query_params(req)
+                        .get("chat")
+                        .is_some_and(|chat| chat == "off"),
+                user_agent: access_log::user_agent(req),
+                waiting: join.role == Role::Watcher && self.config.waiting_room,
+                resume: matches!(join.role, Role::Streamer | Role::Watcher)
+                    .then(|| query_params(req).get("resume").cloned())
+                    .flatten(),
+                last_seq: query_params(req)
+                    .get("last_seq")
+                    .and_then(|last_seq| last_seq.parse().ok()),
+                room,
+                session_id: None,
+                access: self
+                    .access_log
+                    .clone()
+                    .filter(|_| join.role == Role::Watcher)
+                    .map(|log| AccessSession::new(log, req, self.clients.resolve(req.peer_addr()))),
+                capture: self
+                    .capture
+                    .clone()
+                    .map(|capture| CaptureSession::new(capture, req, join.room_id, join.member_id)),
+                archive: self.archive.clone(),
+                heartbeat: self.config.heartbeat,
+                last_seen: Instant::now(),
+                timed_out: false,
+                quota: QuotaMeter::new(self.quotas(req, &join)),
+                policy: self.policy.clone(),
+                limits: LimitMeter::default(),
+                deprecations: self.deprecations,
+                deprecated: Vec::new(),
+                // Admitted, so a version we speak
+                codec: Codec::negotiate(query_params(req).get(VERSION_PARAM).map(String::as_str))
+                    .unwrap_or_default(),
+                // As the handshake picks it, from the client's subprotocols
+                encoding: Encoding::negotiate(
+                    req.headers()
+                        .get(header::SEC_WEBSOCKET_PROTOCOL)
+                        .and_then(|offered| offered.to_str().ok()),
+                ),
+                sanitizer: self.sanitizer.clone(),
+                drain: self.drain.clone(),
+                metrics: self.metrics.clone(),
+                disconnect: None,
+                #[cfg(feature = "chaos")]
+                chaos: self.chaos,
+            },
+            req,
+            stream,
+            self.config.max_frame_bytes,
+            self.config.deflate.contains(&join.role),
+            encoding::SUBPROTOCOLS,
+        )
+        .map(|mut res| {
+            // ✅ Tell the client every version we speak, so it can move up
+            if let Ok(versions) = HeaderValue::from_str(&version::advertised()) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(VERSIONS_HEADER), versions);
+            }
+            res
+        })
+    }
+
+    // ✅ A member back within its grace period picks up where it left off, taking
+    // over its old connection if that hasn't gone yet; a stale token, or one too
+    // far behind to replay, is turned away with 410 rather than start afresh
+    // where the member doesn't expect it
+    async fn resume(
+        &self,
+        req: &HttpRequest,
+        stream: web::Payload,
+        join: Join<'_>,
+        room: Option<&Addr<RoomActor>>,
+        token: String,
+    ) -> Result<HttpResponse, actix_web::Error> {
+        let last_seq = query_params(req)
+            .get("last_seq")
+            .and_then(|last_seq| last_seq.parse().ok());
+        let resumable = match room {
+            Some(room) => room
+                .send(CanResume {
+                    member_id: join.member_id.to_string(),
+                    token,
+                    last_seq,
+                })
+                .await
+                .unwrap_or(false),
+            None => false,
+        };
+        if !resumable {
+            info!(
+                "❌ {} '{}' can't resume in '{}': nothing kept for it",
+                join.role.name(),
+                join.member_id,
+                join.room_id
+            );
+            return Ok(reject(
+                &mut HttpResponse::Gone(),
+                ErrorCode::NothingToResume,
+                format!("nothing kept for {} '{}'", join.role.name(), join.member_id),
+            ));
+        }
+        self.connect(req, stream, join).await
+    }
+}
+
+// Extract a required, non-empty query parameter or build the 400 response
+fn required_param(params: &HashMap<String, String>, name: &str) -> Result<String, HttpResponse> {
+    match params.get(name) {
+        Some(value) if !value.is_empty() => Ok(value.clone()),
+        _ => {
+            info!("❌ Connection rejected: missing '{}' query parameter", name);
+            Err(reject(
+                &mut HttpResponse::BadRequest(),
+                ErrorCode::MissingParameter,
+                format!("'{}' is required", name),
+            ))
+        }
+    }
+}
+
+// ✅ An HTTP error, its body the same `ErrorResponse` as a WebSocket error's
+fn reject(
+    response: &mut HttpResponseBuilder,
+    code: ErrorCode,
+    details: impl Into<String>,
+) -> HttpResponse {
+    response
+        .content_type(ContentType::json())
+        .body(Rejection::new(code, details).to_json())
+}
+
+// An optional query parameter; empty counts as missing
+fn optional_param(params: &HashMap<String, String>, name: &str) -> Option<String> {
+    params.get(name).filter(|value| !value.is_empty()).cloned()
+}
+
+fn query_params(req: &HttpRequest) -> HashMap<String, String> {
+    serde_urlencoded::from_str(req.query_string()).unwrap_or_default()
+}
+
+// WebSocket handler for rooms
+async fn room_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<SignalingServer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = query_params(&req);
+
+    let room_id = match required_param(&params, "room_id") {
+        Ok(id) => id,
+        Err(response) => return Ok(response),
+    };
+
+    let member_id = match required_param(&params, "member_id") {
+        Ok(id) => id,
+        Err(response) => return Ok(response),
+    };
+
+    let join = Join {
+        role: Role::Member,
+        room_id: &room_id,
+        member_id: &member_id,
+        audience_role: None,
+    };
+    server.start_member(&req, stream, join).await
+}
+
+// WebSocket handler for streamers: each streamer publishes into `room_id`, several
+// can share one, and without it into a room named after its own id (`media=audio`
+// lists it in the directory as audio-only)
+async fn streamer_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<SignalingServer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = query_params(&req);
+
+    let streamer_id = match required_param(&params, "id") {
+        Ok(id) => id,
+        Err(response) => return Ok(response),
+    };
+    let room_id = optional_param(&params, "room_id").unwrap_or_else(|| streamer_id.clone());
+
+    let join = Join {
+        role: Role::Streamer,
+        room_id: &room_id,
+        member_id: &streamer_id,
+        audience_role: None,
+    };
+    if let Err(response) = server.admit(&req, &join) {
+        return Ok(response);
+    }
+
+    let room = server.room(&room_id).await;
+
+    if let Some(token) = optional_param(&params, "resume") {
+        return server
+            .resume(&req, stream, join, room.as_ref(), token)
+            .await;
+    }
+    if let Some((url, reason)) = server.elsewhere(&req, &join, room.as_ref()).await {
+        return region::redirect(&req, stream, url, reason);
+    }
+
+    // ✅ Another streamer is connected with this id: turn this one away or give it
+    // a free id, unless it takes over (see `DuplicateStreamer`)
+    let policy = server.config.duplicate_streamer;
+    let members = match room {
+        Some(room) if policy != DuplicateStreamer::Takeover => {
+            room.send(GetMembers).await.unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+    let mut member_id = streamer_id.clone();
+    if members.contains(&streamer_id) {
+        if policy == DuplicateStreamer::Reject {
+            info!(
+                "❌ Streamer '{}' rejected: already connected to '{}'",
+                streamer_id, room_id
+            );
+            return Ok(reject(
+                &mut HttpResponse::Conflict(),
+                ErrorCode::AlreadyConnected,
+                format!("streamer '{}' is already connected", streamer_id),
+            ));
+        }
+        member_id = (2..)
+            .map(|n| format!("{}-{}", streamer_id, n))
+            .find(|id| !members.contains(id))
+            .expect("some suffix is free");
+        info!(
+            "🔀 Streamer '{}' already connected to '{}'; the new one is '{}'",
+            streamer_id, room_id, member_id
+        );
+    }
+
+    let join = Join {
+        member_id: &member_id,
+        ..join
+    };
+    server.connect(&req, stream, join).await
+}
+
+// WebSocket handler for watchers: joins `room_id` and whoever streams into it, or
+// `streamer_id`, the room of a streamer that publishes into its own
+async fn watcher_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<SignalingServer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = query_params(&req);
+
+    let room_id = match optional_param(&params, "room_id") {
+        Some(id) => id,
+        None => match required_param(&params, "streamer_id") {
+            Ok(id) => id,
+            Err(response) => return Ok(response),
+        },
+    };
+
+    let watcher_id = match required_param(&params, "id") {
+        Ok(id) => id,
+        Err(response) => return Ok(response),
+    };
+
+    // ✅ A watcher may ask for more than a viewer's say in the room; see `roles`
+    let audience_role = match optional_param(&params, "role").map(serde_json::Value::from) {
+        Some(role) => match serde_json::from_value(role) {
+            Ok(role) => role,
+            Err(_) => {
+                return Ok(reject(
+                    &mut HttpResponse::BadRequest(),
+                    ErrorCode::InvalidRequest,
+                    "'role' must be viewer, moderator or cohost",
+                ));
+            }
+        },
+        None => AudienceRole::default(),
+    };
+
+    let room = server.room(&room_id).await;
+    let join = Join {
+        role: Role::Watcher,
+        room_id: &room_id,
+        member_id: &watcher_id,
+        audience_role: Some(audience_role),
+    };
+    let join = Join {
+        audience_role: Some(server.grant(&req, &join)),
+        ..join
+    };
+
+    if let Some(token) = optional_param(&params, "resume") {
+        if let Err(response) = server.admit(&req, &join) {
+            return Ok(response);
+        }
+        return server
+            .resume(&req, stream, join, room.as_ref(), token)
+            .await;
+    }
+
+    // ✅ A stream that's live on another instance is watched there; see `presence`.
+    // With --fanout, it's watched here all the same (see `fanout`), or from the
+    // watcher's own region; see `region`
+    if let Some((url, reason)) = server.elsewhere(&req, &join, room.as_ref()).await {
+        return region::redirect(&req, stream, url, reason);
+    }
+
+    if server.config.require_streamer {
+        let mut state = match room {
+            Some(room) => room.send(GetStreamState).await.ok(),
+            None => None,
+        };
+        // A room here only for watchers streams from another instance's
+        if server.fanout.is_some() && !state.is_some_and(StreamState::watchable) {
+            state = server
+                .presence
+                .find_elsewhere(&room_id)
+                .await
+                .map(|present| present.info.state)
+                .or(state);
+        }
+        // ✅ With a waiting room, the room parks the watcher instead; see `room`
+        if !state.is_some_and(StreamState::watchable) && !server.config.waiting_room {
+            let state = state.map_or("not found", StreamState::name);
+            info!(
+                "❌ Watcher '{}' rejected: stream '{}' is {}",
+                watcher_id, room_id, state
+            );
+            return Ok(reject(
+                &mut HttpResponse::BadRequest(),
+                ErrorCode::StreamNotLive,
+                format!("stream '{}' is {}", room_id, state),
+            ));
+        }
+    }
+
+    // ✅ Turn away joins over the stream's rate, telling them when to come back
+    if let Some(Err(retry_after)) = server.throttle.as_ref().map(|t| t.admit(&room_id)) {
+        let seconds = retry_after.as_secs_f64().ceil() as u64;
+        info!(
+            "⏳ Watcher '{}' throttled joining '{}'; retry in {}s",
+            watcher_id, room_id, seconds
+        );
+        return Ok(reject(
+            HttpResponse::ServiceUnavailable()
+                .insert_header((header::RETRY_AFTER, seconds.to_string())),
+            ErrorCode::Busy,
+            format!(
+                "too many watchers joining '{}'; retry in {}s",
+                room_id, seconds
+            ),
+        ));
+    }
+
+    server.start_member(&req, stream, join).await
+}
+
+// Directory of streams: rooms a streamer has gone live in, until they expire
+async fn list_streams(server: web::Data<SignalingServer>) -> HttpResponse {
+    let rooms = server.rooms.send(ListRooms).await.unwrap_or_default();
+
+    let mut streams = Vec::new();
+    for room in rooms {
+        if let Ok(info) = room.send(GetStreamInfo).await
+            && info.state != StreamState::Registered
+        {
+            streams.push(info);
+        }
+    }
+    // ✅ And those live on other instances, with --presence-redis
+    for present in server.presence.elsewhere().await {
+        if !streams.iter().any(|info| info.id == present.info.id) {
+            streams.push(present.info);
+        }
+    }
+    streams.sort_by(|a, b| a.id.cmp(&b.id));
+
+    HttpResponse::Ok().json(streams)
+}
+
+// Streamers connected to this instance, longest-connected first: who's on,
+// with how many watching, for a channel directory
+async fn list_streamers(server: web::Data<SignalingServer>) -> HttpResponse {
+    let rooms = server.rooms.send(ListRooms).await.unwrap_or_default();
+
+    let mut streamers = Vec::new();
+    for room in rooms {
+        if let Ok(found) = room.send(GetStreamers).await {
+            streamers.extend(found);
+        }
+    }
+    streamers.sort_by(|a, b| b.uptime_secs.cmp(&a.uptime_secs).then(a.id.cmp(&b.id)));
+
+    HttpResponse::Ok().json(streamers)
+}
+
+// ✅ DELETE /api/streamers/{id}: disconnect the streamer (4003), wherever it
+// streams on this instance; its watchers see it leave, as if it had
+async fn disconnect_streamer(
+    req: HttpRequest,
+    streamer_id: web::Path<String>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let mut kicked = false;
+    for room in server.rooms.send(ListRooms).await.unwrap_or_default() {
+        let kick = Kick {
+            member_id: streamer_id.to_string(),
+            role: Role::Streamer,
+        };
+        kicked |= room.send(kick).await.unwrap_or(false);
+    }
+    if !kicked {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            format!("streamer '{}' not found", streamer_id),
+        );
+    }
+    info!("🥾 Streamer '{}' disconnected by an operator", streamer_id);
+    HttpResponse::NoContent().finish()
+}
+
+// GET /admin/keys: every stream key, without the keys themselves
+async fn list_keys(req: HttpRequest, server: web::Data<SignalingServer>) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let Some(keys) = &server.keys else {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            "stream keys are off",
+        );
+    };
+    match keys.list() {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(err) => reject(
+            &mut HttpResponse::InternalServerError(),
+            ErrorCode::Internal,
+            err.to_string(),
+        ),
+    }
+}
+
+// ✅ POST /admin/keys {"name":..,"stream_ids":[..]}: make a stream key; the
+// response is the only place the key itself is ever shown
+async fn create_key(
+    req: HttpRequest,
+    new: web::Json<NewKey>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let Some(keys) = &server.keys else {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            "stream keys are off",
+        );
+    };
+    match keys.create(new.into_inner()) {
+        Ok((key, secret)) => {
+            info!("🔑 Stream key {} made for {}", key.id, key.name);
+            let mut body = serde_json::to_value(&key).unwrap_or_default();
+            body["key"] = secret.into();
+            HttpResponse::Created().json(body)
+        }
+        Err(err) if err.kind() == io::ErrorKind::InvalidInput => reject(
+            &mut HttpResponse::BadRequest(),
+            ErrorCode::InvalidRequest,
+            err.to_string(),
+        ),
+        Err(err) => reject(
+            &mut HttpResponse::InternalServerError(),
+            ErrorCode::Internal,
+            err.to_string(),
+        ),
+    }
+}
+
+// ✅ DELETE /admin/keys/{id}: revoke a stream key; streamers connected with it
+// stay connected, but can't reconnect
+async fn revoke_key(
+    req: HttpRequest,
+    key_id: web::Path<String>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let Some(keys) = &server.keys else {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            "stream keys are off",
+        );
+    };
+    match keys.revoke(&key_id) {
+        Ok(Some(key)) => {
+            info!("🔑 Stream key {} of {} revoked", key.id, key.name);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            format!("stream key '{}' not found", key_id),
+        ),
+        Err(err) => reject(
+            &mut HttpResponse::InternalServerError(),
+            ErrorCode::Internal,
+            err.to_string(),
+        ),
+    }
+}
+
+// ✅ DELETE /api/streamers/{id}/watchers/{watcher_id}: disconnect one of the
+// streamer's watchers (4003), which doesn't reconnect on its own
+async fn kick_watcher(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let (streamer_id, watcher_id) = path.into_inner();
+    // The rooms it streams into, or its own while it's away
+    let mut rooms = Vec::new();
+    for room in server.rooms.send(ListRooms).await.unwrap_or_default() {
+        if let Ok(streamers) = room.send(GetStreamers).await
+            && streamers.iter().any(|streamer| streamer.id == streamer_id)
+        {
+            rooms.push(room);
+        }
+    }
+    if rooms.is_empty() {
+        rooms.extend(server.room(&streamer_id).await);
+    }
+    let mut kicked = false;
+    for room in rooms {
+        let kick = Kick {
+            member_id: watcher_id.clone(),
+            role: Role::Watcher,
+        };
+        kicked |= room.send(kick).await.unwrap_or(false);
+    }
+    if !kicked {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            format!("watcher '{}' of '{}' not found", watcher_id, streamer_id),
+        );
+    }
+    info!(
+        "🥾 Watcher '{}' of '{}' disconnected by an operator",
+        watcher_id, streamer_id
+    );
+    HttpResponse::NoContent().finish()
+}
+
+// One room's directory entry, whatever its state; 404 once it has expired
+async fn get_stream(
+    stream_id: web::Path<String>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    match server.room(&stream_id).await {
+        Some(room) => match room.send(GetStreamInfo).await {
+            Ok(info) => HttpResponse::Ok().json(info),
+            Err(_) => stream_not_found(&stream_id),
+        },
+        None => match server.presence.find_elsewhere(&stream_id).await {
+            Some(present) => HttpResponse::Ok().json(present.info),
+            None => stream_not_found(&stream_id),
+        },
+    }
+}
+
+fn stream_not_found(stream_id: &str) -> HttpResponse {
+    reject(
+        &mut HttpResponse::NotFound(),
+        ErrorCode::NotFound,
+        format!("stream '{}' not found", stream_id),
+    )
+}
+
+// GET /streams/{id}/archive?format=ndjson|json: the stream's chat and lifecycle
+// events; unauthenticated, like the directory
+async fn export_archive(
+    req: HttpRequest,
+    stream_id: web::Path<String>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    let Some(archive) = &server.archive else {
+        return reject(
+            &mut HttpResponse::NotFound(),
+            ErrorCode::NotFound,
+            "archives are off",
+        );
+    };
+    let format = query_params(&req).remove("format");
+    let ndjson = match format.as_deref() {
+        None | Some("ndjson") => true,
+        Some("json") => false,
+        Some(other) => {
+            return reject(
+                &mut HttpResponse::BadRequest(),
+                ErrorCode::InvalidRequest,
+                format!("unknown format '{}'; use ndjson or json", other),
+            );
+        }
+    };
+    let records = match archive.read(&stream_id) {
+        Ok(records) if records.is_empty() => {
+            return reject(
+                &mut HttpResponse::NotFound(),
+                ErrorCode::NotFound,
+                format!("no archive for stream '{}'", stream_id),
+            );
+        }
+        Ok(records) => records,
+        Err(err) => {
+            info!(
+                "⚠️ Cannot read the archive of stream '{}': {}",
+                stream_id, err
+            );
+            return reject(
+                &mut HttpResponse::InternalServerError(),
+                ErrorCode::Internal,
+                "cannot read the archive",
+            );
+        }
+    };
+    if !ndjson {
+        return HttpResponse::Ok().json(records);
+    }
+    let body: String = records
+        .iter()
+        .filter_map(|record| serde_json::to_string(record).ok())
+        .map(|line| line + "\n")
+        .collect();
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .body(body)
+}
+
+// Every connection and the client behind it, by room
+async fn list_clients(server: web::Data<SignalingServer>) -> HttpResponse {
+    let rooms = server.rooms.send(ListRooms).await.unwrap_or_default();
+
+    let mut clients = Vec::new();
+    for room in rooms {
+        if let Ok(members) = room.send(GetClients).await {
+            clients.extend(members);
+        }
+    }
+    clients.sort_by(|a, b| (&a.room_id, &a.member_id).cmp(&(&b.room_id, &b.member_id)));
+
+    HttpResponse::Ok().json(clients)
+}
+
+// WebSocket handler for agents: streamer processes waiting to be told what to capture
+async fn agent_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<SignalingServer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = query_params(&req);
+
+    let agent_id = match required_param(&params, "id") {
+        Ok(id) => id,
+        Err(response) => return Ok(response),
+    };
+
+    let join = Join {
+        role: Role::Agent,
+        room_id: &agent_id,
+        member_id: &agent_id,
+        audience_role: None,
+    };
+    if let Err(response) = server
+        .redirect(&req)
+        .and_then(|()| server.authenticate(&req, &join))
+        .and_then(|()| server.authorize(&req, &join))
+    {
+        return Ok(response);
+    }
+
+    deflate::start(
+        AgentWebSocket {
+            agent_id,
+            agents: server.agents.clone(),
+            heartbeat: server.config.heartbeat,
+            last_seen: Instant::now(),
+        },
+        &req,
+        stream,
+        server.config.max_frame_bytes,
+        server.config.deflate.contains(&Role::Agent),
+        &[],
+    )
+}
+
+// Echo pings with this server's timestamps; see `latency`
+async fn latency_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    server: web::Data<SignalingServer>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Never compressed: pings are tiny, and what's measured is the plain round trip
+    deflate::start(
+        LatencyWebSocket {
+            heartbeat: server.config.heartbeat,
+            last_seen: Instant::now(),
+        },
+        &req,
+        stream,
+        server.config.max_frame_bytes,
+        false,
+        &[],
+    )
+}
+
+// Connected agents and what they're streaming
+async fn list_agents(server: web::Data<SignalingServer>) -> HttpResponse {
+    let mut agents: Vec<AgentInfo> = lock_agents(&server.agents)
+        .iter()
+        .map(|(id, agent)| agent.info(id))
+        .collect();
+    agents.sort_by(|a, b| a.id.cmp(&b.id));
+
+    HttpResponse::Ok().json(agents)
+}
+
+fn agent_not_found(agent_id: &str) -> HttpResponse {
+    reject(
+        &mut HttpResponse::NotFound(),
+        ErrorCode::NotFound,
+        format!("agent '{}' not found", agent_id),
+    )
+}
+
+// POST /agents/{id}/start with a `StartStream` body
+async fn start_agent_stream(
+    agent_id: web::Path<String>,
+    start: web::Json<StartStream>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    match server.command_agent(&agent_id, AgentCommand::Start(start.into_inner())) {
+        Some(()) => HttpResponse::Accepted().finish(),
+        None => agent_not_found(&agent_id),
+    }
+}
+
+// POST /agents/{id}/stop with a `StopStream` body
+async fn stop_agent_stream(
+    agent_id: web::Path<String>,
+    stop: web::Json<StopStream>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    match server.command_agent(&agent_id, AgentCommand::Stop(stop.into_inner())) {
+        Some(()) => HttpResponse::Accepted().finish(),
+        None => agent_not_found(&agent_id),
+    }
+}
+
+// GET /metrics, for Prometheus to scrape
+async fn metrics(server: web::Data<SignalingServer>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(server.metrics.render())
+}
+
+// POST /admin/drain body
+#[derive(Deserialize)]
+struct DrainRequest {
+    // The new instance's base URL, e.g. ws://green:8080
+    migrate_to: String,
+}
+
+async fn drain_status(req: HttpRequest, server: web::Data<SignalingServer>) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    HttpResponse::Ok().json(server.drain.status())
+}
+
+// ✅ Start draining: turn new connections away and tell every room where to go
+async fn start_draining(
+    req: HttpRequest,
+    request: web::Json<DrainRequest>,
+    server: web::Data<SignalingServer>,
+) -> HttpResponse {
+    if let Err(response) = server.authenticate_admin(&req) {
+        return response;
+    }
+    let url = request.into_inner().migrate_to;
+    if !(url.starts_with("ws://") || url.starts_with("wss://")) {
+        return reject(
+            &mut HttpResponse::BadRequest(),
+            ErrorCode::InvalidRequest,
+            "'migrate_to' must be a ws:// or wss:// URL",
+        );
+    }
+    server.drain.start(url.clone());
+    for room in server.rooms.send(ListRooms).await.unwrap_or_default() {
+        room.do_send(Migrate { url: url.clone() });
+    }
+    HttpResponse::Accepted().json(server.drain.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn watcher(audience_role: AudienceRole) -> Join<'static> {
+        Join {
+            role: Role::Watcher,
+            room_id: "cam1",
+            member_id: "w1",
+            audience_role: Some(audience_role),
+        }
+    }
+
+    #[actix_web::test]
+    async fn watchers_cannot_make_themselves_more_than_viewers() {
+        let server = SignalingServer::new();
+        let req = TestRequest::with_uri("/watcher?role=cohost").to_http_request();
+        for asked in [
+            AudienceRole::Viewer,
+            AudienceRole::Moderator,
+            AudienceRole::Cohost,
+        ] {
+            assert_eq!(server.grant(&req, &watcher(asked)), AudienceRole::Viewer);
+        }
+    }
+
+    #[actix_web::test]
+    async fn a_token_vouches_for_the_role_asked_for() {
+        // Held to the token's `audience_role` by `authenticate`; see `jwt`
+        let server = SignalingServer::new().with_jwt(JwtAuth::hmac(b"secret"));
+        let req = TestRequest::with_uri("/watcher?role=moderator").to_http_request();
+        let join = watcher(AudienceRole::Moderator);
+        assert_eq!(server.grant(&req, &join), AudienceRole::Moderator);
+    }
+
+    #[actix_web::test]
+    async fn a_stream_key_vouches_for_the_role_asked_for() {
+        let path =
+            std::env::temp_dir().join(format!("tuesdays-grant-{}.json", uuid::Uuid::new_v4()));
+        let keys = KeyStore::open(&path).unwrap();
+        let (_, secret) = keys
+            .create(NewKey {
+                name: "Cam".to_string(),
+                stream_ids: vec!["cam1".to_string()],
+                max_messages_per_sec: None,
+                max_bytes_per_sec: None,
+            })
+            .unwrap();
+        let server = SignalingServer::new().with_stream_keys(keys);
+        let join = watcher(AudienceRole::Cohost);
+
+        let req = TestRequest::with_uri(&format!("/watcher?role=cohost&key={}", secret))
+            .to_http_request();
+        assert_eq!(server.grant(&req, &join), AudienceRole::Cohost);
+        let req = TestRequest::with_uri("/watcher?role=cohost&key=tk_wrong").to_http_request();
+        assert_eq!(server.grant(&req, &join), AudienceRole::Viewer);
+        let other = Join {
+            room_id: "cam2",
+            ..watcher(AudienceRole::Cohost)
+        };
+        let req = TestRequest::with_uri(&format!("/watcher?role=cohost&key={}", secret))
+            .to_http_request();
+        assert_eq!(server.grant(&req, &other), AudienceRole::Viewer);
+        std::fs::remove_file(path).ok();
+    }
+}
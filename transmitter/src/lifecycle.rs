@@ -0,0 +1,49 @@
+// A room's place in the stream lifecycle (see `tuesdays_protocol::lifecycle`).
+// Streamers coming and going move it between registered, live and paused; two
+// timers take it the rest of the way:
+//
+//   paused for PAUSE_GRACE, no streamer back    → ended
+//   ended for ENDED_RETENTION                   → expired: members are closed
+//                                                 and the room goes once empty
+
+use std::time::{Duration, Instant};
+
+use tuesdays_protocol::{Signal, StreamState};
+
+pub(crate) const PAUSE_GRACE: Duration = Duration::from_secs(30);
+pub(crate) const ENDED_RETENTION: Duration = Duration::from_secs(60);
+
+pub(crate) struct Lifecycle {
+    state: StreamState,
+    since: Instant,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle {
+            state: StreamState::Registered,
+            since: Instant::now(),
+        }
+    }
+}
+
+impl Lifecycle {
+    pub fn state(&self) -> StreamState {
+        self.state
+    }
+
+    // ✅ Move to `next` if the lifecycle allows it; the `stream` signal if it moved
+    pub fn enter(&mut self, next: StreamState) -> Option<Signal> {
+        if !self.state.can_become(next) {
+            return None;
+        }
+        self.state = next;
+        self.since = Instant::now();
+        Some(Signal::Stream { state: next })
+    }
+
+    // Still in `state`, and for at least `long`: a timer set on entering it is due
+    pub fn lasted(&self, state: StreamState, long: Duration) -> bool {
+        self.state == state && self.since.elapsed() >= long
+    }
+}
@@ -0,0 +1,210 @@
+// Per-command limits, by role, from the --policy file: how often a command may
+// be sent and how large it may be, e.g. chat often but small, offers large but
+// rarely:
+//
+//   [limits.watcher.chat]
+//   per_sec = 5
+//   max_bytes = 1024
+//
+//   [limits.streamer.offer]
+//   per_min = 10
+//   max_bytes = 102400
+//
+// Unlike the connection's quotas (see `quota`), going over one only costs the
+// command: it's answered `{"error":{"code":"limit_exceeded",...}}` and
+// dropped, and the connection stays. Rates are counted over fixed windows of a
+// second and a minute.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tuesdays_protocol::{ErrorCode, Rejection};
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+    pub per_sec: Option<u32>,
+    pub per_min: Option<u32>,
+    pub max_bytes: Option<usize>,
+}
+
+// One command's sends in the current windows
+struct Window {
+    started: Instant,
+    sent: u32,
+}
+
+impl Window {
+    fn new() -> Self {
+        Window {
+            started: Instant::now(),
+            sent: 0,
+        }
+    }
+
+    // ✅ Count one send; the number sent in this window, starting a new one if
+    // `length` is up
+    fn count(&mut self, length: Duration) -> u32 {
+        if self.started.elapsed() >= length {
+            *self = Window::new();
+        }
+        self.sent += 1;
+        self.sent
+    }
+}
+
+// One connection's sends of each limited command
+#[derive(Default)]
+pub(crate) struct LimitMeter {
+    seconds: HashMap<&'static str, Window>,
+    minutes: HashMap<&'static str, Window>,
+}
+
+impl LimitMeter {
+    // ✅ Count one `command` of `len` bytes against its limit; the rejection if
+    // it's too large or too frequent
+    pub fn check(
+        &mut self,
+        command: &'static str,
+        limit: &Limit,
+        len: usize,
+    ) -> Result<(), Rejection> {
+        if let Some(max_bytes) = limit.max_bytes
+            && len > max_bytes
+        {
+            return Err(Rejection::new(
+                ErrorCode::LimitExceeded,
+                format!(
+                    "'{}' is {} bytes, over the limit of {}",
+                    command, len, max_bytes
+                ),
+            ));
+        }
+        for (windows, length, per) in [
+            (&mut self.seconds, SECOND, limit.per_sec),
+            (&mut self.minutes, MINUTE, limit.per_min),
+        ] {
+            let Some(per) = per else {
+                continue;
+            };
+            let sent = windows
+                .entry(command)
+                .or_insert_with(Window::new)
+                .count(length);
+            if sent > per {
+                return Err(Rejection::new(
+                    ErrorCode::LimitExceeded,
+                    format!(
+                        "'{}' sent {} times in {}s, over the limit of {}",
+                        command,
+                        sent,
+                        length.as_secs(),
+                        per
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(per_sec: Option<u32>, per_min: Option<u32>, max_bytes: Option<usize>) -> Limit {
+        Limit {
+            per_sec,
+            per_min,
+            max_bytes,
+        }
+    }
+
+    fn detail(result: Result<(), Rejection>) -> String {
+        let rejection = result.unwrap_err();
+        assert_eq!(rejection.code, ErrorCode::LimitExceeded);
+        rejection.detail.unwrap()
+    }
+
+    // As if the command's current windows had run out
+    fn roll_over(meter: &mut LimitMeter, command: &str, length: Duration) {
+        let windows = if length == SECOND {
+            &mut meter.seconds
+        } else {
+            &mut meter.minutes
+        };
+        windows.get_mut(command).unwrap().started = Instant::now().checked_sub(length).unwrap();
+    }
+
+    #[test]
+    fn commands_up_to_max_bytes_pass() {
+        let mut meter = LimitMeter::default();
+        let limit = limit(None, None, Some(1024));
+        assert!(meter.check("chat", &limit, 1024).is_ok());
+        assert_eq!(
+            detail(meter.check("chat", &limit, 1025)),
+            "'chat' is 1025 bytes, over the limit of 1024"
+        );
+    }
+
+    #[test]
+    fn per_sec_allows_that_many_a_second() {
+        let mut meter = LimitMeter::default();
+        let limit = limit(Some(3), None, None);
+        for _ in 0..3 {
+            assert!(meter.check("chat", &limit, 10).is_ok());
+        }
+        assert_eq!(
+            detail(meter.check("chat", &limit, 10)),
+            "'chat' sent 4 times in 1s, over the limit of 3"
+        );
+        roll_over(&mut meter, "chat", SECOND);
+        assert!(meter.check("chat", &limit, 10).is_ok());
+    }
+
+    #[test]
+    fn per_min_allows_that_many_a_minute() {
+        let mut meter = LimitMeter::default();
+        let limit = limit(Some(100), Some(2), None);
+        assert!(meter.check("offer", &limit, 10).is_ok());
+        assert!(meter.check("offer", &limit, 10).is_ok());
+        assert_eq!(
+            detail(meter.check("offer", &limit, 10)),
+            "'offer' sent 3 times in 60s, over the limit of 2"
+        );
+        // A new second doesn't help; a new minute does
+        roll_over(&mut meter, "offer", SECOND);
+        assert!(meter.check("offer", &limit, 10).is_err());
+        roll_over(&mut meter, "offer", MINUTE);
+        assert!(meter.check("offer", &limit, 10).is_ok());
+    }
+
+    #[test]
+    fn oversized_commands_dont_count_against_the_rate() {
+        let mut meter = LimitMeter::default();
+        let limit = limit(Some(1), None, Some(10));
+        assert!(meter.check("chat", &limit, 11).is_err());
+        assert!(meter.check("chat", &limit, 10).is_ok());
+    }
+
+    #[test]
+    fn each_command_has_windows_of_its_own() {
+        let mut meter = LimitMeter::default();
+        let limit = limit(Some(1), None, None);
+        assert!(meter.check("chat", &limit, 1).is_ok());
+        assert!(meter.check("chat", &limit, 1).is_err());
+        assert!(meter.check("stats", &limit, 1).is_ok());
+    }
+
+    #[test]
+    fn no_limits_allow_anything() {
+        let mut meter = LimitMeter::default();
+        for _ in 0..1000 {
+            assert!(meter.check("chat", &Limit::default(), 1 << 20).is_ok());
+        }
+    }
+}
@@ -1,314 +1,818 @@
-use actix::ActorFutureExt;
-use actix::ContextFutureSpawner;
-use actix::{Actor, Addr, AsyncContext, Handler, Message, StreamHandler, WrapFuture};
-use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
-use actix_web_actors::ws;
-use log::info;
-use once_cell::sync::Lazy;
-use serde_json::Value;
-use serde_urlencoded;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-
-// Global shared store for rooms
-
-static ROOMS: Lazy<Arc<Mutex<HashMap<String, Addr<RoomActor>>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
-
-// Actix messages for managing members
-#[derive(Message)]
-#[rtype(result = "()")]
-struct AddMember {
-    member_id: String,
-    addr: Addr<MemberWebSocket>,
+use std::fs::OpenOptions;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{App, HttpServer};
+use clap::{Parser, Subcommand};
+use log::{LevelFilter, info};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+#[cfg(feature = "chaos")]
+use transmitter::Chaos;
+use transmitter::tls::{self, Domain, RedirectToTls, TlsFront};
+use transmitter::{
+    AccessLog, AccessLogFormat, AdminToken, Archive, Capture, DEFAULT_MAX_SDP_BYTES,
+    DuplicateStreamer, Fanout, Heartbeat, JwtAuth, KeyStore, NewKey, Origins, Policy, Presence,
+    Quotas, RedisStore, Regions, Role, Sanitizer, SignalingConfig, SignalingServer, Transport,
+};
+use tuesdays_config::{Config, exit};
+use tuesdays_protocol::PROTOCOL_VERSION;
+
+// Smaller frames couldn't carry an SDP offer
+const MIN_FRAME_BYTES: usize = 16 * 1024;
+
+#[derive(Parser, Serialize, Deserialize, Debug)]
+#[command(about = "WebSocket signaling server for Tuesdays streamers and watchers")]
+#[serde(deny_unknown_fields)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+
+    /// Listen on this port instead of --bind's, keeping its address (e.g. a
+    /// platform's assigned port: TUESDAYS_TRANSMITTER_PORT=$PORT)
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Worker threads serving connections (default: one per CPU core)
+    #[arg(long, value_name = "N")]
+    workers: Option<usize>,
+
+    /// What to log, in RUST_LOG's syntax (e.g. "info" or "warn,transmitter=debug");
+    /// replaces RUST_LOG when given
+    #[arg(long, value_name = "FILTER")]
+    log_level: Option<String>,
+
+    /// Turn away new members (503) while this many are connected to rooms
+    #[arg(long, value_name = "N")]
+    max_connections: Option<usize>,
+
+    /// Largest WebSocket frame accepted from a client, in bytes; bigger ones
+    /// close the connection
+    #[arg(long, value_name = "BYTES", default_value_t = 65_536)]
+    max_frame_bytes: usize,
+
+    /// Show a live status console on stdout: connections, message rates, streams
+    /// and recent errors (the log still goes to stderr; redirect it)
+    #[arg(long)]
+    tui: bool,
+
+    /// Also listen on this address with TLS (wss:// and https://), using
+    /// --tls-cert and --tls-key
+    #[arg(long, value_name = "ADDR", requires_all = ["tls_cert", "tls_key"])]
+    tls_bind: Option<SocketAddr>,
+
+    /// PEM certificate chain presented on --tls-bind
+    #[arg(long, value_name = "PATH")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[arg(long, value_name = "PATH")]
+    tls_key: Option<PathBuf>,
+
+    /// Present another certificate to clients asking for this domain (SNI), as
+    /// NAME=CERT,KEY; repeat for each domain
+    #[arg(long, value_name = "NAME=CERT,KEY")]
+    tls_domain: Vec<String>,
+
+    /// Answer plain connections to --bind with a redirect to --tls-bind
+    #[arg(long, requires = "tls_bind")]
+    redirect_to_tls: bool,
+
+    /// Let watchers join a streamer's room before the streamer is connected
+    #[arg(long)]
+    open_rooms: bool,
+
+    /// Accept watchers of a stream that isn't live yet and hold them until its
+    /// streamer connects, instead of rejecting them
+    #[arg(long, conflicts_with = "open_rooms")]
+    waiting_room: bool,
+
+    /// When a streamer connects with the id of one that's still connected:
+    /// reject it, let it take over (the old one is closed, its watchers stay), or
+    /// suffix its id ("<id>-2") so both stream
+    #[arg(long, value_enum, default_value = "takeover")]
+    duplicate_streamer: DuplicateStreamer,
+
+    /// Don't serve the GET /streams and GET /api/streamers directories
+    #[arg(long)]
+    no_directory: bool,
+
+    /// Accept streamer agents on /agent and serve the unauthenticated /agents
+    /// control API for starting and stopping their streams
+    #[arg(long)]
+    agents: bool,
+
+    /// Turn watchers' stats reports (watcher --report-stats) into bitrate
+    /// recommendations the streamer's encoder follows
+    #[arg(long)]
+    recommend_quality: bool,
+
+    /// Serve the unauthenticated GET /clients API: every connection's user agent
+    /// and announced client version
+    #[arg(long)]
+    clients_api: bool,
+
+    /// Serve unauthenticated Prometheus metrics on GET /metrics: members by
+    /// role, disconnects by reason, relayed messages, broadcast fan-out sizes
+    /// and WebSocket errors
+    #[arg(long)]
+    metrics: bool,
+
+    /// Serve the /admin API, to drain the instance before a blue/green deploy:
+    /// POST /admin/drain {"migrate_to":"ws://green:8080"}; unauthenticated
+    /// without --admin-token
+    #[arg(long)]
+    admin_api: bool,
+
+    /// Require this bearer token on the admin API, and serve its moderation
+    /// routes: DELETE /api/streamers/{id} disconnects a streamer, and
+    /// DELETE /api/streamers/{id}/watchers/{watcher_id} one of its watchers,
+    /// and, with --stream-keys, /admin/keys manages stream keys; best kept out of the configuration file with `{ env = "..." }`
+    #[arg(long, value_name = "TOKEN", requires = "admin_api")]
+    admin_token: Option<String>,
+
+    /// Ping every connection this often (0: never), dropping those that stop
+    /// answering so dead TCP connections don't linger as room members
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    heartbeat_interval_secs: u64,
+
+    /// Drop a connection that hasn't sent anything, pongs included, for this long
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    heartbeat_timeout_secs: u64,
+
+    /// Keep a streamer's session, or a watcher's place, this long after its
+    /// connection drops, so it can reconnect and resume without renegotiating
+    #[arg(long, value_name = "SECS")]
+    resume_grace_secs: Option<u64>,
+
+    /// Replay each streamer's last N broadcasts (offers, answers and candidates
+    /// aside) to every watcher that joins, so it has the stream's context at once
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    replay_broadcasts: usize,
+
+    /// Hold broadcasts that aren't signaling (telemetry, overlays) up to MS, and
+    /// send each member whatever came meanwhile as one `batch` frame
+    #[arg(long, value_name = "MS")]
+    batch_window_ms: Option<u64>,
+
+    /// Compress WebSocket messages (permessage-deflate, for clients offering it)
+    /// on these routes: streamer, watcher, member (/room), agent (comma-separated)
+    #[arg(long, value_enum, value_name = "ROLES", value_delimiter = ',')]
+    deflate: Vec<Role>,
+
+    /// Let a connection send at most this many messages per second (averaged
+    /// over a few seconds); it's warned at 80%, and disconnected past 100%
+    #[arg(long, value_name = "N")]
+    max_messages_per_sec: Option<u64>,
+
+    /// Let a connection send at most this many bytes per second, likewise
+    #[arg(long, value_name = "N")]
+    max_bytes_per_sec: Option<u64>,
+
+    /// Let at most this many watchers join one stream per second; the rest are
+    /// told when to retry, and rejoins after a streamer restart are staggered
+    #[arg(long, value_name = "N")]
+    watcher_join_rate: Option<u32>,
+
+    /// Require a JWT signed (HS256) with this secret on every connection, whose
+    /// claims name the role and stream it may join; best kept out of the
+    /// configuration file with `{ env = "..." }`
+    #[arg(long, value_name = "SECRET", conflicts_with = "jwt_public_key")]
+    jwt_secret: Option<String>,
+
+    /// Require a JWT signed (RS256) with the private half of this PEM public key
+    /// on every connection
+    #[arg(long, value_name = "PATH")]
+    jwt_public_key: Option<PathBuf>,
+
+    /// Only let web pages from this origin (https://app.example.com) open
+    /// signaling connections; `https://*.example.com` allows its subdomains, and
+    /// `*` any origin, for development. Repeat for each. Clients that send no
+    /// Origin, like the streamer and watcher, are let in as before
+    #[arg(long, value_name = "ORIGIN")]
+    allowed_origin: Vec<String>,
+
+    /// Require a stream key (`?key=...`) of every streamer, from this JSON file;
+    /// make and revoke them with the `keys` command, or on /admin/keys with
+    /// --admin-token. Watchers need none
+    #[arg(long, value_name = "PATH")]
+    stream_keys: Option<PathBuf>,
+
+    /// Write one record per finished watcher session to this file
+    #[arg(long, value_name = "PATH")]
+    access_log: Option<PathBuf>,
+
+    /// Access log format: combined (Common Log Format plus referrer and user
+    /// agent) or json (JSON Lines, with duration and disconnect reason)
+    #[arg(long, value_enum, default_value = "combined")]
+    access_log_format: AccessLogFormat,
+
+    /// Rotate the access log when it reaches this many megabytes (0: never)
+    #[arg(long, default_value_t = 100)]
+    access_log_max_mb: u64,
+
+    /// Rotated access logs to keep
+    #[arg(long, default_value_t = 5)]
+    access_log_keep: usize,
+
+    /// Keep every stream's chat and lifecycle events in this directory, and serve
+    /// them (unauthenticated) on GET /streams/{id}/archive
+    #[arg(long, value_name = "DIR")]
+    archive_dir: Option<PathBuf>,
+
+    /// TOML file with how long archives are kept: `default_hours`, and hours by
+    /// tenant (stream ids "<tenant>/...") under [tenants]; 30 days without one
+    #[arg(long, value_name = "PATH", requires = "archive_dir")]
+    archive_retention: Option<PathBuf>,
+
+    /// Share which streams are live with the other instances behind the same
+    /// load balancer through this Redis (redis://[[user]:password@]host[:port][/db]),
+    /// best kept out of the configuration file with `{ env = "..." }`
+    #[arg(long, value_name = "URL")]
+    presence_redis: Option<String>,
+
+    /// This instance's own WebSocket URL (ws://tx-a:8080), where other instances
+    /// send watchers and streamers of its streams
+    #[arg(long, value_name = "URL", requires = "presence_redis")]
+    advertise_url: Option<String>,
+
+    /// Pass rooms' broadcasts and relays between instances through the
+    /// --presence-redis Redis, so watchers of another instance's stream join it
+    /// here instead of being redirected
+    #[arg(long, requires = "presence_redis")]
+    fanout: bool,
+
+    /// This instance's region (eu, us-east...), for sending streamers, and with
+    /// --fanout watchers, from other regions to instances of their own
+    #[arg(long, value_name = "NAME")]
+    region: Option<String>,
+
+    /// Where members from another region connect instead, as REGION=URL
+    /// (us=wss://us.example.com); repeat for each region
+    #[arg(long, value_name = "REGION=URL", requires = "region")]
+    region_url: Vec<String>,
+
+    /// Take a member's region from this header, set by the load balancer,
+    /// when it doesn't give one with `?region=`
+    #[arg(long, value_name = "NAME", requires = "region")]
+    region_header: Option<String>,
+
+    /// Capture every frame of each stream session's signaling to a JSON Lines
+    /// file in this directory, for replaying failed handshakes (holds members'
+    /// IP addresses; enable only to debug)
+    #[arg(long, value_name = "DIR")]
+    capture_dir: Option<PathBuf>,
+
+    /// TOML file listing the commands each role (member, streamer, watcher,
+    /// agent, moderator, admin) may send, and how often and how large each may
+    /// be; unlisted roles may send anything
+    #[arg(long, value_name = "PATH")]
+    policy: Option<PathBuf>,
+
+    /// Relay only TURN relay candidates, in trickled candidates and SDP alike
+    #[arg(long)]
+    relay_only: bool,
+
+    /// Don't relay candidates with private, loopback, link-local or mDNS addresses
+    #[arg(long)]
+    strip_private: bool,
+
+    /// Relay only candidates over these transports (comma-separated)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    transports: Vec<Transport>,
+
+    /// Relay offers and answers whose SDP is malformed (a media section's codec
+    /// lines not matching its payload types, say), instead of refusing them
+    #[arg(long)]
+    no_validate_sdp: bool,
+
+    /// Largest SDP relayed in an offer or answer, in bytes (0 for any size)
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_SDP_BYTES)]
+    max_sdp_bytes: usize,
+
+    /// Chaos: hold each relayed message back up to this long (reorders them)
+    #[cfg(feature = "chaos")]
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    chaos_delay_ms: u64,
+
+    /// Chaos: probability (0 to 1) of dropping each relayed message
+    #[cfg(feature = "chaos")]
+    #[arg(long, value_name = "P", default_value_t = 0.0)]
+    chaos_drop: f64,
+
+    /// Chaos: probability (0 to 1) of delivering a relayed message twice
+    #[cfg(feature = "chaos")]
+    #[arg(long, value_name = "P", default_value_t = 0.0)]
+    chaos_duplicate: f64,
+
+    /// Chaos: probability (0 to 1), per relayed message, of dropping the
+    /// receiving member's connection
+    #[cfg(feature = "chaos")]
+    #[arg(long, value_name = "P", default_value_t = 0.0)]
+    chaos_disconnect: f64,
+
+    // Command line only; not part of the configuration file
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
 }
 
-#[derive(Message)]
-#[rtype(result = "()")]
-struct RemoveMember {
-    member_id: String,
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Make, list or revoke the --stream-keys store's keys, then exit; a running
+    /// server picks the changes up
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
 }
 
-#[derive(Message)]
-#[rtype(result = "()")]
-struct BroadcastMessage {
-    message: String,
+#[derive(Subcommand, Debug, Clone)]
+enum KeysAction {
+    /// Make a key and print it; it's never shown again
+    Create {
+        /// Display name of the streamer it's for
+        #[arg(long)]
+        name: String,
+
+        /// Stream id it allows ("alice"), ids under a prefix ("alice/*"), or any ("*");
+        /// repeat for more
+        #[arg(long = "stream", value_name = "ID", required = true)]
+        stream_ids: Vec<String>,
+
+        /// Its own --max-messages-per-sec, instead of the server's
+        #[arg(long, value_name = "N")]
+        max_messages_per_sec: Option<u64>,
+
+        /// Its own --max-bytes-per-sec, instead of the server's
+        #[arg(long, value_name = "N")]
+        max_bytes_per_sec: Option<u64>,
+    },
+    /// List the keys (their ids, not the keys themselves)
+    List,
+    /// Revoke a key by id; streamers connected with it stay connected
+    Revoke { id: String },
 }
 
-// Define a custom message for closing WebSocket connections
-#[derive(Message)]
-#[rtype(result = "()")]
-struct CloseConnection;
-
-// Remove duplicate `GetMembers` struct
-#[derive(Message)]
-#[rtype(result = "Vec<String>")]
-struct GetMembers;
-
-// Room actor to manage members
-#[derive(Clone)]
-struct RoomActor {
-    room_id: String,
-    members: HashMap<String, Addr<MemberWebSocket>>,
-}
-
-impl Actor for RoomActor {
-    type Context = actix::Context<Self>; // Use regular Actix context
+impl Config for Args {
+    const SECTION: &'static str = "transmitter";
 
-    fn started(&mut self, ctx: &mut Self::Context) {
-        let mut store = ROOMS.lock().unwrap();
-        store.insert(self.room_id.clone(), ctx.address()); // Store the actor address
-        info!("📡 Room '{}' created", self.room_id);
+    fn command_line_only(&mut self, cli: Self) {
+        self.command = cli.command;
     }
 
-    fn stopped(&mut self, _: &mut Self::Context) {
-        let mut store = ROOMS.lock().unwrap();
-        store.remove(&self.room_id);
-        info!("❌ Room '{}' removed", self.room_id);
+    fn validate(&self) -> Result<(), String> {
+        if self.port == Some(0) {
+            return Err("--port must be above 0".to_string());
+        }
+        if self.workers == Some(0) {
+            return Err("--workers must be above 0".to_string());
+        }
+        if self.max_connections == Some(0) {
+            return Err("--max-connections must be above 0".to_string());
+        }
+        if self.batch_window_ms == Some(0) {
+            return Err("--batch-window-ms must be above 0".to_string());
+        }
+        if self.max_frame_bytes < MIN_FRAME_BYTES {
+            return Err(format!(
+                "--max-frame-bytes must be at least {}",
+                MIN_FRAME_BYTES
+            ));
+        }
+        // Module names can't be checked, but the levels they're given can
+        if let Some(filter) = &self.log_level {
+            for directive in filter.split(',') {
+                let level = directive.rsplit('=').next().unwrap_or(directive).trim();
+                if directive.contains('=') && LevelFilter::from_str(level).is_err() {
+                    return Err(format!("--log-level: '{}' is not a log level", level));
+                }
+            }
+        }
+        if self.tls_bind.is_some() && (self.tls_cert.is_none() || self.tls_key.is_none()) {
+            return Err("--tls-bind needs --tls-cert and --tls-key".to_string());
+        }
+        if self.tls_bind.is_none() && (self.redirect_to_tls || !self.tls_domain.is_empty()) {
+            return Err("--redirect-to-tls and --tls-domain need --tls-bind".to_string());
+        }
+        if self.heartbeat_interval_secs > 0
+            && self.heartbeat_timeout_secs <= self.heartbeat_interval_secs
+        {
+            return Err("--heartbeat-timeout-secs must be longer than the interval".to_string());
+        }
+        if !self.admin_api && self.admin_token.is_some() {
+            return Err("--admin-token needs --admin-api".to_string());
+        }
+        if let Some(token) = &self.admin_token {
+            AdminToken::new(token).map_err(|err| format!("--admin-token: {}", err))?;
+        }
+        if self.command.is_some() && self.stream_keys.is_none() {
+            return Err("the keys command needs --stream-keys".to_string());
+        }
+        if self.archive_dir.is_none() && self.archive_retention.is_some() {
+            return Err("--archive-retention needs --archive-dir".to_string());
+        }
+        if let Some(url) = &self.presence_redis {
+            RedisStore::new(url).map_err(|err| format!("--presence-redis: {}", err))?;
+        }
+        if self.presence_redis.is_none() && self.advertise_url.is_some() {
+            return Err("--advertise-url needs --presence-redis".to_string());
+        }
+        if self.presence_redis.is_none() && self.fanout {
+            return Err("--fanout needs --presence-redis".to_string());
+        }
+        if let Some(url) = &self.advertise_url
+            && !(url.starts_with("ws://") || url.starts_with("wss://"))
+        {
+            return Err("--advertise-url must be a ws:// or wss:// URL".to_string());
+        }
+        self.tls_domains()?;
+        self.regions()?;
+        self.origins()?;
+        #[cfg(feature = "chaos")]
+        for (flag, p) in [
+            ("--chaos-drop", self.chaos_drop),
+            ("--chaos-duplicate", self.chaos_duplicate),
+            ("--chaos-disconnect", self.chaos_disconnect),
+        ] {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(format!("{} must be between 0 and 1", flag));
+            }
+        }
+        Ok(())
     }
-}
-
-// Implement GetMembers handler in RoomActor
-impl Handler<GetMembers> for RoomActor {
-    type Result = Vec<String>;
 
-    fn handle(&mut self, _: GetMembers, _: &mut Self::Context) -> Self::Result {
-        self.members.keys().cloned().collect()
+    // ✅ The listen addresses must be free (and ours to take), the access log
+    // writable, the policy, JWT key and certificates valid, Redis reachable
+    fn check(&self) -> Result<String, String> {
+        let bind = self.listen_addr();
+        TcpListener::bind(bind).map_err(|err| format!("cannot listen on {}: {}", bind, err))?;
+        if let Some(tls_bind) = self.tls_bind {
+            TcpListener::bind(tls_bind)
+                .map_err(|err| format!("cannot listen on {}: {}", tls_bind, err))?;
+        }
+        if let Some(path) = &self.access_log {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| format!("cannot write {}: {}", path.display(), err))?;
+        }
+        if let Some(dir) = &self.capture_dir {
+            Capture::new(dir)
+                .map_err(|err| format!("cannot capture to {}: {}", dir.display(), err))?;
+        }
+        if let Some(dir) = &self.archive_dir {
+            Archive::new(dir, self.archive_retention.as_deref())
+                .map_err(|err| format!("cannot archive to {}: {}", dir.display(), err))?;
+        }
+        if let Some(path) = &self.stream_keys {
+            KeyStore::open(path)
+                .map_err(|err| format!("invalid stream keys {}: {}", path.display(), err))?;
+        }
+        if let Some(path) = &self.policy {
+            Policy::load(path).map_err(|err| format!("invalid policy: {}", err))?;
+        }
+        if let Some(url) = &self.presence_redis {
+            let store = RedisStore::new(url)?;
+            store
+                .ping()
+                .map_err(|err| format!("cannot reach Redis at {}: {}", store.addr(), err))?;
+        }
+        self.jwt()
+            .map_err(|err| format!("invalid JWT key: {}", err))?;
+        self.tls_front()
+            .map_err(|err| format!("invalid certificate: {}", err))?;
+        Ok(match self.tls_bind {
+            Some(tls_bind) => format!("{} and {} are available", bind, tls_bind),
+            None => format!("{} is available", bind),
+        })
     }
 }
 
-// Handle adding a member
-impl Handler<AddMember> for RoomActor {
-    type Result = ();
-
-    fn handle(&mut self, msg: AddMember, _: &mut Self::Context) {
-        // Check if the member already exists
-        if let Some(existing_addr) = self.members.get(&msg.member_id) {
-            info!(
-                "⚠️ Member '{}' already exists in Room '{}'. Replacing connection.",
-                msg.member_id, self.room_id
-            );
-
-            // Send termination signal using the new CloseConnection message
-            existing_addr.do_send(CloseConnection);
+impl Args {
+    // --bind, on --port if one's given
+    fn listen_addr(&self) -> SocketAddr {
+        let mut bind = self.bind;
+        if let Some(port) = self.port {
+            bind.set_port(port);
         }
+        bind
+    }
 
-        // Replace with the new connection
-        self.members.insert(msg.member_id.clone(), msg.addr);
-        info!(
-            "🙌 Member '{}' added to Room '{}'",
-            msg.member_id, self.room_id
-        );
+    fn jwt(&self) -> std::io::Result<Option<JwtAuth>> {
+        if let Some(secret) = &self.jwt_secret {
+            return Ok(Some(JwtAuth::hmac(secret.as_bytes())));
+        }
+        let Some(path) = &self.jwt_public_key else {
+            return Ok(None);
+        };
+        let pem = std::fs::read_to_string(path)?;
+        JwtAuth::rsa_pem(&pem).map(Some).map_err(|err| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
     }
 }
 
-// Handle removing a member
-impl Handler<RemoveMember> for RoomActor {
-    type Result = ();
-
-    fn handle(&mut self, msg: RemoveMember, _: &mut Self::Context) {
-        self.members.remove(&msg.member_id);
-        info!(
-            "❌ Member '{}' removed from Room '{}'",
-            msg.member_id, self.room_id
-        );
+impl Args {
+    fn tls_domains(&self) -> Result<Vec<Domain>, String> {
+        self.tls_domain
+            .iter()
+            .map(|domain| {
+                domain
+                    .parse()
+                    .map_err(|err| format!("--tls-domain: {}", err))
+            })
+            .collect()
     }
-}
 
-// Handle broadcast messages in RoomActor
-impl Handler<BroadcastMessage> for RoomActor {
-    type Result = ();
+    fn regions(&self) -> Result<Regions, String> {
+        let urls = self
+            .region_url
+            .iter()
+            .map(|url| url.parse().map_err(|err| format!("--region-url: {}", err)))
+            .collect::<Result<_, String>>()?;
+        Ok(Regions::new(
+            self.region.as_deref(),
+            urls,
+            self.region_header.as_deref(),
+        ))
+    }
 
-    fn handle(&mut self, msg: BroadcastMessage, _: &mut Self::Context) {
-        info!("📢 Room '{}' broadcasting: {}", self.room_id, msg.message);
+    fn origins(&self) -> Result<Origins, String> {
+        let allowed = self
+            .allowed_origin
+            .iter()
+            .map(|origin| {
+                origin
+                    .parse()
+                    .map_err(|err| format!("--allowed-origin: {}", err))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Origins::new(allowed))
+    }
 
-        for (_, member_addr) in &self.members {
-            member_addr.do_send(BroadcastMessage {
-                message: msg.message.clone(),
-            });
-        }
+    fn tls_front(&self) -> std::io::Result<Option<TlsFront>> {
+        let (Some(_), Some(cert), Some(key)) = (self.tls_bind, &self.tls_cert, &self.tls_key)
+        else {
+            return Ok(None);
+        };
+        let domains = self.tls_domains().map_err(std::io::Error::other)?;
+        TlsFront::new(cert, key, &domains).map(Some)
     }
 }
 
-// WebSocket Stream Handler for messages
-impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for RoomActor {
-    fn handle(&mut self, _msg: Result<ws::Message, ws::ProtocolError>, _ctx: &mut Self::Context) {
-        // RoomActor should not handle WebSocket messages directly
+#[cfg(feature = "chaos")]
+impl Args {
+    fn chaos(&self) -> Option<Chaos> {
+        let chaos = Chaos {
+            max_delay: Duration::from_millis(self.chaos_delay_ms),
+            drop: self.chaos_drop,
+            duplicate: self.chaos_duplicate,
+            disconnect: self.chaos_disconnect,
+        };
+        let calm = chaos.max_delay.is_zero()
+            && chaos.drop == 0.0
+            && chaos.duplicate == 0.0
+            && chaos.disconnect == 0.0;
+        (!calm).then_some(chaos)
     }
 }
 
-// WebSocket Actor for Members
-struct MemberWebSocket {
-    member_id: String,
-    room_id: String,
+// ✅ `keys create|list|revoke` on the store in `path`
+fn manage_keys(path: &Path, action: KeysAction) -> io::Result<()> {
+    let keys = KeyStore::open(path)?;
+    match action {
+        KeysAction::Create {
+            name,
+            stream_ids,
+            max_messages_per_sec,
+            max_bytes_per_sec,
+        } => {
+            let (key, secret) = keys.create(NewKey {
+                name,
+                stream_ids,
+                max_messages_per_sec,
+                max_bytes_per_sec,
+            })?;
+            println!(
+                "🔑 Stream key {} for {} ({}):",
+                key.id,
+                key.name,
+                key.stream_ids.join(", ")
+            );
+            println!("{}", secret);
+            eprintln!("⚠️ It won't be shown again; the streamer passes it as --stream-key");
+        }
+        KeysAction::List => {
+            let list = keys.list()?;
+            if list.is_empty() {
+                println!("No stream keys in {}", path.display());
+            }
+            for key in list {
+                let created = OffsetDateTime::from_unix_timestamp(key.created_at as i64)
+                    .ok()
+                    .and_then(|at| at.format(&Rfc3339).ok())
+                    .unwrap_or_default();
+                println!(
+                    "{}  {:<20} {:<30} {}",
+                    key.id,
+                    key.name,
+                    key.stream_ids.join(","),
+                    created
+                );
+            }
+        }
+        KeysAction::Revoke { id } => match keys.revoke(&id)? {
+            Some(key) => println!("🗑️ Stream key {} of {} revoked", key.id, key.name),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no stream key '{}'", id),
+                ));
+            }
+        },
+    }
+    Ok(())
 }
 
-impl Actor for MemberWebSocket {
-    type Context = ws::WebsocketContext<Self>;
-
-    fn started(&mut self, ctx: &mut Self::Context) {
-        let store = ROOMS.lock().unwrap();
-        if let Some(room) = store.get(&self.room_id) {
-            let member_addr = ctx.address(); // Get the correct member address
-            room.do_send(AddMember {
-                member_id: self.member_id.clone(),
-                addr: member_addr,
-            });
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args: Args = tuesdays_config::load();
+    if let (Some(Command::Keys { action }), Some(path)) = (args.command.clone(), &args.stream_keys)
+    {
+        if let Err(err) = manage_keys(path, action) {
+            eprintln!("❌ {}", err);
+            std::process::exit(exit::FAILURE);
+        }
+        return Ok(());
+    }
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(filter) = &args.log_level {
+        logger.parse_filters(filter);
+    }
+    logger.init();
+    let bind = args.listen_addr();
+    info!(
+        "🚀 Server is starting at ws://{} (protocol v{})",
+        bind, PROTOCOL_VERSION
+    );
+
+    let mut server = SignalingServer::new().with_config(SignalingConfig {
+        require_streamer: !args.open_rooms,
+        waiting_room: args.waiting_room,
+        directory: !args.no_directory,
+        agents: args.agents,
+        watcher_join_rate: args.watcher_join_rate,
+        clients: args.clients_api,
+        recommend_quality: args.recommend_quality,
+        admin: args.admin_api,
+        heartbeat: (args.heartbeat_interval_secs > 0).then(|| Heartbeat {
+            interval: Duration::from_secs(args.heartbeat_interval_secs),
+            timeout: Duration::from_secs(args.heartbeat_timeout_secs),
+        }),
+        quotas: Quotas {
+            messages_per_sec: args.max_messages_per_sec,
+            bytes_per_sec: args.max_bytes_per_sec,
+        },
+        duplicate_streamer: args.duplicate_streamer,
+        metrics: args.metrics,
+        resume_grace: args.resume_grace_secs.map(Duration::from_secs),
+        replay_broadcasts: args.replay_broadcasts,
+        batch_window: args.batch_window_ms.map(Duration::from_millis),
+        max_connections: args.max_connections,
+        max_frame_bytes: args.max_frame_bytes,
+        deflate: args.deflate.clone(),
+    });
+    if let Some(path) = &args.access_log {
+        let log = AccessLog::open(
+            path,
+            args.access_log_format,
+            args.access_log_max_mb * 1_000_000,
+            args.access_log_keep,
+        )?;
+        info!("📒 Logging watcher sessions to {}", path.display());
+        server = server.with_access_log(log);
+    }
+    if let Some(dir) = &args.capture_dir {
+        server = server.with_capture(Capture::new(dir)?);
+        info!("🎞️ Capturing signaling to {}", dir.display());
+    }
+    if let Some(dir) = &args.archive_dir {
+        let archive = Archive::new(dir, args.archive_retention.as_deref())?;
+        archive.start_purging();
+        server = server.with_archive(archive);
+        info!("🗄️ Archiving chat and stream events to {}", dir.display());
+    }
+    if let Some(url) = &args.presence_redis {
+        let store = RedisStore::new(url).map_err(io::Error::other)?;
+        info!(
+            "📍 Sharing stream presence through Redis at {}",
+            store.addr()
+        );
+        server = server.with_presence(Presence::new(Arc::new(store), args.advertise_url.clone()));
+        if args.fanout {
+            let fanout = Fanout::new(url).map_err(io::Error::other)?;
             info!(
-                "🙌 Member '{}' connected to Room '{}'",
-                self.member_id, self.room_id
+                "🌐 Fanning rooms out to other instances through Redis at {}",
+                fanout.addr()
             );
-            ctx.text(format!(
-                "Connected as Member: {} to Room: {}",
-                self.member_id, self.room_id
-            ));
+            server = server.with_fanout(fanout);
         }
     }
-
-    fn stopped(&mut self, _: &mut Self::Context) {
-        let store = ROOMS.lock().unwrap();
-        if let Some(room) = store.get(&self.room_id) {
-            room.do_send(RemoveMember {
-                member_id: self.member_id.clone(),
-            });
+    if let Some(region) = &args.region {
+        let regions = args.regions().map_err(io::Error::other)?;
+        info!("🧭 Serving region '{}'", region);
+        server = server.with_regions(regions);
+    }
+    if !args.allowed_origin.is_empty() {
+        let origins = args.origins().map_err(io::Error::other)?;
+        if origins.any() {
+            info!("⚠️ Web pages from any origin may connect");
+        } else {
             info!(
-                "❌ Member '{}' disconnected from Room '{}'",
-                self.member_id, self.room_id
+                "🚫 Only web pages from {} may connect",
+                args.allowed_origin.join(", ")
             );
         }
+        server = server.with_origins(origins);
     }
-}
-
-// Implement the handler in MemberWebSocket
-impl Handler<CloseConnection> for MemberWebSocket {
-    type Result = ();
-
-    fn handle(&mut self, _: CloseConnection, ctx: &mut Self::Context) {
-        ctx.close(Some(ws::CloseReason {
-            code: ws::CloseCode::Normal,
-            description: Some("Replaced by new connection".to_string()),
-        }));
+    if let Some(path) = &args.policy {
+        server = server.with_policy(Policy::load(path)?);
+        info!("🔒 Command policy loaded from {}", path.display());
     }
-}
-
-// Implement StreamHandler for MemberWebSocket
-impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MemberWebSocket {
-    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
-        if let Ok(ws::Message::Text(text)) = msg {
-            info!("💬 Member '{}' received message: {}", self.member_id, text);
-
-            match serde_json::from_str::<Value>(&text) {
-                Ok(json) => {
-                    if let Some(command) = json.get("command").and_then(|c| c.as_str()) {
-                        match command {
-                            "list" => {
-                                let store = ROOMS.lock().unwrap();
-                                if let Some(room) = store.get(&self.room_id) {
-                                    let addr = room.clone();
-                                    addr.send(GetMembers)
-                                        .into_actor(self)
-                                        .then(|res, _act, ctx| {
-                                            if let Ok(members) = res {
-                                                let response = serde_json::to_string(&members)
-                                                    .unwrap_or_else(|_| "[]".to_string());
-                                                ctx.text(response);
-                                            }
-                                            actix::fut::ready(())
-                                        })
-                                        .wait(ctx);
-                                }
-                            }
-                            "whois" => {
-                                let response =
-                                    format!(r#"{{ "member_id": "{}" }}"#, self.member_id);
-                                ctx.text(response);
-                            }
-                            "broadcast" => {
-                                if let Some(message) = json.get("message").and_then(|m| m.as_str())
-                                {
-                                    info!(
-                                        "📢 Member '{}' is broadcasting: {}",
-                                        self.member_id, message
-                                    );
-                                    let store = ROOMS.lock().unwrap();
-                                    if let Some(room) = store.get(&self.room_id) {
-                                        room.do_send(BroadcastMessage {
-                                            message: message.to_string(),
-                                        });
-                                    }
-                                }
-                            }
-                            _ => {
-                                ctx.text(r#"{"error": "Unknown command"}"#);
-                            }
-                        }
-                    } else {
-                        ctx.text(r#"{"error": "Invalid command format"}"#);
-                    }
-                }
-                Err(_) => {
-                    ctx.text(r#"{"error": "Invalid JSON"}"#);
-                }
-            }
-        }
+    if let Some(jwt) = args.jwt()? {
+        server = server.with_jwt(jwt);
+        info!("🔑 Connections need a valid JWT");
     }
-}
-
-// Handle broadcast in member
-impl Handler<BroadcastMessage> for MemberWebSocket {
-    type Result = ();
-
-    fn handle(&mut self, msg: BroadcastMessage, ctx: &mut Self::Context) {
-        info!(
-            "📢 Member '{}' received broadcast: {}",
-            self.member_id, msg.message
-        );
-        ctx.text(msg.message);
+    if let Some(path) = &args.stream_keys {
+        server = server.with_stream_keys(KeyStore::open(path)?);
+        info!("🔑 Streamers need a stream key from {}", path.display());
+    }
+    if let Some(token) = &args.admin_token {
+        server = server.with_admin_token(AdminToken::new(token).map_err(io::Error::other)?);
+        info!("🔑 The admin API needs its token; moderation routes are on");
+    }
+    server = server.with_sanitizer(Sanitizer {
+        relay_only: args.relay_only,
+        strip_private: args.strip_private,
+        transports: args.transports.clone(),
+        validate_sdp: !args.no_validate_sdp,
+        max_sdp_bytes: args.max_sdp_bytes,
+    });
+    #[cfg(feature = "chaos")]
+    if let Some(chaos) = args.chaos() {
+        info!("🐒 Chaos is on, relayed messages will suffer: {:?}", chaos);
+        server = server.with_chaos(chaos);
     }
-}
-
-// WebSocket handler for rooms
-async fn room_ws(req: HttpRequest, stream: web::Payload) -> Result<HttpResponse, actix_web::Error> {
-    let query_string = req.query_string();
-    let params: HashMap<String, String> =
-        serde_urlencoded::from_str(query_string).unwrap_or_default();
 
-    let room_id = match params.get("room_id") {
-        Some(id) if !id.is_empty() => id.clone(),
-        _ => {
-            info!("❌ Connection rejected: missing 'room_id' query parameter");
-            return Ok(HttpResponse::BadRequest().body("Missing 'room_id' query parameter"));
-        }
+    let front = args.tls_front()?;
+    if let Some(front) = &front {
+        server = server.with_tls(front);
+    }
+    if args.tui {
+        actix_web::rt::spawn(server.clone().console(bind.to_string()));
+    }
+    let mut http = HttpServer::new(move || {
+        let server = server.clone();
+        App::new().configure(move |cfg| server.configure(cfg))
+    });
+    if let Some(workers) = args.workers {
+        http = http.workers(workers);
+    }
+    let (Some(front), Some(tls_bind)) = (front, args.tls_bind) else {
+        return http.bind(bind)?.run().await;
     };
 
-    let member_id = match params.get("member_id") {
-        Some(id) if !id.is_empty() => id.clone(),
-        _ => {
-            info!("❌ Connection rejected: missing 'member_id' query parameter");
-            return Ok(HttpResponse::BadRequest().body("Missing 'member_id' query parameter"));
-        }
+    // ✅ TLS: the front passes decrypted connections on to the routes over loopback;
+    // --bind serves them too, or only redirects to the front
+    let upstream = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let upstream_addr = upstream.local_addr()?;
+    let mut http = http.listen(upstream)?;
+    let redirect = if args.redirect_to_tls {
+        let to = RedirectToTls(tls_bind.port());
+        info!("↪️ Redirecting plain connections on {} to TLS", bind);
+        let redirect = HttpServer::new(move || {
+            App::new().configure(move |cfg| tls::configure_redirect(cfg, to))
+        });
+        Some(redirect.bind(bind)?.run())
+    } else {
+        http = http.bind(bind)?;
+        None
     };
-
-    // Check if the room exists, if not create it
-    let mut store = ROOMS.lock().unwrap();
-    let _room_actor = store.entry(room_id.clone()).or_insert_with(|| {
-        RoomActor {
-            room_id: room_id.clone(),
-            members: HashMap::new(),
-        }
-        .start() // Now correctly starts as an Actix actor
-    });
-
-    ws::start(MemberWebSocket { member_id, room_id }, &req, stream).map_err(|e| e.into())
-}
-
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    env_logger::init();
-    info!("🚀 Server is starting at ws://127.0.0.1:8080");
-
-    HttpServer::new(move || App::new().route("/room", web::get().to(room_ws)))
-        .bind("127.0.0.1:8080")?
-        .run()
-        .await
+    let tls_listener = TcpListener::bind(tls_bind)?;
+    tokio::select! {
+        result = http.run() => result,
+        result = front.serve(tls_listener, upstream_addr) => result,
+        Some(result) = async { Some(redirect?.await) } => result,
+    }
 }
@@ -0,0 +1,746 @@
+use actix::ActorContext;
+use actix::ActorFutureExt;
+use actix::ContextFutureSpawner;
+use actix::{Actor, Addr, AsyncContext, Handler, StreamHandler, WrapFuture};
+use actix_web_actors::ws;
+use log::info;
+use std::time::Instant;
+use tuesdays_protocol::{
+    ArchiveEvent, AudienceRole, Codec, Command, Deprecation, Encoding, ErrorCode, Pong, Rejection,
+    Signal, WhoisResponse, close, encoding, latency, session,
+};
+
+use crate::access_log::AccessSession;
+use crate::archive::Archive;
+use crate::capture::CaptureSession;
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, Fate};
+use crate::drain::Drain;
+use crate::heartbeat::{self, Heartbeat, Heartbeating};
+use crate::limits::LimitMeter;
+use crate::metrics::{Disconnect, Metrics};
+use crate::policy::{self, Policy};
+use crate::quota::QuotaMeter;
+use crate::room::{
+    AddMember, AssignRole, AudienceChat, BroadcastMessage, CloseConnection, GetMembers, Kicked,
+    MemberBroadcast, Relay, RemoveMember, Resend, Role, RoomActor, SetAudienceRole,
+    SetClientVersion, SetMedia, SetSession, StreamExpired, StreamerLevel, WatcherStats,
+};
+use crate::sanitize::Sanitizer;
+
+// WebSocket Actor for Members
+pub(crate) struct MemberWebSocket {
+    pub member_id: String,
+    pub room_id: String,
+    pub role: Role,
+    // Watchers only: what it may do in the room; see `tuesdays_protocol::roles`
+    pub audience_role: Option<AudienceRole>,
+    // Connected with the --admin-token; an admin to the policy
+    pub admin: bool,
+    // Streamers only: connected with `media=audio`
+    pub audio_only: bool,
+    // Streamers only: connected with `chat=off`, turning audience chat off
+    pub no_chat: bool,
+    // The upgrade request's User-Agent header
+    pub user_agent: Option<String>,
+    // Watchers only, with --waiting-room: parked by the room until the stream starts
+    pub waiting: bool,
+    // Streamers and watchers: the token it's resuming with, and the last message it
+    // saw; see `tuesdays_protocol::resume`
+    pub resume: Option<String>,
+    pub last_seq: Option<u64>,
+    pub room: Addr<RoomActor>,
+    // The room's session, once the room has told us
+    pub session_id: Option<String>,
+    // Watchers' connections, for the access log
+    pub access: Option<AccessSession>,
+    // Every frame in and out, with --capture-dir
+    pub capture: Option<CaptureSession>,
+    // Chat, with --archive-dir
+    pub archive: Option<Archive>,
+    pub heartbeat: Option<Heartbeat>,
+    // The last frame from the client; see `heartbeat`
+    pub last_seen: Instant,
+    // Stopped for not answering pings; the room tells the others so
+    pub timed_out: bool,
+    // What the client sent in the current window; see `quota`
+    pub quota: QuotaMeter,
+    pub policy: Policy,
+    // Each limited command's sends; see `limits`
+    pub limits: LimitMeter,
+    // Commands to warn the client off; see `tuesdays_protocol::deprecation`
+    pub deprecations: &'static [Deprecation],
+    // Deprecated commands the client's been warned about
+    pub deprecated: Vec<&'static str>,
+    // The wire format of the client's protocol version; see `version`
+    pub codec: Codec,
+    // JSON text frames, or CBOR binary ones; see `encoding`
+    pub encoding: Encoding,
+    pub sanitizer: Sanitizer,
+    // Counts us, so a draining instance knows when it's empty
+    pub drain: Drain,
+    pub metrics: Metrics,
+    // Why the connection is ending, once that's known; see `metrics`
+    pub disconnect: Option<Disconnect>,
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<Chaos>,
+}
+
+impl MemberWebSocket {
+    fn session(&self) -> &str {
+        session::label(self.session_id.as_deref())
+    }
+
+    // ✅ Send a message, in the client's protocol version and encoding, counting
+    // it for the access log and capturing it (as JSON, whatever went out)
+    fn send(&mut self, ctx: &mut ws::WebsocketContext<Self>, text: String) {
+        let text = self.codec.encode(text);
+        if let Some(capture) = &self.capture {
+            capture.sent(&text);
+        }
+        let len = match self.encoding {
+            Encoding::Json => {
+                let len = text.len();
+                ctx.text(text);
+                len
+            }
+            Encoding::Cbor => {
+                let bytes = encoding::to_cbor(&text);
+                let len = bytes.len();
+                ctx.binary(bytes);
+                len
+            }
+        };
+        if let Some(access) = &mut self.access {
+            access.sent(len);
+        }
+    }
+
+    // ✅ Every command passes the policy before it's dispatched (but `hello`, which
+    // only describes the client), and a watcher's its audience role too; then it
+    // counts against its limit, if the policy sets one
+    fn permit(&mut self, command: Command, len: usize) -> Result<Command, Rejection> {
+        let roles = policy::roles(self.role, self.audience_role, self.admin);
+        if !matches!(command, Command::Hello { .. } | Command::Ping { .. })
+            && !self.policy.allows(&roles, command.name())
+        {
+            info!(
+                "🔒 {:?} '{}' in Room '{}' may not send '{}' session={}",
+                self.role,
+                self.member_id,
+                self.room_id,
+                command.name(),
+                self.session()
+            );
+            return Err(ErrorCode::Forbidden.into());
+        }
+        if let Some(role) = self.audience_role
+            && !role.may(&command)
+        {
+            info!(
+                "🔒 Watcher '{}' in Room '{}' is a {} and may not send '{}' session={}",
+                self.member_id,
+                self.room_id,
+                role.name(),
+                command.name(),
+                self.session()
+            );
+            let detail = format!("a {} may not {}", role.name(), command.name());
+            return Err(Rejection::new(ErrorCode::Forbidden, detail));
+        }
+        if let Some(limit) = self.policy.limit(&roles, command.name())
+            && let Err(rejection) = self.limits.check(command.name(), limit, len)
+        {
+            info!(
+                "🚦 {:?} '{}' in Room '{}' is over its limit: {} session={}",
+                self.role,
+                self.member_id,
+                self.room_id,
+                rejection.detail.as_deref().unwrap_or_default(),
+                self.session()
+            );
+            return Err(rejection);
+        }
+        Ok(command)
+    }
+
+    // ✅ Count the command, and warn the client, once, if it's deprecated; see
+    // `tuesdays_protocol::deprecation`
+    fn used(&mut self, ctx: &mut ws::WebsocketContext<Self>, command: &Command) {
+        let deprecation = command.deprecation_in(self.deprecations);
+        self.metrics.command(command.name(), deprecation.is_some());
+        if let Some(deprecation) = deprecation
+            && !self.deprecated.contains(&deprecation.command)
+        {
+            info!(
+                "🌅 {:?} '{}' in Room '{}' sent deprecated '{}' ({}) session={}",
+                self.role,
+                self.member_id,
+                self.room_id,
+                deprecation.command,
+                self.user_agent.as_deref().unwrap_or("no user agent"),
+                self.session()
+            );
+            self.deprecated.push(deprecation.command);
+            self.send(ctx, deprecation.warning().to_json());
+        }
+    }
+
+    // ✅ Route an offer, answer, candidate or `send` to one member (with `role`,
+    // only one with that role), sanitized like a broadcast; the sender hears
+    // back if there's no such member. Candidates are held for a member that
+    // hasn't joined yet
+    fn relay(
+        &mut self,
+        ctx: &mut ws::WebsocketContext<Self>,
+        to: String,
+        message: String,
+        hold: bool,
+        role: Option<Role>,
+    ) {
+        let message = match self.sanitizer.sanitize(message) {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(rejection) => return self.send(ctx, rejection.to_json()),
+        };
+        info!(
+            "📨 Member '{}' in Room '{}' sends to '{}': {} session={}",
+            self.member_id,
+            self.room_id,
+            to,
+            message,
+            self.session()
+        );
+        self.room
+            .send(Relay {
+                to,
+                message,
+                hold,
+                role,
+            })
+            .into_actor(self)
+            .then(|delivered, act, ctx| {
+                if !delivered.unwrap_or(false) {
+                    act.send(ctx, ErrorCode::UnknownMember.to_json());
+                }
+                actix::fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    // ✅ Count a frame against the quotas, warning the client as it nears one;
+    // false if it went over, and the connection is closing
+    fn within_quota(&mut self, ctx: &mut ws::WebsocketContext<Self>, len: usize) -> bool {
+        match self.quota.count(len) {
+            Ok(warnings) => {
+                for warning in warnings {
+                    self.send(ctx, warning.to_json());
+                }
+                true
+            }
+            Err(rejection) => {
+                info!(
+                    "🚫 Member '{}' in Room '{}' is over its quota: {} session={}",
+                    self.member_id,
+                    self.room_id,
+                    rejection.detail.as_deref().unwrap_or_default(),
+                    self.session()
+                );
+                self.send(ctx, rejection.to_json());
+                self.closed(Disconnect::OverQuota, "quota exceeded");
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Other(close::QUOTA_EXCEEDED),
+                    description: Some(close::QUOTA_EXCEEDED_REASON.to_string()),
+                }));
+                ctx.stop();
+                false
+            }
+        }
+    }
+
+    // ✅ Note why the connection is ending; the first reason given stands
+    fn closed(&mut self, why: Disconnect, reason: impl Into<String>) {
+        self.disconnect.get_or_insert(why);
+        let reason = reason.into();
+        if let Some(capture) = &mut self.capture {
+            capture.closed(reason.clone());
+        }
+        if let Some(access) = &mut self.access {
+            access.closed(reason);
+        }
+    }
+}
+
+impl Actor for MemberWebSocket {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(capture) = &self.capture {
+            capture.joined();
+        }
+        self.drain.joined();
+        self.metrics.joined(self.role);
+        let member_addr = ctx.address(); // Get the correct member address
+        self.room.do_send(AddMember {
+            member_id: self.member_id.clone(),
+            role: self.role,
+            audience_role: self.audience_role,
+            audio_only: self.audio_only,
+            no_chat: self.no_chat,
+            user_agent: self.user_agent.clone(),
+            waiting: self.waiting,
+            resume: self.resume.clone(),
+            last_seq: self.last_seq,
+            addr: member_addr,
+        });
+        info!(
+            "🙌 Member '{}' connected to Room '{}'",
+            self.member_id, self.room_id
+        );
+        let notice = format!(
+            "Connected as Member: {} to Room: {}",
+            self.member_id, self.room_id
+        );
+        self.send(ctx, notice);
+        if let Some(heartbeat) = self.heartbeat {
+            heartbeat::start(heartbeat, ctx);
+        }
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        if let Some(access) = &self.access {
+            access.finish(&self.member_id, &self.room_id, self.session_id.as_deref());
+        }
+        if let Some(capture) = &self.capture {
+            capture.left();
+        }
+        self.room.do_send(RemoveMember {
+            member_id: self.member_id.clone(),
+            addr: ctx.address(),
+            timed_out: self.timed_out,
+        });
+        self.drain.left();
+        self.metrics.left(
+            self.role,
+            &self.member_id,
+            self.disconnect.unwrap_or(Disconnect::Dropped),
+        );
+        info!(
+            "❌ Member '{}' disconnected from Room '{}' session={}",
+            self.member_id,
+            self.room_id,
+            self.session()
+        );
+    }
+}
+
+impl Heartbeating for MemberWebSocket {
+    fn last_seen(&self) -> Instant {
+        self.last_seen
+    }
+
+    fn timed_out(&mut self) {
+        info!(
+            "💔 Member '{}' in Room '{}' stopped answering pings session={}",
+            self.member_id,
+            self.room_id,
+            self.session()
+        );
+        self.timed_out = true;
+        self.closed(Disconnect::TimedOut, "heartbeat timed out");
+    }
+}
+
+impl Handler<SetSession> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetSession, _: &mut Self::Context) {
+        if let Some(capture) = &self.capture {
+            capture.session(&msg.session_id);
+        }
+        self.session_id = Some(msg.session_id);
+    }
+}
+
+// Implement the handler in MemberWebSocket
+impl Handler<CloseConnection> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, _: CloseConnection, ctx: &mut Self::Context) {
+        self.closed(Disconnect::Replaced, "replaced by another connection");
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(close::REPLACED),
+            description: Some(close::REPLACED_REASON.to_string()),
+        }));
+    }
+}
+
+// Closed and stopped at once, whether or not the client answers the close
+impl Handler<Kicked> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, _: Kicked, ctx: &mut Self::Context) {
+        self.closed(Disconnect::Kicked, "disconnected by an operator");
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(close::KICKED),
+            description: Some(close::KICKED_REASON.to_string()),
+        }));
+        ctx.stop();
+    }
+}
+
+impl Handler<SetAudienceRole> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetAudienceRole, _: &mut Self::Context) {
+        self.audience_role = Some(msg.role);
+    }
+}
+
+impl Handler<StreamExpired> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, _: StreamExpired, ctx: &mut Self::Context) {
+        self.closed(Disconnect::Expired, "stream expired");
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(close::EXPIRED),
+            description: Some(close::EXPIRED_REASON.to_string()),
+        }));
+    }
+}
+
+// Implement StreamHandler for MemberWebSocket
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MemberWebSocket {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match &msg {
+            Ok(ws::Message::Close(reason)) => self.closed(
+                Disconnect::Closed,
+                match reason {
+                    Some(reason) => format!("closed by client ({:?})", reason.code),
+                    None => "closed by client".to_string(),
+                },
+            ),
+            Ok(ws::Message::Ping(bytes)) => ctx.pong(bytes),
+            Err(err) => {
+                self.metrics.websocket_error();
+                self.closed(
+                    Disconnect::ProtocolError,
+                    format!("protocol error: {}", err),
+                );
+            }
+            _ => {}
+        }
+        if msg.is_ok() {
+            self.last_seen = Instant::now();
+        }
+        let len = match &msg {
+            Ok(ws::Message::Text(text)) => Some(text.len()),
+            Ok(ws::Message::Binary(bytes)) => Some(bytes.len()),
+            _ => None,
+        };
+        if len.is_some_and(|len| !self.within_quota(ctx, len)) {
+            return;
+        }
+        let text = match msg {
+            Ok(ws::Message::Text(text)) => Some(text.to_string()),
+            // ✅ A `tuesdays.cbor` client sends its commands as CBOR
+            Ok(ws::Message::Binary(bytes)) if self.encoding.binary() => encoding::from_cbor(&bytes)
+                .map_err(|rejection| self.send(ctx, rejection.to_json()))
+                .ok(),
+            _ => None,
+        };
+        if let Some(text) = text {
+            let received_ms = latency::now_ms();
+            if let Some(capture) = &self.capture {
+                capture.received(&text);
+            }
+            info!(
+                "💬 Member '{}' received message: {} session={}",
+                self.member_id,
+                text,
+                self.session()
+            );
+
+            let command = self
+                .codec
+                .decode(&text)
+                .and_then(|command| self.permit(command, text.len()));
+            if let Ok(command) = &command {
+                self.used(ctx, command);
+            }
+            match command {
+                Ok(Command::List) => {
+                    self.room
+                        .send(GetMembers)
+                        .into_actor(self)
+                        .then(|res, act, ctx| {
+                            if let Ok(members) = res {
+                                let response = serde_json::to_string(&members)
+                                    .unwrap_or_else(|_| "[]".to_string());
+                                act.send(ctx, response);
+                            }
+                            actix::fut::ready(())
+                        })
+                        .wait(ctx);
+                }
+                Ok(Command::Whois) => {
+                    let response = WhoisResponse {
+                        member_id: self.member_id.clone(),
+                        version: self.codec.version(),
+                    };
+                    self.send(ctx, serde_json::to_string(&response).unwrap_or_default());
+                }
+                Ok(Command::Broadcast { message }) => {
+                    info!(
+                        "📢 Member '{}' is broadcasting: {} session={}",
+                        self.member_id,
+                        message,
+                        self.session()
+                    );
+                    let message = match self.sanitizer.sanitize(message) {
+                        Ok(Some(message)) => message,
+                        Ok(None) => return,
+                        Err(rejection) => return self.send(ctx, rejection.to_json()),
+                    };
+                    // ✅ Whatever isn't signaling is chat, for the archive
+                    if let Some(archive) = &self.archive
+                        && serde_json::from_str::<Signal>(&message).is_err()
+                    {
+                        archive.record(
+                            &self.room_id,
+                            self.session_id.as_deref(),
+                            ArchiveEvent::Chat {
+                                member_id: self.member_id.clone(),
+                                message: message.clone(),
+                            },
+                        );
+                    }
+                    self.room.do_send(MemberBroadcast {
+                        member_id: self.member_id.clone(),
+                        message,
+                    });
+                }
+                Ok(Command::Offer {
+                    to,
+                    negotiation_id,
+                    sdp,
+                }) => {
+                    let offer =
+                        Signal::Offer { sdp }.to_routed_json(&self.member_id, &negotiation_id);
+                    self.relay(ctx, to, offer, false, None);
+                }
+                Ok(Command::Answer {
+                    to,
+                    negotiation_id,
+                    sdp,
+                }) => {
+                    let answer =
+                        Signal::Answer { sdp }.to_routed_json(&self.member_id, &negotiation_id);
+                    self.relay(ctx, to, answer, false, None);
+                }
+                Ok(Command::IceCandidate {
+                    to,
+                    negotiation_id,
+                    candidate,
+                }) => {
+                    let candidate = Signal::Candidate(candidate)
+                        .to_routed_json(&self.member_id, &negotiation_id);
+                    self.relay(ctx, to, candidate, true, None);
+                }
+                Ok(Command::Send { to, message }) => {
+                    // Cohosts too, whose role allowed it already
+                    if !matches!(self.role, Role::Streamer | Role::Watcher) {
+                        let rejection = Rejection::new(
+                            ErrorCode::Forbidden,
+                            "only streamers and cohosts may send",
+                        );
+                        return self.send(ctx, rejection.to_json());
+                    }
+                    self.relay(ctx, to, message, false, Some(Role::Watcher));
+                }
+                Ok(Command::Chat { message }) => {
+                    if self.role != Role::Watcher {
+                        let rejection =
+                            Rejection::new(ErrorCode::Forbidden, "only watchers may chat");
+                        return self.send(ctx, rejection.to_json());
+                    }
+                    let chat = AudienceChat {
+                        from: self.member_id.clone(),
+                        message,
+                    };
+                    self.room
+                        .send(chat)
+                        .into_actor(self)
+                        .then(|delivered, act, ctx| {
+                            if !delivered.unwrap_or(false) {
+                                let rejection =
+                                    Rejection::new(ErrorCode::Forbidden, "audience chat is off");
+                                act.send(ctx, rejection.to_json());
+                            }
+                            actix::fut::ready(())
+                        })
+                        .wait(ctx);
+                }
+                Ok(Command::Stats { report }) => {
+                    info!(
+                        "📊 Member '{}' in Room '{}' reported stats: {} session={}",
+                        self.member_id,
+                        self.room_id,
+                        report,
+                        self.session()
+                    );
+                    if self.role == Role::Watcher {
+                        self.room.do_send(WatcherStats {
+                            member_id: self.member_id.clone(),
+                            report,
+                        });
+                    }
+                }
+                Ok(Command::Level { db }) => {
+                    if self.role == Role::Streamer {
+                        self.room.do_send(StreamerLevel {
+                            member_id: self.member_id.clone(),
+                            db,
+                        });
+                    }
+                }
+                Ok(Command::Hello { client_version }) => {
+                    info!(
+                        "👋 {:?} '{}' in Room '{}' runs {} ({}) session={}",
+                        self.role,
+                        self.member_id,
+                        self.room_id,
+                        client_version.as_deref().unwrap_or("an unknown version"),
+                        self.user_agent.as_deref().unwrap_or("no user agent"),
+                        self.session()
+                    );
+                    if let Some(access) = &mut self.access {
+                        access.hello(client_version.clone());
+                    }
+                    self.room.do_send(SetClientVersion {
+                        member_id: self.member_id.clone(),
+                        client_version,
+                    });
+                }
+                Ok(Command::Describe { media }) => {
+                    if self.role == Role::Streamer {
+                        self.room.do_send(SetMedia {
+                            member_id: self.member_id.clone(),
+                            media,
+                        });
+                    }
+                }
+                Ok(Command::Replay { from, to }) => {
+                    let resend = Resend {
+                        member_id: self.member_id.clone(),
+                        addr: ctx.address(),
+                        from,
+                        to,
+                    };
+                    self.room
+                        .send(resend)
+                        .into_actor(self)
+                        .then(move |missed, act, ctx| {
+                            match missed.ok().flatten() {
+                                Some(missed) => {
+                                    info!(
+                                        "🔁 Replaying {} messages ({}-{}) to Member '{}' session={}",
+                                        missed.len(),
+                                        from,
+                                        to,
+                                        act.member_id,
+                                        act.session()
+                                    );
+                                    for message in missed {
+                                        act.send(ctx, message);
+                                    }
+                                }
+                                None => act.send(ctx, ErrorCode::ReplayUnavailable.to_json()),
+                            }
+                            actix::fut::ready(())
+                        })
+                        .wait(ctx);
+                }
+                Ok(Command::Assign { watcher_id, role }) => {
+                    if !matches!(self.role, Role::Streamer | Role::Watcher) {
+                        let rejection = Rejection::new(
+                            ErrorCode::Forbidden,
+                            "only streamers and moderators may assign roles",
+                        );
+                        return self.send(ctx, rejection.to_json());
+                    }
+                    let assign = AssignRole {
+                        from: self.member_id.clone(),
+                        by: self.audience_role,
+                        watcher_id,
+                        role,
+                    };
+                    self.room
+                        .send(assign)
+                        .into_actor(self)
+                        .then(|assigned, act, ctx| {
+                            if let Ok(Err(code)) = assigned {
+                                act.send(ctx, code.to_json());
+                            }
+                            actix::fut::ready(())
+                        })
+                        .wait(ctx);
+                }
+                Ok(Command::Ping { sent_ms }) => {
+                    self.send(ctx, Pong::reply(sent_ms, received_ms).to_json());
+                }
+                Err(rejection) => {
+                    self.send(ctx, rejection.to_json());
+                }
+            }
+        }
+    }
+}
+
+// Handle broadcast in member
+impl Handler<BroadcastMessage> for MemberWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastMessage, ctx: &mut Self::Context) {
+        info!(
+            "📢 Member '{}' received broadcast: {} session={}",
+            self.member_id,
+            msg.message,
+            self.session()
+        );
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            return self.mistreat(chaos.fate(), ctx, msg.message);
+        }
+        self.send(ctx, msg.message);
+    }
+}
+
+#[cfg(feature = "chaos")]
+impl MemberWebSocket {
+    fn mistreat(&mut self, fate: Fate, ctx: &mut ws::WebsocketContext<Self>, message: String) {
+        match fate {
+            Fate::Drop => info!(
+                "🐒 Dropped a message to Member '{}' session={}",
+                self.member_id,
+                self.session()
+            ),
+            Fate::Disconnect => {
+                info!(
+                    "🐒 Disconnecting Member '{}' session={}",
+                    self.member_id,
+                    self.session()
+                );
+                self.closed(Disconnect::Chaos, "disconnected by chaos");
+                ctx.stop();
+            }
+            Fate::Deliver { delay, copies } => {
+                ctx.run_later(delay, move |act, ctx| {
+                    for _ in 0..copies {
+                        act.send(ctx, message.clone());
+                    }
+                });
+            }
+        }
+    }
+}
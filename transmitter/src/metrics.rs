@@ -0,0 +1,301 @@
+// Prometheus metrics (--metrics), served on `GET /metrics` in the text exposition
+// format, for dashboards and alerts on this instance:
+//
+//   tuesdays_members{role}                       connected now (gauge)
+//   tuesdays_disconnects_total{role,reason}      connections ended, and why
+//   tuesdays_relayed_messages_total              offers, answers and candidates routed
+//   tuesdays_broadcast_recipients                members each broadcast reached (histogram)
+//   tuesdays_websocket_errors_total              WebSocket protocol errors
+//   tuesdays_commands_total{command,deprecated}  commands received, by name
+//
+// Rates are Prometheus's to work out, e.g. relays per second:
+//
+//   rate(tuesdays_relayed_messages_total[1m])
+//
+// and an abnormal disconnect rate, say watchers dropping without a close:
+//
+//   rate(tuesdays_disconnects_total{role="watcher",reason="dropped"}[5m])
+//
+// and whether a deprecated command is still in use, before it's removed:
+//
+//   sum by (command) (increase(tuesdays_commands_total{deprecated="true"}[7d]))
+//
+// The last few abnormal disconnects are also kept, by member, for the --tui
+// console; see `console`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::room::Role;
+
+// Upper bounds of the broadcast recipient buckets; +Inf is implied
+const RECIPIENT_BUCKETS: [u64; 10] = [0, 1, 2, 5, 10, 25, 50, 100, 500, 1000];
+// Abnormal disconnects kept for the console
+const INCIDENTS: usize = 10;
+
+// Why a connection ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Disconnect {
+    // The client sent a close frame
+    Closed,
+    // The connection went away without one
+    Dropped,
+    ProtocolError,
+    TimedOut,
+    OverQuota,
+    Replaced,
+    Kicked,
+    Expired,
+    #[cfg(feature = "chaos")]
+    Chaos,
+}
+
+impl Disconnect {
+    fn name(self) -> &'static str {
+        match self {
+            Disconnect::Closed => "closed",
+            Disconnect::Dropped => "dropped",
+            Disconnect::ProtocolError => "protocol_error",
+            Disconnect::TimedOut => "timed_out",
+            Disconnect::OverQuota => "over_quota",
+            Disconnect::Replaced => "replaced",
+            Disconnect::Kicked => "kicked",
+            Disconnect::Expired => "expired",
+            #[cfg(feature = "chaos")]
+            Disconnect::Chaos => "chaos",
+        }
+    }
+
+    // Not the member's own doing, nor an operator's: worth an operator's look
+    fn abnormal(self) -> bool {
+        matches!(
+            self,
+            Disconnect::Dropped
+                | Disconnect::ProtocolError
+                | Disconnect::TimedOut
+                | Disconnect::OverQuota
+        )
+    }
+}
+
+// Everything counted so far, for the console to take rates from
+pub(crate) struct Snapshot {
+    pub members: BTreeMap<&'static str, i64>,
+    pub relayed: u64,
+    pub broadcasts: u64,
+    pub recipients: u64,
+    pub websocket_errors: u64,
+    // Most recent last
+    pub incidents: Vec<(SystemTime, String)>,
+}
+
+#[derive(Default)]
+struct Counters {
+    // By role
+    members: Mutex<BTreeMap<&'static str, i64>>,
+    // By role and reason
+    disconnects: Mutex<BTreeMap<(&'static str, &'static str), u64>>,
+    relayed: AtomicU64,
+    websocket_errors: AtomicU64,
+    // By name, and whether it's deprecated
+    commands: Mutex<BTreeMap<(&'static str, bool), u64>>,
+    // Broadcasts per bucket of RECIPIENT_BUCKETS, the last for more than all of them
+    recipients: [AtomicU64; RECIPIENT_BUCKETS.len() + 1],
+    recipients_sum: AtomicU64,
+    incidents: Mutex<VecDeque<(SystemTime, String)>>,
+}
+
+// Cheap to clone into every room and member
+#[derive(Clone, Default)]
+pub(crate) struct Metrics {
+    counters: Arc<Counters>,
+}
+
+impl Metrics {
+    pub fn joined(&self, role: Role) {
+        let mut members = self
+            .counters
+            .members
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *members.entry(role.name()).or_default() += 1;
+    }
+
+    pub fn left(&self, role: Role, member_id: &str, why: Disconnect) {
+        let mut members = self
+            .counters
+            .members
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *members.entry(role.name()).or_default() -= 1;
+        drop(members);
+        let mut disconnects = self
+            .counters
+            .disconnects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *disconnects.entry((role.name(), why.name())).or_default() += 1;
+        drop(disconnects);
+        if why.abnormal() {
+            let mut incidents = self
+                .counters
+                .incidents
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if incidents.len() == INCIDENTS {
+                incidents.pop_front();
+            }
+            incidents.push_back((
+                SystemTime::now(),
+                format!("{} '{}' {}", role.name(), member_id, why.name()),
+            ));
+        }
+    }
+
+    pub fn relayed(&self) {
+        self.counters.relayed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn websocket_error(&self) {
+        self.counters
+            .websocket_errors
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn command(&self, name: &'static str, deprecated: bool) {
+        let mut commands = self
+            .counters
+            .commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *commands.entry((name, deprecated)).or_default() += 1;
+    }
+
+    pub fn broadcast(&self, recipients: usize) {
+        let recipients = recipients as u64;
+        let bucket = RECIPIENT_BUCKETS
+            .iter()
+            .position(|&bound| recipients <= bound)
+            .unwrap_or(RECIPIENT_BUCKETS.len());
+        self.counters.recipients[bucket].fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .recipients_sum
+            .fetch_add(recipients, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let counters = &self.counters;
+        Snapshot {
+            members: counters
+                .members
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            relayed: counters.relayed.load(Ordering::Relaxed),
+            broadcasts: counters
+                .recipients
+                .iter()
+                .map(|bucket| bucket.load(Ordering::Relaxed))
+                .sum(),
+            recipients: counters.recipients_sum.load(Ordering::Relaxed),
+            websocket_errors: counters.websocket_errors.load(Ordering::Relaxed),
+            incidents: counters
+                .incidents
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // ✅ Everything, in the Prometheus text format
+    pub fn render(&self) -> String {
+        let counters = &self.counters;
+        let mut out = String::new();
+
+        out.push_str("# HELP tuesdays_members Members connected, by role\n");
+        out.push_str("# TYPE tuesdays_members gauge\n");
+        for (role, count) in counters
+            .members
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(out, "tuesdays_members{{role=\"{}\"}} {}", role, count);
+        }
+
+        out.push_str("# HELP tuesdays_disconnects_total Connections ended, by role and reason\n");
+        out.push_str("# TYPE tuesdays_disconnects_total counter\n");
+        for ((role, reason), count) in counters
+            .disconnects
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "tuesdays_disconnects_total{{role=\"{}\",reason=\"{}\"}} {}",
+                role, reason, count
+            );
+        }
+
+        out.push_str("# HELP tuesdays_relayed_messages_total Messages routed to one member\n");
+        out.push_str("# TYPE tuesdays_relayed_messages_total counter\n");
+        let _ = writeln!(
+            out,
+            "tuesdays_relayed_messages_total {}",
+            counters.relayed.load(Ordering::Relaxed)
+        );
+
+        out.push_str("# HELP tuesdays_websocket_errors_total WebSocket protocol errors\n");
+        out.push_str("# TYPE tuesdays_websocket_errors_total counter\n");
+        let _ = writeln!(
+            out,
+            "tuesdays_websocket_errors_total {}",
+            counters.websocket_errors.load(Ordering::Relaxed)
+        );
+
+        out.push_str(
+            "# HELP tuesdays_commands_total Commands received, by name and whether deprecated\n",
+        );
+        out.push_str("# TYPE tuesdays_commands_total counter\n");
+        for ((command, deprecated), count) in counters
+            .commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "tuesdays_commands_total{{command=\"{}\",deprecated=\"{}\"}} {}",
+                command, deprecated, count
+            );
+        }
+
+        out.push_str("# HELP tuesdays_broadcast_recipients Members each broadcast reached\n");
+        out.push_str("# TYPE tuesdays_broadcast_recipients histogram\n");
+        let mut count = 0;
+        for (i, bucket) in counters.recipients.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+            let bound = RECIPIENT_BUCKETS
+                .get(i)
+                .map_or("+Inf".to_string(), u64::to_string);
+            let _ = writeln!(
+                out,
+                "tuesdays_broadcast_recipients_bucket{{le=\"{}\"}} {}",
+                bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "tuesdays_broadcast_recipients_sum {}",
+            counters.recipients_sum.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(out, "tuesdays_broadcast_recipients_count {}", count);
+        out
+    }
+}
@@ -0,0 +1,211 @@
+// Origin allowlist (--allowed-origin): only web pages from approved origins may
+// open signaling connections, so another site can't have its visitors' browsers
+// join streams (and carry their cookies) on its behalf:
+//
+//   transmitter --allowed-origin https://app.tuesdays.example \
+//       --allowed-origin 'https://*.tuesdays.example'
+//
+// `https://*.example.com` allows any subdomain (not example.com itself), and
+// `*` any origin at all, for development. A connection from an origin that
+// isn't allowed is answered 403:
+//
+//   {"error":{"code":"forbidden","message":"Not allowed",
+//             "details":"origin 'https://elsewhere.example' is not allowed"}}
+//
+// Only browsers send an Origin; the streamer, watcher and other native clients
+// don't, and are let through as before.
+
+use std::str::FromStr;
+
+use actix_web::HttpRequest;
+use actix_web::http::header;
+
+const ANY: &str = "*";
+
+// --allowed-origin ORIGIN
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AllowedOrigin {
+    Any,
+    // scheme://host[:port]
+    Exact(String),
+    // scheme:// and .domain[:port] around any subdomain
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl FromStr for AllowedOrigin {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == ANY {
+            return Ok(AllowedOrigin::Any);
+        }
+        let origin = value.trim_end_matches('/').to_ascii_lowercase();
+        let (scheme, host) = origin
+            .split_once("://")
+            .filter(|(scheme, _)| *scheme == "http" || *scheme == "https")
+            .ok_or_else(|| format!("'{}' must be an http:// or https:// origin, or *", value))?;
+        if host.is_empty() || host.contains('/') {
+            return Err(format!(
+                "'{}' must be scheme://host[:port], with no path",
+                value
+            ));
+        }
+        match host.strip_prefix("*.") {
+            Some(domain) if !domain.is_empty() && !domain.contains('*') => {
+                Ok(AllowedOrigin::Subdomains {
+                    scheme: scheme.to_string(),
+                    suffix: format!(".{}", domain),
+                })
+            }
+            None if !host.contains('*') => Ok(AllowedOrigin::Exact(origin)),
+            _ => Err(format!("'{}' may only start its host with '*.'", value)),
+        }
+    }
+}
+
+impl AllowedOrigin {
+    fn allows(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Any => true,
+            AllowedOrigin::Exact(allowed) => allowed == origin,
+            AllowedOrigin::Subdomains { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|rest| rest.strip_prefix("://"))
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| !subdomain.is_empty() && !subdomain.contains('/')),
+        }
+    }
+}
+
+// Empty (the default) allows every origin
+#[derive(Clone, Debug, Default)]
+pub struct Origins {
+    allowed: Vec<AllowedOrigin>,
+}
+
+impl Origins {
+    pub fn new(allowed: Vec<AllowedOrigin>) -> Self {
+        Origins { allowed }
+    }
+
+    // Whether any origin may connect: no list, or `*` on it
+    pub fn any(&self) -> bool {
+        self.allowed.is_empty() || self.allowed.contains(&AllowedOrigin::Any)
+    }
+
+    // ✅ Err(reason) if the request comes from a page whose origin isn't allowed
+    pub(crate) fn check(&self, req: &HttpRequest) -> Result<(), String> {
+        if self.any() {
+            return Ok(());
+        }
+        let Some(origin) = req.headers().get(header::ORIGIN) else {
+            return Ok(());
+        };
+        let origin = origin
+            .to_str()
+            .map_err(|_| "origin is not valid text".to_string())?
+            .trim_end_matches('/')
+            .to_ascii_lowercase();
+        if self.allowed.iter().any(|allowed| allowed.allows(&origin)) {
+            Ok(())
+        } else {
+            Err(format!("origin '{}' is not allowed", origin))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn origins(allowed: &[&str]) -> Origins {
+        Origins::new(
+            allowed
+                .iter()
+                .map(|origin| origin.parse().unwrap())
+                .collect(),
+        )
+    }
+
+    fn check(origins: &Origins, origin: Option<&str>) -> Result<(), String> {
+        let req = match origin {
+            Some(origin) => TestRequest::default().insert_header((header::ORIGIN, origin)),
+            None => TestRequest::default(),
+        };
+        origins.check(&req.to_http_request())
+    }
+
+    #[test]
+    fn exact_origins_match_whole() {
+        let origins = origins(&["https://app.example.com/"]);
+        assert_eq!(check(&origins, Some("https://app.example.com")), Ok(()));
+        assert_eq!(check(&origins, Some("HTTPS://App.Example.com/")), Ok(()));
+        assert_eq!(
+            check(&origins, Some("http://app.example.com")),
+            Err("origin 'http://app.example.com' is not allowed".to_string())
+        );
+        assert!(check(&origins, Some("https://app.example.com.evil.example")).is_err());
+        assert!(check(&origins, Some("https://other.example.com")).is_err());
+    }
+
+    #[test]
+    fn wildcards_match_subdomains_but_not_the_apex() {
+        let origins = origins(&["https://*.example.com"]);
+        assert_eq!(check(&origins, Some("https://app.example.com")), Ok(()));
+        assert_eq!(check(&origins, Some("https://a.b.example.com")), Ok(()));
+        assert!(check(&origins, Some("https://example.com")).is_err());
+        assert!(check(&origins, Some("https://.example.com")).is_err());
+        assert!(check(&origins, Some("https://app.notexample.com")).is_err());
+        assert!(check(&origins, Some("https://evilexample.com")).is_err());
+        assert!(check(&origins, Some("http://app.example.com")).is_err());
+    }
+
+    #[test]
+    fn ports_must_match() {
+        let origins = origins(&["https://app.example.com:8443", "https://*.example.net"]);
+        assert_eq!(
+            check(&origins, Some("https://app.example.com:8443")),
+            Ok(())
+        );
+        assert!(check(&origins, Some("https://app.example.com")).is_err());
+        assert!(check(&origins, Some("https://app.example.com:9443")).is_err());
+        assert!(check(&origins, Some("https://app.example.net:8443")).is_err());
+    }
+
+    #[test]
+    fn a_bare_star_allows_any_origin() {
+        assert_eq!("*".parse(), Ok(AllowedOrigin::Any));
+        for origins in [
+            origins(&["*"]),
+            origins(&["https://app.example.com", "*"]),
+            origins(&[]),
+        ] {
+            assert!(origins.any());
+            assert_eq!(check(&origins, Some("https://anywhere.example")), Ok(()));
+            assert_eq!(check(&origins, Some("null")), Ok(()));
+        }
+    }
+
+    #[test]
+    fn requests_without_an_origin_are_let_through() {
+        let origins = origins(&["https://app.example.com"]);
+        assert!(!origins.any());
+        assert_eq!(check(&origins, None), Ok(()));
+    }
+
+    #[test]
+    fn malformed_origins_are_refused() {
+        for origin in [
+            "app.example.com",
+            "ftp://app.example.com",
+            "https://",
+            "https://app.example.com/path",
+            "https://app.*.example.com",
+            "https://*.",
+            "https://*.*.example.com",
+        ] {
+            assert!(origin.parse::<AllowedOrigin>().is_err(), "{}", origin);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Command policy: which roles may send which commands, checked in one place as
+// every command is dispatched (see `member`). Loaded from a TOML file:
+//
+//   [roles]
+//   watcher = ["whois", "broadcast", "stats"]
+//   moderator = ["whois", "broadcast", "stats", "chat", "assign"]
+//   member = ["list", "whois", "broadcast"]
+//   streamer = ["*"]
+//   admin = ["*"]
+//
+// Besides how it joined (member, streamer, watcher, agent), a member may be a
+// moderator (a watcher whose audience role is moderator or cohost) and an admin
+// (connected with the --admin-token as its bearer token). The most trusted of
+// these the policy lists decides, so a moderator falls back to the watcher's
+// rules when there are none for moderators. A member none of whose roles are
+// listed may send every command, so the empty policy (the default) allows
+// everything. Forbidden commands are answered with
+// `{"error":{"code":"forbidden",...}}` and go no further. The same file may limit
+// how often, and how large, each role's commands are; see `limits`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tuesdays_protocol::{AudienceRole, Command};
+
+use crate::limits::Limit;
+use crate::room::Role;
+
+const ANY: &str = "*";
+
+// What the policy's rules are keyed by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyRole {
+    Member,
+    Streamer,
+    Watcher,
+    Agent,
+    Moderator,
+    Admin,
+}
+
+impl From<Role> for PolicyRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Member => PolicyRole::Member,
+            Role::Streamer => PolicyRole::Streamer,
+            Role::Watcher => PolicyRole::Watcher,
+            Role::Agent => PolicyRole::Agent,
+        }
+    }
+}
+
+// ✅ A member's roles, most trusted first
+pub(crate) fn roles(
+    role: Role,
+    audience_role: Option<AudienceRole>,
+    admin: bool,
+) -> Vec<PolicyRole> {
+    let mut roles = Vec::with_capacity(3);
+    if admin {
+        roles.push(PolicyRole::Admin);
+    }
+    if matches!(
+        audience_role,
+        Some(AudienceRole::Moderator | AudienceRole::Cohost)
+    ) {
+        roles.push(PolicyRole::Moderator);
+    }
+    roles.push(role.into());
+    roles
+}
+
+// Cheap to clone into every connection
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    allowed: Arc<HashMap<PolicyRole, Vec<String>>>,
+    limits: Arc<HashMap<PolicyRole, HashMap<String, Limit>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    roles: HashMap<PolicyRole, Vec<String>>,
+    #[serde(default)]
+    limits: HashMap<PolicyRole, HashMap<String, Limit>>,
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Policy::from_toml(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    // ✅ Parse a policy, rejecting commands that don't exist (likely typos)
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: PolicyFile = toml::from_str(text).map_err(|err| err.to_string())?;
+        for (role, commands) in &file.roles {
+            if let Some(unknown) = commands
+                .iter()
+                .find(|command| *command != ANY && !Command::NAMES.contains(&command.as_str()))
+            {
+                return Err(format!(
+                    "unknown command '{}' for {:?} (known: {})",
+                    unknown,
+                    role,
+                    Command::NAMES.join(", ")
+                ));
+            }
+        }
+        for (role, limits) in &file.limits {
+            if let Some(unknown) = limits
+                .keys()
+                .find(|command| !Command::NAMES.contains(&command.as_str()))
+            {
+                return Err(format!(
+                    "unknown command '{}' in {:?} limits (known: {})",
+                    unknown,
+                    role,
+                    Command::NAMES.join(", ")
+                ));
+            }
+        }
+        Ok(Policy {
+            allowed: Arc::new(file.roles),
+            limits: Arc::new(file.limits),
+        })
+    }
+
+    // ✅ The first of `roles` (see `roles`) with rules decides
+    pub fn allows(&self, roles: &[PolicyRole], command: &str) -> bool {
+        match roles.iter().find_map(|role| self.allowed.get(role)) {
+            Some(commands) => commands.iter().any(|c| c == ANY || c == command),
+            None => true,
+        }
+    }
+
+    // The limit on `command` of the first of `roles` with limits, if it sets one
+    pub fn limit(&self, roles: &[PolicyRole], command: &str) -> Option<&Limit> {
+        roles
+            .iter()
+            .find_map(|role| self.limits.get(role))?
+            .get(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WATCHER: &[PolicyRole] = &[PolicyRole::Watcher];
+
+    fn policy(text: &str) -> Policy {
+        Policy::from_toml(text).unwrap()
+    }
+
+    #[test]
+    fn the_default_policy_allows_everything() {
+        let policy = Policy::default();
+        for role in [Role::Member, Role::Streamer, Role::Watcher, Role::Agent] {
+            assert!(policy.allows(&roles(role, None, false), "broadcast"));
+            assert!(policy.limit(&roles(role, None, false), "chat").is_none());
+        }
+    }
+
+    #[test]
+    fn listed_roles_send_only_their_commands() {
+        let policy = policy(
+            r#"
+            [roles]
+            watcher = ["whois", "stats"]
+            streamer = ["*"]
+            "#,
+        );
+        assert!(policy.allows(WATCHER, "stats"));
+        assert!(!policy.allows(WATCHER, "broadcast"));
+        assert!(policy.allows(&[PolicyRole::Streamer], "broadcast"));
+        // Unlisted
+        assert!(policy.allows(&[PolicyRole::Member], "broadcast"));
+    }
+
+    #[test]
+    fn an_empty_list_forbids_everything() {
+        let policy = policy("[roles]\nagent = []");
+        assert!(!policy.allows(&[PolicyRole::Agent], "list"));
+    }
+
+    #[test]
+    fn the_most_trusted_listed_role_decides() {
+        let policy = policy(
+            r#"
+            [roles]
+            watcher = ["whois"]
+            moderator = ["whois", "assign"]
+            admin = ["*"]
+            "#,
+        );
+        let moderator = roles(Role::Watcher, Some(AudienceRole::Moderator), false);
+        assert!(policy.allows(&moderator, "assign"));
+        assert!(!policy.allows(&moderator, "broadcast"));
+        let cohost = roles(Role::Watcher, Some(AudienceRole::Cohost), false);
+        assert!(policy.allows(&cohost, "assign"));
+        let viewer = roles(Role::Watcher, Some(AudienceRole::Viewer), false);
+        assert!(!policy.allows(&viewer, "assign"));
+        let admin = roles(Role::Watcher, Some(AudienceRole::Viewer), true);
+        assert!(policy.allows(&admin, "broadcast"));
+    }
+
+    #[test]
+    fn unlisted_trusted_roles_fall_back_to_how_the_member_joined() {
+        let policy = policy("[roles]\nwatcher = [\"whois\"]");
+        let moderator = roles(Role::Watcher, Some(AudienceRole::Moderator), true);
+        assert_eq!(
+            moderator,
+            [
+                PolicyRole::Admin,
+                PolicyRole::Moderator,
+                PolicyRole::Watcher
+            ]
+        );
+        assert!(policy.allows(&moderator, "whois"));
+        assert!(!policy.allows(&moderator, "assign"));
+    }
+
+    #[test]
+    fn limits_follow_the_same_roles() {
+        let policy = policy(
+            r#"
+            [limits.watcher.chat]
+            per_sec = 5
+            [limits.moderator.chat]
+            per_sec = 50
+            "#,
+        );
+        let limit = |roles: &[PolicyRole]| policy.limit(roles, "chat").and_then(|l| l.per_sec);
+        assert_eq!(limit(WATCHER), Some(5));
+        assert_eq!(
+            limit(&roles(Role::Watcher, Some(AudienceRole::Moderator), false)),
+            Some(50)
+        );
+        assert_eq!(limit(&[PolicyRole::Streamer]), None);
+        assert!(policy.limit(WATCHER, "stats").is_none());
+    }
+
+    #[test]
+    fn typos_are_refused() {
+        let error = |text| Policy::from_toml(text).unwrap_err();
+        assert!(error("[roles]\nwatcher = [\"wois\"]").contains("unknown command 'wois'"));
+        assert!(error("[limits.watcher.caht]\nper_sec = 1").contains("unknown command 'caht'"));
+        assert!(error("[roles]\nviewer = [\"whois\"]").contains("unknown variant"));
+        assert!(error("[rules]\nwatcher = []").contains("unknown field"));
+        assert!(error("[limits.watcher.chat]\nper_hour = 1").contains("unknown field"));
+        assert!(error("[roles").contains("TOML parse error"));
+    }
+}
@@ -0,0 +1,239 @@
+// Stream presence: which streams are live on which transmitter. By default it's
+// kept in memory and only ever holds this instance's own; with --presence-redis,
+// instances behind one load balancer share it, so each lists the others' streams
+// in its directory and sends a watcher of one of them, or its streamer coming
+// back, to the instance that has it (a `redirect` to --advertise-url, same path
+// and query; see `region`):
+//
+//   transmitter --presence-redis redis://:secret@cache:6379/2 --advertise-url ws://tx-a:8080
+//
+// Every room puts its directory entry under `tuesdays:stream:<id>` whenever it
+// changes and every REFRESH, for TTL, and removes it once it expires. Entries of
+// an instance that died without removing them time out on their own.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use actix_web::web;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tuesdays_protocol::StreamInfo;
+use uuid::Uuid;
+
+use crate::redis::{Redis, Reply};
+
+pub(crate) const REFRESH: Duration = Duration::from_secs(10);
+const TTL: Duration = Duration::from_secs(30);
+const KEY_PREFIX: &str = "tuesdays:stream:";
+
+// Where presence is kept: stream ids to JSON entries, each for a while
+pub trait PresenceStore: Send + Sync {
+    fn put(&self, stream_id: &str, entry: &str, ttl: Duration) -> io::Result<()>;
+    fn remove(&self, stream_id: &str) -> io::Result<()>;
+    // Every entry that hasn't timed out
+    fn entries(&self) -> io::Result<Vec<String>>;
+}
+
+// A stream and the instance serving it
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Present {
+    pub instance: String,
+    // Where its watchers connect; None if it didn't say (--advertise-url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub info: StreamInfo,
+}
+
+enum Change {
+    Put(String, String),
+    Remove(String),
+}
+
+// Shared by every room and handler; cheap to clone. Changes are written from a
+// thread of their own, so a slow store never holds a room up
+#[derive(Clone)]
+pub struct Presence {
+    store: Arc<dyn PresenceStore>,
+    // Tells our own entries from other instances'
+    instance: String,
+    url: Option<String>,
+    changes: Sender<Change>,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Presence::new(Arc::new(MemoryStore::default()), None)
+    }
+}
+
+impl Presence {
+    pub fn new(store: Arc<dyn PresenceStore>, url: Option<String>) -> Self {
+        let (changes, pending) = mpsc::channel();
+        let writer = store.clone();
+        thread::spawn(move || {
+            for change in pending {
+                let written = match &change {
+                    Change::Put(stream_id, entry) => writer.put(stream_id, entry, TTL),
+                    Change::Remove(stream_id) => writer.remove(stream_id),
+                };
+                if let Err(err) = written {
+                    info!("⚠️ Cannot update stream presence: {}", err);
+                }
+            }
+        });
+        Presence {
+            store,
+            instance: Uuid::new_v4().to_string(),
+            url,
+            changes,
+        }
+    }
+
+    // ✅ The stream is here, as `info` describes it
+    pub(crate) fn announce(&self, info: StreamInfo) {
+        let stream_id = info.id.clone();
+        let present = Present {
+            instance: self.instance.clone(),
+            url: self.url.clone(),
+            info,
+        };
+        if let Ok(entry) = serde_json::to_string(&present) {
+            let _ = self.changes.send(Change::Put(stream_id, entry));
+        }
+    }
+
+    pub(crate) fn withdraw(&self, stream_id: &str) {
+        let _ = self.changes.send(Change::Remove(stream_id.to_string()));
+    }
+
+    // ✅ Streams other instances have announced; none if the store can't be read
+    pub(crate) async fn elsewhere(&self) -> Vec<Present> {
+        let store = self.store.clone();
+        let entries = match web::block(move || store.entries()).await {
+            Ok(Ok(entries)) => entries,
+            Ok(Err(err)) => {
+                info!("⚠️ Cannot read stream presence: {}", err);
+                return Vec::new();
+            }
+            Err(_) => return Vec::new(),
+        };
+        entries
+            .iter()
+            .filter_map(|entry| serde_json::from_str::<Present>(entry).ok())
+            .filter(|present| present.instance != self.instance)
+            .collect()
+    }
+
+    pub(crate) async fn find_elsewhere(&self, stream_id: &str) -> Option<Present> {
+        self.elsewhere()
+            .await
+            .into_iter()
+            .find(|present| present.info.id == stream_id)
+    }
+}
+
+// This process only (the default)
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl PresenceStore for MemoryStore {
+    fn put(&self, stream_id: &str, entry: &str, ttl: Duration) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.insert(
+            stream_id.to_string(),
+            (entry.to_string(), Instant::now() + ttl),
+        );
+        Ok(())
+    }
+
+    fn remove(&self, stream_id: &str) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.remove(stream_id);
+        Ok(())
+    }
+
+    fn entries(&self) -> io::Result<Vec<String>> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        entries.retain(|_, (_, until)| *until > now);
+        Ok(entries.values().map(|(entry, _)| entry.clone()).collect())
+    }
+}
+
+// Shared between instances, in Redis: `redis://[[user]:password@]host[:port][/db]`
+pub struct RedisStore {
+    redis: Redis,
+}
+
+impl RedisStore {
+    pub fn new(url: &str) -> Result<Self, String> {
+        Ok(RedisStore {
+            redis: Redis::new(url)?,
+        })
+    }
+
+    pub fn addr(&self) -> &str {
+        self.redis.addr()
+    }
+
+    // ✅ Check Redis can be reached, and the credentials are good
+    pub fn ping(&self) -> io::Result<()> {
+        self.redis.ping()
+    }
+}
+
+impl PresenceStore for RedisStore {
+    fn put(&self, stream_id: &str, entry: &str, ttl: Duration) -> io::Result<()> {
+        let key = format!("{}{}", KEY_PREFIX, stream_id);
+        let ttl = ttl.as_millis().to_string();
+        self.redis
+            .command(&["SET", &key, entry, "PX", &ttl])
+            .map(|_| ())
+    }
+
+    fn remove(&self, stream_id: &str) -> io::Result<()> {
+        let key = format!("{}{}", KEY_PREFIX, stream_id);
+        self.redis.command(&["DEL", &key]).map(|_| ())
+    }
+
+    fn entries(&self) -> io::Result<Vec<String>> {
+        let pattern = format!("{}*", KEY_PREFIX);
+        let mut keys = Vec::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let reply = self
+                .redis
+                .command(&["SCAN", &cursor, "MATCH", &pattern, "COUNT", "100"])?;
+            let Reply::Array(mut parts) = reply else {
+                return Err(reply.unexpected());
+            };
+            match (parts.pop(), parts.pop()) {
+                (Some(Reply::Array(found)), Some(next)) => {
+                    keys.extend(found.into_iter().filter_map(Reply::text));
+                    cursor = next.text().unwrap_or_default();
+                }
+                _ => return Err(io::Error::other("malformed SCAN reply")),
+            }
+            if cursor == "0" || cursor.is_empty() {
+                break;
+            }
+        }
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut args = vec!["MGET"];
+        args.extend(keys.iter().map(String::as_str));
+        match self.redis.command(&args)? {
+            // Keys that timed out since the SCAN come back nil
+            Reply::Array(values) => Ok(values.into_iter().filter_map(Reply::text).collect()),
+            reply => Err(reply.unexpected()),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// Quality recommendations (--recommend-quality): a room keeps its watchers' latest
+// `stats` reports and turns them into a bitrate ceiling for the streamer's
+// encoder, broadcast as a `recommendation` (see `tuesdays_protocol::signal`):
+//
+//   median loss above 5%, or a watcher froze   → 70% of what watchers receive
+//   median loss under 1%, nobody froze         → 15% more than the last ceiling
+//
+// At most one recommendation every RECOMMEND_INTERVAL, only for changes of 10%
+// or more, from reports younger than STALE. Decreases follow what watchers get,
+// so a ceiling is only raised once congestion has made one.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tuesdays_protocol::{Layer, Signal};
+
+const RECOMMEND_INTERVAL: Duration = Duration::from_secs(5);
+const STALE: Duration = Duration::from_secs(15);
+const MIN_KBPS: u32 = 150;
+const MAX_KBPS: u32 = 4000;
+
+// One watcher's last report
+struct Report {
+    at: Instant,
+    bitrate_kbps: f64,
+    packet_loss_pct: f64,
+    // Reported freezes are totals; what this report added
+    freezes: u64,
+    new_freezes: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Qoe {
+    reports: HashMap<String, Report>,
+    ceiling: Option<u32>,
+    recommended_at: Option<Instant>,
+}
+
+impl Qoe {
+    // ✅ Take a watcher's `stats` report; ignored if it lacks what we need
+    pub fn report(&mut self, watcher_id: &str, report: &Value) {
+        let field = |name| report.get(name).and_then(Value::as_f64);
+        let (Some(bitrate_kbps), Some(packet_loss_pct)) =
+            (field("bitrate_kbps"), field("packet_loss_pct"))
+        else {
+            return;
+        };
+        let freezes = report.get("freezes").and_then(Value::as_u64).unwrap_or(0);
+        let previous = self.reports.get(watcher_id).map_or(freezes, |r| r.freezes);
+        self.reports.insert(
+            watcher_id.to_string(),
+            Report {
+                at: Instant::now(),
+                bitrate_kbps,
+                packet_loss_pct,
+                freezes,
+                new_freezes: freezes.saturating_sub(previous),
+            },
+        );
+    }
+
+    pub fn forget(&mut self, watcher_id: &str) {
+        self.reports.remove(watcher_id);
+    }
+
+    // A new streamer starts from its own settings
+    pub fn reset(&mut self) {
+        self.reports.clear();
+        self.ceiling = None;
+    }
+
+    // ✅ A recommendation, if it's time for one and there's something to change
+    pub fn recommend(&mut self) -> Option<Signal> {
+        let now = Instant::now();
+        if self
+            .recommended_at
+            .is_some_and(|at| now.duration_since(at) < RECOMMEND_INTERVAL)
+        {
+            return None;
+        }
+        let fresh: Vec<&Report> = self
+            .reports
+            .values()
+            .filter(|report| now.duration_since(report.at) < STALE)
+            .collect();
+        if fresh.is_empty() {
+            return None;
+        }
+        let loss = median(fresh.iter().map(|report| report.packet_loss_pct));
+        let received = median(fresh.iter().map(|report| report.bitrate_kbps));
+        let froze = fresh.iter().any(|report| report.new_freezes > 0);
+
+        let target = if loss > 5.0 || froze {
+            (received * 0.7) as u32
+        } else if loss < 1.0 {
+            (self.ceiling? as f64 * 1.15) as u32
+        } else {
+            return None;
+        }
+        .clamp(MIN_KBPS, MAX_KBPS);
+        if self
+            .ceiling
+            .is_some_and(|ceiling| target.abs_diff(ceiling) * 10 < ceiling)
+        {
+            return None;
+        }
+
+        self.ceiling = Some(target);
+        self.recommended_at = Some(now);
+        Some(Signal::Recommendation {
+            bitrate_kbps: target,
+            layer: layer(target),
+        })
+    }
+}
+
+fn median(values: impl Iterator<Item = f64>) -> f64 {
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2]
+}
+
+// The simulcast layer a stream of this bitrate would be sent as
+fn layer(kbps: u32) -> Layer {
+    match kbps {
+        1500.. => Layer::High,
+        500.. => Layer::Medium,
+        _ => Layer::Low,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn stats(bitrate_kbps: f64, packet_loss_pct: f64, freezes: u64) -> Value {
+        json!({
+            "bitrate_kbps": bitrate_kbps,
+            "packet_loss_pct": packet_loss_pct,
+            "freezes": freezes,
+        })
+    }
+
+    fn recommended(qoe: &mut Qoe) -> Option<u32> {
+        match qoe.recommend()? {
+            Signal::Recommendation { bitrate_kbps, .. } => Some(bitrate_kbps),
+            signal => panic!("{:?}", signal),
+        }
+    }
+
+    // As if the last recommendation was an interval ago
+    fn wait_out_interval(qoe: &mut Qoe) {
+        qoe.recommended_at = Instant::now().checked_sub(RECOMMEND_INTERVAL);
+    }
+
+    #[test]
+    fn medians_take_the_middle_report() {
+        assert_eq!(median([3.0, 1.0, 2.0].into_iter()), 2.0);
+        assert_eq!(median([4.0, 1.0, 3.0, 2.0].into_iter()), 3.0);
+        assert_eq!(median([7.0].into_iter()), 7.0);
+    }
+
+    #[test]
+    fn loss_brings_the_ceiling_under_what_watchers_receive() {
+        let mut qoe = Qoe::default();
+        qoe.report("w1", &stats(1000.0, 10.0, 0));
+        qoe.report("w2", &stats(2000.0, 8.0, 0));
+        qoe.report("w3", &stats(3000.0, 0.0, 0));
+        assert_eq!(
+            qoe.recommend(),
+            Some(Signal::Recommendation {
+                bitrate_kbps: 1400,
+                layer: Layer::Medium,
+            })
+        );
+        // And not again within the interval
+        assert_eq!(qoe.recommend(), None);
+    }
+
+    #[test]
+    fn freezes_count_from_the_first_report() {
+        let mut qoe = Qoe::default();
+        // Frozen before the room heard from it
+        qoe.report("w1", &stats(1000.0, 0.0, 4));
+        assert_eq!(qoe.recommend(), None);
+        qoe.report("w1", &stats(1000.0, 0.0, 5));
+        assert_eq!(recommended(&mut qoe), Some(700));
+    }
+
+    #[test]
+    fn a_clean_room_raises_the_ceiling_it_was_given() {
+        let mut qoe = Qoe::default();
+        // Nothing to raise yet
+        qoe.report("w1", &stats(1000.0, 0.0, 0));
+        assert_eq!(qoe.recommend(), None);
+
+        qoe.report("w1", &stats(1000.0, 6.0, 0));
+        assert_eq!(recommended(&mut qoe), Some(700));
+        qoe.report("w1", &stats(700.0, 0.5, 0));
+        wait_out_interval(&mut qoe);
+        // 15% more, rounded down
+        assert_eq!(recommended(&mut qoe), Some(804));
+        // Some loss, but not enough to back off
+        qoe.report("w1", &stats(800.0, 3.0, 0));
+        wait_out_interval(&mut qoe);
+        assert_eq!(qoe.recommend(), None);
+    }
+
+    #[test]
+    fn small_changes_and_stale_reports_are_ignored() {
+        let mut qoe = Qoe::default();
+        qoe.report("w1", &stats(1000.0, 6.0, 0));
+        assert_eq!(recommended(&mut qoe), Some(700));
+        // 693 is within 10% of 700
+        qoe.report("w1", &stats(990.0, 6.0, 0));
+        wait_out_interval(&mut qoe);
+        assert_eq!(qoe.recommend(), None);
+
+        qoe.reports.get_mut("w1").unwrap().at = Instant::now().checked_sub(STALE).unwrap();
+        qoe.report("w2", &stats(3000.0, 0.0, 0));
+        wait_out_interval(&mut qoe);
+        assert_eq!(recommended(&mut qoe), Some(804));
+    }
+
+    #[test]
+    fn ceilings_stay_within_bounds() {
+        let mut qoe = Qoe::default();
+        qoe.report("w1", &stats(100.0, 50.0, 0));
+        assert_eq!(
+            qoe.recommend(),
+            Some(Signal::Recommendation {
+                bitrate_kbps: MIN_KBPS,
+                layer: Layer::Low,
+            })
+        );
+        qoe.reset();
+        qoe.report("w1", &stats(10_000.0, 50.0, 0));
+        wait_out_interval(&mut qoe);
+        assert_eq!(recommended(&mut qoe), Some(MAX_KBPS));
+    }
+
+    #[test]
+    fn reports_missing_figures_are_ignored() {
+        let mut qoe = Qoe::default();
+        qoe.report("w1", &json!({"bitrate_kbps": 1000.0}));
+        qoe.report("w2", &json!({"packet_loss_pct": 50.0}));
+        assert_eq!(qoe.recommend(), None);
+        qoe.report("w1", &stats(1000.0, 6.0, 0));
+        qoe.forget("w1");
+        assert_eq!(qoe.recommend(), None);
+    }
+}
@@ -0,0 +1,170 @@
+// Per-connection quotas (--max-messages-per-sec, --max-bytes-per-sec) on what a
+// member sends, with warnings as it nears them (see `tuesdays_protocol::quota`).
+// Frames are counted over fixed windows of WINDOW, the limits scaled to match,
+// so a burst is fine as long as the window as a whole stays under the rate.
+
+use std::time::{Duration, Instant};
+
+use tuesdays_protocol::{ErrorCode, Quota, Rejection, Warning};
+
+const WINDOW: Duration = Duration::from_secs(5);
+// Warn once this much of a quota is used up
+const WARN_PERCENT: u64 = 80;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Quotas {
+    pub messages_per_sec: Option<u64>,
+    pub bytes_per_sec: Option<u64>,
+}
+
+// One connection's usage in the current window
+pub(crate) struct QuotaMeter {
+    quotas: Quotas,
+    started: Instant,
+    messages: u64,
+    bytes: u64,
+    // Quotas already warned about this window
+    warned: Vec<Quota>,
+}
+
+impl QuotaMeter {
+    pub fn new(quotas: Quotas) -> Self {
+        QuotaMeter {
+            quotas,
+            started: Instant::now(),
+            messages: 0,
+            bytes: 0,
+            warned: Vec::new(),
+        }
+    }
+
+    // ✅ Count one frame of `len` bytes: warnings for quotas it brings near, or
+    // the rejection if it goes over one
+    pub fn count(&mut self, len: usize) -> Result<Vec<Warning>, Rejection> {
+        if self.started.elapsed() >= WINDOW {
+            *self = QuotaMeter::new(self.quotas);
+        }
+        self.messages += 1;
+        self.bytes += len as u64;
+
+        let mut warnings = Vec::new();
+        for (quota, used, per_sec) in [
+            (Quota::Messages, self.messages, self.quotas.messages_per_sec),
+            (Quota::Bytes, self.bytes, self.quotas.bytes_per_sec),
+        ] {
+            let Some(per_sec) = per_sec else {
+                continue;
+            };
+            let limit = per_sec * WINDOW.as_secs();
+            if used > limit {
+                return Err(Rejection::new(
+                    ErrorCode::QuotaExceeded,
+                    format!(
+                        "{} {} in {}s, over the limit of {}",
+                        used,
+                        quota.name(),
+                        WINDOW.as_secs(),
+                        limit
+                    ),
+                ));
+            }
+            if used * 100 >= limit * WARN_PERCENT && !self.warned.contains(&quota) {
+                self.warned.push(quota);
+                warnings.push(Warning::Quota {
+                    quota,
+                    used,
+                    limit,
+                    window_ms: WINDOW.as_millis() as u64,
+                });
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meter(messages_per_sec: Option<u64>, bytes_per_sec: Option<u64>) -> QuotaMeter {
+        QuotaMeter::new(Quotas {
+            messages_per_sec,
+            bytes_per_sec,
+        })
+    }
+
+    // As if the current window had run out
+    fn roll_over(meter: &mut QuotaMeter) {
+        meter.started = Instant::now().checked_sub(WINDOW).unwrap();
+    }
+
+    #[test]
+    fn no_quotas_allow_anything() {
+        let mut meter = meter(None, None);
+        for _ in 0..1000 {
+            assert_eq!(meter.count(1 << 20).unwrap(), []);
+        }
+    }
+
+    #[test]
+    fn the_exact_limit_is_allowed_and_one_more_is_not() {
+        // 2 a second, so 10 in the window
+        let mut meter = meter(Some(2), None);
+        for _ in 0..10 {
+            assert!(meter.count(1).is_ok());
+        }
+        let rejection = meter.count(1).unwrap_err();
+        assert_eq!(rejection.code, ErrorCode::QuotaExceeded);
+        assert_eq!(
+            rejection.detail.as_deref(),
+            Some("11 messages in 5s, over the limit of 10")
+        );
+    }
+
+    #[test]
+    fn bytes_are_limited_by_their_total() {
+        let mut meter = meter(None, Some(100));
+        assert!(meter.count(500).is_ok());
+        let rejection = meter.count(1).unwrap_err();
+        assert_eq!(
+            rejection.detail.as_deref(),
+            Some("501 bytes in 5s, over the limit of 500")
+        );
+    }
+
+    #[test]
+    fn nearing_a_quota_warns_once_a_window() {
+        let mut meter = meter(Some(2), None);
+        for _ in 0..7 {
+            assert_eq!(meter.count(1).unwrap(), []);
+        }
+        let warning = Warning::Quota {
+            quota: Quota::Messages,
+            used: 8,
+            limit: 10,
+            window_ms: 5000,
+        };
+        assert_eq!(meter.count(1).unwrap(), [warning]);
+        assert_eq!(meter.count(1).unwrap(), []);
+    }
+
+    #[test]
+    fn a_new_window_starts_from_nothing() {
+        let mut meter = meter(Some(2), Some(100));
+        for _ in 0..10 {
+            let _ = meter.count(50);
+        }
+        assert!(meter.count(1).is_err());
+
+        roll_over(&mut meter);
+        assert_eq!(meter.count(1).unwrap(), []);
+        for _ in 0..6 {
+            let _ = meter.count(1);
+        }
+        // The warning comes again in the new window
+        assert_eq!(meter.count(1).unwrap().len(), 1);
+        assert!(meter.count(1).is_ok());
+        assert!(meter.count(1).is_ok());
+        assert!(meter.count(1).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// Regions (--region, --region-url): in a deployment spanning several, each
+// member is sent to an instance near it instead of having its media and
+// signaling relayed across regions:
+//
+//   transmitter --region eu --region-url us=wss://us.tuesdays.example \
+//       --region-url ap=wss://ap.tuesdays.example --region-header X-Client-Region
+//
+// A member says where it is with `?region=us`, or the load balancer in front
+// says for it in --region-header. Then:
+//
+//   - a streamer from a region with a --region-url is sent there, so its stream
+//     is ingested near its source
+//   - with --fanout, so is a watcher, which watches any stream from its own
+//     region's instances
+//
+// Before either, with --presence-redis, a member of a stream that's live on
+// another instance is sent to it: the streamer (reconnecting, or joining a
+// shared room) always, and a watcher without --fanout. Being sent somewhere is
+// a `redirect` signal with the URL (same path and query) on an accepted
+// WebSocket, then `close::REDIRECTED`; see `tuesdays_protocol::signal`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use actix::{Actor, ActorContext, StreamHandler};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_actors::ws;
+use tuesdays_protocol::{RedirectReason, Signal, close};
+
+// --region-url REGION=URL
+#[derive(Clone, Debug)]
+pub struct RegionUrl {
+    pub region: String,
+    pub url: String,
+}
+
+impl FromStr for RegionUrl {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (region, url) = value
+            .split_once('=')
+            .filter(|(region, _)| !region.is_empty())
+            .ok_or_else(|| format!("expected REGION=URL, not '{}'", value))?;
+        if !(url.starts_with("ws://") || url.starts_with("wss://")) {
+            return Err(format!("'{}' must be a ws:// or wss:// URL", url));
+        }
+        Ok(RegionUrl {
+            region: region.to_ascii_lowercase(),
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Regions {
+    // This instance's
+    region: Option<String>,
+    // Where each of the others is served
+    urls: HashMap<String, String>,
+    // The header a load balancer puts the member's region in
+    header: Option<String>,
+}
+
+impl Regions {
+    pub fn new(region: Option<&str>, urls: Vec<RegionUrl>, header: Option<&str>) -> Self {
+        Regions {
+            region: region.map(str::to_ascii_lowercase),
+            urls: urls.into_iter().map(|url| (url.region, url.url)).collect(),
+            header: header.map(str::to_string),
+        }
+    }
+
+    // ✅ The member's region: its own say, or the load balancer's
+    fn of(&self, req: &HttpRequest) -> Option<String> {
+        let header = || {
+            let value = req.headers().get(self.header.as_deref()?)?;
+            value.to_str().ok().map(str::to_string)
+        };
+        crate::query_params(req)
+            .remove("region")
+            .or_else(header)
+            .map(|region| region.trim().to_ascii_lowercase())
+            .filter(|region| !region.is_empty())
+    }
+
+    // ✅ Where a member from another region belongs, with the same path and
+    // query; None if it's from ours, or one without a URL
+    pub(crate) fn elsewhere(&self, req: &HttpRequest) -> Option<String> {
+        let region = self.of(req)?;
+        if self.region.as_ref() == Some(&region) {
+            return None;
+        }
+        let url = self.urls.get(&region)?;
+        let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+        Some(format!("{}{}", url, path_and_query))
+    }
+}
+
+// Says where to go, and hangs up
+struct Redirecting {
+    signal: String,
+}
+
+impl Actor for Redirecting {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.text(self.signal.clone());
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(close::REDIRECTED),
+            description: Some(close::REDIRECTED_REASON.to_string()),
+        }));
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Redirecting {
+    fn handle(&mut self, _: Result<ws::Message, ws::ProtocolError>, _: &mut Self::Context) {}
+}
+
+// ✅ Accept the connection only to send the member to `url`
+pub(crate) fn redirect(
+    req: &HttpRequest,
+    stream: web::Payload,
+    url: String,
+    reason: RedirectReason,
+) -> Result<HttpResponse, actix_web::Error> {
+    let signal = Signal::Redirect { url, reason }.to_json();
+    ws::start(Redirecting { signal }, req, stream)
+}
@@ -0,0 +1,134 @@
+// Room registry: the one actor that knows every room by id. Handlers and actors
+// look rooms up, create them and forget them by message, so none of them blocks
+// its worker on a lock shared with all the others. A member is handed its room
+// once, as it connects (see `SignalingServer::connect`), and talks to it directly
+// from then on.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix::{Actor, Addr, ArbiterHandle, AsyncContext, Context, Handler, Message, MessageResult};
+
+use crate::archive::Archive;
+use crate::fanout::{Arrived, Fanout};
+use crate::metrics::Metrics;
+use crate::presence::Presence;
+use crate::room::RoomActor;
+use crate::throttle::JoinLimiter;
+
+#[derive(Default)]
+pub(crate) struct Registry {
+    rooms: HashMap<String, Addr<RoomActor>>,
+}
+
+impl Actor for Registry {
+    type Context = Context<Self>;
+}
+
+// Look up a room, creating it on `arbiter` (the caller's worker) if it doesn't
+// exist yet
+#[derive(Message)]
+#[rtype(result = "Addr<RoomActor>")]
+pub(crate) struct EnsureRoom {
+    pub room_id: String,
+    pub throttle: Option<JoinLimiter>,
+    pub recommend: bool,
+    pub archive: Option<Archive>,
+    pub presence: Presence,
+    pub fanout: Option<Fanout>,
+    pub metrics: Metrics,
+    pub resume_grace: Option<Duration>,
+    pub replay_broadcasts: usize,
+    pub batch_window: Option<Duration>,
+    pub arbiter: ArbiterHandle,
+}
+
+#[derive(Message)]
+#[rtype(result = "Option<Addr<RoomActor>>")]
+pub(crate) struct GetRoom(pub String);
+
+// Every room, for the directory and the admin API
+#[derive(Message)]
+#[rtype(result = "Vec<Addr<RoomActor>>")]
+pub(crate) struct ListRooms;
+
+// The room has stopped; `addr`, so a room created since under the same id stays
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct RoomStopped {
+    pub room_id: String,
+    pub addr: Addr<RoomActor>,
+}
+
+impl Handler<EnsureRoom> for Registry {
+    type Result = MessageResult<EnsureRoom>;
+
+    fn handle(&mut self, msg: EnsureRoom, ctx: &mut Self::Context) -> Self::Result {
+        // A room that has stopped but isn't forgotten yet is replaced
+        if let Some(room) = self.rooms.get(&msg.room_id)
+            && room.connected()
+        {
+            return MessageResult(room.clone());
+        }
+        let registry = ctx.address();
+        let room_id = msg.room_id.clone();
+        let room = RoomActor::start_in_arbiter(&msg.arbiter, move |_| {
+            RoomActor::new(
+                room_id,
+                registry,
+                msg.throttle,
+                msg.recommend,
+                msg.archive,
+                msg.presence,
+                msg.fanout,
+            )
+            .with_metrics(msg.metrics)
+            .with_resume_grace(msg.resume_grace)
+            .with_replay_broadcasts(msg.replay_broadcasts)
+            .with_batch_window(msg.batch_window)
+        });
+        self.rooms.insert(msg.room_id, room.clone());
+        MessageResult(room)
+    }
+}
+
+impl Handler<GetRoom> for Registry {
+    type Result = Option<Addr<RoomActor>>;
+
+    fn handle(&mut self, msg: GetRoom, _: &mut Self::Context) -> Self::Result {
+        self.rooms.get(&msg.0).cloned()
+    }
+}
+
+impl Handler<ListRooms> for Registry {
+    type Result = Vec<Addr<RoomActor>>;
+
+    fn handle(&mut self, _: ListRooms, _: &mut Self::Context) -> Self::Result {
+        self.rooms.values().cloned().collect()
+    }
+}
+
+// ✅ Pass what another instance's room published on to ours, if there is one
+impl Handler<Arrived> for Registry {
+    type Result = ();
+
+    fn handle(&mut self, msg: Arrived, _: &mut Self::Context) {
+        if let Some(room) = self.rooms.get(&msg.room_id) {
+            room.do_send(msg);
+        }
+    }
+}
+
+impl Handler<RoomStopped> for Registry {
+    type Result = ();
+
+    fn handle(&mut self, msg: RoomStopped, _: &mut Self::Context) {
+        if self
+            .rooms
+            .get(&msg.room_id)
+            .is_some_and(|room| *room == msg.addr)
+        {
+            self.rooms.remove(&msg.room_id);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// What the room sent a member that may resume (--resume-grace-secs), numbered,
+// so that after a dropped connection it can be sent what it missed; see
+// `tuesdays_protocol::resume`. The room keeps one for each streamer and watcher
+// while it's connected, and through its grace period once it's dropped.
+
+use std::collections::VecDeque;
+
+use tuesdays_protocol::resume::{self, REPLAY_WINDOW};
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Outbox {
+    last_seq: u64,
+    // The last REPLAY_WINDOW numbered messages, oldest first
+    kept: VecDeque<(u64, String)>,
+}
+
+impl Outbox {
+    // ✅ Number a message and keep it; anything but a JSON object goes as it is
+    pub fn push(&mut self, message: String) -> String {
+        let Some(numbered) = resume::number(&message, self.last_seq + 1) else {
+            return message;
+        };
+        self.last_seq += 1;
+        if self.kept.len() == REPLAY_WINDOW {
+            self.kept.pop_front();
+        }
+        self.kept.push_back((self.last_seq, numbered.clone()));
+        numbered
+    }
+
+    // ✅ What came after `last_seq`; None if some of it is no longer kept, or
+    // `last_seq` was never sent
+    pub fn since(&self, last_seq: u64) -> Option<Vec<String>> {
+        if last_seq > self.last_seq {
+            return None;
+        }
+        self.between(last_seq + 1, self.last_seq)
+    }
+
+    // ✅ Messages `from` through `to`; None if some of them are no longer kept, or
+    // were never sent. Empty when `to` comes before `from`
+    pub fn between(&self, from: u64, to: u64) -> Option<Vec<String>> {
+        let oldest = self.kept.front().map_or(self.last_seq + 1, |(seq, _)| *seq);
+        if from > to {
+            return Some(Vec::new());
+        }
+        if from < oldest || to > self.last_seq {
+            return None;
+        }
+        Some(
+            self.kept
+                .iter()
+                .filter(|(seq, _)| (from..=to).contains(seq))
+                .map(|(_, message)| message.clone())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(n: usize) -> String {
+        format!(r#"{{"n":{}}}"#, n)
+    }
+
+    // An outbox that's been sent messages 1 through `count`
+    fn outbox(count: usize) -> Outbox {
+        let mut outbox = Outbox::default();
+        for n in 1..=count {
+            outbox.push(message(n));
+        }
+        outbox
+    }
+
+    fn numbers(messages: Option<Vec<String>>) -> Option<Vec<u64>> {
+        messages.map(|messages| {
+            messages
+                .iter()
+                .map(|message| {
+                    let value: serde_json::Value = serde_json::from_str(message).unwrap();
+                    value["seq"].as_u64().unwrap()
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn json_objects_are_numbered_in_order() {
+        let mut outbox = Outbox::default();
+        assert_eq!(outbox.push(message(1)), r#"{"n":1,"seq":1}"#);
+        assert_eq!(outbox.push("not json".to_string()), "not json");
+        assert_eq!(outbox.push("[1,2]".to_string()), "[1,2]");
+        assert_eq!(outbox.push(message(2)), r#"{"n":2,"seq":2}"#);
+    }
+
+    #[test]
+    fn a_resume_gets_everything_after_its_last_seq() {
+        let outbox = outbox(5);
+        assert_eq!(numbers(outbox.since(2)), Some(vec![3, 4, 5]));
+        assert_eq!(numbers(outbox.since(0)), Some(vec![1, 2, 3, 4, 5]));
+        assert_eq!(numbers(outbox.since(5)), Some(vec![]));
+        // Never sent
+        assert_eq!(outbox.since(6), None);
+    }
+
+    #[test]
+    fn only_the_last_window_is_kept() {
+        let count = REPLAY_WINDOW + 10;
+        let outbox = outbox(count);
+        assert_eq!(outbox.kept.len(), REPLAY_WINDOW);
+        // The oldest still kept is number 11
+        assert_eq!(numbers(outbox.between(11, 11)), Some(vec![11]));
+        assert_eq!(
+            numbers(outbox.since(10)).map(|seqs| seqs.len()),
+            Some(REPLAY_WINDOW)
+        );
+    }
+
+    #[test]
+    fn a_gap_older_than_the_window_cannot_be_replayed() {
+        let outbox = outbox(REPLAY_WINDOW + 10);
+        assert_eq!(outbox.since(9), None);
+        assert_eq!(outbox.between(10, 12), None);
+    }
+
+    #[test]
+    fn replays_cover_exactly_the_range_asked_for() {
+        let outbox = outbox(10);
+        assert_eq!(numbers(outbox.between(4, 6)), Some(vec![4, 5, 6]));
+        assert_eq!(numbers(outbox.between(6, 4)), Some(vec![]));
+        assert_eq!(outbox.between(9, 11), None);
+        assert_eq!(Outbox::default().between(1, 1), None);
+    }
+}
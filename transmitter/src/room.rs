@@ -0,0 +1,1451 @@
+use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, MessageResult};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tuesdays_protocol::{
+    ArchiveEvent, AudienceRole, ClientInfo, ErrorCode, Signal, StreamDescriptor, StreamInfo,
+    StreamState, StreamerInfo, session,
+};
+use uuid::Uuid;
+
+use crate::archive::{self, Archive};
+use crate::batch::{self, Batcher};
+use crate::fanout::{Arrived, Crossing, Fanout};
+use crate::lifecycle::{ENDED_RETENTION, Lifecycle, PAUSE_GRACE};
+use crate::member::MemberWebSocket;
+use crate::metrics::Metrics;
+use crate::presence::{self, Presence};
+use crate::qoe::Qoe;
+use crate::registry::{Registry, RoomStopped};
+use crate::replay::Outbox;
+use crate::speaker::Speakers;
+use crate::throttle::{self, JoinLimiter};
+
+// Routed candidates for a member that hasn't joined yet: kept this long, this many
+const HELD_FOR: Duration = Duration::from_secs(30);
+const MAX_HELD: usize = 64;
+
+// How a member joined its room
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Plain room member (joined through /room)
+    Member,
+    // Publishes into the room, alone or alongside others (joined through /streamer)
+    Streamer,
+    // Consumes what the room's streamers publish (joined through /watcher)
+    Watcher,
+    // Streamer process waiting for start/stop commands (joined through /agent);
+    // never a room member, `room_id` is its own id
+    Agent,
+}
+
+impl Role {
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Member => "member",
+            Role::Streamer => "streamer",
+            Role::Watcher => "watcher",
+            Role::Agent => "agent",
+        }
+    }
+}
+
+// What to do when a streamer connects with the id of one that's still connected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateStreamer {
+    // Turn the new connection away (409 Conflict)
+    Reject,
+    // Close the old connection (4000, replaced); the room's watchers stay and
+    // renegotiate with the new one in a new session
+    #[default]
+    Takeover,
+    // Let both stream: the new one gets the first free "<id>-2", "<id>-3"...,
+    // which it can learn with `whois`
+    Suffix,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct AddMember {
+    pub member_id: String,
+    pub role: Role,
+    // Watchers only: its audience role; see `tuesdays_protocol::roles`
+    pub audience_role: Option<AudienceRole>,
+    // Streamers only: publishes no video
+    pub audio_only: bool,
+    // Streamers only: turned audience chat off
+    pub no_chat: bool,
+    pub user_agent: Option<String>,
+    // Watchers only, with --waiting-room: park it if the stream isn't live yet
+    pub waiting: bool,
+    // Streamers and watchers: the token it's resuming with, and the last message
+    // it saw; see `tuesdays_protocol::resume`
+    pub resume: Option<String>,
+    pub last_seq: Option<u64>,
+    pub addr: Addr<MemberWebSocket>,
+}
+
+// Whether a member may resume with `token`, and be sent what came after `last_seq`
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub(crate) struct CanResume {
+    pub member_id: String,
+    pub token: String,
+    pub last_seq: Option<u64>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct RemoveMember {
+    pub member_id: String,
+    // The connection that went, so one that replaced it isn't removed instead
+    pub addr: Addr<MemberWebSocket>,
+    // Dropped for not answering pings; see `heartbeat`
+    pub timed_out: bool,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct BroadcastMessage {
+    pub message: String,
+}
+
+// Numbered messages a member missed, again, for its `replay`; None if they're
+// no longer kept
+#[derive(Message)]
+#[rtype(result = "Option<Vec<String>>")]
+pub(crate) struct Resend {
+    pub member_id: String,
+    pub addr: Addr<MemberWebSocket>,
+    pub from: u64,
+    pub to: u64,
+}
+
+// A member's `broadcast`, for everyone in the room
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct MemberBroadcast {
+    pub member_id: String,
+    pub message: String,
+}
+
+// The room's current session, for the member's log lines
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SetSession {
+    pub session_id: String,
+}
+
+// A watcher's `stats` report, for quality recommendations
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct WatcherStats {
+    pub member_id: String,
+    pub report: Value,
+}
+
+// A watcher's `chat`, for everyone in the room; false if a streamer turned
+// audience chat off
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub(crate) struct AudienceChat {
+    pub from: String,
+    pub message: String,
+}
+
+// A streamer's or watcher's `assign`; `by` is the sender's audience role, None
+// for a streamer, who may assign any
+#[derive(Message)]
+#[rtype(result = "Result<(), ErrorCode>")]
+pub(crate) struct AssignRole {
+    pub from: String,
+    pub by: Option<AudienceRole>,
+    pub watcher_id: String,
+    pub role: AudienceRole,
+}
+
+// A watcher's new audience role, for its permission checks
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SetAudienceRole {
+    pub role: AudienceRole,
+}
+
+// A streamer's audio level, for the active speaker
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StreamerLevel {
+    pub member_id: String,
+    pub db: f64,
+}
+
+// A streamer's `describe`, for the directory
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SetMedia {
+    pub member_id: String,
+    pub media: StreamDescriptor,
+}
+
+// A routed offer, answer or candidate for one member; false if it isn't in the
+// room (and the message isn't held for it, or passed on to other instances)
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub(crate) struct Relay {
+    pub to: String,
+    pub message: String,
+    // Keep it for `to` if it hasn't joined yet (candidates racing the join)
+    pub hold: bool,
+    // Deliver it only if `to` has this role
+    pub role: Option<Role>,
+}
+
+// The transmitter is draining; members should reconnect to `url` next time
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Migrate {
+    pub url: String,
+}
+
+// An operator disconnects the member with this id and role, through the admin
+// API; false if there's no such member
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub(crate) struct Kick {
+    pub member_id: String,
+    pub role: Role,
+}
+
+// Define a custom message for closing WebSocket connections
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct CloseConnection;
+
+// An operator disconnected the member; see `Kick`
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Kicked;
+
+// The room expired; the member should go too
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct StreamExpired;
+
+// The member said which client build it is
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct SetClientVersion {
+    pub member_id: String,
+    pub client_version: Option<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Vec<String>")]
+pub(crate) struct GetMembers;
+
+// Every connection in the room, for the /clients API
+#[derive(Message)]
+#[rtype(result = "Vec<ClientInfo>")]
+pub(crate) struct GetClients;
+
+// The room's streamers, for the /api/streamers directory
+#[derive(Message)]
+#[rtype(result = "Vec<StreamerInfo>")]
+pub(crate) struct GetStreamers;
+
+// Directory entry for the room, whatever its state
+#[derive(Message)]
+#[rtype(result = "StreamInfo")]
+pub(crate) struct GetStreamInfo;
+
+#[derive(Message)]
+#[rtype(result = "StreamState")]
+pub(crate) struct GetStreamState;
+
+// A connected member and the role it joined with
+#[derive(Clone)]
+struct Member {
+    role: Role,
+    // Watchers only
+    audience_role: Option<AudienceRole>,
+    addr: Addr<MemberWebSocket>,
+    // Streamers only: publishes no video
+    audio_only: bool,
+    // Streamers only: turned audience chat off
+    no_chat: bool,
+    user_agent: Option<String>,
+    client_version: Option<String>,
+    // Streamers only: what it said it sends; see `Command::Describe`
+    media: Option<StreamDescriptor>,
+    // Streamers and watchers, with --resume-grace-secs: what it resumes with, and
+    // what it was sent, numbered, to replay; see `replay`
+    resume_token: Option<String>,
+    outbox: Option<Outbox>,
+    connected_at: Instant,
+}
+
+impl Member {
+    // ✅ Send a message to the member, numbered if it may resume
+    fn send(&mut self, message: String) {
+        let message = match &mut self.outbox {
+            Some(outbox) => outbox.push(message),
+            None => message,
+        };
+        self.addr.do_send(BroadcastMessage { message });
+    }
+
+    // ✅ Tell the member about the current session, if there is one
+    fn announce_session(&mut self, session_id: Option<&str>, rejoin_after: Option<Duration>) {
+        if let Some(session_id) = session_id {
+            self.addr.do_send(SetSession {
+                session_id: session_id.to_string(),
+            });
+            self.send(session::announce(session_id, rejoin_after));
+        }
+    }
+}
+
+// A member whose connection dropped, kept through its grace period to resume
+struct Dropped {
+    role: Role,
+    // Assigned at runtime, perhaps; it comes back with it
+    audience_role: Option<AudienceRole>,
+    token: String,
+    at: Instant,
+    // Whether it stopped answering pings, for the room to hear if it doesn't resume
+    timed_out: bool,
+    // What it's missing meanwhile is numbered and kept here
+    outbox: Outbox,
+}
+
+// Room actor to manage members
+pub(crate) struct RoomActor {
+    room_id: String,
+    registry: Addr<Registry>,
+    members: HashMap<String, Member>,
+    // Minted when the first streamer registers, or one re-registers; see
+    // `tuesdays_protocol::session`
+    session_id: Option<String>,
+    // Paces watchers back in when the streamer restarts; see `throttle`
+    throttle: Option<JoinLimiter>,
+    // Watchers' reports, with --recommend-quality; see `qoe`
+    qoe: Option<Qoe>,
+    // Streamers' audio levels; see `speaker`
+    speakers: Speakers,
+    // Registered, live, paused...; see `lifecycle`
+    lifecycle: Lifecycle,
+    // Where its lifecycle events are kept, with --archive-dir; see `archive`
+    archive: Option<Archive>,
+    // Where it's announced, for other instances; see `presence`
+    presence: Presence,
+    // Where what crosses it is passed on to other instances, with --fanout; see
+    // `fanout`
+    fanout: Option<Fanout>,
+    // Broadcast sizes and relays, for `GET /metrics`; see `metrics`
+    metrics: Metrics,
+    // Routed messages waiting for their member to join, oldest first
+    held: HashMap<String, Vec<(Instant, String)>>,
+    // Watchers parked until the stream starts, with --waiting-room, oldest first
+    waiting: Vec<(String, Member)>,
+    // How long a dropped streamer or watcher has to resume, with
+    // --resume-grace-secs
+    resume_grace: Option<Duration>,
+    // Dropped members that may still resume, by id
+    resumable: HashMap<String, Dropped>,
+    // How many of each streamer's broadcasts to replay to a watcher that joins,
+    // with --replay-broadcasts
+    replay_broadcasts: usize,
+    // Those broadcasts, by streamer, oldest first
+    recent: HashMap<String, VecDeque<String>>,
+    // Broadcasts held to go out together, with --batch-window-ms
+    batcher: Option<Batcher>,
+}
+
+impl RoomActor {
+    pub fn new(
+        room_id: String,
+        registry: Addr<Registry>,
+        throttle: Option<JoinLimiter>,
+        recommend: bool,
+        archive: Option<Archive>,
+        presence: Presence,
+        fanout: Option<Fanout>,
+    ) -> Self {
+        RoomActor {
+            room_id,
+            registry,
+            members: HashMap::new(),
+            session_id: None,
+            throttle,
+            qoe: recommend.then(Qoe::default),
+            speakers: Speakers::default(),
+            lifecycle: Lifecycle::default(),
+            archive,
+            presence,
+            fanout,
+            metrics: Metrics::default(),
+            held: HashMap::new(),
+            waiting: Vec::new(),
+            resume_grace: None,
+            resumable: HashMap::new(),
+            replay_broadcasts: 0,
+            recent: HashMap::new(),
+            batcher: None,
+        }
+    }
+
+    // Count into the server's metrics rather than a set of the room's own
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    // ✅ Keep each streamer's last `count` broadcasts for watchers that join late
+    pub fn with_replay_broadcasts(mut self, count: usize) -> Self {
+        self.replay_broadcasts = count;
+        self
+    }
+
+    // ✅ Send broadcasts that aren't signaling several to a frame; see `batch`
+    pub fn with_batch_window(mut self, window: Option<Duration>) -> Self {
+        self.batcher = window.map(Batcher::new);
+        self
+    }
+
+    pub fn with_resume_grace(mut self, grace: Option<Duration>) -> Self {
+        self.resume_grace = grace;
+        self
+    }
+
+    fn session(&self) -> &str {
+        session::label(self.session_id.as_deref())
+    }
+
+    // ✅ Send a message to every member, tagged with the session, and to the
+    // stream's rooms on other instances
+    fn broadcast(&mut self, message: String) {
+        let message = self.tag(message);
+        self.deliver(&message);
+        if let Some(fanout) = &self.fanout {
+            fanout.publish(&self.room_id, Crossing::Broadcast { message });
+        }
+    }
+
+    // ✅ A member's broadcast, held for the next batch if it may wait; one that
+    // may not sends what's held first, so it doesn't overtake it
+    fn broadcast_batched(&mut self, message: String, ctx: &mut actix::Context<Self>) {
+        let Some(batcher) = self.batcher.as_mut().filter(|_| batch::batches(&message)) else {
+            self.flush();
+            return self.broadcast(message);
+        };
+        if batcher.hold(message) {
+            ctx.run_later(batcher.window(), |room, _| room.flush());
+        }
+        if batcher.full() {
+            self.flush();
+        }
+    }
+
+    // Send what's held for the batch, if anything
+    fn flush(&mut self) {
+        if let Some(frame) = self.batcher.as_mut().and_then(Batcher::take) {
+            self.broadcast(frame);
+        }
+    }
+
+    // Send a message, as it is, to every member here, and keep it for those
+    // that may resume
+    fn deliver(&mut self, message: &str) {
+        self.metrics.broadcast(self.members.len());
+        for member in self.members.values_mut() {
+            member.send(message.to_string());
+        }
+        for dropped in self.resumable.values_mut() {
+            dropped.outbox.push(message.to_string());
+        }
+    }
+
+    // Stamp a message for one member with the session, like a broadcast
+    fn tag(&self, message: String) -> String {
+        match &self.session_id {
+            Some(session_id) => session::tag(&message, session_id),
+            None => message,
+        }
+    }
+
+    // ✅ Move the stream on in its lifecycle, telling the room, and set the timer
+    // for what comes next
+    fn enter(&mut self, state: StreamState, ctx: &mut actix::Context<Self>) {
+        let Some(signal) = self.lifecycle.enter(state) else {
+            return;
+        };
+        info!(
+            "📺 Stream '{}' is {} session={}",
+            self.room_id,
+            state.name(),
+            self.session()
+        );
+        if let Some(archive) = &self.archive {
+            archive.record(
+                &self.room_id,
+                self.session_id.as_deref(),
+                ArchiveEvent::Stream { state },
+            );
+        }
+        self.broadcast(signal.to_json());
+        self.announce();
+        match state {
+            StreamState::Paused => {
+                ctx.run_later(PAUSE_GRACE, |act, ctx| {
+                    if act.lifecycle.lasted(StreamState::Paused, PAUSE_GRACE) {
+                        act.enter(StreamState::Ended, ctx);
+                    }
+                });
+            }
+            StreamState::Ended => {
+                ctx.run_later(ENDED_RETENTION, |act, ctx| {
+                    if act.lifecycle.lasted(StreamState::Ended, ENDED_RETENTION) {
+                        act.enter(StreamState::Expired, ctx);
+                    }
+                });
+            }
+            StreamState::Expired => {
+                let parked = self.waiting.iter().map(|(_, parked)| parked);
+                for member in self.members.values().chain(parked) {
+                    member.addr.do_send(StreamExpired);
+                }
+                self.stop_if_expired(ctx);
+            }
+            StreamState::Registered | StreamState::Live => {}
+        }
+    }
+
+    // Directory entry for the room, whatever its state
+    fn info(&self) -> StreamInfo {
+        let mut streamers: Vec<(&String, &Member)> = self
+            .members
+            .iter()
+            .filter(|(_, m)| m.role == Role::Streamer)
+            .collect();
+        streamers.sort_by_key(|(member_id, _)| *member_id);
+        StreamInfo {
+            id: self.room_id.clone(),
+            watchers: self
+                .members
+                .values()
+                .filter(|m| m.role == Role::Watcher)
+                .count(),
+            session_id: self.session_id.clone(),
+            audio_only: !streamers.is_empty() && streamers.iter().all(|(_, m)| m.audio_only),
+            streamers: streamers.iter().map(|(id, _)| id.to_string()).collect(),
+            state: self.lifecycle.state(),
+            media: streamers
+                .iter()
+                .filter_map(|(id, m)| Some((id.to_string(), m.media.clone()?)))
+                .collect(),
+        }
+    }
+
+    // ✅ Tell other instances the stream is here, as it is now; gone once expired
+    fn announce(&self) {
+        match self.lifecycle.state() {
+            StreamState::Registered => {}
+            StreamState::Expired => self.presence.withdraw(&self.room_id),
+            _ => self.presence.announce(self.info()),
+        }
+    }
+
+    // An expired room goes once its last member, or watcher waiting, has
+    fn stop_if_expired(&self, ctx: &mut actix::Context<Self>) {
+        if self.lifecycle.state() == StreamState::Expired
+            && self.members.is_empty()
+            && self.waiting.is_empty()
+        {
+            ctx.stop();
+        }
+    }
+
+    // Whether `token` is the one the member was given, its time isn't up, and
+    // what came after `last_seq` is still kept
+    fn resumes(&self, member_id: &str, token: &str, last_seq: Option<u64>) -> bool {
+        let replayable =
+            |outbox: &Outbox| last_seq.is_none_or(|last_seq| outbox.since(last_seq).is_some());
+        let dropped = self.resumable.get(member_id).is_some_and(|dropped| {
+            dropped.token == token
+                && self
+                    .resume_grace
+                    .is_some_and(|grace| dropped.at.elapsed() < grace)
+                && replayable(&dropped.outbox)
+        });
+        // Back before we noticed it had gone
+        let connected = self.members.get(member_id).is_some_and(|member| {
+            member.resume_token.as_deref() == Some(token)
+                && member.outbox.as_ref().is_some_and(replayable)
+        });
+        dropped || connected
+    }
+
+    // ✅ Hold on to a dropped member's place, and for a streamer the session; end
+    // the session if the grace period passes with no streamer back
+    fn keep_for_resume(
+        &mut self,
+        member_id: String,
+        dropped: Dropped,
+        grace: Duration,
+        ctx: &mut actix::Context<Self>,
+    ) {
+        info!(
+            "⏳ Keeping session {} for {} '{}' to resume within {}s",
+            self.session(),
+            dropped.role.name(),
+            member_id,
+            grace.as_secs()
+        );
+        let (role, token, timed_out) = (dropped.role, dropped.token.clone(), dropped.timed_out);
+        self.resumable.insert(member_id.clone(), dropped);
+        let session_id = self.session_id.clone();
+        ctx.run_later(grace, move |act, _| {
+            if act
+                .resumable
+                .get(&member_id)
+                .is_some_and(|dropped| dropped.token == token)
+            {
+                act.resumable.remove(&member_id);
+                act.recent.remove(&member_id);
+                act.depart(&member_id, role, timed_out);
+            }
+            let streaming = act.members.values().any(|m| m.role == Role::Streamer);
+            if role == Role::Streamer && !streaming && act.session_id == session_id {
+                act.end_session();
+            }
+        });
+    }
+
+    // ✅ Tell the others a member has gone for good, so nobody keeps a peer for it
+    fn depart(&mut self, member_id: &str, role: Role, timed_out: bool) {
+        if role == Role::Watcher {
+            self.tell_streamers(Signal::WatcherLeft {
+                watcher_id: member_id.to_string(),
+                at_ms: archive::now_ms(),
+            });
+            self.tell_audience();
+        }
+        self.broadcast(
+            Signal::Left {
+                member_id: member_id.to_string(),
+                timed_out,
+            }
+            .to_json(),
+        );
+    }
+
+    fn end_session(&mut self) {
+        if let Some(session_id) = self.session_id.take() {
+            info!(
+                "🏁 Session {} ended for stream '{}'",
+                session_id, self.room_id
+            );
+        }
+    }
+
+    // ✅ Tell the streamers how many are watching
+    fn tell_audience(&mut self) {
+        let watchers = self.members.values().filter(|m| m.role == Role::Watcher);
+        let audience = Signal::Audience {
+            watchers: watchers.count(),
+        };
+        self.tell_streamers(audience);
+    }
+
+    // The streamers' recent broadcasts, tagged with the session
+    fn recent_broadcasts(&self) -> Vec<String> {
+        self.recent
+            .values()
+            .flatten()
+            .map(|message| self.tag(message.clone()))
+            .collect()
+    }
+
+    // Send a signal to the room's streamers alone
+    fn tell_streamers(&mut self, signal: Signal) {
+        let message = self.tag(signal.to_json());
+        for member in self
+            .members
+            .values_mut()
+            .filter(|m| m.role == Role::Streamer)
+        {
+            member.send(message.clone());
+        }
+    }
+
+    // ✅ Let an admitted member into the room, bringing it up to date
+    fn seat(&mut self, member_id: String, mut member: Member, resumed: bool) {
+        member.announce_session(self.session_id.as_deref(), None);
+        // ✅ Tell the member where the stream is at; one joining an expired room is
+        // sent away, and the room goes once it has
+        member.send(
+            self.tag(
+                Signal::Stream {
+                    state: self.lifecycle.state(),
+                }
+                .to_json(),
+            ),
+        );
+        if self.lifecycle.state() == StreamState::Expired {
+            member.addr.do_send(StreamExpired);
+        }
+
+        // ✅ Hand over what was routed to the member before it got here
+        if let Some(held) = self.held.remove(&member_id) {
+            for (at, message) in held {
+                if at.elapsed() < HELD_FOR {
+                    member.send(self.tag(message));
+                }
+            }
+        }
+
+        // ✅ A streamer is told who's already watching; see `Signal::WatcherJoined`
+        if member.role == Role::Streamer {
+            let now_ms = archive::now_ms();
+            for (watcher_id, watcher) in &self.members {
+                if watcher.role == Role::Watcher {
+                    let joined = Signal::WatcherJoined {
+                        watcher_id: watcher_id.clone(),
+                        at_ms: now_ms
+                            .saturating_sub(watcher.connected_at.elapsed().as_millis() as u64),
+                    };
+                    member.send(self.tag(joined.to_json()));
+                }
+            }
+        }
+
+        // Replace with the new connection
+        let role = member.role;
+        self.members.insert(member_id.clone(), member);
+        self.announce();
+        // ✅ One resuming never left, so the streamers still have a peer for it
+        if role == Role::Watcher && !resumed {
+            self.tell_streamers(Signal::WatcherJoined {
+                watcher_id: member_id.clone(),
+                at_ms: archive::now_ms(),
+            });
+        }
+        self.tell_audience();
+        info!(
+            "🙌 Member '{}' added to Room '{}' session={}",
+            member_id,
+            self.room_id,
+            self.session()
+        );
+    }
+}
+
+impl Actor for RoomActor {
+    type Context = actix::Context<Self>; // Use regular Actix context
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        info!("📡 Room '{}' created", self.room_id);
+        // Before the entry times out
+        ctx.run_interval(presence::REFRESH, |act, _| act.announce());
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        self.presence.withdraw(&self.room_id);
+        self.registry.do_send(RoomStopped {
+            room_id: self.room_id.clone(),
+            addr: ctx.address(),
+        });
+        info!("❌ Room '{}' removed", self.room_id);
+    }
+}
+
+// Implement GetMembers handler in RoomActor
+impl Handler<GetMembers> for RoomActor {
+    type Result = Vec<String>;
+
+    fn handle(&mut self, _: GetMembers, _: &mut Self::Context) -> Self::Result {
+        self.members.keys().cloned().collect()
+    }
+}
+
+impl Handler<GetClients> for RoomActor {
+    type Result = Vec<ClientInfo>;
+
+    fn handle(&mut self, _: GetClients, _: &mut Self::Context) -> Self::Result {
+        self.members
+            .iter()
+            .map(|(member_id, member)| ClientInfo {
+                room_id: self.room_id.clone(),
+                member_id: member_id.clone(),
+                role: member.role.name().to_string(),
+                user_agent: member.user_agent.clone(),
+                client_version: member.client_version.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Handler<GetStreamers> for RoomActor {
+    type Result = Vec<StreamerInfo>;
+
+    fn handle(&mut self, _: GetStreamers, _: &mut Self::Context) -> Self::Result {
+        let watchers = self
+            .members
+            .values()
+            .filter(|m| m.role == Role::Watcher)
+            .count();
+        self.members
+            .iter()
+            .filter(|(_, member)| member.role == Role::Streamer)
+            .map(|(member_id, member)| StreamerInfo {
+                id: member_id.clone(),
+                room_id: self.room_id.clone(),
+                watchers,
+                uptime_secs: member.connected_at.elapsed().as_secs(),
+            })
+            .collect()
+    }
+}
+
+impl Handler<SetClientVersion> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetClientVersion, _: &mut Self::Context) {
+        let parked = self
+            .waiting
+            .iter_mut()
+            .find(|(id, _)| *id == msg.member_id)
+            .map(|(_, parked)| parked);
+        if let Some(member) = self.members.get_mut(&msg.member_id).or(parked) {
+            member.client_version = msg.client_version;
+        }
+    }
+}
+
+impl Handler<AudienceChat> for RoomActor {
+    type Result = bool;
+
+    fn handle(&mut self, msg: AudienceChat, _: &mut Self::Context) -> Self::Result {
+        if self
+            .members
+            .values()
+            .any(|m| m.role == Role::Streamer && m.no_chat)
+        {
+            return false;
+        }
+        info!(
+            "💬 Watcher '{}' chats in Room '{}': {} session={}",
+            msg.from,
+            self.room_id,
+            msg.message,
+            self.session()
+        );
+        if let Some(archive) = &self.archive {
+            archive.record(
+                &self.room_id,
+                self.session_id.as_deref(),
+                ArchiveEvent::Chat {
+                    member_id: msg.from.clone(),
+                    message: msg.message.clone(),
+                },
+            );
+        }
+        let chat = Signal::Chat {
+            from: msg.from,
+            message: msg.message,
+        };
+        self.broadcast(chat.to_json());
+        true
+    }
+}
+
+impl Handler<AssignRole> for RoomActor {
+    type Result = Result<(), ErrorCode>;
+
+    fn handle(&mut self, msg: AssignRole, _: &mut Self::Context) -> Self::Result {
+        let watcher = self
+            .members
+            .get_mut(&msg.watcher_id)
+            .filter(|m| m.role == Role::Watcher)
+            .ok_or(ErrorCode::UnknownMember)?;
+        // ✅ Nobody demotes a watcher ranking above them
+        if msg
+            .by
+            .is_some_and(|by| watcher.audience_role.unwrap_or_default() > by)
+        {
+            return Err(ErrorCode::Forbidden);
+        }
+        watcher.audience_role = Some(msg.role);
+        watcher.addr.do_send(SetAudienceRole { role: msg.role });
+        info!(
+            "🎖️ '{}' made Watcher '{}' a {} in Room '{}' session={}",
+            msg.from,
+            msg.watcher_id,
+            msg.role.name(),
+            self.room_id,
+            self.session()
+        );
+        let signal = Signal::Role {
+            watcher_id: msg.watcher_id.clone(),
+            role: msg.role,
+        };
+        let message = self.tag(signal.to_json());
+        if let Some(watcher) = self.members.get_mut(&msg.watcher_id) {
+            watcher.send(message);
+        }
+        self.tell_streamers(signal);
+        Ok(())
+    }
+}
+
+impl Handler<GetStreamInfo> for RoomActor {
+    type Result = MessageResult<GetStreamInfo>;
+
+    fn handle(&mut self, _: GetStreamInfo, _: &mut Self::Context) -> Self::Result {
+        MessageResult(self.info())
+    }
+}
+
+impl Handler<GetStreamState> for RoomActor {
+    type Result = MessageResult<GetStreamState>;
+
+    fn handle(&mut self, _: GetStreamState, _: &mut Self::Context) -> Self::Result {
+        MessageResult(self.lifecycle.state())
+    }
+}
+
+impl Handler<CanResume> for RoomActor {
+    type Result = bool;
+
+    fn handle(&mut self, msg: CanResume, _: &mut Self::Context) -> Self::Result {
+        self.resumes(&msg.member_id, &msg.token, msg.last_seq)
+    }
+}
+
+// Handle adding a member
+impl Handler<AddMember> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: AddMember, ctx: &mut Self::Context) {
+        // Check if the member already exists
+        if let Some(existing) = self.members.get(&msg.member_id) {
+            info!(
+                "⚠️ Member '{}' already exists in Room '{}'. Replacing connection.",
+                msg.member_id, self.room_id
+            );
+
+            // Send termination signal using the new CloseConnection message
+            existing.addr.do_send(CloseConnection);
+        }
+        if let Some(at) = self.waiting.iter().position(|(id, _)| *id == msg.member_id) {
+            let (_, parked) = self.waiting.remove(at);
+            parked.addr.do_send(CloseConnection);
+        }
+
+        let may_resume =
+            matches!(msg.role, Role::Streamer | Role::Watcher) && self.resume_grace.is_some();
+        let mut member = Member {
+            role: msg.role,
+            audience_role: msg.audience_role,
+            addr: msg.addr,
+            audio_only: msg.audio_only,
+            no_chat: msg.no_chat,
+            user_agent: msg.user_agent,
+            client_version: None,
+            media: None,
+            resume_token: may_resume.then(|| Uuid::new_v4().to_string()),
+            outbox: may_resume.then(Outbox::default),
+            connected_at: Instant::now(),
+        };
+
+        // ✅ A member back in time carries on numbering where its old connection,
+        // or the room while it was away, left off, and is first sent what it missed
+        let resumed = msg
+            .resume
+            .as_deref()
+            .is_some_and(|token| self.resumes(&msg.member_id, token, msg.last_seq));
+        let dropped = self.resumable.remove(&msg.member_id);
+        // ✅ One back too late, or without its token, starts over: first the room
+        // hears its old place is gone
+        if let Some(dropped) = dropped.as_ref().filter(|_| !resumed) {
+            self.depart(&msg.member_id, dropped.role, dropped.timed_out);
+        }
+        if resumed {
+            let (audience_role, outbox) = match dropped {
+                Some(dropped) => (dropped.audience_role, Some(dropped.outbox)),
+                None => self
+                    .members
+                    .get(&msg.member_id)
+                    .map_or((None, None), |existing| {
+                        (existing.audience_role, existing.outbox.clone())
+                    }),
+            };
+            // ✅ Along with the role it had, which may have changed since it joined
+            if let Some(role) = audience_role.filter(|role| member.audience_role != Some(*role)) {
+                member.addr.do_send(SetAudienceRole { role });
+                member.audience_role = Some(role);
+            }
+            if let Some(outbox) = outbox {
+                let missed = msg.last_seq.and_then(|last_seq| outbox.since(last_seq));
+                for message in missed.unwrap_or_default() {
+                    member.addr.do_send(BroadcastMessage { message });
+                }
+                member.outbox = Some(outbox);
+            }
+        }
+
+        // ✅ With a waiting room, a watcher of a stream that hasn't started is parked
+        // until a streamer registers; one that's over is still sent away below
+        let state = self.lifecycle.state();
+        if msg.waiting && !resumed && !state.watchable() && state != StreamState::Expired {
+            info!(
+                "⏳ Watcher '{}' waiting for stream '{}' to start",
+                msg.member_id, self.room_id
+            );
+            self.waiting.push((msg.member_id, member));
+            return;
+        }
+
+        // ✅ The first streamer, or one re-registering, starts a new session for
+        // everyone in the room; others join the one that's live
+        let live = self.members.iter().any(|(member_id, existing)| {
+            existing.role == Role::Streamer && *member_id != msg.member_id
+        });
+        if resumed {
+            info!(
+                "🔁 {:?} '{}' resumed stream '{}' session={}",
+                msg.role,
+                msg.member_id,
+                self.room_id,
+                self.session()
+            );
+        } else if msg.role == Role::Streamer && live {
+            info!(
+                "🎥 Streamer '{}' joins stream '{}' session={}",
+                msg.member_id,
+                self.room_id,
+                self.session()
+            );
+        } else if msg.role == Role::Streamer {
+            let session_id = Uuid::new_v4().to_string();
+            info!(
+                "🆔 Session {} started for stream '{}'",
+                session_id, self.room_id
+            );
+            self.session_id = Some(session_id);
+            // Nobody resumes a session that's been replaced, and what was said in
+            // it is no context for the new one
+            for (member_id, dropped) in std::mem::take(&mut self.resumable) {
+                self.depart(&member_id, dropped.role, dropped.timed_out);
+            }
+            self.recent.clear();
+            if let Some(qoe) = &mut self.qoe {
+                qoe.reset();
+            }
+            // Everyone renegotiates with the new streamer; stagger them
+            let window = self
+                .throttle
+                .as_ref()
+                .map(|throttle| throttle.rejoin_window(self.members.len()));
+            let session_id = self.session_id.clone();
+            for existing in self.members.values_mut() {
+                existing.announce_session(session_id.as_deref(), window.map(throttle::spread));
+            }
+        }
+        if msg.role == Role::Streamer {
+            self.enter(StreamState::Live, ctx);
+        }
+        // ✅ Give the member what it needs to resume after a dropped connection
+        if let (Some(token), Some(grace)) = (member.resume_token.clone(), self.resume_grace) {
+            member.send(
+                self.tag(
+                    Signal::Resume {
+                        token,
+                        grace_secs: grace.as_secs(),
+                    }
+                    .to_json(),
+                ),
+            );
+        }
+        let streamer = (msg.role == Role::Streamer).then(|| msg.member_id.clone());
+        // ✅ A watcher joining late hears what the streamers said recently, such as
+        // the stream's metadata; one resuming has heard it already
+        let replay = if msg.role == Role::Watcher && !resumed {
+            self.recent_broadcasts()
+        } else {
+            Vec::new()
+        };
+        let member_id = msg.member_id.clone();
+        self.seat(msg.member_id, member, resumed);
+        if let Some(watcher) = self.members.get_mut(&member_id) {
+            for message in replay {
+                watcher.send(message);
+            }
+        }
+
+        // ✅ The stream has started: seat whoever was waiting for it
+        if let Some(streamer_id) = streamer {
+            for (member_id, mut parked) in std::mem::take(&mut self.waiting) {
+                parked.send(
+                    self.tag(
+                        Signal::StreamStarted {
+                            streamer_id: streamer_id.clone(),
+                        }
+                        .to_json(),
+                    ),
+                );
+                self.seat(member_id, parked, false);
+            }
+        }
+    }
+}
+
+// Handle removing a member
+impl Handler<RemoveMember> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: RemoveMember, ctx: &mut Self::Context) {
+        // ✅ A watcher that gave up waiting was never in the room; nobody to tell
+        if let Some(at) = self
+            .waiting
+            .iter()
+            .position(|(id, parked)| *id == msg.member_id && parked.addr == msg.addr)
+        {
+            self.waiting.remove(at);
+            info!(
+                "❌ Watcher '{}' stopped waiting for stream '{}'",
+                msg.member_id, self.room_id
+            );
+            self.stop_if_expired(ctx);
+            return;
+        }
+        // Only remove the member if it's still this connection, not one that
+        // replaced it
+        if self
+            .members
+            .get(&msg.member_id)
+            .is_none_or(|member| member.addr != msg.addr)
+        {
+            return;
+        }
+        let Some(removed) = self.members.remove(&msg.member_id) else {
+            return;
+        };
+        let removed_streamer = removed.role == Role::Streamer;
+        if let Some(qoe) = &mut self.qoe {
+            qoe.forget(&msg.member_id);
+        }
+        info!(
+            "❌ Member '{}' removed from Room '{}' session={}",
+            msg.member_id,
+            self.room_id,
+            self.session()
+        );
+        if let Some(speaker) = self.speakers.forget(&msg.member_id) {
+            self.broadcast(speaker.to_json());
+        }
+        // ✅ The session lasts as long as any streamer does, or, with a grace
+        // period, until the last one has had its chance to resume; without one,
+        // the stream is paused until one comes back
+        let streaming = self.members.values().any(|m| m.role == Role::Streamer);
+        let paused = removed_streamer && !streaming;
+        if paused {
+            self.enter(StreamState::Paused, ctx);
+        }
+        let role = removed.role;
+        let dropped = removed
+            .resume_token
+            .zip(removed.outbox)
+            .map(|(token, outbox)| Dropped {
+                role,
+                audience_role: removed.audience_role,
+                token,
+                at: Instant::now(),
+                timed_out: msg.timed_out,
+                outbox,
+            });
+        // ✅ One that may yet resume hasn't left: its peers keep their connections
+        // to it, and the room hears it's gone only if its grace is up first
+        if let (Some(dropped), Some(grace)) = (dropped, self.resume_grace) {
+            self.keep_for_resume(msg.member_id.clone(), dropped, grace, ctx);
+        } else {
+            self.depart(&msg.member_id, role, msg.timed_out);
+            self.recent.remove(&msg.member_id);
+            if paused {
+                self.end_session();
+            }
+        }
+        self.announce();
+        self.stop_if_expired(ctx);
+    }
+}
+
+// ✅ Close the member's connection and remove it right away, as if it had left
+impl Handler<Kick> for RoomActor {
+    type Result = bool;
+
+    fn handle(&mut self, msg: Kick, ctx: &mut Self::Context) -> Self::Result {
+        let Some(member) = self
+            .members
+            .get_mut(&msg.member_id)
+            .filter(|member| member.role == msg.role)
+        else {
+            return false;
+        };
+        // A kicked streamer's session isn't kept for it
+        member.resume_token = None;
+        let addr = member.addr.clone();
+        info!(
+            "🥾 {:?} '{}' disconnected from Room '{}' by an operator session={}",
+            msg.role,
+            msg.member_id,
+            self.room_id,
+            self.session()
+        );
+        addr.do_send(Kicked);
+        self.handle(
+            RemoveMember {
+                member_id: msg.member_id,
+                addr,
+                timed_out: false,
+            },
+            ctx,
+        );
+        true
+    }
+}
+
+// Handle broadcast messages in RoomActor
+impl Handler<BroadcastMessage> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastMessage, _: &mut Self::Context) {
+        info!(
+            "📢 Room '{}' broadcasting: {} session={}",
+            self.room_id,
+            msg.message,
+            self.session()
+        );
+
+        self.broadcast(msg.message);
+    }
+}
+
+impl Handler<Resend> for RoomActor {
+    type Result = Option<Vec<String>>;
+
+    fn handle(&mut self, msg: Resend, _: &mut Self::Context) -> Self::Result {
+        let member = self
+            .members
+            .get(&msg.member_id)
+            .filter(|member| member.addr == msg.addr)?;
+        member.outbox.as_ref()?.between(msg.from, msg.to)
+    }
+}
+
+impl Handler<MemberBroadcast> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: MemberBroadcast, ctx: &mut Self::Context) {
+        info!(
+            "📢 Room '{}' broadcasting from '{}': {} session={}",
+            self.room_id,
+            msg.member_id,
+            msg.message,
+            self.session()
+        );
+        // ✅ Keep what a streamer says about the stream for watchers yet to join;
+        // offers, answers and candidates belong to one negotiation, and a late
+        // watcher has its own
+        let streamer = self
+            .members
+            .get(&msg.member_id)
+            .is_some_and(|member| member.role == Role::Streamer);
+        let negotiating = matches!(
+            serde_json::from_str(&msg.message),
+            Ok(Signal::Offer { .. } | Signal::Answer { .. } | Signal::Candidate(_))
+        );
+        if streamer && !negotiating && self.replay_broadcasts > 0 {
+            let recent = self.recent.entry(msg.member_id).or_default();
+            if recent.len() == self.replay_broadcasts {
+                recent.pop_front();
+            }
+            recent.push_back(msg.message.clone());
+        }
+        self.broadcast_batched(msg.message, ctx);
+    }
+}
+
+// Deliver to one member, tagged with the session like a broadcast
+impl Handler<Relay> for RoomActor {
+    type Result = bool;
+
+    fn handle(&mut self, msg: Relay, _: &mut Self::Context) -> Self::Result {
+        self.metrics.relayed();
+        let wanted = |role: Role| msg.role.is_none_or(|wanted| role == wanted);
+        if self.members.get(&msg.to).is_some_and(|m| wanted(m.role)) {
+            let message = self.tag(msg.message);
+            if let Some(member) = self.members.get_mut(&msg.to) {
+                member.send(message);
+            }
+            return true;
+        }
+        // ✅ Away, but may be back: it gets it when it resumes
+        if self.resumable.get(&msg.to).is_some_and(|d| wanted(d.role)) {
+            let message = self.tag(msg.message);
+            if let Some(dropped) = self.resumable.get_mut(&msg.to) {
+                dropped.outbox.push(message);
+            }
+            return true;
+        }
+        // ✅ With --fanout it may be on another instance; only that one knows, so
+        // it counts as delivered
+        if let Some(fanout) = &self.fanout {
+            let crossing = Crossing::Relay {
+                to: msg.to.clone(),
+                message: self.tag(msg.message.clone()),
+            };
+            fanout.publish(&self.room_id, crossing);
+        }
+        if !msg.hold {
+            return self.fanout.is_some();
+        }
+        // ✅ Hold it for the member, dropping what's gone stale or the oldest
+        self.held.retain(|_, held| {
+            held.retain(|(at, _)| at.elapsed() < HELD_FOR);
+            !held.is_empty()
+        });
+        let held = self.held.entry(msg.to.clone()).or_default();
+        if held.len() == MAX_HELD {
+            held.remove(0);
+        }
+        held.push((Instant::now(), msg.message));
+        info!(
+            "📥 Room '{}' holds a message for '{}' until it joins session={}",
+            self.room_id,
+            msg.to,
+            self.session()
+        );
+        true
+    }
+}
+
+// What the stream's room on another instance passed on; see `fanout`
+impl Handler<Arrived> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Arrived, _: &mut Self::Context) {
+        match msg.crossing {
+            Crossing::Broadcast { message } => {
+                info!(
+                    "🌐 Room '{}' broadcasting from another instance: {} session={}",
+                    self.room_id,
+                    message,
+                    self.session()
+                );
+                self.deliver(&message);
+            }
+            Crossing::Relay { to, message } => {
+                let session = session::label(self.session_id.as_deref());
+                if let Some(member) = self.members.get_mut(&to) {
+                    info!(
+                        "🌐 Room '{}' delivers to '{}' from another instance: {} session={}",
+                        self.room_id, to, message, session
+                    );
+                    member.send(message);
+                }
+            }
+        }
+    }
+}
+
+impl Handler<Migrate> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Migrate, _: &mut Self::Context) {
+        info!(
+            "🚚 Room '{}' told to migrate to {} session={}",
+            self.room_id,
+            msg.url,
+            self.session()
+        );
+        // Only this instance is draining
+        let migrate = self.tag(Signal::Migrate { url: msg.url }.to_json());
+        self.deliver(&migrate);
+    }
+}
+
+// Fold a watcher's report in, and pass on what comes of it
+impl Handler<WatcherStats> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: WatcherStats, _: &mut Self::Context) {
+        let Some(qoe) = &mut self.qoe else {
+            return;
+        };
+        qoe.report(&msg.member_id, &msg.report);
+        if let Some(recommendation) = qoe.recommend() {
+            info!(
+                "🎚️ Room '{}' recommends {} session={}",
+                self.room_id,
+                recommendation.to_json(),
+                self.session()
+            );
+            self.broadcast(recommendation.to_json());
+        }
+    }
+}
+
+// ✅ Keep a streamer's description for the directory, and tell other instances
+impl Handler<SetMedia> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: SetMedia, _: &mut Self::Context) {
+        let Some(member) = self
+            .members
+            .get_mut(&msg.member_id)
+            .filter(|member| member.role == Role::Streamer)
+        else {
+            return;
+        };
+        let summary = msg.media.summary();
+        member.media = Some(msg.media);
+        info!(
+            "🎞️ Streamer '{}' in Room '{}' sends {} session={}",
+            msg.member_id,
+            self.room_id,
+            summary,
+            self.session()
+        );
+        self.announce();
+    }
+}
+
+// Pass on who's speaking, once there's more than one streamer to tell apart
+impl Handler<StreamerLevel> for RoomActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: StreamerLevel, _: &mut Self::Context) {
+        let streamers = self
+            .members
+            .values()
+            .filter(|m| m.role == Role::Streamer)
+            .count();
+        if streamers < 2 {
+            return;
+        }
+        if let Some(speaker) = self.speakers.level(&msg.member_id, msg.db) {
+            info!(
+                "🗣️ Room '{}' speaker: {} session={}",
+                self.room_id,
+                speaker.to_json(),
+                self.session()
+            );
+            self.broadcast(speaker.to_json());
+        }
+    }
+}
@@ -0,0 +1,589 @@
+// Relay sanitization: what the room forwards of offers, answers and candidates,
+// per deployment. Offers and answers are checked before they're relayed, and
+// refused, saying what's wrong, if their SDP is over --max-sdp-bytes (64 KiB by
+// default) or isn't structurally sound, as are signals missing their fields:
+//
+//   {"error":{"code":"malformed_signal","message":"Malformed signaling message",
+//             "details":"SDP media section 2 (video): payload type 120 has no rtpmap"}}
+//
+// --no-validate-sdp leaves only the size limit (--max-sdp-bytes 0 lifts that
+// too). Candidates are filtered only when asked:
+//
+//   --relay-only        only TURN relay candidates (`typ relay`), for networks
+//                       where peers must never learn each other's addresses
+//   --strip-private     no candidates with private, loopback, link-local or mDNS
+//                       (`.local`) addresses
+//   --transports udp    only candidates over these transports
+//
+// Filtered trickled candidates are dropped quietly; candidates inside an SDP are
+// cut from it. Broadcasts that aren't signals pass untouched.
+
+use std::net::IpAddr;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tuesdays_protocol::{ErrorCode, Rejection, Signal};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+// The largest SDP relayed unless --max-sdp-bytes says otherwise; a browser's,
+// candidates and all, is a few KiB
+pub const DEFAULT_MAX_SDP_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct Sanitizer {
+    pub relay_only: bool,
+    pub strip_private: bool,
+    // Empty: any transport
+    pub transports: Vec<Transport>,
+    pub validate_sdp: bool,
+    // The largest SDP relayed; 0 for any size
+    pub max_sdp_bytes: usize,
+}
+
+// ✅ Checking SDP, filtering nothing
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Sanitizer {
+            relay_only: false,
+            strip_private: false,
+            transports: Vec::new(),
+            validate_sdp: true,
+            max_sdp_bytes: DEFAULT_MAX_SDP_BYTES,
+        }
+    }
+}
+
+impl Sanitizer {
+    fn filters(&self) -> bool {
+        self.relay_only || self.strip_private || !self.transports.is_empty()
+    }
+
+    // ✅ The message to relay, None to drop it, or why it's refused
+    pub(crate) fn sanitize(&self, message: String) -> Result<Option<String>, Rejection> {
+        if !self.filters() && !self.validate_sdp && self.max_sdp_bytes == 0 {
+            return Ok(Some(message));
+        }
+        let signal = match serde_json::from_str::<Signal>(&message) {
+            Ok(signal) => signal,
+            Err(err) if self.validate_sdp && claims_signal(&message) => {
+                return Err(Rejection::new(ErrorCode::MalformedSignal, err.to_string()));
+            }
+            Err(_) => return Ok(Some(message)),
+        };
+        match signal {
+            Signal::Offer { sdp } | Signal::Answer { sdp } => {
+                let oversized =
+                    (self.max_sdp_bytes > 0 && sdp.len() > self.max_sdp_bytes).then(|| {
+                        format!(
+                            "is {} bytes, over the limit of {}",
+                            sdp.len(),
+                            self.max_sdp_bytes
+                        )
+                    });
+                let problem = match oversized {
+                    Some(problem) => Some(problem),
+                    None if self.validate_sdp => validate_sdp(&sdp).err(),
+                    None => None,
+                };
+                if let Some(problem) = problem {
+                    info!("🧹 Refused SDP: {}", problem);
+                    return Err(Rejection::new(
+                        ErrorCode::MalformedSignal,
+                        format!("SDP {}", problem),
+                    ));
+                }
+                Ok(Some(self.filter_sdp(message, &sdp)))
+            }
+            Signal::Candidate(candidate) => {
+                if candidate.is_end_of_candidates() || self.allows(&candidate.candidate) {
+                    return Ok(Some(message));
+                }
+                info!("🧹 Dropped candidate: {}", candidate.candidate);
+                Ok(None)
+            }
+            _ => Ok(Some(message)),
+        }
+    }
+
+    // Cut unwanted `a=candidate` lines, leaving the message as sent if there are none
+    fn filter_sdp(&self, message: String, sdp: &str) -> String {
+        if !self.filters() {
+            return message;
+        }
+        let kept: Vec<&str> = sdp
+            .split_inclusive('\n')
+            .filter(|line| match line.trim_end().strip_prefix("a=") {
+                Some(attribute) if attribute.starts_with("candidate:") => self.allows(attribute),
+                _ => true,
+            })
+            .collect();
+        if kept.len() == sdp.split_inclusive('\n').count() {
+            return message;
+        }
+        let Ok(mut json) = serde_json::from_str::<Value>(&message) else {
+            return message;
+        };
+        json["sdp"] = Value::from(kept.concat());
+        json.to_string()
+    }
+
+    // `candidate:<foundation> <component> <transport> <priority> <address> <port> typ <type> ...`
+    fn allows(&self, candidate: &str) -> bool {
+        let fields: Vec<&str> = candidate.split_whitespace().collect();
+        let (Some(transport), Some(address), Some(kind)) =
+            (fields.get(2), fields.get(4), fields.get(7))
+        else {
+            return false;
+        };
+        if self.relay_only && *kind != "relay" {
+            return false;
+        }
+        if self.strip_private && is_private(address) {
+            return false;
+        }
+        self.transports.is_empty()
+            || self
+                .transports
+                .iter()
+                .any(|allowed| allowed.name().eq_ignore_ascii_case(transport))
+    }
+}
+
+impl Transport {
+    fn name(self) -> &'static str {
+        match self {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+        }
+    }
+}
+
+fn is_private(address: &str) -> bool {
+    if address.ends_with(".local") {
+        return true;
+    }
+    match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()
+        }
+        Ok(IpAddr::V6(ip)) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || first & 0xfe00 == 0xfc00 // unique local
+                || first & 0xffc0 == 0xfe80 // link-local
+        }
+        Err(_) => false,
+    }
+}
+
+// JSON whose `type` says offer/answer/candidate, whether or not it's a valid one
+fn claims_signal(message: &str) -> bool {
+    let Ok(json) = serde_json::from_str::<Value>(message) else {
+        return false;
+    };
+    matches!(
+        json.get("type").and_then(Value::as_str),
+        Some("offer" | "answer" | "candidate")
+    )
+}
+
+// ✅ The shape every session description has (RFC 8866): `v=0` first, `<letter>=`
+// lines only, the session's o=/s=/t= before the first m=, and at least one m=
+// of `<media> <port> <proto> <format>...`, each of whose codec lines agrees
+// with it (see `Media::check`)
+fn validate_sdp(sdp: &str) -> Result<(), String> {
+    let mut lines = sdp.lines().filter(|line| !line.is_empty());
+    if lines.next() != Some("v=0") {
+        return Err("does not start with v=0".to_string());
+    }
+    let (mut origin, mut name, mut timing) = (false, false, false);
+    let mut sections: Vec<Media> = Vec::new();
+    for line in lines {
+        let bytes = line.as_bytes();
+        if bytes.len() < 2 || !bytes[0].is_ascii_lowercase() || bytes[1] != b'=' {
+            return Err(format!(
+                "has a line that's not <type>=<value>: {:.40}",
+                line
+            ));
+        }
+        match (bytes[0], sections.last_mut()) {
+            (b'o', None) => origin = true,
+            (b's', None) => name = true,
+            (b't', None) => timing = true,
+            (b'm', _) => {
+                if !(origin && name && timing) {
+                    return Err("has media before the session's o=, s= and t=".to_string());
+                }
+                let media = Media::parse(&line[2..])
+                    .ok_or_else(|| format!("has a malformed media line: {:.40}", line))?;
+                sections.push(media);
+            }
+            (b'a', Some(media)) => media.attribute(&line[2..]),
+            _ => {}
+        }
+    }
+    if sections.is_empty() {
+        return Err("has no media".to_string());
+    }
+    for (index, media) in sections.iter().enumerate() {
+        media.check().map_err(|problem| {
+            format!("media section {} ({}): {}", index + 1, media.kind, problem)
+        })?;
+    }
+    Ok(())
+}
+
+// One m= section, as far as it's checked
+struct Media<'a> {
+    kind: &'a str,
+    // Over RTP (`RTP/AVP`, `UDP/TLS/RTP/SAVPF`...), so its formats are payload types
+    rtp: bool,
+    formats: Vec<&'a str>,
+    // `<payload type> <encoding>/<clock rate>[/<channels>]`
+    rtpmaps: Vec<&'a str>,
+    // The payload types its a=fmtp lines are for
+    fmtps: Vec<&'a str>,
+}
+
+impl<'a> Media<'a> {
+    // `<media> <port>[/<count>] <proto> <format>...`
+    fn parse(line: &'a str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let kind = fields
+            .next()
+            .filter(|kind| !kind.is_empty() && kind.bytes().all(|b| b.is_ascii_alphanumeric()))?;
+        if fields.next()?.split('/').any(|n| n.parse::<u16>().is_err()) {
+            return None;
+        }
+        let proto = fields.next().filter(|proto| !proto.is_empty())?;
+        let formats: Vec<&str> = fields.collect();
+        if formats.is_empty() || formats.contains(&"") {
+            return None;
+        }
+        Some(Media {
+            kind,
+            rtp: proto.split('/').any(|part| part == "RTP"),
+            formats,
+            rtpmaps: Vec::new(),
+            fmtps: Vec::new(),
+        })
+    }
+
+    fn attribute(&mut self, attribute: &'a str) {
+        if let Some(rtpmap) = attribute.strip_prefix("rtpmap:") {
+            self.rtpmaps.push(rtpmap);
+        } else if let Some(fmtp) = attribute.strip_prefix("fmtp:") {
+            self.fmtps.push(fmtp.split(' ').next().unwrap_or_default());
+        }
+    }
+
+    // ✅ Payload types 0-127, each dynamic one (96-127) with its a=rtpmap of
+    // `<encoding>/<clock rate>`, and no a=rtpmap or a=fmtp for one it doesn't list
+    fn check(&self) -> Result<(), String> {
+        if !self.rtp {
+            return Ok(());
+        }
+        let payload_type = |format: &str| format.parse::<u8>().ok().filter(|pt| *pt <= 127);
+        if let Some(format) = self.formats.iter().find(|f| payload_type(f).is_none()) {
+            return Err(format!("'{}' is not an RTP payload type", format));
+        }
+        let mut mapped = Vec::new();
+        for rtpmap in &self.rtpmaps {
+            let malformed = || format!("malformed rtpmap: {:.40}", rtpmap);
+            let (format, encoding) = rtpmap.split_once(' ').ok_or_else(malformed)?;
+            let mut parts = encoding.split('/');
+            let (Some(codec), Some(clock_rate)) = (parts.next(), parts.next()) else {
+                return Err(malformed());
+            };
+            if codec.is_empty() || !clock_rate.parse::<u32>().is_ok_and(|rate| rate > 0) {
+                return Err(malformed());
+            }
+            if !self.formats.contains(&format) {
+                return Err(format!(
+                    "rtpmap for payload type {}, which it doesn't list",
+                    format
+                ));
+            }
+            mapped.push(format);
+        }
+        if let Some(format) = self.fmtps.iter().find(|f| !self.formats.contains(f)) {
+            return Err(format!(
+                "fmtp for payload type {}, which it doesn't list",
+                format
+            ));
+        }
+        if let Some(format) = self
+            .formats
+            .iter()
+            .find(|f| payload_type(f).is_some_and(|pt| pt >= 96) && !mapped.contains(f))
+        {
+            return Err(format!("payload type {} has no rtpmap", format));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = "v=0\r\no=- 1 2 IN IP4 0.0.0.0\r\ns=-\r\nt=0 0\r\n";
+
+    fn sdp(media: &str) -> String {
+        format!("{}{}", SESSION, media)
+    }
+
+    fn offer(sdp: &str) -> String {
+        Signal::Offer {
+            sdp: sdp.to_string(),
+        }
+        .to_json()
+    }
+
+    #[test]
+    fn sound_sdp_is_valid() {
+        let browser = sdp(concat!(
+            "a=group:BUNDLE 0 1 2\r\n",
+            "m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n",
+            "c=IN IP4 0.0.0.0\r\n",
+            "a=rtpmap:111 opus/48000/2\r\n",
+            "a=fmtp:111 minptime=10;useinbandfec=1\r\n",
+            "m=video 9 UDP/TLS/RTP/SAVPF 96 97\r\n",
+            "a=rtpmap:96 VP8/90000\r\n",
+            "a=rtpmap:97 rtx/90000\r\n",
+            "a=fmtp:97 apt=96\r\n",
+            "a=rtcp-fb:96 nack pli\r\n",
+            "m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n",
+            "a=sctp-port:5000\r\n",
+        ));
+        assert_eq!(validate_sdp(&browser), Ok(()));
+        // Bundled away, port 0, but still well formed
+        assert_eq!(validate_sdp(&sdp("m=audio 0 RTP/AVP 0 8\r\n")), Ok(()));
+    }
+
+    #[test]
+    fn each_problem_is_named() {
+        let cases = [
+            ("o=- 1 2 IN IP4 0.0.0.0\r\n", "does not start with v=0"),
+            (
+                &sdp("not a line\r\n"),
+                "has a line that's not <type>=<value>: not a line",
+            ),
+            (
+                "v=0\r\nm=audio 9 RTP/AVP 0\r\n",
+                "has media before the session's o=, s= and t=",
+            ),
+            (
+                &sdp("m=audio nine RTP/AVP 0\r\n"),
+                "has a malformed media line: m=audio nine RTP/AVP 0",
+            ),
+            (
+                &sdp("m=audio 9 RTP/AVP\r\n"),
+                "has a malformed media line: m=audio 9 RTP/AVP",
+            ),
+            (&sdp("a=ice-lite\r\n"), "has no media"),
+            (
+                &sdp("m=audio 9 RTP/AVP 200\r\n"),
+                "media section 1 (audio): '200' is not an RTP payload type",
+            ),
+            (
+                &sdp("m=audio 9 RTP/AVP 96\r\na=rtpmap:96 opus\r\n"),
+                "media section 1 (audio): malformed rtpmap: 96 opus",
+            ),
+            (
+                &sdp("m=audio 9 RTP/AVP 96\r\na=rtpmap:96 opus/0\r\n"),
+                "media section 1 (audio): malformed rtpmap: 96 opus/0",
+            ),
+            (
+                &sdp("m=audio 9 RTP/AVP 0\r\na=rtpmap:8 PCMA/8000\r\n"),
+                "media section 1 (audio): rtpmap for payload type 8, which it doesn't list",
+            ),
+            (
+                &sdp("m=audio 9 RTP/AVP 0\r\na=fmtp:101 0-15\r\n"),
+                "media section 1 (audio): fmtp for payload type 101, which it doesn't list",
+            ),
+            (
+                &sdp(
+                    "m=audio 9 RTP/AVP 0\r\nm=video 9 RTP/AVP 96 120\r\na=rtpmap:96 VP8/90000\r\n",
+                ),
+                "media section 2 (video): payload type 120 has no rtpmap",
+            ),
+        ];
+        for (sdp, problem) in cases {
+            assert_eq!(validate_sdp(sdp), Err(problem.to_string()), "{:?}", sdp);
+        }
+    }
+
+    #[test]
+    fn malformed_sdp_is_refused_by_default() {
+        let sanitizer = Sanitizer::default();
+        let refused = sanitizer
+            .sanitize(offer(&sdp("m=video 9 RTP/AVP 96\r\n")))
+            .unwrap_err();
+        assert_eq!(refused.code, ErrorCode::MalformedSignal);
+        assert_eq!(
+            refused.detail.as_deref(),
+            Some("SDP media section 1 (video): payload type 96 has no rtpmap")
+        );
+
+        let sound = offer(&sdp("m=audio 9 RTP/AVP 0\r\n"));
+        assert_eq!(sanitizer.sanitize(sound.clone()), Ok(Some(sound)));
+
+        let refused = sanitizer
+            .sanitize(r#"{"type":"answer"}"#.to_string())
+            .unwrap_err();
+        assert_eq!(refused.code, ErrorCode::MalformedSignal);
+        // Chat and the like pass untouched
+        let chat = r#"{"type":"hello","text":"v=1"}"#.to_string();
+        assert_eq!(sanitizer.sanitize(chat.clone()), Ok(Some(chat)));
+    }
+
+    #[test]
+    fn oversized_sdp_is_refused_even_unvalidated() {
+        let sanitizer = Sanitizer {
+            validate_sdp: false,
+            max_sdp_bytes: 100,
+            ..Sanitizer::default()
+        };
+        let padding = "a=x-padding\r\n".repeat(10);
+        let big = sdp(&format!("m=audio 9 RTP/AVP 0\r\n{}", padding));
+        let refused = sanitizer.sanitize(offer(&big)).unwrap_err();
+        assert_eq!(
+            refused.detail,
+            Some(format!("SDP is {} bytes, over the limit of 100", big.len()))
+        );
+
+        // Unvalidated, anything under the limit goes
+        let garbage = offer("not sdp");
+        assert_eq!(sanitizer.sanitize(garbage.clone()), Ok(Some(garbage)));
+
+        let unlimited = Sanitizer {
+            max_sdp_bytes: 0,
+            ..Sanitizer::default()
+        };
+        assert!(unlimited.sanitize(offer(&big)).is_ok());
+    }
+
+    fn candidate(candidate: &str) -> String {
+        format!(
+            r#"{{"type":"candidate","candidate":"{}","sdpMid":"0"}}"#,
+            candidate
+        )
+    }
+
+    const HOST: &str = "candidate:1 1 udp 2130706431 192.168.1.20 50000 typ host";
+    const MDNS: &str =
+        "candidate:2 1 udp 2130706431 3f1c2a9e-0000-4c1a-9d3e-0123456789ab.local 50001 typ host";
+    const SRFLX: &str =
+        "candidate:3 1 udp 1694498815 203.0.113.7 50002 typ srflx raddr 192.168.1.20 rport 50000";
+    const RELAY: &str =
+        "candidate:4 1 udp 16777215 198.51.100.9 3478 typ relay raddr 203.0.113.7 rport 50002";
+    const TCP: &str = "candidate:5 1 tcp 1518280447 203.0.113.7 9 typ host tcptype active";
+
+    #[test]
+    fn relay_only_keeps_only_relay_candidates() {
+        let sanitizer = Sanitizer {
+            relay_only: true,
+            ..Sanitizer::default()
+        };
+        for dropped in [HOST, MDNS, SRFLX, TCP] {
+            assert!(!sanitizer.allows(dropped), "{}", dropped);
+            assert_eq!(sanitizer.sanitize(candidate(dropped)), Ok(None));
+        }
+        assert!(sanitizer.allows(RELAY));
+        let relay = candidate(RELAY);
+        assert_eq!(sanitizer.sanitize(relay.clone()), Ok(Some(relay)));
+        // End of candidates always goes through
+        let end = candidate("");
+        assert_eq!(sanitizer.sanitize(end.clone()), Ok(Some(end)));
+    }
+
+    #[test]
+    fn private_addresses_are_told_apart() {
+        for private in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.20",
+            "127.0.0.1",
+            "169.254.10.10",
+            "0.0.0.0",
+            "3f1c2a9e-0000-4c1a-9d3e-0123456789ab.local",
+            "::1",
+            "::",
+            "fd12:3456:789a::1",
+            "fc00::1",
+            "fe80::1c2b:3aff:fe4d:5e6f",
+        ] {
+            assert!(is_private(private), "{}", private);
+        }
+        for public in ["203.0.113.7", "8.8.8.8", "2001:db8::1", "example.com"] {
+            assert!(!is_private(public), "{}", public);
+        }
+
+        let sanitizer = Sanitizer {
+            strip_private: true,
+            ..Sanitizer::default()
+        };
+        assert!(!sanitizer.allows(HOST));
+        assert!(!sanitizer.allows(MDNS));
+        // Only the candidate's own address counts, not its raddr
+        assert!(sanitizer.allows(SRFLX));
+        assert!(sanitizer.allows(RELAY));
+    }
+
+    #[test]
+    fn transports_keep_only_those_listed() {
+        let udp = Sanitizer {
+            transports: vec![Transport::Udp],
+            ..Sanitizer::default()
+        };
+        assert!(udp.allows(SRFLX));
+        assert!(!udp.allows(TCP));
+        let tcp = Sanitizer {
+            transports: vec![Transport::Tcp],
+            ..Sanitizer::default()
+        };
+        assert!(!tcp.allows(SRFLX));
+        assert!(tcp.allows(TCP));
+        assert!(tcp.allows(&TCP.replace(" tcp ", " TCP ")));
+        // Too short to say
+        assert!(!udp.allows("candidate:1 1 udp"));
+    }
+
+    #[test]
+    fn filtered_candidates_are_cut_from_sdp() {
+        let sanitizer = Sanitizer {
+            relay_only: true,
+            ..Sanitizer::default()
+        };
+        let full = sdp(&format!(
+            "m=audio 9 UDP/TLS/RTP/SAVPF 0\r\na=mid:0\r\na={}\r\na={}\r\na={}\r\na=end-of-candidates\r\n",
+            HOST, RELAY, SRFLX
+        ));
+        let Ok(Some(filtered)) = sanitizer.sanitize(offer(&full)) else {
+            panic!("offer refused");
+        };
+        let Ok(Signal::Offer { sdp: filtered }) = serde_json::from_str(&filtered) else {
+            panic!("not an offer: {}", filtered);
+        };
+        assert_eq!(
+            filtered,
+            sdp(&format!(
+                "m=audio 9 UDP/TLS/RTP/SAVPF 0\r\na=mid:0\r\na={}\r\na=end-of-candidates\r\n",
+                RELAY
+            ))
+        );
+
+        // Nothing to cut: relayed exactly as sent
+        let relayed = offer(&sdp(&format!("m=audio 9 RTP/AVP 0\r\na={}\r\n", RELAY)));
+        assert_eq!(sanitizer.sanitize(relayed.clone()), Ok(Some(relayed)));
+    }
+}
@@ -0,0 +1,159 @@
+// Active speaker: when several streamers share a room, the audio `level`s they
+// report (streamer --report-level) decide who's talking, announced to the room
+// as a `speaker` (see `tuesdays_protocol::signal`):
+//
+//   the loudest streamer above SPEAKING_DB takes over from a quiet one, or from
+//   one at least MARGIN_DB quieter; nobody speaks once all are quiet
+//
+// A speaker keeps the floor for at least HOLD, so crosstalk and pauses between
+// words don't flicker the highlight. Levels older than STALE count as quiet.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tuesdays_protocol::Signal;
+
+const SPEAKING_DB: f64 = -45.0;
+const MARGIN_DB: f64 = 6.0;
+const HOLD: Duration = Duration::from_secs(1);
+const STALE: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub(crate) struct Speakers {
+    // Each streamer's last level and when it came
+    levels: HashMap<String, (Instant, f64)>,
+    active: Option<String>,
+    switched_at: Option<Instant>,
+}
+
+impl Speakers {
+    // ✅ Take a streamer's level; a `speaker` signal if the floor changes hands
+    pub fn level(&mut self, streamer_id: &str, db: f64) -> Option<Signal> {
+        let now = Instant::now();
+        self.levels.insert(streamer_id.to_string(), (now, db));
+        if self
+            .switched_at
+            .is_some_and(|at| now.duration_since(at) < HOLD)
+        {
+            return None;
+        }
+
+        let speaking =
+            |(at, db): &(Instant, f64)| now.duration_since(*at) < STALE && *db > SPEAKING_DB;
+        let loudest = self
+            .levels
+            .iter()
+            .filter(|(_, level)| speaking(level))
+            .max_by(|(_, (_, a)), (_, (_, b))| a.total_cmp(b))
+            .map(|(id, (_, db))| (id.clone(), *db));
+        let current = self
+            .active
+            .as_ref()
+            .and_then(|id| self.levels.get(id))
+            .filter(|level| speaking(level))
+            .map(|(_, db)| *db);
+        let next = match (loudest, current) {
+            (Some((id, db)), Some(current)) if db >= current + MARGIN_DB => Some(id),
+            (_, Some(_)) => return None,
+            (loudest, None) => loudest.map(|(id, _)| id),
+        };
+        self.switch(next, now)
+    }
+
+    // ✅ A streamer left; if it had the floor, nobody has
+    pub fn forget(&mut self, streamer_id: &str) -> Option<Signal> {
+        self.levels.remove(streamer_id);
+        if self.active.as_deref() != Some(streamer_id) {
+            return None;
+        }
+        self.switch(None, Instant::now())
+    }
+
+    fn switch(&mut self, next: Option<String>, now: Instant) -> Option<Signal> {
+        if next == self.active {
+            return None;
+        }
+        self.active = next.clone();
+        self.switched_at = Some(now);
+        Some(Signal::Speaker { streamer_id: next })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speaker(id: &str) -> Option<Signal> {
+        Some(Signal::Speaker {
+            streamer_id: Some(id.to_string()),
+        })
+    }
+
+    // As if the floor last changed hands a hold ago
+    fn wait_out_hold(speakers: &mut Speakers) {
+        speakers.switched_at = Instant::now().checked_sub(HOLD);
+    }
+
+    #[test]
+    fn the_first_to_speak_takes_the_floor() {
+        let mut speakers = Speakers::default();
+        assert_eq!(speakers.level("cam1", -60.0), None);
+        assert_eq!(speakers.level("cam1", -20.0), speaker("cam1"));
+        assert_eq!(speakers.level("cam1", -18.0), None);
+    }
+
+    #[test]
+    fn the_floor_is_held_through_crosstalk_and_pauses() {
+        let mut speakers = Speakers::default();
+        speakers.level("cam1", -30.0);
+        // Much louder, and quiet, but too soon
+        assert_eq!(speakers.level("cam2", -5.0), None);
+        assert_eq!(speakers.level("cam1", -70.0), None);
+    }
+
+    #[test]
+    fn only_a_clearly_louder_speaker_takes_over() {
+        let mut speakers = Speakers::default();
+        speakers.level("cam1", -30.0);
+        wait_out_hold(&mut speakers);
+        assert_eq!(speakers.level("cam2", -25.0), None);
+        assert_eq!(speakers.level("cam2", -24.0), speaker("cam2"));
+    }
+
+    #[test]
+    fn a_quiet_speaker_yields_to_anyone_talking() {
+        let mut speakers = Speakers::default();
+        speakers.level("cam1", -30.0);
+        speakers.level("cam2", -40.0);
+        wait_out_hold(&mut speakers);
+        assert_eq!(speakers.level("cam1", -50.0), speaker("cam2"));
+
+        // And once everyone's quiet, nobody speaks
+        wait_out_hold(&mut speakers);
+        assert_eq!(
+            speakers.level("cam2", -60.0),
+            Some(Signal::Speaker { streamer_id: None })
+        );
+    }
+
+    #[test]
+    fn stale_levels_count_as_quiet() {
+        let mut speakers = Speakers::default();
+        speakers.level("cam1", -10.0);
+        speakers.levels.get_mut("cam1").unwrap().0 = Instant::now().checked_sub(STALE).unwrap();
+        wait_out_hold(&mut speakers);
+        assert_eq!(speakers.level("cam2", -40.0), speaker("cam2"));
+    }
+
+    #[test]
+    fn the_floor_is_freed_when_its_speaker_leaves() {
+        let mut speakers = Speakers::default();
+        speakers.level("cam1", -30.0);
+        speakers.level("cam2", -20.0);
+        assert_eq!(speakers.forget("cam2"), None);
+        assert_eq!(
+            speakers.forget("cam1"),
+            Some(Signal::Speaker { streamer_id: None })
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// Reconnect storm protection. When a popular streamer restarts, every watcher
+// renegotiates at once; two things spread them out:
+//
+// - Watcher joins are rate limited per stream (a token bucket refilled at
+//   `watcher_join_rate` per second, bursting up to one second's worth). Joins over
+//   the limit get 503 with a jittered Retry-After that grows with the backlog.
+// - Watchers already in the room when the streamer comes back get a jittered
+//   `rejoin_after_ms` with the new session, spread over the time the limiter
+//   needs to let all of them through (see `tuesdays_protocol::session`).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+// Retry-After never goes beyond this, however long the queue
+const MAX_RETRY: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+pub(crate) struct JoinLimiter {
+    // Joins per second per stream
+    rate: u32,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    // Joins turned away since the bucket was last full
+    rejected: u32,
+}
+
+impl JoinLimiter {
+    pub fn new(rate: u32) -> Self {
+        JoinLimiter {
+            rate: rate.max(1),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // ✅ Let a watcher join `stream_id` now, or say how long it should wait
+    pub fn admit(&self, stream_id: &str) -> Result<(), Duration> {
+        let rate = f64::from(self.rate);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        // Full buckets are the default; forget them so idle streams cost nothing
+        buckets.retain(|_, bucket| bucket.refilled(now, rate) < rate);
+
+        let bucket = buckets.entry(stream_id.to_string()).or_insert(Bucket {
+            tokens: rate,
+            updated: now,
+            rejected: 0,
+        });
+        bucket.tokens = bucket.refilled(now, rate);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        // Spread the rejected over as long as it takes to admit them all
+        bucket.rejected += 1;
+        Err(jittered(Duration::from_secs_f64(
+            f64::from(bucket.rejected) / rate,
+        )))
+    }
+
+    // ✅ Rejoin delays for watchers already in a room whose streamer came back
+    pub fn rejoin_window(&self, watchers: usize) -> Duration {
+        Duration::from_secs_f64(watchers as f64 / f64::from(self.rate)).min(MAX_RETRY)
+    }
+}
+
+impl Bucket {
+    fn refilled(&self, now: Instant, rate: f64) -> f64 {
+        (self.tokens + now.duration_since(self.updated).as_secs_f64() * rate).min(rate)
+    }
+}
+
+// A random point in the second half of `window` (at least one second), so
+// retries neither bunch up at the start nor wait needlessly long
+fn jittered(window: Duration) -> Duration {
+    let window = window.clamp(Duration::from_secs(1), MAX_RETRY);
+    window.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+// A random point anywhere in `window`
+pub(crate) fn spread(window: Duration) -> Duration {
+    window.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seconds_worth_of_joins_gets_through_at_once() {
+        let limiter = JoinLimiter::new(5);
+        for _ in 0..5 {
+            assert!(limiter.admit("cam").is_ok());
+        }
+        let retry = limiter.admit("cam").unwrap_err();
+        assert!(retry >= Duration::from_millis(500) && retry <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn retries_spread_out_as_the_backlog_grows() {
+        let limiter = JoinLimiter::new(1);
+        assert!(limiter.admit("cam").is_ok());
+        let retries: Vec<Duration> = (0..4).map(|_| limiter.admit("cam").unwrap_err()).collect();
+        // The fourth turned away waits for four seconds' worth of joins
+        assert!(retries[3] >= Duration::from_secs(2) && retries[3] <= Duration::from_secs(4));
+        assert!(retries.iter().all(|retry| *retry <= MAX_RETRY));
+    }
+
+    #[test]
+    fn the_bucket_refills_over_time() {
+        let limiter = JoinLimiter::new(20);
+        for _ in 0..20 {
+            assert!(limiter.admit("cam").is_ok());
+        }
+        assert!(limiter.admit("cam").is_err());
+        // One join every 50 ms
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.admit("cam").is_ok());
+    }
+
+    #[test]
+    fn streams_are_limited_separately() {
+        let limiter = JoinLimiter::new(1);
+        assert!(limiter.admit("cam1").is_ok());
+        assert!(limiter.admit("cam1").is_err());
+        assert!(limiter.admit("cam2").is_ok());
+        assert!(limiter.admit("cam2").is_err());
+    }
+
+    #[test]
+    fn rejoins_spread_over_the_time_to_admit_everyone() {
+        let limiter = JoinLimiter::new(10);
+        assert_eq!(limiter.rejoin_window(50), Duration::from_secs(5));
+        assert_eq!(limiter.rejoin_window(10_000), MAX_RETRY);
+        // A rate of 0 would never admit anyone
+        assert_eq!(JoinLimiter::new(0).rejoin_window(3), Duration::from_secs(3));
+    }
+}
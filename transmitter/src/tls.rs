@@ -0,0 +1,217 @@
+// Native TLS (--tls-bind with --tls-cert and --tls-key), so browsers on HTTPS
+// pages can connect with wss://. Connections are decrypted in front of the HTTP
+// server and passed on to it over loopback:
+//
+//   client ──TLS──▶ --tls-bind ──▶ 127.0.0.1:<any> ──▶ signaling routes
+//
+// --tls-domain NAME=CERT,KEY adds a certificate for clients asking for NAME (SNI),
+// for one instance serving several domains; the others get --tls-cert. With
+// --redirect-to-tls, plain connections to --bind get a 308 to the same path and
+// query over TLS instead. The server sees decrypted connections coming from
+// loopback, so `ClientAddrs` keeps who is really behind each one.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, web};
+use log::info;
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+
+// Who is behind each decrypted connection, by its loopback address
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ClientAddrs(Arc<Mutex<HashMap<SocketAddr, SocketAddr>>>);
+
+impl ClientAddrs {
+    fn lock(&self) -> MutexGuard<'_, HashMap<SocketAddr, SocketAddr>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // ✅ The client's own address; `peer` itself if it didn't come through TLS
+    pub fn resolve(&self, peer: Option<SocketAddr>) -> Option<SocketAddr> {
+        let peer = peer?;
+        Some(self.lock().get(&peer).copied().unwrap_or(peer))
+    }
+}
+
+// The certificate to present: by SNI name, or the default
+#[derive(Debug)]
+struct Certificates {
+    default: Arc<CertifiedKey>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for Certificates {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let named = hello
+            .server_name()
+            .and_then(|name| self.by_name.get(&name.to_ascii_lowercase()));
+        Some(named.unwrap_or(&self.default).clone())
+    }
+}
+
+// Another domain's certificate (--tls-domain NAME=CERT,KEY)
+#[derive(Clone, Debug)]
+pub struct Domain {
+    pub name: String,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl std::str::FromStr for Domain {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, cert, key) = value
+            .split_once('=')
+            .and_then(|(name, files)| Some((name, files.split_once(',')?)))
+            .map(|(name, (cert, key))| (name, cert, key))
+            .ok_or_else(|| format!("expected NAME=CERT,KEY, not '{}'", value))?;
+        Ok(Domain {
+            name: name.to_ascii_lowercase(),
+            cert: cert.into(),
+            key: key.into(),
+        })
+    }
+}
+
+// Terminates TLS in front of the signaling routes
+pub struct TlsFront {
+    acceptor: TlsAcceptor,
+    clients: ClientAddrs,
+}
+
+impl TlsFront {
+    // ✅ Load the default certificate and key (PEM), and those of other domains
+    pub fn new(cert: &Path, key: &Path, domains: &[Domain]) -> io::Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut by_name = HashMap::new();
+        for domain in domains {
+            let certified = certified_key(&provider, &domain.cert, &domain.key)?;
+            by_name.insert(domain.name.clone(), certified);
+        }
+        let certificates = Certificates {
+            default: certified_key(&provider, cert, key)?,
+            by_name,
+        };
+        let mut config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(certificates));
+        // Passed on to a plain HTTP/1.1 listener
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsFront {
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+            clients: ClientAddrs::default(),
+        })
+    }
+
+    pub(crate) fn clients(&self) -> ClientAddrs {
+        self.clients.clone()
+    }
+
+    // ✅ Accept TLS connections on `listener` and pass them on to `upstream`
+    pub async fn serve(
+        self,
+        listener: std::net::TcpListener,
+        upstream: SocketAddr,
+    ) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let listener = TcpListener::from_std(listener)?;
+        info!("🔐 Serving wss:// on {}", listener.local_addr()?);
+        loop {
+            let (stream, client) = listener.accept().await?;
+            let acceptor = self.acceptor.clone();
+            let clients = self.clients.clone();
+            actix_web::rt::spawn(async move {
+                if let Err(err) = pass_on(acceptor, stream, client, upstream, &clients).await {
+                    info!("🔐 TLS connection from {} failed: {}", client, err);
+                }
+            });
+        }
+    }
+}
+
+// ✅ One connection: the handshake, then bytes both ways until either side closes
+async fn pass_on(
+    acceptor: TlsAcceptor,
+    stream: TcpStream,
+    client: SocketAddr,
+    upstream: SocketAddr,
+    clients: &ClientAddrs,
+) -> io::Result<()> {
+    let mut tls = acceptor.accept(stream).await?;
+    let mut plain = TcpStream::connect(upstream).await?;
+    let local = plain.local_addr()?;
+    clients.lock().insert(local, client);
+    let result = tokio::io::copy_bidirectional(&mut tls, &mut plain).await;
+    clients.lock().remove(&local);
+    result.map(|_| ())
+}
+
+fn certified_key(
+    provider: &CryptoProvider,
+    cert: &Path,
+    key: &Path,
+) -> io::Result<Arc<CertifiedKey>> {
+    let invalid = |path: &Path, err: rustls::pki_types::pem::Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    };
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| invalid(cert, err))?;
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(|err| invalid(key, err))?;
+    let signing_key = provider
+        .key_provider
+        .load_private_key(private_key)
+        .map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", key.display(), err),
+            )
+        })?;
+    Ok(Arc::new(CertifiedKey::new(chain, signing_key)))
+}
+
+// The TLS port plain connections are sent to, with --redirect-to-tls
+#[derive(Clone, Copy)]
+pub struct RedirectToTls(pub u16);
+
+// ✅ Answer every plain request with the same path and query over TLS
+pub fn configure_redirect(cfg: &mut web::ServiceConfig, to: RedirectToTls) {
+    cfg.app_data(web::Data::new(to))
+        .default_service(web::to(redirect));
+}
+
+async fn redirect(req: HttpRequest, to: web::Data<RedirectToTls>) -> HttpResponse {
+    let websocket = req
+        .headers()
+        .get(header::UPGRADE)
+        .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"));
+    let scheme = if websocket { "wss" } else { "https" };
+    let host = req.connection_info().host().to_string();
+    // The host without the plain port, if it named one
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => &host,
+    };
+    let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    let location = format!("{}://{}:{}{}", scheme, host, to.0, path_and_query);
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
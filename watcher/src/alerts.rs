@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::stats::Stats;
+
+// Thresholds for picture-quality alerts
+pub struct AlertRules {
+    // No video frame for this long
+    pub freeze: Duration,
+    // Frame rate below this...
+    pub min_fps: f64,
+    // ...for at least this long
+    pub low_fps_for: Duration,
+    // Picture black for this long
+    pub black: Duration,
+    // POST every alert as JSON to this URL
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    Freeze,
+    LowFps,
+    BlackFrames,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Raised,
+    Cleared,
+}
+
+// One alert event, as logged and sent to the webhook
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub state: AlertState,
+    pub streamer_id: String,
+    pub watcher_id: String,
+    // Correlates with the streamer's and transmitter's logs; unknown until announced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub message: String,
+    // Measured value behind the alert (seconds frozen/black, or fps)
+    pub value: f64,
+    // Unix time in milliseconds
+    pub timestamp: u64,
+}
+
+// Raises an alert when a condition has held long enough and clears it once it's gone
+pub struct AlertMonitor {
+    rules: AlertRules,
+    streamer_id: String,
+    watcher_id: String,
+    session_id: Option<String>,
+    active: HashSet<AlertKind>,
+    // Frame counter and time at the previous evaluation, for the fps estimate
+    last_sample: Option<(u64, Instant)>,
+    low_fps_since: Option<Instant>,
+    client: reqwest::Client,
+}
+
+impl AlertMonitor {
+    pub fn new(rules: AlertRules, streamer_id: &str, watcher_id: &str) -> Self {
+        AlertMonitor {
+            rules,
+            streamer_id: streamer_id.to_string(),
+            watcher_id: watcher_id.to_string(),
+            session_id: None,
+            active: HashSet::new(),
+            last_sample: None,
+            low_fps_since: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    // ✅ Check the current stats against the rules; call about once a second
+    fn evaluate(&mut self, stats: &Stats) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let now = Instant::now();
+
+        // Nothing to judge until the first frame; the startup timeout covers that case
+        let Some(since_frame) = stats.since_last_frame() else {
+            return alerts;
+        };
+
+        let frozen = since_frame.as_secs_f64();
+        self.update(
+            &mut alerts,
+            AlertKind::Freeze,
+            since_frame >= self.rules.freeze,
+            frozen,
+            format!("No video frames for {:.1}s", frozen),
+        );
+
+        let frames = stats.video_frames();
+        if let Some((last_frames, last_time)) = self.last_sample.replace((frames, now)) {
+            let elapsed = now.duration_since(last_time).as_secs_f64();
+            let fps = if elapsed > 0.0 {
+                (frames - last_frames) as f64 / elapsed
+            } else {
+                0.0
+            };
+            let low = fps < self.rules.min_fps;
+            let since = if low {
+                *self.low_fps_since.get_or_insert(now)
+            } else {
+                self.low_fps_since = None;
+                now
+            };
+            self.update(
+                &mut alerts,
+                AlertKind::LowFps,
+                low && now.duration_since(since) >= self.rules.low_fps_for,
+                fps,
+                format!(
+                    "Frame rate {:.1} fps below {:.1} fps",
+                    fps, self.rules.min_fps
+                ),
+            );
+        }
+
+        let black = stats.black_for().unwrap_or_default();
+        self.update(
+            &mut alerts,
+            AlertKind::BlackFrames,
+            black >= self.rules.black,
+            black.as_secs_f64(),
+            format!("Picture black for {:.1}s", black.as_secs_f64()),
+        );
+
+        alerts
+    }
+
+    // Emit an event only when a condition flips
+    fn update(
+        &mut self,
+        alerts: &mut Vec<Alert>,
+        kind: AlertKind,
+        firing: bool,
+        value: f64,
+        message: String,
+    ) {
+        let state = match (firing, self.active.contains(&kind)) {
+            (true, false) => {
+                self.active.insert(kind);
+                AlertState::Raised
+            }
+            (false, true) => {
+                self.active.remove(&kind);
+                AlertState::Cleared
+            }
+            _ => return,
+        };
+        alerts.push(Alert {
+            kind,
+            state,
+            streamer_id: self.streamer_id.clone(),
+            watcher_id: self.watcher_id.clone(),
+            session_id: self.session_id.clone(),
+            message,
+            value,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        });
+    }
+
+    pub fn set_session(&mut self, session_id: &str) {
+        self.session_id = Some(session_id.to_string());
+    }
+
+    // ✅ Evaluate and emit whatever changed
+    pub fn check(&mut self, stats: &Stats) {
+        for alert in self.evaluate(stats) {
+            self.emit(alert);
+        }
+    }
+
+    // Log the alert and deliver it to the webhook in the background
+    fn emit(&self, alert: Alert) {
+        let body = match serde_json::to_string(&alert) {
+            Ok(body) => body,
+            Err(err) => {
+                eprintln!("⚠️ Cannot serialize alert: {}", err);
+                return;
+            }
+        };
+        println!("🚨 {}", body);
+
+        if let Some(url) = self.rules.webhook.clone() {
+            let request = self
+                .client
+                .post(url)
+                .header("content-type", "application/json")
+                .body(body);
+            tokio::spawn(async move {
+                if let Err(err) = request.send().await.and_then(|r| r.error_for_status()) {
+                    eprintln!("⚠️ Alert webhook failed: {}", err);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,83 @@
+// Delivery modes (--mode): one stream serves interactive and lean-back viewers
+// alike, each watcher trading latency for smoothness on its own connection:
+//
+//   realtime    50 ms jitter buffer, late packets dropped, lost ones not re-requested
+//   low         200 ms jitter buffer, lost packets re-requested every 100 ms
+//   resilient   1 s jitter buffer, lost packets re-requested every 50 ms
+//
+// Without --mode the watcher behaves as `low` but asks nothing of the room.
+// --jitter-latency and --jitter-drop-late still override the mode's buffer.
+
+use std::time::Duration;
+
+use tuesdays_protocol::DeliveryMode;
+use webrtc::api::interceptor_registry::{configure_rtcp_reports, configure_twcc_receiver_only};
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::interceptor::nack::generator::Generator;
+use webrtc::interceptor::nack::responder::Responder;
+use webrtc::interceptor::registry::Registry;
+use webrtc::rtp_transceiver::RTCPFeedback;
+use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
+
+use crate::player::Jitter;
+
+pub struct Profile {
+    pub jitter: Jitter,
+    // How often to NACK lost packets; None never asks for them again
+    pub nack_interval: Option<Duration>,
+}
+
+pub fn profile(mode: Option<DeliveryMode>) -> Profile {
+    match mode {
+        Some(DeliveryMode::Realtime) => Profile {
+            jitter: Jitter {
+                latency_ms: 50,
+                drop_late: true,
+            },
+            // A retransmission would arrive after its frame was due
+            nack_interval: None,
+        },
+        Some(DeliveryMode::Low) | None => Profile {
+            jitter: Jitter {
+                latency_ms: 200,
+                drop_late: false,
+            },
+            nack_interval: Some(Duration::from_millis(100)),
+        },
+        Some(DeliveryMode::Resilient) => Profile {
+            jitter: Jitter {
+                latency_ms: 1000,
+                drop_late: false,
+            },
+            nack_interval: Some(Duration::from_millis(50)),
+        },
+    }
+}
+
+// ✅ RTCP interceptors (reports, and NACKs as eagerly as the mode wants them)
+pub fn interceptors(
+    profile: &Profile,
+    media_engine: &mut MediaEngine,
+) -> Result<Registry, webrtc::Error> {
+    let mut registry = Registry::new();
+    // Keyframe requests are worth it in every mode
+    let feedback = match profile.nack_interval {
+        Some(_) => &["", "pli"][..],
+        None => &["pli"][..],
+    };
+    for parameter in feedback {
+        media_engine.register_feedback(
+            RTCPFeedback {
+                typ: "nack".to_owned(),
+                parameter: parameter.to_string(),
+            },
+            RTPCodecType::Video,
+        );
+    }
+    if let Some(interval) = profile.nack_interval {
+        registry.add(Box::new(Responder::builder()));
+        registry.add(Box::new(Generator::builder().with_interval(interval)));
+    }
+    registry = configure_rtcp_reports(registry);
+    configure_twcc_receiver_only(registry, media_engine)
+}
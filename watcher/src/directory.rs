@@ -0,0 +1,83 @@
+use std::io::{self, BufRead, Write};
+
+use tuesdays_protocol::StreamInfo;
+
+use crate::error::WatchError;
+
+// The directory is served over HTTP(S) next to the WebSocket routes
+fn http_base(server: &str) -> String {
+    if let Some(rest) = server.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = server.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        server.to_string()
+    }
+}
+
+pub async fn fetch_streams(server: &str) -> Result<Vec<StreamInfo>, WatchError> {
+    let url = format!("{}/streams", http_base(server).trim_end_matches('/'));
+    let streams = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .json::<Vec<StreamInfo>>()
+        .await?;
+    Ok(streams)
+}
+
+pub fn print_streams(streams: &[StreamInfo]) {
+    if streams.is_empty() {
+        println!("📭 No live streams");
+        return;
+    }
+    for (index, stream) in streams.iter().enumerate() {
+        println!(
+            "{:>3}) {}  ({} watching){}",
+            index + 1,
+            stream.id,
+            stream.watchers,
+            if stream.audio_only {
+                "  🎧 audio only"
+            } else {
+                ""
+            }
+        );
+        // ✅ What each streamer sends, so it's clear up front whether it plays here
+        for (streamer_id, media) in &stream.media {
+            println!("       {}: {}", streamer_id, media.summary());
+            if let Some(url) = media.hls() {
+                println!("       {}  HLS: {}", " ".repeat(streamer_id.len()), url);
+            }
+        }
+    }
+}
+
+// ✅ Let the user pick a stream by number (or type its id)
+pub fn pick_stream(streams: &[StreamInfo]) -> Result<String, WatchError> {
+    if streams.is_empty() {
+        return Err(WatchError::NoStreams);
+    }
+    print_streams(streams);
+
+    let stdin = io::stdin();
+    loop {
+        print!("Select a stream [1-{}]: ", streams.len());
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Err(WatchError::NoSelection);
+        }
+        let choice = line.trim();
+
+        if let Ok(number) = choice.parse::<usize>()
+            && (1..=streams.len()).contains(&number)
+        {
+            return Ok(streams[number - 1].id.clone());
+        }
+        if let Some(stream) = streams.iter().find(|s| s.id == choice) {
+            return Ok(stream.id.clone());
+        }
+        println!("⚠️ '{}' is not in the list", choice);
+    }
+}
@@ -0,0 +1,49 @@
+use tuesdays_protocol::{IceError, SignalingError};
+
+use crate::player::PlayerError;
+
+// Why the watcher stopped (or couldn't start) watching
+#[derive(Debug, thiserror::Error)]
+pub enum WatchError {
+    #[error(transparent)]
+    Signaling(#[from] SignalingError),
+    #[error(transparent)]
+    Ice(#[from] IceError),
+    #[error(transparent)]
+    Player(#[from] PlayerError),
+    #[error("Stream directory: {0}")]
+    Directory(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("No live streams to watch")]
+    NoStreams,
+    #[error("No stream selected")]
+    NoSelection,
+    #[error("--record only works when watching a single stream")]
+    RecordSeveral,
+    // --assert-frames failed
+    #[error("{0}")]
+    FrameCheck(String),
+    #[error("'{0}' is audio-only; --assert-frames needs video")]
+    AudioOnly(String),
+    #[error("Stopped before the frame check completed")]
+    Stopped,
+    // The session ended and --no-reconnect is set
+    #[error("{0}")]
+    Disconnected(String),
+    #[error("Giving up after {attempts} reconnect attempts: {reason}")]
+    GaveUp { attempts: u32, reason: String },
+    // One stream of a mosaic
+    #[error("[{streamer_id}] {source}")]
+    Stream {
+        streamer_id: String,
+        #[source]
+        source: Box<WatchError>,
+    },
+}
+
+impl From<webrtc::Error> for WatchError {
+    fn from(err: webrtc::Error) -> Self {
+        WatchError::Ice(err.into())
+    }
+}
@@ -0,0 +1,1082 @@
+mod alerts;
+mod delivery;
+mod directory;
+mod error;
+mod monitor;
+mod mosaic;
+mod player;
+mod signaling;
+mod stats;
+
+use clap::Parser;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{
+    self, Message,
+    client::IntoClientRequest,
+    handshake::client::Request,
+    http::{HeaderValue, StatusCode, header},
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
+use webrtc::api::APIBuilder;
+use webrtc::api::media_engine::MediaEngine;
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::configuration::RTCConfiguration;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
+use webrtc::track::track_remote::TrackRemote;
+use webrtc::util::Marshal;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app::AppSrc;
+
+use alerts::{AlertMonitor, AlertRules};
+use error::WatchError;
+use monitor::{FrameCheck, WatermarkCheck};
+use mosaic::{Mosaic, Tile};
+use player::{Jitter, Player, PlayerError, PlayerOptions};
+use signaling::Negotiator;
+use stats::{Reporter, Stats};
+use tuesdays_config::{Config, exit};
+use tuesdays_protocol::resume::{Arrival, Resumption};
+use tuesdays_protocol::{
+    AudienceRole, Command, DeliveryMode, Encoding, ErrorResponse, Layer, Measurement,
+    PROTOCOL_VERSION, Pong, RecordAction, Signal, SignalingError, close, encoding, latency,
+    session,
+};
+
+// A session that stayed up this long resets the reconnect backoff
+const RECONNECT_RESET: Duration = Duration::from_secs(30);
+
+// Jitter buffer latency: adjusted in these steps, up to this much
+const JITTER_STEP_MS: u32 = 50;
+const MAX_JITTER_LATENCY_MS: u32 = 10_000;
+
+// No video frame for this long: ask the streamer for a keyframe (again)
+const KEYFRAME_STALE: Duration = Duration::from_secs(2);
+
+// How often to try reconnecting while resuming
+const RESUME_RETRY: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Serialize, Deserialize, Debug)]
+#[command(about = "Watch a WebRTC stream published through the transmitter")]
+#[serde(deny_unknown_fields)]
+struct Args {
+    /// Id(s) of the streamer(s) to watch; several ids are shown as a mosaic.
+    /// Pick interactively from the live streams when omitted
+    streamer_ids: Vec<String>,
+
+    /// List the live streams and exit
+    #[arg(long)]
+    list: bool,
+
+    /// Show every live stream in a grid (when no ids are given)
+    #[arg(long)]
+    mosaic: bool,
+
+    /// Signaling server base URL
+    #[arg(long, default_value = "ws://localhost:8080")]
+    server: String,
+
+    /// Watcher id announced to the signaling server
+    #[arg(long, default_value = "watcher")]
+    id: String,
+
+    /// Simulcast layer to request; a streamer with one encoding caps its bitrate to it
+    #[arg(long, value_enum)]
+    quality: Option<Layer>,
+
+    /// Latency tier: realtime (as live as possible), low, or resilient (smooth on
+    /// a bad network, seconds behind). Sets the jitter buffer and how eagerly lost
+    /// packets are re-requested, and asks whatever forwards the stream to match
+    #[arg(long, value_enum)]
+    mode: Option<DeliveryMode>,
+
+    /// Audience role to join with; granted only with a token (transmitter --jwt-*)
+    /// or stream key that vouches for it, else a viewer. A streamer or moderator
+    /// may change it while watching
+    #[arg(long, value_enum)]
+    role: Option<AudienceRole>,
+
+    /// How signaling messages are framed: json, or cbor (binary, smaller for the
+    /// candidates and stats reports; needs a transmitter that speaks it)
+    #[arg(long, value_enum, default_value = "json")]
+    signaling_encoding: Encoding,
+
+    /// Save the received stream to a file (.mkv or .mp4) without re-encoding
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Ask the streamer to record the stream on its side (if its
+    /// --record-requests allows it) until this watcher exits
+    #[arg(long)]
+    request_recording: bool,
+
+    /// Don't render the stream locally (useful together with --record)
+    #[arg(long)]
+    no_display: bool,
+
+    /// Run without any local output (implies --no-display)
+    #[arg(long)]
+    headless: bool,
+
+    /// Verify that video frames arrive at --min-fps for --duration seconds,
+    /// then exit with a non-zero status if they didn't
+    #[arg(long)]
+    assert_frames: bool,
+
+    /// Measurement window for --assert-frames, in seconds
+    #[arg(long, default_value_t = 10)]
+    duration: u64,
+
+    /// Minimum average frame rate for --assert-frames
+    #[arg(long, default_value_t = 10.0)]
+    min_fps: f64,
+
+    /// Seconds to wait for the first frame before --assert-frames fails
+    #[arg(long, default_value_t = 30)]
+    startup_timeout: u64,
+
+    /// With --assert-frames, also read the stamps of a streamer running with
+    /// --watermark and fail on dropped or reordered frames, or frames arriving
+    /// later than --max-latency-ms
+    #[arg(long, requires = "assert_frames")]
+    assert_watermark: bool,
+
+    /// Frames --assert-watermark tolerates going missing
+    #[arg(long, default_value_t = 0)]
+    max_dropped_frames: u64,
+
+    /// Capture-to-decode latency --assert-watermark tolerates, in milliseconds
+    /// (streamer and watcher clocks must agree)
+    #[arg(long, default_value_t = 1000)]
+    max_latency_ms: u32,
+
+    /// Print bitrate, fps, jitter, loss, decode time, freezes and signaling round trip, and
+    /// overlay them on the video
+    #[arg(long)]
+    stats: bool,
+
+    /// Send the same stats to the transmitter as viewer-side QoE telemetry
+    #[arg(long)]
+    report_stats: bool,
+
+    /// Seconds between stats samples
+    #[arg(long, default_value_t = 1)]
+    stats_interval: u64,
+
+    /// Watch for frozen, slow or black video and emit structured alerts
+    #[arg(long)]
+    alerts: bool,
+
+    /// Raise a freeze alert after this many seconds without a frame
+    #[arg(long, default_value_t = 3.0)]
+    alert_freeze: f64,
+
+    /// Raise a low-fps alert when the frame rate stays below this for --alert-sustain seconds
+    #[arg(long, default_value_t = 10.0)]
+    alert_min_fps: f64,
+
+    /// Seconds a low frame rate must last before alerting
+    #[arg(long, default_value_t = 5.0)]
+    alert_sustain: f64,
+
+    /// Raise a black-frames alert after this many seconds of black picture
+    #[arg(long, default_value_t = 3.0)]
+    alert_black: f64,
+
+    /// POST every alert as JSON to this URL (implies --alerts)
+    #[arg(long, value_name = "URL")]
+    alert_webhook: Option<String>,
+
+    /// Jitter buffer delay in milliseconds: higher is smoother on a bad network,
+    /// lower is closer to live (default: the --mode's, 200 without one). Type +
+    /// or - and Enter while watching to adjust it
+    #[arg(long)]
+    jitter_latency: Option<u32>,
+
+    /// Cap the jitter buffer at --jitter-latency, dropping packets that arrive
+    /// later instead of waiting for them
+    #[arg(long)]
+    jitter_drop_late: bool,
+
+    /// Exit instead of reconnecting when the connection drops
+    #[arg(long)]
+    no_reconnect: bool,
+
+    /// Give up after this many consecutive reconnect attempts (default: keep trying)
+    #[arg(long)]
+    max_reconnects: Option<u32>,
+}
+
+impl Config for Args {
+    const SECTION: &'static str = "watcher";
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.server.starts_with("ws://") && !self.server.starts_with("wss://") {
+            return Err(format!(
+                "server must be a ws:// or wss:// URL, not '{}'",
+                self.server
+            ));
+        }
+        if self.id.is_empty() {
+            return Err("id must not be empty".to_string());
+        }
+        if self.assert_watermark && !self.assert_frames {
+            return Err("assert_watermark needs assert_frames".to_string());
+        }
+        if self.duration == 0 || self.stats_interval == 0 {
+            return Err("duration and stats_interval must be at least 1 second".to_string());
+        }
+        if let Some(latency) = self.jitter_latency
+            && latency > MAX_JITTER_LATENCY_MS
+        {
+            return Err(format!(
+                "jitter_latency must be at most {} ms, not {}",
+                MAX_JITTER_LATENCY_MS, latency
+            ));
+        }
+        let thresholds = [
+            ("min_fps", self.min_fps),
+            ("alert_freeze", self.alert_freeze),
+            ("alert_min_fps", self.alert_min_fps),
+            ("alert_sustain", self.alert_sustain),
+            ("alert_black", self.alert_black),
+        ];
+        if let Some((name, value)) = thresholds
+            .iter()
+            .find(|(_, value)| !value.is_finite() || *value < 0.0)
+        {
+            return Err(format!(
+                "{} must be a non-negative number, not {}",
+                name, value
+            ));
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<String, String> {
+        let options = PlayerOptions {
+            display: !(self.no_display || self.headless),
+            record: self.record.clone(),
+            overlay: self.stats,
+            analyze: self.alerts || self.alert_webhook.is_some(),
+            watermark: self.assert_watermark,
+            tile: None,
+            jitter: self.jitter(),
+        };
+        let mosaic = self.mosaic || self.streamer_ids.len() > 1;
+        let missing = player::missing_elements(&options, mosaic).map_err(|err| err.to_string())?;
+        if !missing.common.is_empty() {
+            return Err(format!(
+                "missing GStreamer elements: {}",
+                missing.common.join(", ")
+            ));
+        }
+        for (mime_type, elements) in &missing.codecs {
+            println!(
+                "⚠️ Cannot receive {}: missing {}",
+                mime_type,
+                elements.join(", ")
+            );
+        }
+        if !missing.any_video() {
+            return Err("no video codec can be received".to_string());
+        }
+        Ok("GStreamer elements for the configured outputs are installed".to_string())
+    }
+}
+
+impl Args {
+    // The mode's jitter buffer, unless the flags say otherwise
+    fn jitter(&self) -> Jitter {
+        let mode = delivery::profile(self.mode).jitter;
+        Jitter {
+            latency_ms: self.jitter_latency.unwrap_or(mode.latency_ms),
+            drop_late: self.jitter_drop_late || mode.drop_late,
+        }
+    }
+}
+
+// Why a signaling session ended
+enum SessionEnd {
+    // Stop watching with this result (Ctrl+C, frame check done, playback error)
+    Finished(Result<(), WatchError>),
+    // Lost the connection; reconnect unless disabled
+    Dropped(String),
+    // The streamer restarted; reconnect after the delay the transmitter picked
+    Rejoin(Duration),
+    // Sent to another transmitter instance; connect there right away
+    Redirect(String),
+}
+
+// Everything that outlives a single signaling session, so playback, recording
+// and stats carry on across reconnects
+struct Watch<'a> {
+    args: &'a Args,
+    streamer_id: String,
+    // Where the last redirect sent us, instead of --server
+    url: Option<String>,
+    // Prepended to per-stream log lines when watching several streams
+    prefix: String,
+    player: Arc<Player>,
+    stats: Arc<Stats>,
+    reporter: Reporter,
+    stats_ticker: tokio::time::Interval,
+    // Signaling round trip and clock offset, from the last `ping` answered
+    latency: Option<Measurement>,
+    check: Pin<Box<dyn Future<Output = Result<String, String>> + Send>>,
+    alerts: Option<AlertMonitor>,
+    alert_ticker: tokio::time::Interval,
+}
+
+async fn watch_stream(args: Args) -> Result<(), WatchError> {
+    // ✅ Initialize GStreamer
+    gst::init().map_err(PlayerError::from)?;
+
+    // ✅ Pick the stream(s) from the directory unless given
+    let (streamer_ids, streams) = if !args.streamer_ids.is_empty() {
+        // Only to learn which streams are audio-only; the directory may be turned off
+        let streams = directory::fetch_streams(&args.server)
+            .await
+            .unwrap_or_default();
+        (args.streamer_ids.clone(), streams)
+    } else {
+        let streams = directory::fetch_streams(&args.server).await?;
+        let streamer_ids = if args.mosaic {
+            if streams.is_empty() {
+                return Err(WatchError::NoStreams);
+            }
+            streams.iter().map(|stream| stream.id.clone()).collect()
+        } else {
+            vec![directory::pick_stream(&streams)?]
+        };
+        (streamer_ids, streams)
+    };
+
+    let display = !(args.no_display || args.headless);
+    let mosaic = if streamer_ids.len() > 1 {
+        if args.record.is_some() {
+            return Err(WatchError::RecordSeveral);
+        }
+        Some(Mosaic::new(streamer_ids.len(), display)?)
+    } else {
+        None
+    };
+
+    let mut watches = Vec::new();
+    for (index, streamer_id) in streamer_ids.iter().enumerate() {
+        let tile = mosaic.as_ref().map(|m| m.tile(index, streamer_id));
+        let audio_only = streams
+            .iter()
+            .any(|stream| stream.id == *streamer_id && stream.audio_only);
+        watches.push(new_watch(&args, streamer_id, display, tile, audio_only)?);
+    }
+
+    // ✅ Mosaic players all share one pipeline, so starting the first starts them all
+    let player = watches[0].player.clone();
+    player.play()?;
+    jitter_keys(watches.iter().map(|watch| watch.player.clone()).collect());
+    let bus = player.pipeline().bus().ok_or(PlayerError::NoBus)?;
+
+    // ✅ Keep every stream going until they're all done, or playback breaks
+    let outcome = tokio::select! {
+        outcomes = futures_util::future::join_all(watches.iter_mut().map(keep_watching)) => {
+            outcomes.into_iter().collect::<Result<Vec<()>, _>>().map(|_| ())
+        }
+        err = playback_error(bus.stream()) => Err(err),
+    };
+
+    for watch in &watches {
+        watch.player.stop()?;
+    }
+    outcome
+}
+
+// ✅ Player, stats and checks for one stream
+fn new_watch<'a>(
+    args: &'a Args,
+    streamer_id: &str,
+    display: bool,
+    tile: Option<Tile>,
+    audio_only: bool,
+) -> Result<Watch<'a>, WatchError> {
+    let prefix = match tile {
+        Some(_) => format!("[{}] ", streamer_id),
+        None => String::new(),
+    };
+
+    // ✅ Nothing to overlay, analyze or count frames of without video
+    if audio_only {
+        if args.assert_frames {
+            return Err(WatchError::AudioOnly(streamer_id.to_string()));
+        }
+        println!("🎧 {}'{}' is audio-only", prefix, streamer_id);
+    }
+    let alerts_enabled = (args.alerts || args.alert_webhook.is_some()) && !audio_only;
+
+    // ✅ Playback pipeline; branches are added as tracks arrive
+    let stats = Arc::new(Stats::default());
+    let player = Arc::new(Player::new(
+        PlayerOptions {
+            display,
+            record: args.record.clone(),
+            overlay: args.stats && !audio_only,
+            analyze: alerts_enabled,
+            watermark: args.assert_watermark && !audio_only,
+            tile,
+            jitter: args.jitter(),
+        },
+        stats.clone(),
+    )?);
+
+    // ✅ Frame-rate assertion for synthetic monitoring; never completes otherwise
+    let frames = stats.clone();
+    let frame_check = args.assert_frames.then(|| FrameCheck {
+        duration: Duration::from_secs(args.duration),
+        min_fps: args.min_fps,
+        startup_timeout: Duration::from_secs(args.startup_timeout),
+        watermark: args.assert_watermark.then_some(WatermarkCheck {
+            max_dropped: args.max_dropped_frames,
+            max_latency_ms: args.max_latency_ms,
+        }),
+    });
+    let check = Box::pin(async move {
+        match frame_check {
+            Some(check) => monitor::assert_frames(frames, check).await,
+            None => std::future::pending().await,
+        }
+    });
+
+    // ✅ Picture-quality alerts
+    let alerts = alerts_enabled.then(|| {
+        AlertMonitor::new(
+            AlertRules {
+                freeze: Duration::from_secs_f64(args.alert_freeze),
+                min_fps: args.alert_min_fps,
+                low_fps_for: Duration::from_secs_f64(args.alert_sustain),
+                black: Duration::from_secs_f64(args.alert_black),
+                webhook: args.alert_webhook.clone(),
+            },
+            streamer_id,
+            &args.id,
+        )
+    });
+
+    Ok(Watch {
+        args,
+        streamer_id: streamer_id.to_string(),
+        url: None,
+        prefix,
+        player,
+        stats,
+        reporter: Reporter::new(),
+        stats_ticker: tokio::time::interval(Duration::from_secs(args.stats_interval.max(1))),
+        latency: None,
+        check,
+        alerts,
+        alert_ticker: tokio::time::interval(Duration::from_secs(1)),
+    })
+}
+
+impl Watch<'_> {
+    // Say which stream failed when watching several
+    fn failed(&self, err: WatchError) -> WatchError {
+        if self.prefix.is_empty() {
+            return err;
+        }
+        WatchError::Stream {
+            streamer_id: self.streamer_id.clone(),
+            source: Box::new(err),
+        }
+    }
+}
+
+// ✅ Re-run signaling whenever the connection drops, backing off between attempts
+async fn keep_watching(watch: &mut Watch<'_>) -> Result<(), WatchError> {
+    let args = watch.args;
+    let mut attempts = 0u32;
+    loop {
+        let started = Instant::now();
+        // The transmitter may set the pace itself, to spread out a reconnect storm
+        let (reason, paced) = match run_session(watch).await {
+            Ok(SessionEnd::Finished(outcome)) => return outcome.map_err(|err| watch.failed(err)),
+            Ok(SessionEnd::Dropped(reason)) => (reason, None),
+            Ok(SessionEnd::Rejoin(delay)) => ("Stream restarted".to_string(), Some(delay)),
+            Ok(SessionEnd::Redirect(url)) => {
+                println!("🧭 {}Sent to {}", watch.prefix, url);
+                watch.url = Some(url);
+                continue;
+            }
+            Err(WatchError::Signaling(err @ SignalingError::Busy { retry_after })) => {
+                (err.to_string(), Some(retry_after))
+            }
+            Err(err) => (err.to_string(), None),
+        };
+
+        if args.no_reconnect {
+            return Err(watch.failed(WatchError::Disconnected(reason)));
+        }
+        let delay = match paced {
+            // Not a failure, so no backoff and no attempt used up
+            Some(delay) => {
+                println!(
+                    "🔄 {}{}; reconnecting in {:.1}s",
+                    watch.prefix,
+                    reason,
+                    delay.as_secs_f64()
+                );
+                delay
+            }
+            None => {
+                // A session that ran for a while was healthy; start backing off from scratch
+                if started.elapsed() > RECONNECT_RESET {
+                    attempts = 0;
+                }
+                attempts += 1;
+                if let Some(max) = args.max_reconnects
+                    && attempts > max
+                {
+                    return Err(watch.failed(WatchError::GaveUp {
+                        attempts: max,
+                        reason,
+                    }));
+                }
+
+                let delay = reconnect_delay(attempts);
+                println!(
+                    "🔄 {}{}; reconnecting in {}s (attempt {})",
+                    watch.prefix,
+                    reason,
+                    delay.as_secs(),
+                    attempts
+                );
+                delay
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = tokio::signal::ctrl_c() => return stopped(args),
+        }
+    }
+}
+
+// ✅ Adjust the jitter buffer from the terminal: `+`/`-` step it, a number sets it.
+// Lines are read on a thread of their own so a pending read never holds up shutdown
+fn jitter_keys(players: Vec<Arc<Player>>) {
+    let latency = players[0].jitter().latency_ms;
+    println!(
+        "🎚️ Jitter buffer: {} ms (type + or - and Enter to adjust)",
+        latency
+    );
+    std::thread::spawn(move || {
+        let mut latency = latency;
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            let requested = match line.trim() {
+                "+" | "=" => latency.saturating_add(JITTER_STEP_MS),
+                "-" | "_" => latency.saturating_sub(JITTER_STEP_MS),
+                other => match other.trim_end_matches("ms").trim().parse() {
+                    Ok(ms) => ms,
+                    Err(_) => continue,
+                },
+            };
+            latency = requested.min(MAX_JITTER_LATENCY_MS);
+            for player in &players {
+                player.set_jitter_latency(latency);
+            }
+            println!("🎚️ Jitter buffer: {} ms", latency);
+        }
+    });
+}
+
+// Resolves with the first pipeline error; a broken pipeline ends every stream
+async fn playback_error(mut messages: gst::bus::BusStream) -> WatchError {
+    while let Some(msg) = messages.next().await {
+        if let gst::MessageView::Error(err) = msg.view() {
+            return PlayerError::Playback(err.error()).into();
+        }
+    }
+    std::future::pending().await
+}
+
+// Result of stopping before the job was done: only a failure when asserting frames
+fn stopped(args: &Args) -> Result<(), WatchError> {
+    if args.assert_frames {
+        Err(WatchError::Stopped)
+    } else {
+        Ok(())
+    }
+}
+
+// Exponential backoff: 1s, 2s, 4s, ... capped at 30s
+fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1u64 << attempt.saturating_sub(1).min(5)).min(Duration::from_secs(30))
+}
+
+// ✅ One signaling connection + peer connection, until it ends one way or another
+async fn run_session(watch: &mut Watch<'_>) -> Result<SessionEnd, WatchError> {
+    let args = watch.args;
+
+    // ✅ Connect to Signaling Server, or wherever it sent us last time
+    let signaling_server_url = watch.url.clone().unwrap_or_else(|| {
+        let mut url = format!(
+            "{}/watcher?streamer_id={}&id={}&version={}",
+            args.server, watch.streamer_id, args.id, PROTOCOL_VERSION
+        );
+        if let Some(role) = args.role {
+            url.push_str(&format!("&role={}", role.name()));
+        }
+        url
+    });
+    let encoding = args.signaling_encoding;
+    let (ws_stream, _) = connect_async(signaling_request(&signaling_server_url, encoding)?)
+        .await
+        .map_err(|err| connect_error(&signaling_server_url, err))?;
+    let (mut write, mut read) = ws_stream.split();
+    println!("📡 Connected to {}", signaling_server_url);
+
+    // ✅ Say which build we are, for the transmitter's /clients API and access log
+    let hello = Command::hello("tuesdays-watcher", env!("CARGO_PKG_VERSION"));
+    write
+        .send(frame(encoding, hello.to_json()))
+        .await
+        .map_err(SignalingError::transport)?;
+
+    // ✅ Register codecs and RTCP interceptors (NACK as the mode wants it, reports)
+    let mut media_engine = MediaEngine::default();
+    media_engine.register_default_codecs()?;
+    let registry = delivery::interceptors(&delivery::profile(args.mode), &mut media_engine)?;
+    let api = APIBuilder::new()
+        .with_media_engine(media_engine)
+        .with_interceptor_registry(registry)
+        .build();
+
+    let config = RTCConfiguration {
+        ice_servers: vec![],
+        ..Default::default()
+    };
+    let peer_connection = Arc::new(api.new_peer_connection(config).await?);
+
+    let track_player = watch.player.clone();
+    let track_stats = watch.stats.clone();
+    let track_connection = Arc::downgrade(&peer_connection);
+    peer_connection.on_track(Box::new(move |track, _receiver, _transceiver| {
+        let player = track_player.clone();
+        let stats = track_stats.clone();
+        let connection = track_connection.clone();
+        Box::pin(async move {
+            match player.add_track(&track.codec()) {
+                Ok(src) => {
+                    if track.kind() == RTPCodecType::Video {
+                        tokio::spawn(request_keyframes(connection, track.ssrc(), stats.clone()));
+                    }
+                    tokio::spawn(forward_rtp(track, src, stats));
+                }
+                Err(err) => eprintln!("❌ Cannot play track {}: {}", track.id(), err),
+            }
+        })
+    }));
+
+    // ✅ Trickle our candidates to the streamer as soon as they're gathered
+    let (candidate_tx, mut candidate_rx) = tokio::sync::mpsc::unbounded_channel();
+    peer_connection.on_ice_candidate(Box::new(move |candidate| {
+        let init = match candidate.map(|c| c.to_json()) {
+            Some(Ok(init)) => init,
+            Some(Err(err)) => {
+                eprintln!("⚠️ Cannot serialize ICE candidate: {}", err);
+                return Box::pin(async {});
+            }
+            // Gathering finished: an empty candidate signals end-of-candidates
+            None => RTCIceCandidateInit::default(),
+        };
+        let _ = candidate_tx.send(init);
+        Box::pin(async {})
+    }));
+
+    let (failed_tx, mut failed_rx) = tokio::sync::mpsc::unbounded_channel();
+    peer_connection.on_peer_connection_state_change(Box::new(move |state| {
+        println!("🔗 Peer connection state: {}", state);
+        if state == RTCPeerConnectionState::Failed {
+            let _ = failed_tx.send(());
+        }
+        Box::pin(async {})
+    }));
+
+    println!(
+        "👀 Waiting for an offer from '{}'... Press Ctrl+C to stop.",
+        watch.streamer_id
+    );
+
+    let mut negotiator = Negotiator::new(peer_connection.clone());
+
+    // ✅ What we'd need to resume after a dropped connection (transmitter
+    // --resume-grace-secs); see `tuesdays_protocol::resume`
+    let mut resumption = Resumption::default();
+
+    let end = loop {
+        tokio::select! {
+            msg = read.next() => match decoded(encoding, msg) {
+                Some(Ok(Message::Text(text))) => {
+                    // ✅ The answer to our last ping, for the stats
+                    if let Ok(pong) = serde_json::from_str::<Pong>(&text) {
+                        watch.latency = Some(pong.measure(latency::now_ms()));
+                        continue;
+                    }
+                    // ✅ Skip copies; ask again for what went missing on the way
+                    match resumption.observe(&text) {
+                        Arrival::Copy => continue,
+                        Arrival::Gap { from, to } => {
+                            println!("🕳️ Missed messages {}-{}; asking for them again", from, to);
+                            write
+                                .send(frame(encoding, Command::Replay { from, to }.to_json()))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+                        Arrival::New => {}
+                    }
+                    let answer = negotiator.handle(&text).await?;
+                    if let Some(delay) = negotiator.take_rejoin() {
+                        break SessionEnd::Rejoin(delay);
+                    }
+                    if let Some(url) = negotiator.take_redirect() {
+                        break SessionEnd::Redirect(url);
+                    }
+                    // ✅ Tag alerts with the stream's session so they line up with the server logs
+                    if let (Some(alerts), Some(session_id)) =
+                        (watch.alerts.as_mut(), negotiator.session_id())
+                    {
+                        alerts.set_session(session_id);
+                    }
+                    if let Some(answer) = answer {
+                        // ✅ The answer, then the candidates we gathered meanwhile
+                        for command in std::iter::once(answer).chain(negotiator.take_unsent()) {
+                            write
+                                .send(frame(encoding, command))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+
+                        // ✅ Ask for our preferred layer once the session is (re)negotiated
+                        if let Some(layer) = args.quality {
+                            let request = Signal::Quality { watcher_id: args.id.clone(), layer };
+                            write
+                                .send(frame(encoding, request.to_command()))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+
+                        // ✅ Tell whatever forwards the stream how we want it delivered
+                        if let Some(mode) = args.mode {
+                            let request = Signal::Mode { watcher_id: args.id.clone(), mode };
+                            write
+                                .send(frame(encoding, request.to_command()))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+
+                        // ✅ (Re)ask for the recording; the streamer keeps one running for us
+                        if args.request_recording {
+                            let request = record_request(args, RecordAction::Start);
+                            write
+                                .send(frame(encoding, request))
+                                .await
+                                .map_err(SignalingError::transport)?;
+                        }
+                    }
+                }
+                // ✅ Another watcher took over our id; reconnecting would just kick it out again
+                Some(Ok(Message::Close(Some(frame)))) if u16::from(frame.code) == close::REPLACED => {
+                    break SessionEnd::Finished(Err(SignalingError::Replaced.into()));
+                }
+                // ✅ An operator sent us away; coming straight back would defy them
+                Some(Ok(Message::Close(Some(frame)))) if u16::from(frame.code) == close::KICKED => {
+                    break SessionEnd::Finished(Err(SignalingError::Kicked.into()));
+                }
+                // ✅ The stream ended a while ago and its room is gone; nothing to come back to
+                Some(Ok(Message::Close(Some(frame)))) if u16::from(frame.code) == close::EXPIRED => {
+                    println!("🏁 Stream '{}' has expired", watch.streamer_id);
+                    break SessionEnd::Finished(stopped(args));
+                }
+                Some(Ok(Message::Close(reason))) => {
+                    break SessionEnd::Dropped(format!("Signaling connection closed: {:?}", reason));
+                }
+                Some(Ok(_)) => {}
+                // ✅ Pick up where we left off on a new connection, keeping the peer
+                // connection rather than renegotiate it
+                Some(Err(_)) | None if resumption.resumable() => {
+                    let Some((url, grace)) = resumption.take(&signaling_server_url) else {
+                        continue;
+                    };
+                    println!("🔌 Signaling connection lost; resuming within {}s", grace.as_secs());
+                    match resume_signaling(&url, encoding, grace).await {
+                        Ok(ws_stream) => (write, read) = ws_stream.split(),
+                        Err(err) => break SessionEnd::Dropped(format!("Cannot resume: {}", err)),
+                    }
+                    write
+                        .send(frame(encoding, hello.to_json()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                    println!("🔁 Resumed");
+                }
+                Some(Err(err)) => break SessionEnd::Dropped(format!("Signaling error: {}", err)),
+                None => break SessionEnd::Dropped("Signaling connection lost".to_string()),
+            },
+            Some(candidate) = candidate_rx.recv() => {
+                let Some(command) = negotiator.local_candidate(candidate.into()) else {
+                    continue;
+                };
+                write
+                    .send(frame(encoding, command))
+                    .await
+                    .map_err(SignalingError::transport)?;
+            }
+            Some(()) = failed_rx.recv() => {
+                break SessionEnd::Dropped("Peer connection failed".to_string());
+            }
+            _ = watch.stats_ticker.tick(), if args.stats || args.report_stats => {
+                let mut snapshot = watch.reporter.sample(&watch.stats);
+                if let Some(latency) = watch.latency {
+                    snapshot.signaling_rtt_ms = Some(latency.rtt_ms);
+                    snapshot.clock_offset_ms = Some(latency.offset_ms);
+                }
+                if args.stats {
+                    println!("📊 {}{}", watch.prefix, snapshot);
+                    watch.player.set_overlay_text(&snapshot.to_string());
+                }
+                if args.report_stats {
+                    let mut report =
+                        serde_json::to_value(&snapshot).map_err(SignalingError::from)?;
+                    if let (Some(report), Some(session_id)) =
+                        (report.as_object_mut(), negotiator.session_id())
+                    {
+                        report.insert(session::SESSION_KEY.to_string(), session_id.into());
+                    }
+                    let command = Command::Stats { report };
+                    write
+                        .send(frame(encoding, command.to_json()))
+                        .await
+                        .map_err(SignalingError::transport)?;
+                }
+                // ✅ Measure the signaling round trip again for the next sample
+                let ping = Command::Ping { sent_ms: latency::now_ms() };
+                write
+                    .send(frame(encoding, ping.to_json()))
+                    .await
+                    .map_err(SignalingError::transport)?;
+            }
+            _ = watch.alert_ticker.tick(), if watch.alerts.is_some() => {
+                if let Some(alerts) = watch.alerts.as_mut() {
+                    alerts.check(&watch.stats);
+                }
+            }
+            result = &mut watch.check => {
+                let outcome = result
+                    .map(|summary| println!("✅ {}{}", watch.prefix, summary))
+                    .map_err(WatchError::FrameCheck);
+                break SessionEnd::Finished(outcome);
+            }
+            _ = tokio::signal::ctrl_c() => {
+                if args.request_recording {
+                    let request = record_request(args, RecordAction::Stop);
+                    let _ = write.send(frame(encoding, request)).await;
+                }
+                break SessionEnd::Finished(stopped(args));
+            }
+        }
+    };
+
+    peer_connection.close().await?;
+    Ok(end)
+}
+
+// ✅ Reconnect until the grace period is up, or the transmitter says there's
+// nothing left to resume (410 Gone)
+async fn resume_signaling(
+    url: &str,
+    encoding: Encoding,
+    grace: Duration,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, SignalingError> {
+    let deadline = tokio::time::Instant::now() + grace;
+    loop {
+        let err = match connect_async(signaling_request(url, encoding)?).await {
+            Ok((ws_stream, _)) => return Ok(ws_stream),
+            Err(err) => err,
+        };
+        let gone = matches!(&err, tungstenite::Error::Http(response)
+            if response.status() == StatusCode::GONE);
+        if gone || tokio::time::Instant::now() + RESUME_RETRY > deadline {
+            return Err(connect_error(url, err));
+        }
+        println!("🔄 Couldn't reconnect yet: {}", err);
+        tokio::time::sleep(RESUME_RETRY).await;
+    }
+}
+
+// ✅ A 503 from the join throttle says when to come back; anything else is a failure
+fn connect_error(url: &str, err: tungstenite::Error) -> SignalingError {
+    if let tungstenite::Error::Http(response) = &err
+        && response.status() == StatusCode::SERVICE_UNAVAILABLE
+    {
+        let seconds = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .unwrap_or(1);
+        return SignalingError::Busy {
+            retry_after: Duration::from_secs(seconds),
+        };
+    }
+    SignalingError::Connect {
+        url: url.to_string(),
+        source: err.into(),
+    }
+}
+
+// ✅ The request for the signaling connection, asking for CBOR if that's the
+// encoding (JSON is the default, and asked for by asking for nothing)
+fn signaling_request(url: &str, encoding: Encoding) -> Result<Request, SignalingError> {
+    let mut request = url
+        .into_client_request()
+        .map_err(|err| connect_error(url, err))?;
+    if encoding.binary() {
+        request.headers_mut().insert(
+            header::SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static(encoding.subprotocol()),
+        );
+    }
+    Ok(request)
+}
+
+// ✅ A message for the transmitter, in the connection's encoding
+fn frame(encoding: Encoding, text: String) -> Message {
+    match encoding {
+        Encoding::Json => Message::Text(text.into()),
+        Encoding::Cbor => Message::Binary(encoding::to_cbor(&text).into()),
+    }
+}
+
+// ✅ A CBOR message from the transmitter as the JSON it stands for, so it's
+// handled like any other
+fn decoded<E>(encoding: Encoding, msg: Option<Result<Message, E>>) -> Option<Result<Message, E>> {
+    match msg {
+        Some(Ok(Message::Binary(bytes))) if encoding.binary() => {
+            match encoding::from_cbor(&bytes) {
+                Ok(text) => Some(Ok(Message::Text(text.into()))),
+                Err(rejection) => {
+                    eprintln!(
+                        "⚠️ Cannot decode a signaling message: {}",
+                        ErrorResponse::from(rejection)
+                    );
+                    Some(Ok(Message::Binary(bytes)))
+                }
+            }
+        }
+        msg => msg,
+    }
+}
+
+fn record_request(args: &Args, action: RecordAction) -> String {
+    Signal::Record {
+        watcher_id: args.id.clone(),
+        action,
+    }
+    .to_command()
+}
+
+// ✅ Pump RTP packets from the remote track into the playback branch
+async fn forward_rtp(track: Arc<TrackRemote>, src: AppSrc, stats: Arc<Stats>) {
+    let clock_rate = track.codec().capability.clock_rate;
+    while let Ok((packet, _)) = track.read_rtp().await {
+        let data = match packet.marshal() {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("⚠️ Dropping malformed RTP packet: {}", err);
+                continue;
+            }
+        };
+        stats.on_rtp(&packet, data.len(), clock_rate);
+        if src.push_buffer(gst::Buffer::from_slice(data)).is_err() {
+            break;
+        }
+    }
+    // No EOS here: the branch is reused if the track comes back after a reconnect
+    println!("🛑 Track {} ended", track.id());
+}
+
+// ✅ Ask for a keyframe (PLI) as soon as video arrives, so decoding starts now rather
+// than at the streamer's next scheduled one, and again whenever the picture stalls
+async fn request_keyframes(
+    connection: Weak<RTCPeerConnection>,
+    media_ssrc: u32,
+    stats: Arc<Stats>,
+) {
+    let mut ticker = tokio::time::interval(KEYFRAME_STALE);
+    loop {
+        ticker.tick().await;
+        let stale = stats
+            .since_last_frame()
+            .is_none_or(|since| since >= KEYFRAME_STALE);
+        if !stale {
+            continue;
+        }
+        let Some(connection) = connection.upgrade() else {
+            return;
+        };
+        let pli = PictureLossIndication {
+            sender_ssrc: 0,
+            media_ssrc,
+        };
+        // Fails once the connection is closed
+        if connection.write_rtcp(&[Box::new(pli)]).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+extern crate cocoa;
+
+#[cfg(target_os = "macos")]
+use cocoa::appkit::NSApplication;
+#[cfg(target_os = "macos")]
+use cocoa::base::nil;
+
+fn initialize_macos_ui() {
+    #[cfg(target_os = "macos")]
+    unsafe {
+        let ns_app = NSApplication::sharedApplication(nil);
+        ns_app.activateIgnoringOtherApps_(true);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    initialize_macos_ui(); // 🛠️ Video sinks need NSApplication on macOS
+
+    let args: Args = tuesdays_config::load();
+
+    if args.list {
+        match directory::fetch_streams(&args.server).await {
+            Ok(streams) => directory::print_streams(&streams),
+            Err(err) => {
+                eprintln!("❌ Error: {}", err);
+                std::process::exit(exit::FAILURE);
+            }
+        }
+        return;
+    }
+
+    if let Err(err) = watch_stream(args).await {
+        eprintln!("❌ Error: {}", err);
+        std::process::exit(exit::FAILURE);
+    }
+}
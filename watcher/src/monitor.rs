@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::{Instant, interval, sleep_until};
+
+use crate::stats::{Stats, Watermarks};
+
+// Expectations for a headless `--assert-frames` run
+pub struct FrameCheck {
+    // How long to measure once the first frame has arrived
+    pub duration: Duration,
+    // Average frame rate required over the measurement window
+    pub min_fps: f64,
+    // How long to wait for the first frame before failing
+    pub startup_timeout: Duration,
+    // Also check the streamer's watermark stamps (--assert-watermark)
+    pub watermark: Option<WatermarkCheck>,
+}
+
+// Limits for the stamps read during the measurement window
+pub struct WatermarkCheck {
+    // Frames that may go missing between encoder and display
+    pub max_dropped: u64,
+    // Capture-to-decode delay no frame may exceed
+    pub max_latency_ms: u32,
+}
+
+// ✅ Watch the frame counter and fail on no video, low average fps, or a stall
+pub async fn assert_frames(stats: Arc<Stats>, check: FrameCheck) -> Result<String, String> {
+    let startup_deadline = Instant::now() + check.startup_timeout;
+    let mut ticker = interval(Duration::from_millis(100));
+    while stats.video_frames() == 0 {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = sleep_until(startup_deadline) => {
+                return Err(format!(
+                    "No video frames received within {}s",
+                    check.startup_timeout.as_secs()
+                ));
+            }
+        }
+    }
+
+    println!(
+        "🔍 First frame received, measuring for {}s",
+        check.duration.as_secs()
+    );
+
+    // Only stamps from the measurement window count
+    stats.take_watermarks();
+    let start_count = stats.video_frames();
+    let start = Instant::now();
+    let mut last_count = start_count;
+    let mut stalled_seconds = 0u32;
+    let mut ticker = interval(Duration::from_secs(1));
+    ticker.tick().await; // First tick completes immediately
+
+    while start.elapsed() < check.duration {
+        ticker.tick().await;
+        let count = stats.video_frames();
+        if count == last_count {
+            stalled_seconds += 1;
+            println!("⚠️ No frames in the last second");
+        }
+        last_count = count;
+    }
+
+    let received = last_count - start_count;
+    let fps = received as f64 / start.elapsed().as_secs_f64();
+    let mut summary = format!(
+        "{} frames in {:.1}s ({:.1} fps, {} stalled second(s))",
+        received,
+        start.elapsed().as_secs_f64(),
+        fps,
+        stalled_seconds
+    );
+    let watermarks = stats.take_watermarks();
+    if check.watermark.is_some() {
+        summary.push_str(&format!(
+            ", {} stamps read ({} unreadable, {} missing, {} out of order), latency {} ms mean, {} ms max",
+            watermarks.read,
+            watermarks.unreadable,
+            watermarks.missing,
+            watermarks.reordered,
+            watermarks.mean_latency_ms(),
+            watermarks.max_latency_ms
+        ));
+    }
+
+    if fps < check.min_fps {
+        Err(format!(
+            "Frame rate too low: {} < {:.1} fps required",
+            summary, check.min_fps
+        ))
+    } else if stalled_seconds > 0 {
+        Err(format!("Stream stalled: {}", summary))
+    } else if let Some(limits) = &check.watermark {
+        match check_watermarks(&watermarks, limits) {
+            Ok(()) => Ok(summary),
+            Err(reason) => Err(format!("{}: {}", reason, summary)),
+        }
+    } else {
+        Ok(summary)
+    }
+}
+
+fn check_watermarks(watermarks: &Watermarks, limits: &WatermarkCheck) -> Result<(), String> {
+    if watermarks.read == 0 {
+        Err("No watermarks found (is the streamer running with --watermark?)".to_string())
+    } else if watermarks.missing > limits.max_dropped {
+        Err(format!(
+            "Frames dropped: {} > {} allowed",
+            watermarks.missing, limits.max_dropped
+        ))
+    } else if watermarks.reordered > 0 {
+        Err("Frames out of order".to_string())
+    } else if watermarks.max_latency_ms > limits.max_latency_ms {
+        Err(format!(
+            "Latency too high: {} ms > {} ms allowed",
+            watermarks.max_latency_ms, limits.max_latency_ms
+        ))
+    } else {
+        Ok(())
+    }
+}
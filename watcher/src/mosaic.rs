@@ -0,0 +1,85 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use crate::player::PlayerError;
+
+// Size of one cell of the grid
+const TILE_WIDTH: i32 = 640;
+const TILE_HEIGHT: i32 = 360;
+
+// Grid view of several streams sharing one pipeline and one video sink:
+//
+//   player 1 … decoder → videoconvert ─┐
+//   player 2 … decoder → videoconvert ─┼→ compositor → videoconvert → sink
+//   player N …                        ─┘
+pub struct Mosaic {
+    pipeline: gst::Pipeline,
+    compositor: gst::Element,
+    columns: usize,
+}
+
+// Where one stream is drawn in the mosaic
+pub struct Tile {
+    pub pipeline: gst::Pipeline,
+    pub compositor: gst::Element,
+    pub xpos: i32,
+    pub ypos: i32,
+    // Caption drawn in the corner of the tile
+    pub label: String,
+}
+
+impl Mosaic {
+    pub fn new(count: usize, display: bool) -> Result<Self, PlayerError> {
+        let pipeline = gst::Pipeline::new();
+
+        // ✅ Tiles come and go with their streams; don't wait for ones that aren't sending
+        let compositor = gst::ElementFactory::make("compositor")
+            .property_from_str("background", "black")
+            .property("ignore-inactive-pads", true)
+            .build()?;
+        let convert = gst::ElementFactory::make("videoconvert").build()?;
+        let sink = if display {
+            gst::ElementFactory::make("autovideosink").build()?
+        } else {
+            gst::ElementFactory::make("fakesink").build()?
+        };
+        pipeline.add_many([&compositor, &convert, &sink])?;
+        gst::Element::link_many([&compositor, &convert, &sink])?;
+
+        let columns = (count as f64).sqrt().ceil().max(1.0) as usize;
+        let rows = count.div_ceil(columns);
+        println!("🧩 Mosaic of {} streams ({}x{} grid)", count, columns, rows);
+
+        Ok(Mosaic {
+            pipeline,
+            compositor,
+            columns,
+        })
+    }
+
+    // ✅ Grid cell for the stream at `index`, filled row by row
+    pub fn tile(&self, index: usize, label: &str) -> Tile {
+        Tile {
+            pipeline: self.pipeline.clone(),
+            compositor: self.compositor.clone(),
+            xpos: (index % self.columns) as i32 * TILE_WIDTH,
+            ypos: (index / self.columns) as i32 * TILE_HEIGHT,
+            label: label.to_string(),
+        }
+    }
+}
+
+impl Tile {
+    // ✅ Request a compositor input scaled and positioned for this tile
+    pub fn request_pad(&self) -> Result<gst::Pad, PlayerError> {
+        let pad = self
+            .compositor
+            .request_pad_simple("sink_%u")
+            .ok_or(PlayerError::NoCompositorPad)?;
+        pad.set_property("xpos", self.xpos);
+        pad.set_property("ypos", self.ypos);
+        pad.set_property("width", TILE_WIDTH);
+        pad.set_property("height", TILE_HEIGHT);
+        Ok(pad)
+    }
+}
@@ -0,0 +1,572 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use gstreamer_app::{AppSink, AppSinkCallbacks, AppSrc};
+use tuesdays_media::watermark;
+use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecParameters;
+
+use crate::mosaic::Tile;
+use crate::stats::Stats;
+
+// Why playback (or recording) couldn't be set up or broke down
+#[derive(Debug, thiserror::Error)]
+pub enum PlayerError {
+    #[error("GStreamer: {0}")]
+    Gst(#[from] glib::Error),
+    // Usually a missing plugin, or elements that can't be linked
+    #[error("GStreamer: {0}")]
+    Element(#[from] glib::BoolError),
+    #[error("GStreamer: {0}")]
+    StateChange(#[from] gst::StateChangeError),
+    #[error("GStreamer: {0}")]
+    PadLink(#[from] gst::PadLinkError),
+    #[error("{0} has no {1} pad")]
+    MissingPad(&'static str, &'static str),
+    #[error("Compositor refused a sink pad")]
+    NoCompositorPad,
+    #[error("Pipeline has no bus")]
+    NoBus,
+    #[error("Invalid mime type '{0}'")]
+    InvalidMimeType(String),
+    #[error("Unsupported codec '{0}'")]
+    UnsupportedCodec(String),
+    #[error("Unsupported recording container '{}' (use .mkv or .mp4)", .0.display())]
+    UnsupportedContainer(PathBuf),
+    #[error("Playback error: {0}")]
+    Playback(glib::Error),
+}
+
+// Size frames are scaled down to before picture analysis
+const ANALYSIS_WIDTH: i32 = 64;
+const ANALYSIS_HEIGHT: i32 = 36;
+
+// What to do with received media
+pub struct PlayerOptions {
+    // Render video/audio to the local sinks
+    pub display: bool,
+    // Mux the encoded (not re-encoded) tracks into this file
+    pub record: Option<PathBuf>,
+    // Draw a text overlay (updated through `set_overlay_text`) on rendered video
+    pub overlay: bool,
+    // Decode video for picture analysis (black frames) even when not displaying
+    pub analyze: bool,
+    // Decode every video frame at full size to read the streamer's watermark
+    pub watermark: bool,
+    // Draw video into a mosaic tile instead of a window of its own; audio is muted
+    pub tile: Option<Tile>,
+    pub jitter: Jitter,
+}
+
+// Jitter buffer settings: more latency rides out a worse network, less keeps it live
+#[derive(Debug, Clone, Copy)]
+pub struct Jitter {
+    // How long packets are held to reorder them and wait for retransmissions
+    pub latency_ms: u32,
+    // Never hold more than `latency_ms`: packets that come later are dropped
+    // instead of the buffer growing to wait for them
+    pub drop_late: bool,
+}
+
+// Playback pipeline, one branch per remote track:
+//
+//   appsrc → rtpjitterbuffer → depayloader → tee ─┬→ queue → decoder → sink
+//                                                 └→ queue → muxer → filesink
+pub struct Player {
+    pipeline: gst::Pipeline,
+    display: bool,
+    overlay: bool,
+    analyze: bool,
+    watermark: bool,
+    tile: Option<Tile>,
+    muxer: Option<gst::Element>,
+    stats: Arc<Stats>,
+    overlays: Mutex<Vec<gst::Element>>,
+    jitter: Mutex<Jitter>,
+    jitterbuffers: Mutex<Vec<gst::Element>>,
+    // Existing branches by mime type, reused when a track reappears after a reconnect
+    branches: Mutex<HashMap<String, AppSrc>>,
+}
+
+// Elements used to handle one codec
+struct CodecChain {
+    depay: &'static [&'static str],
+    decode: &'static [&'static str],
+}
+
+impl Player {
+    pub fn new(options: PlayerOptions, stats: Arc<Stats>) -> Result<Self, PlayerError> {
+        let pipeline = match &options.tile {
+            Some(tile) => tile.pipeline.clone(),
+            None => gst::Pipeline::new(),
+        };
+
+        // ✅ Shared muxer + filesink for recording; tracks request pads as they arrive
+        let muxer = match &options.record {
+            Some(path) => {
+                let muxer = gst::ElementFactory::make(muxer_for(path)?).build()?;
+                let filesink = gst::ElementFactory::make("filesink")
+                    .property("location", path.to_string_lossy().as_ref())
+                    .build()?;
+                pipeline.add_many([&muxer, &filesink])?;
+                muxer.link(&filesink)?;
+                println!("💾 Recording to {}", path.display());
+                Some(muxer)
+            }
+            None => None,
+        };
+
+        Ok(Player {
+            pipeline,
+            display: options.display,
+            overlay: options.overlay,
+            analyze: options.analyze,
+            watermark: options.watermark,
+            tile: options.tile,
+            muxer,
+            stats,
+            overlays: Mutex::new(Vec::new()),
+            jitter: Mutex::new(options.jitter),
+            jitterbuffers: Mutex::new(Vec::new()),
+            branches: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn pipeline(&self) -> &gst::Pipeline {
+        &self.pipeline
+    }
+
+    pub fn set_overlay_text(&self, text: &str) {
+        for overlay in lock(&self.overlays).iter() {
+            overlay.set_property("text", text);
+        }
+    }
+
+    pub fn jitter(&self) -> Jitter {
+        *lock(&self.jitter)
+    }
+
+    // ✅ Change the jitter buffer latency of every track, live
+    pub fn set_jitter_latency(&self, latency_ms: u32) {
+        lock(&self.jitter).latency_ms = latency_ms;
+        for jitterbuffer in lock(&self.jitterbuffers).iter() {
+            jitterbuffer.set_property("latency", latency_ms);
+        }
+        // Sinks must learn the new delay, or they'd drop everything as late
+        let _ = self.pipeline.recalculate_latency();
+    }
+
+    pub fn play(&self) -> Result<(), PlayerError> {
+        self.pipeline.set_state(gst::State::Playing)?;
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), PlayerError> {
+        // ✅ Let the muxer write its trailer/index before tearing down, or the file is unplayable
+        if self.muxer.is_some()
+            && self.pipeline.send_event(gst::event::Eos::new())
+            && let Some(bus) = self.pipeline.bus()
+        {
+            let _ = bus.timed_pop_filtered(
+                gst::ClockTime::from_seconds(5),
+                &[gst::MessageType::Eos, gst::MessageType::Error],
+            );
+        }
+        self.pipeline.set_state(gst::State::Null)?;
+        Ok(())
+    }
+
+    // ✅ Add a branch for a newly received track and return the appsrc to feed RTP into
+    pub fn add_track(&self, codec: &RTCRtpCodecParameters) -> Result<AppSrc, PlayerError> {
+        let mime_type = codec.capability.mime_type.to_lowercase();
+        let (media, encoding_name) = mime_type
+            .split_once('/')
+            .ok_or_else(|| PlayerError::InvalidMimeType(mime_type.clone()))?;
+        let chain = codec_chain(&mime_type)?;
+
+        // ✅ RTP caps so the jitter buffer and depayloader know what they're getting
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("media", media)
+            .field("encoding-name", encoding_name.to_uppercase())
+            .field("payload", codec.payload_type as i32)
+            .field("clock-rate", codec.capability.clock_rate as i32)
+            .build();
+
+        // ✅ Same codec as before a reconnect: keep decoding/recording into the same branch
+        if let Some(src) = lock(&self.branches).get(&mime_type) {
+            src.set_caps(Some(&caps));
+            println!("🎬 Resuming {} track ({})", media, mime_type);
+            return Ok(src.clone());
+        }
+
+        let src = AppSrc::builder()
+            .caps(&caps)
+            .is_live(true)
+            .format(gst::Format::Time)
+            .do_timestamp(true)
+            .build();
+        let jitter = self.jitter();
+        let jitterbuffer = gst::ElementFactory::make("rtpjitterbuffer")
+            .property("latency", jitter.latency_ms)
+            .property("drop-on-latency", jitter.drop_late)
+            .build()?;
+        lock(&self.jitterbuffers).push(jitterbuffer.clone());
+        let tee = gst::ElementFactory::make("tee").build()?;
+
+        let mut elements = vec![src.clone().upcast::<gst::Element>(), jitterbuffer];
+        elements.extend(make_elements(chain.depay)?);
+        elements.push(tee.clone());
+        self.add_linked(&elements)?;
+
+        // ✅ Count complete frames coming out of the depayloader
+        if media == "video" {
+            let stats = self.stats.clone();
+            tee.static_pad("sink")
+                .ok_or(PlayerError::MissingPad("tee", "sink"))?
+                .add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+                    stats.on_video_frame();
+                    gst::PadProbeReturn::Ok
+                });
+        }
+
+        // ✅ Render branch (or discard when not displaying, so the tee always has a consumer)
+        let mut render = vec![gst::ElementFactory::make("queue").build()?];
+        let tile = self.tile.as_ref().filter(|_| self.display);
+        let muted = tile.is_some() && media == "audio";
+        if self.display && !muted {
+            let mut decode = make_elements(chain.decode)?;
+            self.probe_decoder(&decode[0])?;
+            if media == "video" && self.overlay {
+                let overlay = gst::ElementFactory::make("textoverlay")
+                    .property_from_str("valignment", "top")
+                    .property_from_str("halignment", "left")
+                    .property("font-desc", "Monospace 10")
+                    .build()?;
+                decode.insert(1, overlay.clone());
+                lock(&self.overlays).push(overlay);
+            }
+            if let Some(tile) = tile {
+                // The compositor replaces the video sink; caption the tile with its stream
+                decode.pop();
+                let label = gst::ElementFactory::make("textoverlay")
+                    .property("text", tile.label.as_str())
+                    .property_from_str("valignment", "bottom")
+                    .property_from_str("halignment", "left")
+                    .property("font-desc", "Sans 14")
+                    .build()?;
+                decode.push(label);
+            }
+            render.extend(decode);
+        } else {
+            render.push(gst::ElementFactory::make("fakesink").build()?);
+        }
+        self.add_linked(&render)?;
+        tee.link(&render[0])?;
+        if let Some(tile) = tile
+            && !muted
+        {
+            render
+                .last()
+                .and_then(|last| last.static_pad("src"))
+                .ok_or(PlayerError::MissingPad("render branch", "src"))?
+                .link(&tile.request_pad()?)?;
+        }
+        elements.extend(render);
+
+        // ✅ Analysis branch: decode and shrink to a tiny grayscale frame for luma checks
+        if media == "video" && self.analyze {
+            let analysis = self.analysis_branch(chain.decode[0])?;
+            tee.link(&analysis[0])?;
+            elements.extend(analysis);
+        }
+
+        // ✅ Watermark branch: every frame, full size, to read the stamp in the corner
+        if media == "video" && self.watermark {
+            let stamps = self.watermark_branch(chain.decode[0])?;
+            tee.link(&stamps[0])?;
+            elements.extend(stamps);
+        }
+
+        // ✅ Record branch: encoded frames go straight into the muxer
+        if let Some(muxer) = &self.muxer {
+            let template = if media == "audio" {
+                "audio_%u"
+            } else {
+                "video_%u"
+            };
+            match muxer.request_pad_simple(template) {
+                Some(mux_pad) => {
+                    let queue = gst::ElementFactory::make("queue").build()?;
+                    self.pipeline.add(&queue)?;
+                    tee.link(&queue)?;
+                    queue
+                        .static_pad("src")
+                        .ok_or(PlayerError::MissingPad("queue", "src"))?
+                        .link(&mux_pad)?;
+                    elements.push(queue);
+                }
+                None => eprintln!(
+                    "⚠️ Muxer refused a {} pad; {} will not be recorded",
+                    media, mime_type
+                ),
+            }
+        }
+
+        // ✅ Start downstream elements first so nothing pushes into a stopped element
+        for element in elements.iter().rev() {
+            element.sync_state_with_parent()?;
+        }
+
+        lock(&self.branches).insert(mime_type.clone(), src.clone());
+        println!("🎬 Receiving {} track ({})", media, mime_type);
+        Ok(src)
+    }
+
+    fn analysis_branch(&self, decoder: &str) -> Result<Vec<gst::Element>, PlayerError> {
+        // Leaky queue: analysis must never hold back playback or recording
+        let queue = gst::ElementFactory::make("queue")
+            .property_from_str("leaky", "downstream")
+            .property("max-size-buffers", 2u32)
+            .build()?;
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("format", "GRAY8")
+            .field("width", ANALYSIS_WIDTH)
+            .field("height", ANALYSIS_HEIGHT)
+            .build();
+        let capsfilter = gst::ElementFactory::make("capsfilter")
+            .property("caps", &caps)
+            .build()?;
+        let sink = AppSink::builder()
+            .sync(false)
+            .drop(true)
+            .max_buffers(1)
+            .build();
+
+        let stats = self.stats.clone();
+        sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    if !map.is_empty() {
+                        let sum: u64 = map.iter().map(|&y| y as u64).sum();
+                        stats.on_luma((sum / map.len() as u64) as u8);
+                    }
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        let branch = vec![
+            queue,
+            gst::ElementFactory::make(decoder).build()?,
+            gst::ElementFactory::make("videoconvert").build()?,
+            gst::ElementFactory::make("videoscale").build()?,
+            capsfilter,
+            sink.upcast(),
+        ];
+        self.add_linked(&branch)?;
+        Ok(branch)
+    }
+
+    fn watermark_branch(&self, decoder: &str) -> Result<Vec<gst::Element>, PlayerError> {
+        // Not leaky, unlike analysis: a frame dropped here would count as missing
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("format", "I420")
+            .build();
+        let capsfilter = gst::ElementFactory::make("capsfilter")
+            .property("caps", &caps)
+            .build()?;
+        let sink = AppSink::builder().sync(false).build();
+
+        let stats = self.stats.clone();
+        sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let structure = sample
+                        .caps()
+                        .and_then(|caps| caps.structure(0))
+                        .ok_or(gst::FlowError::Error)?;
+                    let (Ok(width), Ok(height)) = (
+                        structure.get::<i32>("width"),
+                        structure.get::<i32>("height"),
+                    ) else {
+                        return Err(gst::FlowError::Error);
+                    };
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    // GStreamer's default I420 layout: the Y plane comes first, rows padded to 4 bytes
+                    let stride = (width as usize).next_multiple_of(4);
+                    stats.on_watermark(watermark::read(&map, stride, height as usize));
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        let branch = vec![
+            gst::ElementFactory::make("queue").build()?,
+            gst::ElementFactory::make(decoder).build()?,
+            gst::ElementFactory::make("videoconvert").build()?,
+            capsfilter,
+            sink.upcast(),
+        ];
+        self.add_linked(&branch)?;
+        Ok(branch)
+    }
+
+    // ✅ Time buffers through the decoder by matching PTS on its sink and src pads
+    fn probe_decoder(&self, decoder: &gst::Element) -> Result<(), PlayerError> {
+        let stats = self.stats.clone();
+        decoder
+            .static_pad("sink")
+            .ok_or(PlayerError::MissingPad("decoder", "sink"))?
+            .add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+                if let Some(buffer) = info.buffer() {
+                    stats.on_decode_start(buffer.pts());
+                }
+                gst::PadProbeReturn::Ok
+            });
+
+        let stats = self.stats.clone();
+        decoder
+            .static_pad("src")
+            .ok_or(PlayerError::MissingPad("decoder", "src"))?
+            .add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+                if let Some(buffer) = info.buffer() {
+                    stats.on_decode_end(buffer.pts());
+                }
+                gst::PadProbeReturn::Ok
+            });
+        Ok(())
+    }
+
+    fn add_linked(&self, elements: &[gst::Element]) -> Result<(), PlayerError> {
+        self.pipeline.add_many(elements)?;
+        if elements.len() > 1 {
+            gst::Element::link_many(elements)?;
+        }
+        Ok(())
+    }
+}
+
+// Everything `codec_chain` knows, for --check
+const MIME_TYPES: [&str; 4] = ["video/vp8", "video/vp9", "video/h264", "audio/opus"];
+
+// GStreamer elements a player configuration needs that aren't installed
+#[derive(Debug, Default)]
+pub struct MissingElements {
+    // Needed whatever the streams carry
+    pub common: Vec<&'static str>,
+    // Codecs that can't be received, with the elements they lack
+    pub codecs: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+impl MissingElements {
+    // Whether at least one video codec can be received
+    pub fn any_video(&self) -> bool {
+        MIME_TYPES
+            .iter()
+            .filter(|mime_type| mime_type.starts_with("video/"))
+            .any(|mime_type| !self.codecs.iter().any(|(codec, _)| codec == mime_type))
+    }
+}
+
+// ✅ Probe the registry for what `options` (plus a mosaic, if any) would build
+pub fn missing_elements(
+    options: &PlayerOptions,
+    mosaic: bool,
+) -> Result<MissingElements, PlayerError> {
+    gst::init()?;
+    let missing = |names: Vec<&'static str>| -> Vec<&'static str> {
+        names
+            .into_iter()
+            .filter(|name| gst::ElementFactory::find(name).is_none())
+            .collect()
+    };
+
+    let mut common = vec!["appsrc", "rtpjitterbuffer", "tee", "queue", "fakesink"];
+    if options.display && (options.overlay || mosaic) {
+        common.push("textoverlay");
+    }
+    if options.analyze {
+        common.extend(["videoconvert", "videoscale", "capsfilter", "appsink"]);
+    }
+    if options.watermark {
+        common.extend(["videoconvert", "capsfilter", "appsink"]);
+    }
+    if let Some(path) = &options.record {
+        common.extend([muxer_for(path)?, "filesink"]);
+    }
+    if mosaic {
+        common.extend(["compositor", "videoconvert", "autovideosink"]);
+    }
+
+    let mut codecs = Vec::new();
+    for mime_type in MIME_TYPES {
+        let chain = codec_chain(mime_type)?;
+        let mut needed = chain.depay.to_vec();
+        if options.display
+            || ((options.analyze || options.watermark) && mime_type.starts_with("video/"))
+        {
+            needed.extend(chain.decode);
+        }
+        let lacking = missing(needed);
+        if !lacking.is_empty() {
+            codecs.push((mime_type, lacking));
+        }
+    }
+
+    let mut common = missing(common);
+    common.sort_unstable();
+    common.dedup();
+    Ok(MissingElements { common, codecs })
+}
+
+fn codec_chain(mime_type: &str) -> Result<CodecChain, PlayerError> {
+    let chain = match mime_type {
+        "video/vp8" => CodecChain {
+            depay: &["rtpvp8depay"],
+            decode: &["vp8dec", "videoconvert", "autovideosink"],
+        },
+        "video/vp9" => CodecChain {
+            depay: &["rtpvp9depay"],
+            decode: &["vp9dec", "videoconvert", "autovideosink"],
+        },
+        "video/h264" => CodecChain {
+            depay: &["rtph264depay", "h264parse"],
+            decode: &["avdec_h264", "videoconvert", "autovideosink"],
+        },
+        "audio/opus" => CodecChain {
+            depay: &["rtpopusdepay"],
+            decode: &["opusdec", "audioconvert", "audioresample", "autoaudiosink"],
+        },
+        _ => return Err(PlayerError::UnsupportedCodec(mime_type.to_string())),
+    };
+    Ok(chain)
+}
+
+fn make_elements(names: &[&str]) -> Result<Vec<gst::Element>, PlayerError> {
+    names
+        .iter()
+        .map(|name| Ok(gst::ElementFactory::make(name).build()?))
+        .collect()
+}
+
+// Pick a container from the file extension
+fn muxer_for(path: &Path) -> Result<&'static str, PlayerError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("mkv") | Some("webm") => Ok("matroskamux"),
+        Some("mp4") | Some("m4v") => Ok("mp4mux"),
+        _ => Err(PlayerError::UnsupportedContainer(path.to_path_buf())),
+    }
+}
+
+// Overlays and branches stay usable even if a streaming thread panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,275 @@
+// Watcher side of the signaling protocol (see `tuesdays_protocol::signal`): answer
+// the offer a streamer routes to us, and trickle candidates both ways within
+// that one negotiation (see `Command::Offer`).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tuesdays_protocol::{ErrorResponse, IceCandidate, IceError, Route, Signal, Warning, session};
+use webrtc::ice_transport::ice_candidate::RTCIceCandidateInit;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
+
+// Answers offers and applies remote candidates for one peer connection
+pub struct Negotiator {
+    peer_connection: Arc<RTCPeerConnection>,
+    // Remote candidates that arrived before the offer they belong to
+    pending: Vec<(Route, RTCIceCandidateInit)>,
+    // The streamer and negotiation we answered, and our answer, sent again if
+    // the offer is
+    negotiation: Option<(Route, String)>,
+    // Our own candidates gathered before there was anyone to send them to
+    unsent: Vec<IceCandidate>,
+    // The stream's session, once the transmitter has announced it
+    session_id: Option<String>,
+    // Set when a new session replaces ours: the streamer restarted, so this peer
+    // connection is dead and we should start over after the given delay
+    rejoin: Option<Duration>,
+    // Set when the transmitter sends us to another instance (its region's, or
+    // the stream's), where we should connect right away
+    redirect: Option<String>,
+}
+
+impl Negotiator {
+    pub fn new(peer_connection: Arc<RTCPeerConnection>) -> Self {
+        Negotiator {
+            peer_connection,
+            pending: Vec::new(),
+            negotiation: None,
+            unsent: Vec::new(),
+            session_id: None,
+            rejoin: None,
+            redirect: None,
+        }
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    pub fn take_rejoin(&mut self) -> Option<Duration> {
+        self.rejoin.take()
+    }
+
+    pub fn take_redirect(&mut self) -> Option<String> {
+        self.redirect.take()
+    }
+
+    // ✅ The command that trickles one of our candidates to the streamer we're
+    // negotiating with; until there is one, it's kept for `take_unsent`
+    pub fn local_candidate(&mut self, candidate: IceCandidate) -> Option<String> {
+        let Some((route, _)) = &self.negotiation else {
+            self.unsent.push(candidate);
+            return None;
+        };
+        Signal::Candidate(candidate).to_routed_command(&route.from, &route.negotiation_id)
+    }
+
+    // The candidates gathered before we answered, to send right after the answer
+    pub fn take_unsent(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.unsent)
+            .into_iter()
+            .filter_map(|candidate| self.local_candidate(candidate))
+            .collect()
+    }
+
+    // ✅ Handle one incoming message; returns the answer command to send back, if any
+    pub async fn handle(&mut self, text: &str) -> Result<Option<String>, IceError> {
+        let signal = match serde_json::from_str::<Signal>(text) {
+            Ok(signal) => signal,
+            Err(_) => {
+                if let Some(error) = ErrorResponse::parse(text) {
+                    eprintln!("⚠️ Transmitter refused: {}", error);
+                } else if let Ok(warning) = serde_json::from_str::<Warning>(text) {
+                    eprintln!("⚠️ Transmitter warns: {}", warning);
+                } else {
+                    println!("💬 {}", text);
+                }
+                return Ok(None);
+            }
+        };
+
+        let route = Route::of(text);
+        match signal {
+            Signal::Offer { sdp } => {
+                // Offers are routed to us; one broadcast to the room is for nobody
+                // in particular
+                let Some(route) = route else {
+                    return Ok(None);
+                };
+                match &self.negotiation {
+                    // The streamer offers again until it hears our answer
+                    Some((answered, answer)) if *answered == route => {
+                        let answer = Signal::Answer {
+                            sdp: answer.clone(),
+                        };
+                        Ok(answer.to_routed_command(&route.from, &route.negotiation_id))
+                    }
+                    // Our streamer started over with us: this connection is done with
+                    Some((answered, _)) if answered.from == route.from => {
+                        println!("🔄 '{}' offered again; renegotiating", route.from);
+                        self.rejoin = Some(Duration::ZERO);
+                        Ok(None)
+                    }
+                    // One peer connection, one streamer; a co-streamer's offer waits
+                    // for a connection of its own
+                    Some(_) => Ok(None),
+                    None => self.answer(route, sdp).await.map(Some),
+                }
+            }
+            // Answers and requests come from other watchers (or ourselves) in the room
+            Signal::Answer { .. }
+            | Signal::Quality { .. }
+            | Signal::Mode { .. }
+            | Signal::Record { .. } => Ok(None),
+            // Ours to resume with, taken care of by `Resumption`
+            Signal::Resume { .. } => Ok(None),
+            // The streamer's own, to see its audience
+            Signal::Audience { .. } | Signal::WatcherJoined { .. } | Signal::WatcherLeft { .. } => {
+                Ok(None)
+            }
+            // The streamer follows it; we only see how the stream is doing
+            Signal::Recommendation {
+                bitrate_kbps,
+                layer,
+            } => {
+                println!(
+                    "🎚️ Transmitter recommends {} kbps ({:?} layer)",
+                    bitrate_kbps, layer
+                );
+                Ok(None)
+            }
+            // The stream carries on; the next connection goes to the new transmitter
+            Signal::Migrate { url } => {
+                println!("🚚 Transmitter is draining; next time connect to {}", url);
+                Ok(None)
+            }
+            // This isn't the instance for us; the transmitter closes right after
+            Signal::Redirect { url, .. } => {
+                self.redirect = Some(url);
+                Ok(None)
+            }
+            // Co-streaming: who's talking, for a UI to highlight
+            Signal::Speaker { streamer_id } => {
+                match streamer_id {
+                    Some(streamer_id) => println!("🗣️ '{}' is speaking", streamer_id),
+                    None => println!("🤫 Nobody is speaking"),
+                }
+                Ok(None)
+            }
+            // Someone's connection went; if it's the streamer we negotiated with,
+            // so is our peer connection, and another streamer may take us on
+            Signal::Left {
+                member_id,
+                timed_out,
+            } => {
+                let how = if timed_out { " (timed out)" } else { "" };
+                println!("👋 '{}' left{}", member_id, how);
+                if self
+                    .negotiation
+                    .as_ref()
+                    .is_some_and(|(route, _)| route.from == member_id)
+                {
+                    self.rejoin = Some(Duration::ZERO);
+                }
+                Ok(None)
+            }
+            // Paused while the streamer is gone, ended if it doesn't come back
+            Signal::Stream { state } => {
+                println!("📺 Stream is {}", state.name());
+                Ok(None)
+            }
+            // We connected before the stream was live and were kept waiting; the
+            // streamer's offer follows
+            Signal::StreamStarted { streamer_id } => {
+                println!("▶️ Stream started by '{}'", streamer_id);
+                Ok(None)
+            }
+            // Audience chat, our own messages included
+            Signal::Chat { from, message } => {
+                println!("💬 {}: {}", from, message);
+                Ok(None)
+            }
+            // Broadcasts that came together (transmitter --batch-window-ms); they're
+            // never signals, so nothing of ours
+            Signal::Batch { messages } => {
+                for message in messages {
+                    println!("💬 {}", message);
+                }
+                Ok(None)
+            }
+            // Ours changed: what we may now do in the room
+            Signal::Role { watcher_id, role } => {
+                println!("🎖️ '{}' is now a {}", watcher_id, role.name());
+                Ok(None)
+            }
+            // Whoever asked for it, everyone watching gets to know the stream is recorded
+            Signal::Recording(status) => {
+                println!("⏺️ {}", status);
+                Ok(None)
+            }
+            Signal::Session {
+                session_id,
+                rejoin_after_ms,
+            } => {
+                println!("🆔 Session {}", session_id);
+                if self
+                    .session_id
+                    .as_ref()
+                    .is_some_and(|current| *current != session_id)
+                {
+                    self.rejoin = Some(Duration::from_millis(rejoin_after_ms.unwrap_or(0)));
+                }
+                self.session_id = Some(session_id);
+                Ok(None)
+            }
+            // ✅ Only the streamer's candidates for the negotiation we're in
+            Signal::Candidate(candidate) => {
+                let Some(route) = route.filter(|_| !candidate.is_end_of_candidates()) else {
+                    return Ok(None);
+                };
+                match &self.negotiation {
+                    None => self.pending.push((route, candidate.into())),
+                    Some((answered, _)) if *answered == route => {
+                        self.peer_connection
+                            .add_ice_candidate(candidate.into())
+                            .await?;
+                    }
+                    Some(_) => {}
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    // ✅ Answer an offer, taking the candidates that came ahead of it
+    async fn answer(&mut self, route: Route, sdp: String) -> Result<String, IceError> {
+        println!(
+            "📡 Received WebRTC Offer from '{}' (session {})",
+            route.from,
+            session::label(self.session_id())
+        );
+        self.peer_connection
+            .set_remote_description(RTCSessionDescription::offer(sdp)?)
+            .await?;
+
+        for (from, candidate) in std::mem::take(&mut self.pending) {
+            if from == route {
+                self.peer_connection.add_ice_candidate(candidate).await?;
+            }
+        }
+
+        let answer = self.peer_connection.create_answer(None).await?;
+        self.peer_connection
+            .set_local_description(answer.clone())
+            .await?;
+        println!("📡 Sending WebRTC Answer");
+        let command = Signal::Answer {
+            sdp: answer.sdp.clone(),
+        }
+        .to_routed_command(&route.from, &route.negotiation_id)
+        .expect("answers are routed");
+        self.negotiation = Some((route, answer.sdp));
+        Ok(command)
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+use gstreamer as gst;
+use serde::Serialize;
+use tuesdays_media::watermark::Watermark;
+use webrtc::rtp::packet::Packet;
+
+// A gap between two video frames longer than this counts as a freeze
+const FREEZE_GAP: Duration = Duration::from_millis(500);
+
+// Decoded frames with a mean luma at or below this are considered black
+const BLACK_LUMA: u8 = 24;
+
+// Receive-side QoE counters shared by the RTP pump, the pipeline probes and the reporter
+#[derive(Default)]
+pub struct Stats {
+    video_frames: AtomicU64,
+    inner: Mutex<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    tracks: HashMap<u32, RtpCounters>,
+    last_frame: Option<Instant>,
+    black_since: Option<Instant>,
+    freezes: u64,
+    decoded: u64,
+    decode_time: Duration,
+    decoding: VecDeque<(gst::ClockTime, Instant)>,
+    watermarks: Watermarks,
+    last_sequence: Option<u32>,
+}
+
+// Stamps read off decoded frames (streamer --watermark) since they were last taken
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Watermarks {
+    pub read: u64,
+    // Decoded frames without a readable stamp
+    pub unreadable: u64,
+    // Sequence numbers skipped, i.e. frames that never made it here
+    pub missing: u64,
+    // Stamps at or before one already read
+    pub reordered: u64,
+    pub latency_sum_ms: u64,
+    pub max_latency_ms: u32,
+}
+
+impl Watermarks {
+    pub fn mean_latency_ms(&self) -> u64 {
+        self.latency_sum_ms / self.read.max(1)
+    }
+}
+
+// Per-SSRC counters following RFC 3550 (extended sequence numbers, interarrival jitter)
+struct RtpCounters {
+    clock_rate: u32,
+    started: Instant,
+    packets: u64,
+    bytes: u64,
+    base_seq: u64,
+    highest_seq: u64,
+    last_transit: Option<f64>,
+    jitter: f64,
+}
+
+// One reporting interval, as printed and as sent to the transmitter
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub bitrate_kbps: f64,
+    pub fps: f64,
+    pub jitter_ms: f64,
+    pub packet_loss_pct: f64,
+    pub decode_ms: f64,
+    pub freezes: u64,
+    // From the last `ping` answered; see `tuesdays_protocol::latency`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signaling_rtt_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>,
+}
+
+// Keeps the previous totals so each snapshot covers only the last interval
+pub struct Reporter {
+    last_at: Instant,
+    last_frames: u64,
+    last_bytes: u64,
+    last_packets: u64,
+    last_expected: u64,
+    last_decoded: u64,
+    last_decode_time: Duration,
+}
+
+impl Stats {
+    // Counters stay usable even if a streaming thread panicked while holding them
+    fn counters(&self) -> MutexGuard<'_, Counters> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn video_frames(&self) -> u64 {
+        self.video_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn on_rtp(&self, packet: &Packet, size: usize, clock_rate: u32) {
+        let now = Instant::now();
+        let mut inner = self.counters();
+        let track = inner
+            .tracks
+            .entry(packet.header.ssrc)
+            .or_insert_with(|| RtpCounters {
+                clock_rate: clock_rate.max(1),
+                started: now,
+                packets: 0,
+                bytes: 0,
+                base_seq: packet.header.sequence_number as u64,
+                highest_seq: packet.header.sequence_number as u64,
+                last_transit: None,
+                jitter: 0.0,
+            });
+
+        track.packets += 1;
+        track.bytes += size as u64;
+
+        // ✅ Extend the 16-bit sequence number across wraparounds; reordered packets don't move it back
+        let delta = packet
+            .header
+            .sequence_number
+            .wrapping_sub(track.highest_seq as u16) as i16;
+        if delta > 0 {
+            track.highest_seq += delta as u64;
+        }
+
+        // ✅ Interarrival jitter in RTP clock units
+        let arrival = now.duration_since(track.started).as_secs_f64() * track.clock_rate as f64;
+        let transit = arrival - packet.header.timestamp as f64;
+        if let Some(last) = track.last_transit {
+            let d = (transit - last).abs();
+            track.jitter += (d - track.jitter) / 16.0;
+        }
+        track.last_transit = Some(transit);
+    }
+
+    pub fn on_video_frame(&self) {
+        self.video_frames.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut inner = self.counters();
+        if let Some(last) = inner.last_frame
+            && now.duration_since(last) > FREEZE_GAP
+        {
+            inner.freezes += 1;
+        }
+        inner.last_frame = Some(now);
+    }
+
+    // Mean luma of a decoded (downscaled) frame, from the analysis branch
+    pub fn on_luma(&self, mean: u8) {
+        let mut inner = self.counters();
+        if mean > BLACK_LUMA {
+            inner.black_since = None;
+        } else if inner.black_since.is_none() {
+            inner.black_since = Some(Instant::now());
+        }
+    }
+
+    // A full-size decoded frame, with the stamp read off it (None if it had none)
+    pub fn on_watermark(&self, mark: Option<Watermark>) {
+        let mut inner = self.counters();
+        let Some(mark) = mark else {
+            inner.watermarks.unreadable += 1;
+            return;
+        };
+        let latency_ms = mark.latency_ms(SystemTime::now());
+        match inner.last_sequence {
+            Some(last) if mark.sequence <= last => inner.watermarks.reordered += 1,
+            last => {
+                inner.watermarks.missing +=
+                    last.map_or(0, |last| u64::from(mark.sequence - last - 1));
+                inner.last_sequence = Some(mark.sequence);
+            }
+        }
+        let watermarks = &mut inner.watermarks;
+        watermarks.read += 1;
+        watermarks.latency_sum_ms += u64::from(latency_ms);
+        watermarks.max_latency_ms = watermarks.max_latency_ms.max(latency_ms);
+    }
+
+    // ✅ The stamps read since the last call; gaps are still measured across calls
+    pub fn take_watermarks(&self) -> Watermarks {
+        std::mem::take(&mut self.counters().watermarks)
+    }
+
+    // Time since the last video frame arrived, if any did
+    pub fn since_last_frame(&self) -> Option<Duration> {
+        self.counters().last_frame.map(|t| t.elapsed())
+    }
+
+    // How long the picture has been black, if it currently is
+    pub fn black_for(&self) -> Option<Duration> {
+        self.counters().black_since.map(|t| t.elapsed())
+    }
+
+    pub fn on_decode_start(&self, pts: Option<gst::ClockTime>) {
+        if let Some(pts) = pts {
+            let mut inner = self.counters();
+            inner.decoding.push_back((pts, Instant::now()));
+            // Frames the decoder dropped never come out; don't let them pile up
+            if inner.decoding.len() > 64 {
+                inner.decoding.pop_front();
+            }
+        }
+    }
+
+    pub fn on_decode_end(&self, pts: Option<gst::ClockTime>) {
+        let Some(pts) = pts else { return };
+        let mut inner = self.counters();
+        if let Some(index) = inner.decoding.iter().position(|(p, _)| *p == pts)
+            && let Some((_, started)) = inner.decoding.remove(index)
+        {
+            inner.decoded += 1;
+            inner.decode_time += started.elapsed();
+        }
+    }
+}
+
+impl Reporter {
+    pub fn new() -> Self {
+        Reporter {
+            last_at: Instant::now(),
+            last_frames: 0,
+            last_bytes: 0,
+            last_packets: 0,
+            last_expected: 0,
+            last_decoded: 0,
+            last_decode_time: Duration::ZERO,
+        }
+    }
+
+    pub fn sample(&mut self, stats: &Stats) -> Snapshot {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_at).as_secs_f64().max(0.001);
+        let frames = stats.video_frames();
+        let inner = stats.counters();
+
+        let bytes: u64 = inner.tracks.values().map(|t| t.bytes).sum();
+        let packets: u64 = inner.tracks.values().map(|t| t.packets).sum();
+        let expected: u64 = inner
+            .tracks
+            .values()
+            .map(|t| t.highest_seq - t.base_seq + 1)
+            .sum();
+        let jitter_ms = inner
+            .tracks
+            .values()
+            .map(|t| t.jitter / t.clock_rate as f64 * 1000.0)
+            .fold(0.0, f64::max);
+
+        let interval_expected = expected.saturating_sub(self.last_expected);
+        let interval_received = packets.saturating_sub(self.last_packets);
+        let packet_loss_pct = if interval_expected > 0 {
+            interval_expected.saturating_sub(interval_received) as f64 * 100.0
+                / interval_expected as f64
+        } else {
+            0.0
+        };
+
+        let interval_decoded = inner.decoded - self.last_decoded;
+        let decode_ms = if interval_decoded > 0 {
+            (inner.decode_time - self.last_decode_time).as_secs_f64() * 1000.0
+                / interval_decoded as f64
+        } else {
+            0.0
+        };
+
+        let snapshot = Snapshot {
+            bitrate_kbps: (bytes - self.last_bytes) as f64 * 8.0 / 1000.0 / elapsed,
+            fps: (frames - self.last_frames) as f64 / elapsed,
+            jitter_ms,
+            packet_loss_pct,
+            decode_ms,
+            freezes: inner.freezes,
+            signaling_rtt_ms: None,
+            clock_offset_ms: None,
+        };
+
+        self.last_at = now;
+        self.last_frames = frames;
+        self.last_bytes = bytes;
+        self.last_packets = packets;
+        self.last_expected = expected;
+        self.last_decoded = inner.decoded;
+        self.last_decode_time = inner.decode_time;
+        snapshot
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} kbps | {:.1} fps | jitter {:.1} ms | loss {:.1}% | decode {:.1} ms | freezes {}",
+            self.bitrate_kbps,
+            self.fps,
+            self.jitter_ms,
+            self.packet_loss_pct,
+            self.decode_ms,
+            self.freezes
+        )?;
+        if let Some(rtt_ms) = self.signaling_rtt_ms {
+            write!(f, " | signaling {} ms", rtt_ms)?;
+        }
+        Ok(())
+    }
+}